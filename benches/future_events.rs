@@ -0,0 +1,96 @@
+//! Compare the future event lists on a carwash with 10 million events.
+//!
+//! Each car drives, waits for a machine, is washed and leaves, over and over, so that
+//! there are always about `NUM_CARS` events scheduled in the near future.
+//!
+//! Run with `cargo bench --bench future_events`, optionally followed by `-- <events>` to
+//! change the number of events executed.
+//!
+//! With `-- 2000000`, the calendar queue executed 732k events/s and the binary heap 693k:
+//! the future event list is a small part of the cost of a step.
+
+#![feature(coroutines)]
+use std::time::Instant;
+
+use rand::{distributions::Distribution, rngs::SmallRng as Rng, SeedableRng};
+use rand_distr::Exp;
+
+use desim::event_list::{BoxedEventList, CalendarQueue, HeapEventList};
+use desim::prelude::*;
+use desim::resources::SimpleResource;
+use CarState::*;
+
+const NUM_MACHINES: usize = 2_500;
+const NUM_CARS: usize = 100_000;
+const LAMBDA_DRIVE: f64 = 100.0; // Each car drives for ~100 minutes between washes
+const LAMBDA_WASH: f64 = 2.0; // It takes ~2 minutes to wash a car
+const EVENTS: usize = 10_000_000;
+
+#[derive(Copy, Clone, Debug)]
+enum CarState {
+    Drive(f64),
+    WaitMachine(ResourceId),
+    Wash(f64),
+    Leave(ResourceId),
+}
+
+impl SimState for CarState {
+    fn get_effect(&self) -> Effect {
+        match self {
+            Drive(t) => Effect::TimeOut(*t),
+            WaitMachine(r) => Effect::Request(*r),
+            Wash(t) => Effect::TimeOut(*t),
+            Leave(r) => Effect::Release(*r),
+        }
+    }
+    fn set_effect(&mut self, _: Effect) {}
+    fn should_log(&self) -> bool {
+        false
+    }
+}
+
+fn carwash(list: BoxedEventList<CarState>, events: usize) -> f64 {
+    let mut sim = Simulation::builder()
+        .future_event_list(list)
+        .event_capacity(NUM_CARS)
+        .build();
+    let carwash = sim.create_resource(Box::new(SimpleResource::new(NUM_MACHINES)));
+    let distr_drive = Exp::new(1.0 / LAMBDA_DRIVE).unwrap();
+    let distr_wash = Exp::new(1.0 / LAMBDA_WASH).unwrap();
+    for car in 0..NUM_CARS {
+        let mut rng = Rng::seed_from_u64(car as u64);
+        let p = sim.create_process(Box::new(
+            #[coroutine]
+            move |_| loop {
+                yield Drive(distr_drive.sample(&mut rng));
+                yield WaitMachine(carwash);
+                yield Wash(distr_wash.sample(&mut rng));
+                yield Leave(carwash);
+            },
+        ));
+        sim.schedule_event(0.0, p, Drive(0.0)).unwrap();
+    }
+
+    let start = Instant::now();
+    let sim = sim.run(EndCondition::NSteps(events));
+    let elapsed = start.elapsed().as_secs_f64();
+    assert_eq!(sim.steps(), events);
+    elapsed
+}
+
+fn main() {
+    let events = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(EVENTS);
+    let heap = carwash(Box::new(HeapEventList::new()), events);
+    let calendar = carwash(Box::new(CalendarQueue::new()), events);
+    for (name, elapsed) in [("binary heap", heap), ("calendar queue", calendar)] {
+        println!(
+            "{:<16}{:>10.3} s{:>14.0} events/s",
+            name,
+            elapsed,
+            events as f64 / elapsed
+        );
+    }
+}
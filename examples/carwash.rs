@@ -3,10 +3,10 @@
 //! Features shown in this example:
 //! * Custom state
 //! * Simple Resource
+//! * Statistics
 //! * prelude
 
-#![feature(coroutines, coroutine_trait)]
-use std::collections::HashMap;
+#![feature(coroutines)]
 use std::fmt::{Display, Formatter, Result};
 
 use rand::{
@@ -18,6 +18,8 @@ use rand_distr::Exp;
 
 use desim::prelude::*;
 use desim::resources::SimpleResource;
+use desim::stats::Histogram;
+use desim::StatId;
 use CarState::*;
 
 const NUM_MACHINES: usize = 4; // A carwash with 4 machines to wash cars
@@ -32,6 +34,7 @@ enum CarState {
     WaitMachine(ResourceId),
     Wash(f32),
     Leave(ResourceId),
+    Waited(StatId, f64),
 }
 
 impl SimState for CarState {
@@ -41,6 +44,7 @@ impl SimState for CarState {
             WaitMachine(r) => Effect::Request(*r),
             Wash(t) => Effect::TimeOut(*t as f64),
             Leave(r) => Effect::Release(*r),
+            Waited(stat, t) => Effect::Record(*stat, *t),
         }
     }
     fn set_effect(&mut self, _: Effect) {
@@ -58,6 +62,7 @@ impl Display for CarState {
             WaitMachine(_) => write!(f, "Waiting a machine"),
             Wash(t) => write!(f, "Washing for {} minutes", t),
             Leave(_) => write!(f, "Clean! Leaving carwash"),
+            Waited(_, t) => write!(f, "Waited a machine for {} minutes", t),
         }
     }
 }
@@ -65,6 +70,7 @@ impl Display for CarState {
 // Create a car process
 fn car_process<'a>(
     carwash: ResourceId,
+    waits: StatId,
     rng: &'a mut Rng,
     distr_drive: &'a impl Distribution<f32>,
     distr_wash: &'a impl Distribution<f32>,
@@ -76,9 +82,12 @@ fn car_process<'a>(
         #[coroutine]
         move |_| {
             // The car drives for `t_drive` time
-            yield Drive(t_drive);
+            let ctx: SimContext<CarState> = yield Drive(t_drive);
             // Arrives at carwash and waits for a machine to be free
-            yield WaitMachine(carwash);
+            let arrival = ctx.time();
+            let ctx = yield WaitMachine(carwash);
+            // Record how long the car waited
+            yield Waited(waits, ctx.time() - arrival);
             // The car wash for `t_wash` time, keeping the carwash machine (resource) occupied
             yield Wash(t_wash);
             // The car leaves the carwash, freeing the resource
@@ -94,6 +103,9 @@ fn main() {
     // Create the carwash resource: It contains `NUM_MACHINES` machines to wash cars`
     let carwash = sim.create_resource(Box::new(SimpleResource::new(NUM_MACHINES)));
 
+    // Collect the waiting times of the cars
+    let waits = sim.create_tally("wait");
+
     // Create random number genrator and some distributions
     let mut rng = Rng::from_entropy();
     let unif = Uniform::new(0.0, SIM_TIME);
@@ -102,8 +114,14 @@ fn main() {
 
     // Create NUM_CARS car processes and schedule them at random times
     for t in unif.sample_iter(rng.clone()).take(NUM_CARS) {
-        let p = sim.create_process(car_process(carwash, &mut rng, &distr_drive, &distr_wash));
-        sim.schedule_event(t, p, CarState::Drive(0.0));
+        let p = sim.create_process(car_process(
+            carwash,
+            waits,
+            &mut rng,
+            &distr_drive,
+            &distr_wash,
+        ));
+        sim.schedule_event(t, p, CarState::Drive(0.0)).unwrap();
     }
 
     // Run the simulation until all cars have been washed
@@ -114,21 +132,25 @@ fn main() {
         println!("{}\t{}", e.time(), state);
     }
 
-    // Compute average waiting time
-    let mut wait_start_time = HashMap::new();
-
-    let sum: (f64, f64) = sim
-        .processed_events()
-        .iter()
-        .filter_map(|(e, state)| match state {
-            WaitMachine(_) => {
-                wait_start_time.insert(e.process(), e.time());
-                None
-            }
-            Wash(_) => Some(e.time() - wait_start_time.remove(&e.process()).unwrap()),
-            _ => None,
-        })
-        .fold((0.0, 0.0), |(t, c), t0| (t + t0, c + 1.0));
-
-    println!("The average waiting time was: {}", sum.0 / sum.1);
+    // Print the average waiting time
+    let stats = sim.stats();
+    let wait = stats.tally(waits).unwrap();
+    println!(
+        "The average waiting time was: {} (std. dev. {}, max {})",
+        wait.mean(),
+        wait.std_dev(),
+        wait.max().unwrap_or(0.0)
+    );
+
+    // Print the percentiles of the waiting times, collected by the carwash resource
+    let waits = sim
+        .resource_stats(carwash)
+        .wait_histogram(Histogram::log(100));
+    for q in [0.5, 0.95, 0.99] {
+        println!(
+            "{}th percentile of the waiting time: {}",
+            q * 100.0,
+            waits.quantile(q).unwrap_or(0.0)
+        );
+    }
 }
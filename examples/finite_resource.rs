@@ -17,6 +17,7 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //!
 //! Features shown in this example:
 //! * Custom Resources
+//! * Rejected requests and SimContext::outcome
 //! * Prelude
 //! * Custom state
 //! * EndCondition::NoEvents
@@ -39,20 +40,21 @@ const SIM_TIME: f64 = 50.0;
 #[derive(Copy, Clone, Debug)]
 struct State {
     effect: Effect,
-    queue_full: bool,
+    /// Set by the clients that left because the queue was full
+    lost: bool,
 }
 
 impl State {
     fn new(effect: Effect) -> State {
         State {
             effect,
-            queue_full: false,
+            lost: false,
         }
     }
 }
 
 struct FiniteQueue {
-    _quantity: usize,
+    quantity: usize,
     available: usize,
     queue: [Option<Event<State>>; Q_SIZE],
     queue_start: usize,
@@ -72,45 +74,57 @@ impl SimState for State {
 }
 
 impl Resource<State> for FiniteQueue {
-    fn allocate_or_enqueue(&mut self, event: Event<State>) -> Option<Event<State>> {
+    fn allocate_or_enqueue(&mut self, event: Event<State>) -> Vec<Event<State>> {
         if self.available > 0 {
             self.available -= 1;
-            Some(event)
+            vec![event]
         } else if self.queue_len == Q_SIZE {
             let mut event = event;
-            event.state_mut().queue_full = true;
-            Some(event)
+            event.reject();
+            vec![event]
         } else {
             let first_position = (self.queue_start + self.queue_len) % Q_SIZE;
             self.queue[first_position] = Some(event);
             self.queue_len += 1;
-            None
+            Vec::new()
         }
     }
-    fn release_and_schedule_next(&mut self, event: Event<State>) -> Option<Event<State>> {
+    fn release_and_schedule_next(&mut self, event: Event<State>) -> Vec<Event<State>> {
         if self.queue_len > 0 {
             let mut next_event = self.queue[self.queue_start].take().unwrap();
             self.queue_start = (self.queue_start + 1) % Q_SIZE;
             self.queue_len -= 1;
             next_event.set_time(event.time());
-            Some(next_event)
+            vec![next_event]
         } else {
             self.available += 1;
-            None
+            Vec::new()
         }
     }
+    fn capacity(&self) -> Option<usize> {
+        Some(self.quantity)
+    }
+    fn available(&self) -> Option<usize> {
+        Some(self.available)
+    }
+    fn queue_len(&self) -> usize {
+        self.queue_len
+    }
 }
 
 fn client_process(res: ResourceId) -> Box<Process<State>> {
     Box::new(
         #[coroutine]
-        move |response: SimContext<State>| {
-            yield State::new(Effect::Request(res));
-            if !response.state().queue_full {
+        move |_: SimContext<State>| {
+            let response = yield State::new(Effect::Request(res));
+            if response.outcome() == Outcome::Rejected(res) {
+                yield State {
+                    effect: Effect::Trace,
+                    lost: true,
+                };
+            } else {
                 yield State::new(Effect::TimeOut(5.0));
                 yield State::new(Effect::Release(res));
-            } else {
-                yield State::new(Effect::Trace);
             }
         },
     )
@@ -122,7 +136,7 @@ fn main() {
     let rng = Rng::from_entropy();
 
     let res = FiniteQueue {
-        _quantity: 4,
+        quantity: 4,
         available: 4,
         queue: [None; Q_SIZE],
         queue_start: 0,
@@ -133,7 +147,8 @@ fn main() {
     // Create NUM_CLIENTS processes and schedule them at random times
     for t in unif.sample_iter(rng).take(NUM_CLIENTS) {
         let p = sim.create_process(client_process(res));
-        sim.schedule_event(t, p, State::new(Effect::TimeOut(0.0)));
+        sim.schedule_event(t, p, State::new(Effect::TimeOut(0.0)))
+            .unwrap();
     }
 
     sim = sim.run(EndCondition::NoEvents);
@@ -146,7 +161,7 @@ fn main() {
         "Lost clients: {}",
         sim.processed_events()
             .iter()
-            .filter(|(e, _)| e.state().queue_full)
+            .filter(|(_, state)| state.lost)
             .count()
     );
 }
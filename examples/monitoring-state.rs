@@ -11,7 +11,7 @@
 // API so that the code written inside the process coroutine is simple and can
 // be followed naturally using domain-specific notions.
 //
-#![feature(coroutines, coroutine_trait)]
+#![feature(coroutines)]
 use desim::prelude::*;
 use desim::resources::SimpleResource;
 use rand::rngs::SmallRng as Rng;
@@ -203,7 +203,8 @@ fn main() {
                 log: true,
                 stage: PCBStage::Init,
             },
-        );
+        )
+        .unwrap();
     }
     s = s.run(EndCondition::Time(500.0));
     let evts = s.processed_events();
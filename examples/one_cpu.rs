@@ -5,7 +5,7 @@
 //! * Effects
 //! * EndCondition::Time
 //! * Simulation
-#![feature(coroutines, coroutine_trait)]
+#![feature(coroutines)]
 use rand::{rngs::SmallRng as Rng, RngCore as RngT, SeedableRng};
 
 use desim::resources::SimpleResource;
@@ -42,9 +42,9 @@ fn main() {
         },
     ));
     // let p1 to start immediately...
-    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.)).unwrap();
     // ...and p2 after 17 time units
-    s.schedule_event(17.0, p2, Effect::TimeOut(0.));
+    s.schedule_event(17.0, p2, Effect::TimeOut(0.)).unwrap();
 
     s = s.run(EndCondition::Time(100.0));
 
@@ -1,20 +1,22 @@
 //! A very simple example that simulate the scheduling of two processes on one CPU
 //!
 //! Features shown in this example:
-//! * SimpleStore
+//! * ItemStore and the Payload trait
 //! * Effects
 //! * EndCondition::NoEvents
 //! * Simulation
-#![feature(coroutines, coroutine_trait)]
+#![feature(coroutines)]
 
-use desim::resources::SimpleStore;
-use desim::{Effect, EndCondition, SimState, Simulation, StoreId};
+use desim::resources::{ItemStore, Payload};
+use desim::{Effect, EndCondition, SimContext, SimState, Simulation, StoreId};
 #[derive(Default, Clone, Debug)]
 enum MyState {
     #[default]
     Continue,
-    Push(StoreId, u32),
-    Pull(StoreId),
+    /// Push the value, taken out of the state by the store
+    Push(StoreId, Option<u32>),
+    /// Pull a value, put into the state by the store
+    Pull(StoreId, Option<u32>),
     Wait(f64),
 }
 
@@ -23,15 +25,15 @@ impl SimState for MyState {
         match self {
             MyState::Continue => Effect::TimeOut(0.),
             MyState::Push(id, _) => Effect::Push(*id),
-            MyState::Pull(id) => Effect::Pull(*id),
+            MyState::Pull(id, _) => Effect::Pull(*id),
             MyState::Wait(time) => Effect::TimeOut(*time),
         }
     }
 
     fn set_effect(&mut self, effect: Effect) {
         *self = match effect {
-            Effect::Push(id) => MyState::Push(id, 0),
-            Effect::Pull(id) => MyState::Pull(id),
+            Effect::Push(id) => MyState::Push(id, None),
+            Effect::Pull(id) => MyState::Pull(id, None),
             _ => unimplemented!(),
         };
     }
@@ -41,15 +43,30 @@ impl SimState for MyState {
     }
 }
 
+impl Payload<u32> for MyState {
+    fn take_item(&mut self) -> Option<u32> {
+        match self {
+            MyState::Push(_, value) => value.take(),
+            _ => None,
+        }
+    }
+
+    fn put_item(&mut self, item: u32) {
+        if let MyState::Pull(_, value) = self {
+            *value = Some(item);
+        }
+    }
+}
+
 fn main() {
     let mut s = Simulation::new();
-    let queue = s.create_store(Box::new(SimpleStore::new(1)));
+    let queue = s.create_store(Box::new(ItemStore::new(1)));
     let p1 = s.create_process(Box::new(
         #[coroutine]
         move |_| {
             for i in 0..10 {
                 // wait for the cpu to be available
-                yield MyState::Push(queue, i);
+                yield MyState::Push(queue, Some(i));
                 // do some job that requires a fixed amount of 5 time units
                 // release the CPU
                 yield MyState::Wait(10.0);
@@ -58,11 +75,13 @@ fn main() {
     ));
     let p2 = s.create_process(Box::new(
         #[coroutine]
-        move |_| {
+        move |_: SimContext<MyState>| {
             for _ in 0..10 {
                 // wait for the CPU
-                let ret = yield MyState::Pull(queue);
-                println!("ret: {:?}", ret);
+                let ret = yield MyState::Pull(queue, None);
+                if let MyState::Pull(_, Some(value)) = ret.state() {
+                    println!("pulled: {}", value);
+                }
                 // do some job for a random amount of time units between 0 and 10
                 // yield MyState::Wait(10.0);
                 // release the CPU
@@ -70,9 +89,9 @@ fn main() {
         },
     ));
     // let p1 to start immediately...
-    s.schedule_event(0.0, p1, MyState::default());
+    s.schedule_event(0.0, p1, MyState::default()).unwrap();
     // ...and p2 after 17 time units
-    s.schedule_event(17.0, p2, MyState::default());
+    s.schedule_event(17.0, p2, MyState::default()).unwrap();
 
     s = s.run(EndCondition::NoEvents);
 
@@ -0,0 +1,101 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Running simulations on a background thread.
+//!
+//! Available with the `send` feature. `Simulation::nonblocking_run` moves the simulation
+//! to a new thread and returns a `RunHandle`, that can be used to query the progress of
+//! the run and to get the simulation back when it ends.
+use crate::{EndCondition, SimState, Simulation};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// The progress of a simulation running on another thread.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Progress {
+    /// Current simulation time
+    pub time: f64,
+    /// Number of steps executed
+    pub steps: usize,
+}
+
+/// A handle to a simulation running on another thread.
+pub struct RunHandle<T: SimState + Clone> {
+    thread: JoinHandle<Simulation<T>>,
+    requests: Sender<()>,
+    replies: Receiver<Progress>,
+}
+
+impl<T: SimState + Clone> RunHandle<T> {
+    /// Returns the progress of the simulation.
+    ///
+    /// The running thread answers between two steps, so this method blocks for the duration
+    /// of a step at most. Returns `None` if the run already ended.
+    pub fn progress(&self) -> Option<Progress> {
+        self.requests.send(()).ok()?;
+        self.replies.recv().ok()
+    }
+
+    /// Returns `true` if the run ended
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the end of the run and return the simulation.
+    ///
+    /// Returns an error if the simulation panicked.
+    pub fn join(self) -> thread::Result<Simulation<T>> {
+        self.thread.join()
+    }
+}
+
+impl<T: 'static + SimState + Clone + Send> Simulation<T> {
+    /// Run the simulation on another thread until an ending condition is met.
+    ///
+    /// Like `run`, the run stops if a step fails.
+    pub fn nonblocking_run(mut self, until: EndCondition) -> RunHandle<T> {
+        let (requests, incoming) = channel();
+        let (outgoing, replies) = channel();
+        let thread = thread::spawn(move || {
+            self.start_run();
+            loop {
+                while incoming.try_recv().is_ok() {
+                    let progress = Progress {
+                        time: self.time(),
+                        steps: self.steps(),
+                    };
+                    // the handle may have been dropped
+                    let _ = outgoing.send(progress);
+                }
+                if self.should_stop(&until) {
+                    break;
+                }
+                if let Err(e) = self.step() {
+                    self.fail(e);
+                    break;
+                }
+            }
+            self.end_run();
+            self
+        });
+        RunHandle {
+            thread,
+            requests,
+            replies,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
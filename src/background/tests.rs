@@ -0,0 +1,35 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn nonblocking_run() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let handle = s.nonblocking_run(EndCondition::Time(1000.0));
+    if let Some(progress) = handle.progress() {
+        assert!(progress.time <= 1000.0);
+    }
+    let s = handle.join().unwrap();
+    assert_eq!(s.time(), 1000.0);
+    assert_eq!(s.steps(), 1001);
+}
@@ -0,0 +1,121 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Breakdowns of resources.
+//!
+//! A resource breaks down when a process yields `Effect::Fail`: the processes holding it
+//! are interrupted and lose their units, and the resource grants nothing until a process
+//! yields `Effect::Repair`. The processes that request it in the meantime wait in its
+//! queue. A process that was preempted can tell with `SimContext::interrupted()`, and
+//! must request the resource again if it still needs it.
+//!
+//! `Simulation::attach_breakdown` adds a failure process that breaks a resource down
+//! and repairs it over and over, after times drawn by samplers, e.g. exponential times
+//! with the mean time between failures and the mean time to repair.
+//!
+//! The failure process never completes, so it always has an event scheduled: a run with
+//! `EndCondition::NoEvents` would never end. Run until a time, or stop the failure process
+//! with `Simulation::terminate_process` when the rest of the model is done.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::resources::SimpleResource;
+//! use desim::{Effect, EndCondition, SimContext, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let machine = sim.create_resource(Box::new(SimpleResource::new(1)));
+//! let failure = sim.attach_breakdown(machine, Box::new(|| 5.0), Box::new(|| 1.0), Effect::Trace);
+//! let job = sim.create_process(Box::new(#[coroutine] move |_| {
+//!     loop {
+//!         yield Effect::Request(machine);
+//!         let ctx: SimContext<Effect> = yield Effect::TimeOut(3.0);
+//!         if !ctx.interrupted() {
+//!             yield Effect::Release(machine);
+//!             break;
+//!         }
+//!     }
+//! }));
+//! sim.schedule_event(4.0, job, Effect::TimeOut(0.0)).unwrap();
+//! let mut sim = sim.run(EndCondition::Time(20.0));
+//! // the job is preempted at 5, and starts again after the repair
+//! let released = sim.processed_events().iter().find(|(_, s)| matches!(s, Effect::Release(_)));
+//! assert_eq!(released.unwrap().0.time(), 9.0);
+//! // without the failure process, there are no events left
+//! sim.terminate_process(failure, Effect::Trace).unwrap();
+//! assert_eq!(sim.peek_next_time(), None);
+//! ```
+use crate::process::{ProcessFn, ProcessStep, StateMachine};
+use crate::{Effect, ProcessId, ResourceId, SimContext, SimState, Simulation};
+
+/// Draws the times between failures or the repair times
+#[cfg(not(feature = "send"))]
+pub type Sampler = Box<dyn FnMut() -> f64>;
+/// Draws the times between failures or the repair times
+#[cfg(feature = "send")]
+pub type Sampler = Box<dyn FnMut() -> f64 + Send>;
+
+/// The failure process added by `Simulation::attach_breakdown`.
+///
+/// It yields the state it is resumed with, so that it never keeps a state between steps.
+struct Failure {
+    resource: ResourceId,
+    time_to_failure: Sampler,
+    repair_time: Sampler,
+    /// Number of effects yielded so far
+    yielded: usize,
+}
+
+impl<T: SimState + Clone> ProcessFn<T> for Failure {
+    fn resume(&mut self, ctx: SimContext<T>) -> ProcessStep<T> {
+        let effect = match self.yielded % 4 {
+            0 => Effect::TimeOut((self.time_to_failure)()),
+            1 => Effect::Fail(self.resource),
+            2 => Effect::TimeOut((self.repair_time)()),
+            _ => Effect::Repair(self.resource),
+        };
+        self.yielded += 1;
+        let mut state = ctx.state().clone();
+        state.set_effect(effect);
+        ProcessStep::Yield(state)
+    }
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Add a process that breaks `resource` down after a time drawn by `time_to_failure`,
+    /// and repairs it after a time drawn by `repair_time`, forever.
+    ///
+    /// The failure process starts now with `state`, and yields copies of it with the
+    /// effects set, e.g. to interrupt the holders of the resource with it.
+    /// Returns the id of the failure process, that keeps a run with `EndCondition::NoEvents`
+    /// from ending until it is terminated.
+    pub fn attach_breakdown(
+        &mut self,
+        resource: ResourceId,
+        time_to_failure: Sampler,
+        repair_time: Sampler,
+        state: T,
+    ) -> ProcessId {
+        let process = self.create_process(Box::new(StateMachine::new(Failure {
+            resource,
+            time_to_failure,
+            repair_time,
+            yielded: 0,
+        })));
+        let time = self.time();
+        self.schedule_event(time, process, state)
+            .expect("the failure process was just created");
+        process
+    }
+}
@@ -0,0 +1,210 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Configuration of a simulation before it is created.
+//!
+//! A `SimulationBuilder`, returned by `Simulation::builder`, collects the configuration of
+//! a simulation and produces it with `build`. Everything it sets can also be changed
+//! later with the setters of `Simulation`.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::limits::ZeroDelayPolicy;
+//! use desim::sink::TailSink;
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::builder()
+//!     .seed(42)
+//!     .log_sink(Box::new(TailSink::with_capacity(100)))
+//!     .zero_delay_policy(ZeroDelayPolicy::Fail(1000))
+//!     .end_condition(EndCondition::Time(10.0))
+//!     .event_capacity(1024)
+//!     .build();
+//! let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+//!     yield Effect::TimeOut(1.0);
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run_to_end();
+//! assert_eq!(sim.time(), 10.0);
+//! assert_eq!(sim.metadata().seed(), Some(42));
+//! ```
+use crate::event_list::BoxedEventList;
+use crate::limits::{BudgetPolicy, ReleasePolicy, ZeroDelayPolicy};
+use crate::observer::BoxedObserver;
+use crate::progress::ProgressHook;
+use crate::sink::{BoxedLogSink, LogFilter};
+use crate::soak::CheckInterval;
+use crate::{EndCondition, SimState, Simulation};
+
+/// Collects the configuration of a `Simulation`.
+pub struct SimulationBuilder<T> {
+    start_time: f64,
+    seed: Option<(u64, u32)>,
+    log_sink: Option<BoxedLogSink<T>>,
+    log_filter: Option<LogFilter<T>>,
+    observers: Vec<BoxedObserver<T>>,
+    end_condition: Option<EndCondition>,
+    event_capacity: usize,
+    future_event_list: Option<BoxedEventList<T>>,
+    zero_delay_policy: Option<ZeroDelayPolicy>,
+    release_policy: Option<ReleasePolicy>,
+    event_budget: Option<(usize, BudgetPolicy)>,
+    progress_hook: Option<(CheckInterval, ProgressHook)>,
+}
+
+impl<T> Default for SimulationBuilder<T> {
+    fn default() -> Self {
+        SimulationBuilder {
+            start_time: 0.0,
+            seed: None,
+            log_sink: None,
+            log_filter: None,
+            observers: Vec::new(),
+            end_condition: None,
+            event_capacity: 0,
+            future_event_list: None,
+            zero_delay_policy: None,
+            release_policy: None,
+            event_budget: None,
+            progress_hook: None,
+        }
+    }
+}
+
+impl<T: 'static + SimState + Clone> SimulationBuilder<T> {
+    /// Create a builder with the default configuration, that of `Simulation::new`
+    pub fn new() -> SimulationBuilder<T> {
+        SimulationBuilder::default()
+    }
+
+    /// Start the simulation at `time`. See `Simulation::set_start_time`.
+    pub fn start_time(mut self, time: f64) -> Self {
+        self.start_time = time;
+        self
+    }
+
+    /// Seed the random streams of the processes. See `Simulation::with_seed`.
+    pub fn seed(self, seed: u64) -> Self {
+        self.replication(seed, 0)
+    }
+
+    /// Seed the random streams of the processes for a replication of an experiment.
+    /// See `Simulation::set_seed`.
+    pub fn replication(mut self, seed: u64, replication: u32) -> Self {
+        self.seed = Some((seed, replication));
+        self
+    }
+
+    /// Set the destination of the log. See `Simulation::set_log_sink`.
+    pub fn log_sink(mut self, sink: BoxedLogSink<T>) -> Self {
+        self.log_sink = Some(sink);
+        self
+    }
+
+    /// Set the filter of the log. See `Simulation::set_log_filter`.
+    pub fn log_filter(mut self, filter: LogFilter<T>) -> Self {
+        self.log_filter = Some(filter);
+        self
+    }
+
+    /// Add an observer. See `Simulation::add_observer`.
+    pub fn observer(mut self, observer: BoxedObserver<T>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Set the end condition used by `Simulation::run_to_end`.
+    pub fn end_condition(mut self, until: EndCondition) -> Self {
+        self.end_condition = Some(until);
+        self
+    }
+
+    /// Reserve room for `capacity` scheduled events.
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity;
+        self
+    }
+
+    /// Set the data structure of the future events. See `Simulation::set_future_event_list`.
+    pub fn future_event_list(mut self, list: BoxedEventList<T>) -> Self {
+        self.future_event_list = Some(list);
+        self
+    }
+
+    /// Set the zero-delay policy. See `Simulation::set_zero_delay_policy`.
+    pub fn zero_delay_policy(mut self, policy: ZeroDelayPolicy) -> Self {
+        self.zero_delay_policy = Some(policy);
+        self
+    }
+
+    /// Set the release policy. See `Simulation::set_release_policy`.
+    pub fn release_policy(mut self, policy: ReleasePolicy) -> Self {
+        self.release_policy = Some(policy);
+        self
+    }
+
+    /// Set the event budget of the processes. See `Simulation::set_event_budget`.
+    pub fn event_budget(mut self, max_events: usize, policy: BudgetPolicy) -> Self {
+        self.event_budget = Some((max_events, policy));
+        self
+    }
+
+    /// Set the progress hook. See `Simulation::set_progress_hook`.
+    pub fn progress_hook(mut self, interval: CheckInterval, hook: ProgressHook) -> Self {
+        self.progress_hook = Some((interval, hook));
+        self
+    }
+
+    /// Create the simulation
+    pub fn build(self) -> Simulation<T> {
+        let mut sim = Simulation::new();
+        sim.set_start_time(self.start_time);
+        if let Some((seed, replication)) = self.seed {
+            sim.set_seed(seed, replication);
+        }
+        if let Some(sink) = self.log_sink {
+            sim.set_log_sink(sink);
+        }
+        if let Some(filter) = self.log_filter {
+            sim.set_log_filter(filter);
+        }
+        for observer in self.observers {
+            sim.add_observer(observer);
+        }
+        if let Some(until) = self.end_condition {
+            sim.set_end_condition(until);
+        }
+        if let Some(list) = self.future_event_list {
+            sim.set_future_event_list(list);
+        }
+        sim.reserve_events(self.event_capacity);
+        if let Some(policy) = self.zero_delay_policy {
+            sim.set_zero_delay_policy(policy);
+        }
+        if let Some(policy) = self.release_policy {
+            sim.set_release_policy(policy);
+        }
+        if let Some((max_events, policy)) = self.event_budget {
+            sim.set_event_budget(max_events, policy);
+        }
+        if let Some((interval, hook)) = self.progress_hook {
+            sim.set_progress_hook(interval, hook);
+        }
+        sim
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
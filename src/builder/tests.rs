@@ -0,0 +1,45 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::SimulationBuilder;
+use crate::{Effect, EndCondition, EndReason, Simulation};
+
+#[test]
+fn run_to_end() {
+    let build = |until: Option<EndCondition>| {
+        let mut builder = SimulationBuilder::new();
+        if let Some(until) = until {
+            builder = builder.end_condition(until);
+        }
+        let mut s: Simulation<Effect> = builder.seed(7).build();
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            |_| {
+                for _ in 0..5 {
+                    yield Effect::TimeOut(1.0);
+                }
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+        s
+    };
+    let s = build(Some(EndCondition::NSteps(3))).run_to_end();
+    assert_eq!(s.end_reason(), Some(&EndReason::StepLimit));
+    assert_eq!(s.time(), 2.0);
+    // without an end condition, it runs until there are no events
+    let s = build(None).run_to_end();
+    assert_eq!(s.end_reason(), Some(&EndReason::NoEvents));
+    assert_eq!(s.time(), 5.0);
+}
@@ -0,0 +1,81 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Domain events.
+//!
+//! Besides the low level effects that move entities through resources and stores, a model
+//! often has events that are meaningful in its own domain, like "order shipped".
+//! Processes publish them on a topic, created with `Simulation::create_topic`, yielding
+//! `Effect::Publish` with the payload in their state.
+//!
+//! A process that yields `Effect::Subscribe` waits for the next event published on the topic,
+//! and is resumed at the same time with the state of the publisher.
+//! Listeners registered with `Simulation::subscribe` are called on every publication,
+//! e.g. to collect metrics, without being part of the model.
+use crate::{Event, ProcessId};
+
+/// A user callback invoked with the time and the state of every event published on a topic
+#[cfg(not(feature = "send"))]
+pub type Listener<T> = Box<dyn FnMut(f64, &T)>;
+/// A user callback invoked with the time and the state of every event published on a topic
+#[cfg(feature = "send")]
+pub type Listener<T> = Box<dyn FnMut(f64, &T) + Send>;
+
+/// A named channel of domain events.
+pub(crate) struct Topic<T> {
+    name: String,
+    waiting: Vec<Event<T>>,
+    listeners: Vec<Listener<T>>,
+}
+
+impl<T: Clone> Topic<T> {
+    pub(crate) fn new(name: String) -> Topic<T> {
+        Topic {
+            name,
+            waiting: Vec::new(),
+            listeners: Vec::new(),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn add_listener(&mut self, listener: Listener<T>) {
+        self.listeners.push(listener);
+    }
+
+    /// Park the event of a subscribed process until the next publication.
+    pub(crate) fn wait(&mut self, event: Event<T>) {
+        self.waiting.push(event);
+    }
+
+    /// Remove a subscribed process, e.g. because it was interrupted.
+    pub(crate) fn cancel(&mut self, process: ProcessId) {
+        self.waiting.retain(|e| e.process() != process);
+    }
+
+    /// Deliver `state` to the listeners, returning the events that resume the subscribers.
+    pub(crate) fn publish(&mut self, time: f64, state: &T) -> Vec<Event<T>> {
+        for listener in self.listeners.iter_mut() {
+            listener(time, state);
+        }
+        let mut subscribers = std::mem::take(&mut self.waiting);
+        for event in subscribers.iter_mut() {
+            event.set_time(time);
+            event.set_state(state.clone());
+        }
+        subscribers
+    }
+}
@@ -0,0 +1,247 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Controlling a run, e.g. to debug a model.
+//!
+//! `Simulation::run_with_control` returns a `RunControl`, that runs the simulation until an
+//! ending condition is met, like `run`, but can be paused in between. `resume` runs until
+//! a breakpoint is hit, the run is paused with a `PauseHandle`, e.g. from a GUI thread,
+//! or the run ends; `step` executes a single step. Between two calls, the simulation can
+//! be inspected with `simulation`.
+//!
+//! A breakpoint is checked after each step: the run stops when the clock reaches a time,
+//! when a process is resumed or when a process yields a state matching a predicate.
+//! A time breakpoint is hit only once.
+//!
+//! Any run, controlled or not, can be stopped with the `StopHandle` returned by
+//! `Simulation::stop_handle`, e.g. from another thread or a signal handler when the user
+//! presses Ctrl-C: the run stops cleanly before the next step, with `EndReason::Stopped`,
+//! and returns the simulation with the events processed so far.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::control::{Breakpoint, Halt};
+//! use desim::{Effect, EndCondition, EndReason, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let p = sim.create_process(Box::new(#[coroutine] |_| {
+//!     for t in 1..=5 {
+//!         yield Effect::TimeOut(t as f64);
+//!     }
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! let mut control = sim.run_with_control(EndCondition::NoEvents);
+//! let long = control.add_breakpoint(Breakpoint::State(Box::new(|s| {
+//!     matches!(s, Effect::TimeOut(t) if *t > 3.0)
+//! })));
+//! assert_eq!(control.resume(), Halt::Breakpoint(long));
+//! assert_eq!(control.simulation().time(), 6.0);
+//! control.remove_breakpoint(long);
+//! assert_eq!(control.step(), Halt::Stepped);
+//! assert_eq!(control.resume(), Halt::Ended(EndReason::NoEvents));
+//! assert_eq!(sim.time(), 15.0);
+//! ```
+use crate::{EndCondition, EndReason, ProcessId, SimState, Simulation, StepResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Identifies a breakpoint of a `RunControl`.
+pub type BreakpointId = usize;
+
+/// A predicate over the states yielded by the processes
+#[cfg(not(feature = "send"))]
+pub type StatePredicate<T> = Box<dyn Fn(&T) -> bool>;
+/// A predicate over the states yielded by the processes
+#[cfg(feature = "send")]
+pub type StatePredicate<T> = Box<dyn Fn(&T) -> bool + Send>;
+
+/// Where a controlled run stops.
+pub enum Breakpoint<T> {
+    /// When the clock reaches the time
+    Time(f64),
+    /// After the process is resumed
+    Process(ProcessId),
+    /// After a process yields a state matching the predicate
+    State(StatePredicate<T>),
+}
+
+/// Why `RunControl::resume` or `RunControl::step` returned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Halt {
+    /// The step was executed
+    Stepped,
+    /// A breakpoint was hit
+    Breakpoint(BreakpointId),
+    /// The run was paused with a `PauseHandle`
+    Paused,
+    /// The run ended
+    Ended(EndReason),
+}
+
+/// Pauses a controlled run, also from another thread.
+#[derive(Debug, Clone)]
+pub struct PauseHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl PauseHandle {
+    /// Pause the run in progress after the current step, or the next call to
+    /// `RunControl::resume` after its first step
+    pub fn pause(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Stops the runs of a simulation, also from another thread or a signal handler.
+///
+/// Returned by `Simulation::stop_handle`, before or during a run.
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    pub(crate) fn new(flag: Arc<AtomicBool>) -> StopHandle {
+        StopHandle { flag }
+    }
+
+    /// Stop the run in progress before its next step, or the next run if none is in
+    /// progress
+    pub fn stop(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A run of a simulation that can be paused, returned by `Simulation::run_with_control`.
+pub struct RunControl<'a, T: SimState + Clone> {
+    sim: &'a mut Simulation<T>,
+    until: EndCondition,
+    breakpoints: Vec<Option<Breakpoint<T>>>,
+    pause: Arc<AtomicBool>,
+    end: Option<EndReason>,
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Returns a handle that stops the runs of the simulation. See the
+    /// [`control`](crate::control) module.
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle::new(Arc::clone(&self.stop))
+    }
+
+    /// Start a run that can be paused and resumed. See the [`control`](crate::control)
+    /// module.
+    ///
+    /// Like `run`, the run stops if a step fails, and the error is returned by `error`.
+    pub fn run_with_control(&mut self, until: EndCondition) -> RunControl<'_, T> {
+        self.start_run();
+        RunControl {
+            sim: self,
+            until,
+            breakpoints: Vec::new(),
+            pause: Arc::new(AtomicBool::new(false)),
+            end: None,
+        }
+    }
+}
+
+impl<T: 'static + SimState + Clone> RunControl<'_, T> {
+    /// Add a breakpoint, returning an id that can be used to remove it
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint<T>) -> BreakpointId {
+        self.breakpoints.push(Some(breakpoint));
+        self.breakpoints.len() - 1
+    }
+
+    /// Remove a breakpoint
+    pub fn remove_breakpoint(&mut self, breakpoint: BreakpointId) {
+        if let Some(b) = self.breakpoints.get_mut(breakpoint) {
+            *b = None;
+        }
+    }
+
+    /// Returns a handle that pauses the run
+    pub fn pause_handle(&self) -> PauseHandle {
+        PauseHandle {
+            flag: Arc::clone(&self.pause),
+        }
+    }
+
+    /// Returns the simulation, to inspect it while the run is paused
+    pub fn simulation(&self) -> &Simulation<T> {
+        self.sim
+    }
+
+    /// Execute a single step, unless the run ended
+    pub fn step(&mut self) -> Halt {
+        if let Some(halt) = self.ended() {
+            return halt;
+        }
+        match self.sim.step() {
+            Ok(result) => match self.hit(&result) {
+                Some(breakpoint) => Halt::Breakpoint(breakpoint),
+                None => Halt::Stepped,
+            },
+            Err(e) => {
+                self.sim.fail(e.clone());
+                self.end = Some(EndReason::Error(e.clone()));
+                Halt::Ended(EndReason::Error(e))
+            }
+        }
+    }
+
+    /// Run until a breakpoint is hit, the run is paused or it ends
+    pub fn resume(&mut self) -> Halt {
+        loop {
+            match self.step() {
+                Halt::Stepped => {}
+                halt => return halt,
+            }
+            if self.pause.swap(false, Ordering::Relaxed) {
+                return Halt::Paused;
+            }
+        }
+    }
+
+    /// Returns `Halt::Ended` if the run already ended or the ending condition is met
+    fn ended(&mut self) -> Option<Halt> {
+        if self.end.is_none() && self.sim.should_stop(&self.until) {
+            self.end = self.sim.end_reason().cloned();
+        }
+        self.end.clone().map(Halt::Ended)
+    }
+
+    /// Returns the first breakpoint hit by the step that returned `result`
+    fn hit(&mut self, result: &StepResult<T>) -> Option<BreakpointId> {
+        let time = self.sim.time();
+        let (id, breakpoint) = self
+            .breakpoints
+            .iter_mut()
+            .enumerate()
+            .find(|(_, b)| match b {
+                Some(Breakpoint::Time(t)) => time >= *t,
+                Some(Breakpoint::Process(p)) => result.process() == Some(*p),
+                Some(Breakpoint::State(predicate)) => {
+                    matches!(result, StepResult::Executed { state, .. } if predicate(state))
+                }
+                None => false,
+            })?;
+        if let Some(Breakpoint::Time(_)) = breakpoint {
+            *breakpoint = None;
+        }
+        Some(id)
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,120 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{Breakpoint, Halt};
+use crate::{Effect, EndCondition, EndReason, SimError, Simulation};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn breakpoints() {
+    let mut s = Simulation::new();
+    let looping = || {
+        Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::TimeOut(1.0);
+            },
+        )
+    };
+    let p = s.create_process(looping());
+    let q = s.create_process(looping());
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(0.5, q, Effect::TimeOut(0.)).unwrap();
+    let mut control = s.run_with_control(EndCondition::Time(10.0));
+    let at_3 = control.add_breakpoint(Breakpoint::Time(3.0));
+    let on_q = control.add_breakpoint(Breakpoint::Process(q));
+    assert_eq!(control.resume(), Halt::Breakpoint(on_q));
+    assert_eq!(control.simulation().time(), 0.5);
+    control.remove_breakpoint(on_q);
+    assert_eq!(control.resume(), Halt::Breakpoint(at_3));
+    assert_eq!(control.simulation().time(), 3.0);
+    // the time breakpoint is hit only once
+    assert_eq!(control.resume(), Halt::Ended(EndReason::TimeReached));
+    assert_eq!(control.step(), Halt::Ended(EndReason::TimeReached));
+}
+
+#[test]
+fn pause() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let mut control = s.run_with_control(EndCondition::NoEvents);
+    let pause = control.pause_handle();
+    control.add_breakpoint(Breakpoint::State(Box::new(move |_| {
+        pause.pause();
+        false
+    })));
+    assert_eq!(control.resume(), Halt::Paused);
+    assert_eq!(control.simulation().steps(), 1);
+
+    // a pause requested between two calls stops the next one after its first step
+    let mut control = s.run_with_control(EndCondition::NoEvents);
+    control.pause_handle().pause();
+    assert_eq!(control.resume(), Halt::Paused);
+    assert_eq!(control.simulation().steps(), 2);
+}
+
+#[test]
+fn error() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::Record(7, 1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(1.0, p, Effect::TimeOut(0.)).unwrap();
+    let mut control = s.run_with_control(EndCondition::NoEvents);
+    let ended = Halt::Ended(EndReason::Error(SimError::UnknownStat(7)));
+    assert_eq!(control.resume(), ended);
+    // the run does not go on after the error
+    assert_eq!(control.step(), ended);
+    assert_eq!(control.simulation().steps(), 1);
+}
+
+#[test]
+fn stop_handle() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    // a stop requested before the run stops it before the first step
+    let stop = s.stop_handle();
+    stop.stop();
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.end_reason(), Some(&EndReason::Stopped));
+    assert_eq!(s.steps(), 0);
+
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        stop.stop();
+    });
+    s = s.run(EndCondition::NoEvents);
+    stopper.join().unwrap();
+    assert_eq!(s.end_reason(), Some(&EndReason::Stopped));
+    assert!(s.steps() > 0);
+    assert_eq!(s.processed_events().len(), s.steps());
+}
@@ -0,0 +1,265 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Coupling of simulations built separately.
+//!
+//! A `CoSim` advances two or more simulations in lock-step, e.g. a network model and a
+//! compute model, possibly with different state types. Time is divided in windows of a
+//! fixed length: in each window, every model executes its events up to the end of the
+//! window, then the models exchange the messages sent in the window, at the
+//! synchronization point.
+//!
+//! A message is an event for a process of another model, sent with the `Link` returned
+//! when that model was added, e.g. captured by the processes of the sender. It is
+//! scheduled in the receiving model at its timestamp or, if the receiver already executed
+//! events after it, at the time of the last one. Messages with a timestamp at least one
+//! window ahead of the time they are sent, i.e. whose lookahead is at least the length of
+//! the windows, are always received at their timestamp.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::cosim::CoSim;
+//! use desim::{Effect, SimContext, Simulation};
+//!
+//! let mut cosim = CoSim::new(1.0);
+//! let mut compute = Simulation::new();
+//! let server = compute.create_process(Box::new(#[coroutine] |_| loop {
+//!     yield Effect::Wait;
+//! }));
+//! let to_compute = cosim.add_model(compute);
+//!
+//! let mut network = Simulation::new();
+//! let link = to_compute.clone();
+//! let client = network.create_process(Box::new(#[coroutine] move |mut ctx: SimContext<Effect>| {
+//!     for _ in 0..3 {
+//!         ctx = yield Effect::TimeOut(2.0);
+//!         // the request reaches the server after the latency of the network
+//!         link.send(ctx.time() + 1.0, server, Effect::TimeOut(0.0)).unwrap();
+//!     }
+//! }));
+//! network.schedule_event(0.0, client, Effect::TimeOut(0.0)).unwrap();
+//! cosim.add_model(network);
+//!
+//! cosim.run(10.0).unwrap();
+//! let received: Vec<f64> = cosim
+//!     .model(&to_compute)
+//!     .processed_events()
+//!     .iter()
+//!     .map(|(event, _)| event.time())
+//!     .collect();
+//! assert_eq!(received, [3.0, 5.0, 7.0]);
+//! ```
+use crate::error::SimError;
+use crate::{ProcessId, SimState, Simulation};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+/// The index of a model in a co-simulation
+pub type ModelId = usize;
+
+/// The messages sent to a model and not yet received.
+type Outbox<T> = Arc<Mutex<Vec<(f64, ProcessId, T)>>>;
+
+/// A handle to send messages to a model of a co-simulation.
+pub struct Link<T> {
+    model: ModelId,
+    outbox: Outbox<T>,
+}
+
+impl<T> Link<T> {
+    pub(crate) fn new(model: ModelId) -> Link<T> {
+        Link {
+            model,
+            outbox: Outbox::default(),
+        }
+    }
+
+    /// Take the messages sent and not yet received
+    pub(crate) fn take_sent(&self) -> Vec<(f64, ProcessId, T)> {
+        std::mem::take(&mut *self.outbox.lock().unwrap())
+    }
+
+    /// Returns the model the messages are sent to
+    pub fn model(&self) -> ModelId {
+        self.model
+    }
+
+    /// Send a message that resumes `process` with `state` at the absolute `time`.
+    ///
+    /// The message is received at the next synchronization point.
+    /// Returns an error if `time` is NaN.
+    pub fn send(&self, time: f64, process: ProcessId, state: T) -> Result<(), SimError> {
+        if time.is_nan() {
+            return Err(SimError::InvalidTime(process));
+        }
+        self.outbox.lock().unwrap().push((time, process, state));
+        Ok(())
+    }
+}
+
+impl<T> Clone for Link<T> {
+    fn clone(&self) -> Self {
+        Link {
+            model: self.model,
+            outbox: Arc::clone(&self.outbox),
+        }
+    }
+}
+
+/// A model of a co-simulation, whatever its state type.
+trait Model {
+    fn advance_to(&mut self, time: f64) -> Result<(), SimError>;
+    fn receive(&mut self) -> Result<(), SimError>;
+    fn next_time(&self) -> Option<f64>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A simulation with the link of the messages sent to it.
+struct Coupled<T: SimState + Clone> {
+    sim: Simulation<T>,
+    link: Link<T>,
+}
+
+impl<T: 'static + SimState + Clone> Model for Coupled<T> {
+    /// Execute the events up to `time`, included.
+    fn advance_to(&mut self, time: f64) -> Result<(), SimError> {
+        while self.sim.peek_next_time().is_some_and(|next| next <= time) {
+            if let Err(e) = self.sim.step() {
+                self.sim.fail(e.clone());
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Schedule the messages sent to the simulation.
+    fn receive(&mut self) -> Result<(), SimError> {
+        for (time, process, state) in self.link.take_sent() {
+            self.sim
+                .schedule_event(time.max(self.sim.time()), process, state)?;
+        }
+        Ok(())
+    }
+
+    fn next_time(&self) -> Option<f64> {
+        self.sim.peek_next_time()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        &self.sim
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        &mut self.sim
+    }
+}
+
+/// Advances several simulations in lock-step, exchanging messages between them.
+pub struct CoSim {
+    models: Vec<Box<dyn Model>>,
+    window: f64,
+    time: f64,
+}
+
+impl CoSim {
+    /// Create a co-simulation that synchronizes the models every `window` time units.
+    ///
+    /// # Panics
+    /// Panics if `window` is not positive and finite.
+    pub fn new(window: f64) -> CoSim {
+        assert!(
+            window > 0.0 && window.is_finite(),
+            "ERROR. The synchronization window must be positive and finite."
+        );
+        CoSim {
+            models: Vec::new(),
+            window,
+            time: 0.0,
+        }
+    }
+
+    /// Add a model, returning the link to send messages to it.
+    pub fn add_model<T: 'static + SimState + Clone>(&mut self, sim: Simulation<T>) -> Link<T> {
+        let link = Link::new(self.models.len());
+        self.models.push(Box::new(Coupled {
+            sim,
+            link: link.clone(),
+        }));
+        link
+    }
+
+    /// Returns the model that `link` sends messages to
+    pub fn model<T: 'static + SimState + Clone>(&self, link: &Link<T>) -> &Simulation<T> {
+        self.models[link.model]
+            .as_any()
+            .downcast_ref()
+            .expect("ERROR. The link belongs to another co-simulation.")
+    }
+
+    /// Returns the model that `link` sends messages to, e.g. to schedule its first events
+    pub fn model_mut<T: 'static + SimState + Clone>(
+        &mut self,
+        link: &Link<T>,
+    ) -> &mut Simulation<T> {
+        self.models[link.model]
+            .as_any_mut()
+            .downcast_mut()
+            .expect("ERROR. The link belongs to another co-simulation.")
+    }
+
+    /// Returns the time of the last synchronization point
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the time of the next event of any model, if there is one
+    pub fn next_time(&self) -> Option<f64> {
+        self.models
+            .iter()
+            .filter_map(|m| m.next_time())
+            .min_by(f64::total_cmp)
+    }
+
+    /// Advance all the models to the end of the next window and exchange the messages.
+    ///
+    /// Returns the error of the first model whose step failed, if any. The error is also
+    /// recorded by the model, and returned by its `error()`.
+    pub fn step(&mut self) -> Result<(), SimError> {
+        self.advance_to(self.time + self.window)
+    }
+
+    /// Advance all the models window by window, until `time`.
+    pub fn run(&mut self, time: f64) -> Result<(), SimError> {
+        while self.time < time {
+            self.advance_to((self.time + self.window).min(time))?;
+        }
+        Ok(())
+    }
+
+    fn advance_to(&mut self, time: f64) -> Result<(), SimError> {
+        for model in self.models.iter_mut() {
+            model.advance_to(time)?;
+        }
+        for model in self.models.iter_mut() {
+            model.receive()?;
+        }
+        self.time = time;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,67 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::CoSim;
+use crate::{Effect, SimContext, Simulation};
+
+#[test]
+fn exchange() {
+    let mut cosim = CoSim::new(2.0);
+    // a ping-pong between two models, with a lookahead shorter than the window
+    let ping = cosim.add_model(Simulation::<Effect>::new());
+    let pong = cosim.add_model(Simulation::<Effect>::new());
+    let (to_ping, to_pong) = (ping.clone(), pong.clone());
+    let pinger = cosim.model_mut(&ping).create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            let ctx: SimContext<Effect> = yield Effect::Wait;
+            assert_eq!(ctx.time(), 1.8);
+        },
+    ));
+    let ponger = cosim.model_mut(&pong).create_process(Box::new(
+        #[coroutine]
+        move |ctx: SimContext<Effect>| {
+            to_ping
+                .send(ctx.time() + 0.5, pinger, Effect::TimeOut(0.))
+                .unwrap();
+        },
+    ));
+    let starter = cosim.model_mut(&ping).create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(0.5);
+            to_pong.send(1.0, ponger, Effect::TimeOut(0.)).unwrap();
+            yield Effect::TimeOut(1.3);
+        },
+    ));
+    cosim
+        .model_mut(&ping)
+        .schedule_event(0.0, starter, Effect::TimeOut(0.))
+        .unwrap();
+    cosim
+        .model_mut(&ping)
+        .schedule_event(0.0, pinger, Effect::TimeOut(0.))
+        .unwrap();
+    assert_eq!(cosim.next_time(), Some(0.0));
+    cosim.step().unwrap();
+    // the message to pong is received at its timestamp
+    assert_eq!(cosim.model(&pong).peek_next_time(), Some(1.0));
+    cosim.run(5.0).unwrap();
+    assert_eq!(cosim.time(), 5.0);
+    // the answer for 1.5 is received at 1.8, after the last event of ping
+    assert_eq!(cosim.model(&ping).time(), 1.8);
+    assert_eq!(cosim.model(&ping).active_processes(), 0);
+    assert!(pong.send(f64::NAN, ponger, Effect::Trace).is_err());
+}
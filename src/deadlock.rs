@@ -0,0 +1,87 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Detection of deadlocks.
+//!
+//! A process can be parked: in the queue of a resource or store, waiting for a signal, a
+//! gate, a message or a domain event, waiting for the effects of a condition, or after
+//! yielding `Effect::Wait`. `Simulation::blocked_processes` returns the processes that
+//! are parked and the reason.
+//!
+//! If there are no more events to execute while some processes are parked, nothing can
+//! ever wake them up from inside the model. A run with `EndCondition::NoEvents` then ends
+//! with `EndReason::Deadlock`, that lists them, instead of `EndReason::NoEvents`.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::deadlock::{BlockedProcess, WaitingFor};
+//! use desim::resources::SimpleResource;
+//! use desim::{Effect, EndCondition, EndReason, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let r = sim.create_resource(Box::new(SimpleResource::new(1)));
+//! // each process forgets to release the resource
+//! let mut processes = Vec::new();
+//! for _ in 0..2 {
+//!     let p = sim.create_process(Box::new(#[coroutine] move |_| {
+//!         yield Effect::Request(r);
+//!         yield Effect::Wait;
+//!     }));
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!     processes.push(p);
+//! }
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(
+//!     sim.end_reason(),
+//!     Some(&EndReason::Deadlock(vec![
+//!         BlockedProcess { process: processes[0], waiting_for: WaitingFor::Resume },
+//!         BlockedProcess { process: processes[1], waiting_for: WaitingFor::Resource(r) },
+//!     ]))
+//! );
+//! ```
+use crate::{GateId, MailboxId, PredicateId, ProcessId, ResourceId, SignalId, StoreId, TopicId};
+
+/// What a parked process is waiting for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WaitingFor {
+    /// To be granted a resource
+    Resource(ResourceId),
+    /// To push into or pull from a store
+    Store(StoreId),
+    /// A domain event published on a topic
+    Topic(TopicId),
+    /// A signal to be fired
+    Signal(SignalId),
+    /// A gate to be opened
+    Gate(GateId),
+    /// To send to or receive from a mailbox
+    Mailbox(MailboxId),
+    /// A predicate to hold, after yielding `Effect::WaitUntil`
+    Predicate(PredicateId),
+    /// The effects of an `AnyOf`, `AllOf` or `Select` condition
+    Condition,
+    /// To be resumed with `Simulation::resume_process`, after yielding `Effect::Wait`
+    Resume,
+}
+
+/// A process that is parked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedProcess {
+    /// The parked process
+    pub process: ProcessId,
+    /// What it is waiting for
+    pub waiting_for: WaitingFor,
+}
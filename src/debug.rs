@@ -0,0 +1,275 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Timelines of the processes, to debug a model.
+//!
+//! A `Timeline` is the ordered sequence of what happened to a process: the effects it
+//! yielded, the resources it was granted and its completion. It answers questions like
+//! "what was process 42 waiting for at t=310?" with `waiting_for`, and prints as a compact
+//! list of entries, one per line.
+//!
+//! `Simulation::timelines` rebuilds the timelines from the log of processed events, where
+//! grants are the events that resume a process after it requested a resource, and
+//! completions are not recorded. A `TimelineRecorder` registered as an observer builds them
+//! live, from the notifications of the simulation, including completions.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::debug::TimelineRecorder;
+//! use desim::resources::SimpleResource;
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let r = sim.create_resource(Box::new(SimpleResource::new(1)));
+//! let mut processes = Vec::new();
+//! for _ in 0..2 {
+//!     let p = sim.create_process(Box::new(#[coroutine] move |_| {
+//!         yield Effect::Request(r);
+//!         yield Effect::TimeOut(5.0);
+//!         yield Effect::Release(r);
+//!     }));
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!     processes.push(p);
+//! }
+//! let recorder = TimelineRecorder::new();
+//! let timelines = recorder.timelines();
+//! sim.add_observer(Box::new(recorder));
+//! let sim = sim.run(EndCondition::NoEvents);
+//!
+//! let timelines = timelines.lock().unwrap();
+//! assert!(matches!(timelines.get(processes[1]).unwrap().waiting_for(3.0), Some(Effect::Request(_))));
+//! assert_eq!(timelines.get(processes[1]).unwrap().completed(), Some(10.0));
+//! assert_eq!(sim.timelines().get(processes[1]).unwrap().completed(), None);
+//! assert!(timelines.to_string().starts_with("process 0:\n  t=0 yield Request"));
+//! ```
+use crate::observer::{ResourceChange, SimulationObserver};
+use crate::{Effect, Event, ProcessId, ResourceId, SimState, Simulation};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Rebuild the timelines of the processes from the log of processed events.
+    /// See the [`debug`](crate::debug) module.
+    pub fn timelines(&self) -> Timelines {
+        Timelines::from_log(self.processed_events())
+    }
+}
+
+/// Something that happened to a process.
+#[derive(Debug, Copy, Clone)]
+pub enum TimelineEntry {
+    /// The process was resumed and yielded an effect
+    Yielded { time: f64, effect: Effect },
+    /// The process was granted units of a resource
+    Granted {
+        time: f64,
+        resource: ResourceId,
+        units: usize,
+    },
+    /// The process completed or was terminated
+    Completed { time: f64 },
+}
+
+impl TimelineEntry {
+    /// Returns the time of the entry
+    pub fn time(&self) -> f64 {
+        match *self {
+            TimelineEntry::Yielded { time, .. }
+            | TimelineEntry::Granted { time, .. }
+            | TimelineEntry::Completed { time } => time,
+        }
+    }
+}
+
+impl fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelineEntry::Yielded { time, effect } => write!(f, "t={} yield {:?}", time, effect),
+            TimelineEntry::Granted {
+                time,
+                resource,
+                units,
+            } => write!(f, "t={} granted {} of resource {}", time, units, resource),
+            TimelineEntry::Completed { time } => write!(f, "t={} completed", time),
+        }
+    }
+}
+
+/// The timeline of a process.
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    entries: Vec<TimelineEntry>,
+}
+
+impl Timeline {
+    /// Returns the entries, in order of time
+    pub fn entries(&self) -> &[TimelineEntry] {
+        &self.entries
+    }
+
+    /// Returns the effect the process was waiting for at `time`: the last one it yielded
+    /// until then, or `None` if it was not started yet or it already completed
+    pub fn waiting_for(&self, time: f64) -> Option<Effect> {
+        let end = self.entries.partition_point(|e| e.time() <= time);
+        self.entries[..end].iter().rev().find_map(|e| match *e {
+            TimelineEntry::Yielded { effect, .. } => Some(Some(effect)),
+            TimelineEntry::Completed { .. } => Some(None),
+            TimelineEntry::Granted { .. } => None,
+        })?
+    }
+
+    /// Returns the time the process completed, if it was recorded
+    pub fn completed(&self) -> Option<f64> {
+        match self.entries.last() {
+            Some(TimelineEntry::Completed { time }) => Some(*time),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "  {}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// The timelines of all the processes.
+#[derive(Debug, Clone, Default)]
+pub struct Timelines {
+    timelines: BTreeMap<ProcessId, Timeline>,
+}
+
+impl Timelines {
+    /// Rebuild the timelines from a log of processed events
+    pub fn from_log<T: SimState>(log: &[(Event<T>, T)]) -> Timelines {
+        let mut timelines = Timelines::default();
+        // requests with a timeout, granted if the next event of the process says so
+        let mut timed_requests = HashMap::new();
+        for (event, state) in log {
+            if let Some(resource) = timed_requests.remove(&event.process()) {
+                if event.granted_in_time() {
+                    timelines.grant(event.process(), event.time(), resource, 1);
+                }
+            }
+            if let Effect::RequestTimeout(resource, _) = state.get_effect() {
+                timed_requests.insert(event.process(), resource);
+            }
+            if !event.interrupted {
+                match event.state().get_effect() {
+                    Effect::Request(resource) => {
+                        timelines.grant(event.process(), event.time(), resource, 1)
+                    }
+                    Effect::RequestN(resource, units) => {
+                        timelines.grant(event.process(), event.time(), resource, units)
+                    }
+                    _ => {}
+                }
+            }
+            timelines.record(event, state);
+        }
+        timelines
+    }
+
+    /// Returns the timeline of a process
+    pub fn get(&self, process: ProcessId) -> Option<&Timeline> {
+        self.timelines.get(&process)
+    }
+
+    /// Returns the processes and their timelines, by id
+    pub fn iter(&self) -> impl Iterator<Item = (ProcessId, &Timeline)> {
+        self.timelines.iter().map(|(p, t)| (*p, t))
+    }
+
+    fn entry(&mut self, process: ProcessId) -> &mut Vec<TimelineEntry> {
+        &mut self.timelines.entry(process).or_default().entries
+    }
+
+    fn record<T: SimState>(&mut self, event: &Event<T>, state: &T) {
+        self.entry(event.process()).push(TimelineEntry::Yielded {
+            time: event.time(),
+            effect: state.get_effect(),
+        });
+    }
+
+    fn grant(&mut self, process: ProcessId, time: f64, resource: ResourceId, units: usize) {
+        self.entry(process).push(TimelineEntry::Granted {
+            time,
+            resource,
+            units,
+        });
+    }
+}
+
+impl fmt::Display for Timelines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (process, timeline) in &self.timelines {
+            writeln!(f, "process {}:", process)?;
+            write!(f, "{}", timeline)?;
+        }
+        Ok(())
+    }
+}
+
+/// An observer that builds the timelines of the processes while the simulation runs.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineRecorder {
+    timelines: Arc<Mutex<Timelines>>,
+}
+
+impl TimelineRecorder {
+    /// Create a recorder with empty timelines
+    pub fn new() -> TimelineRecorder {
+        TimelineRecorder::default()
+    }
+
+    /// Returns the timelines, shared with the recorder
+    pub fn timelines(&self) -> Arc<Mutex<Timelines>> {
+        Arc::clone(&self.timelines)
+    }
+}
+
+impl<T: SimState> SimulationObserver<T> for TimelineRecorder {
+    fn on_event_fired(&mut self, event: &Event<T>, state: &T) {
+        self.timelines.lock().unwrap().record(event, state);
+    }
+
+    fn on_process_complete(&mut self, process: ProcessId, time: f64) {
+        let mut timelines = self.timelines.lock().unwrap();
+        timelines
+            .entry(process)
+            .push(TimelineEntry::Completed { time });
+    }
+
+    fn on_resource_state_change(
+        &mut self,
+        resource: ResourceId,
+        time: f64,
+        change: &ResourceChange,
+    ) {
+        if let ResourceChange::Acquired { process, units } = *change {
+            self.timelines
+                .lock()
+                .unwrap()
+                .grant(process, time, resource, units);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
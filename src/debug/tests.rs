@@ -0,0 +1,98 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::TimelineEntry;
+use crate::resources::SimpleResource;
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn timelines_from_log() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let mut processes = Vec::new();
+    for _ in 0..2 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(5.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        processes.push(p);
+    }
+    let s = s.run(EndCondition::NoEvents);
+    let timelines = s.timelines();
+    let second = timelines.get(processes[1]).unwrap();
+    assert!(matches!(
+        second.entries()[..3],
+        [
+            TimelineEntry::Yielded {
+                time: 0.0,
+                effect: Effect::Request(_)
+            },
+            TimelineEntry::Granted {
+                time: 5.0,
+                units: 1,
+                ..
+            },
+            TimelineEntry::Yielded {
+                time: 5.0,
+                effect: Effect::TimeOut(_)
+            },
+        ]
+    ));
+    assert!(matches!(second.waiting_for(4.0), Some(Effect::Request(_))));
+    assert!(matches!(second.waiting_for(7.0), Some(Effect::TimeOut(_))));
+    assert!(second.waiting_for(-1.0).is_none());
+}
+
+#[test]
+fn timed_requests() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let mut processes = Vec::new();
+    for patience in [10.0, 10.0, 2.0] {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::RequestTimeout(r, patience);
+                yield Effect::TimeOut(5.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        processes.push(p);
+    }
+    let s = s.run(EndCondition::NoEvents);
+    let timelines = s.timelines();
+    let grants = |p| {
+        timelines
+            .get(p)
+            .unwrap()
+            .entries()
+            .iter()
+            .filter_map(|e| match *e {
+                TimelineEntry::Granted { time, .. } => Some(time),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(grants(processes[0]), [0.0]);
+    assert_eq!(grants(processes[1]), [5.0]);
+    // the last process gave up at 2
+    assert!(grants(processes[2]).is_empty());
+}
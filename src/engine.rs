@@ -0,0 +1,228 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Performance counters of the simulation engine.
+//!
+//! `Simulation::engine_stats` reports how fast the engine executed the events, e.g. to
+//! tell whether a large model is limited by the engine or by the code of its processes:
+//! the events executed per wall-clock second, the largest number of events scheduled at
+//! once and the events executed per simulated time unit.
+//!
+//! The wall-clock time is measured over the runs executed by `run`, `run_while`, `soak`
+//! and `nonblocking_run`, not over the steps executed with `step` or by the iterators and
+//! controls that give the control back to the caller between the steps. The allocations
+//! made during the runs are counted only with the `count-allocations` feature, if the
+//! program uses `CountingAllocator` as its global allocator.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! for _ in 0..10 {
+//!     let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+//!         yield Effect::TimeOut(1.0);
+//!     }));
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! }
+//! let sim = sim.run(EndCondition::Time(100.0));
+//! let stats = sim.engine_stats();
+//! assert_eq!(stats.max_pending_events(), 10);
+//! // 10 events at each time from 0 to 99, and the first one at 100
+//! assert_eq!(stats.steps(), 1001);
+//! assert_eq!(stats.events_per_time_unit(), Some(10.01));
+//! assert!(stats.events_per_second() > 0.0);
+//! ```
+#[cfg(feature = "count-allocations")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "count-allocations")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The allocations counted by `CountingAllocator`
+#[cfg(feature = "count-allocations")]
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// A global allocator that counts the allocations, reported by `EngineStats::allocations`.
+/// Available with the `count-allocations` feature.
+///
+/// It forwards the allocations to the system allocator. The counter is shared by all the
+/// threads, so it includes the allocations of the other threads running at the same time.
+///
+/// ```
+/// # #![feature(coroutines)]
+/// use desim::engine::CountingAllocator;
+/// use desim::{Effect, EndCondition, Simulation};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+///
+/// fn main() {
+///     let mut sim = Simulation::new();
+///     let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+///         yield Effect::TimeOut(1.0);
+///     }));
+///     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+///     let sim = sim.run(EndCondition::Time(100.0));
+///     assert!(sim.engine_stats().allocations().is_some());
+/// }
+/// ```
+#[cfg(feature = "count-allocations")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "count-allocations")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Returns the allocations counted so far, or `None` if `CountingAllocator` is not the
+/// global allocator.
+#[cfg(feature = "count-allocations")]
+fn allocations() -> Option<u64> {
+    // the program always allocates before a simulation is run
+    Some(ALLOCATIONS.load(Ordering::Relaxed)).filter(|n| *n > 0)
+}
+
+/// The allocations are not counted without the `count-allocations` feature.
+#[cfg(not(feature = "count-allocations"))]
+fn allocations() -> Option<u64> {
+    None
+}
+
+/// The counters updated by the simulation.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EngineCounters {
+    wall_time: Duration,
+    run_steps: usize,
+    allocations: Option<u64>,
+    /// The allocations counted at the start of the current run
+    run_allocations: Option<u64>,
+    max_pending: usize,
+}
+
+impl EngineCounters {
+    /// Record that `pending` events are scheduled.
+    #[inline]
+    pub(crate) fn record_pending(&mut self, pending: usize) {
+        self.max_pending = self.max_pending.max(pending);
+    }
+
+    pub(crate) fn start_run(&mut self) {
+        self.run_allocations = allocations();
+    }
+
+    /// Record the end of a run that executed `steps` steps in `wall_time`.
+    pub(crate) fn end_run(&mut self, steps: usize, wall_time: Duration) {
+        self.wall_time += wall_time;
+        self.run_steps += steps;
+        if let (Some(start), Some(end)) = (self.run_allocations.take(), allocations()) {
+            *self.allocations.get_or_insert(0) += end - start;
+        }
+    }
+
+    pub(crate) fn stats(&self, steps: usize, pending: usize, elapsed: f64) -> EngineStats {
+        EngineStats {
+            steps,
+            run_steps: self.run_steps,
+            wall_time: self.wall_time,
+            allocations: self.allocations,
+            pending,
+            max_pending: self.max_pending.max(pending),
+            elapsed,
+        }
+    }
+}
+
+/// Performance counters of a simulation, returned by `Simulation::engine_stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineStats {
+    steps: usize,
+    run_steps: usize,
+    wall_time: Duration,
+    allocations: Option<u64>,
+    pending: usize,
+    max_pending: usize,
+    elapsed: f64,
+}
+
+impl EngineStats {
+    /// Returns the steps executed
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns the wall-clock time spent in the runs, see the [`engine`](crate::engine)
+    /// module
+    pub fn wall_time(&self) -> Duration {
+        self.wall_time
+    }
+
+    /// Returns the steps executed by the runs per wall-clock second, or 0 if the
+    /// simulation was never run
+    pub fn events_per_second(&self) -> f64 {
+        let secs = self.wall_time.as_secs_f64();
+        if secs > 0.0 {
+            self.run_steps as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the steps executed per simulated time unit, or `None` if the simulation
+    /// time did not advance
+    pub fn events_per_time_unit(&self) -> Option<f64> {
+        (self.elapsed > 0.0).then(|| self.steps as f64 / self.elapsed)
+    }
+
+    /// Returns the number of events scheduled and not executed yet
+    pub fn pending_events(&self) -> usize {
+        self.pending
+    }
+
+    /// Returns the largest number of events scheduled at the same time, the high-water
+    /// mark of the future event list
+    pub fn max_pending_events(&self) -> usize {
+        self.max_pending
+    }
+
+    /// Returns the allocations made during the runs, if counted by `CountingAllocator`
+    pub fn allocations(&self) -> Option<u64> {
+        self.allocations
+    }
+
+    /// Returns the allocations made per step during the runs, if counted by
+    /// `CountingAllocator`
+    pub fn allocations_per_step(&self) -> Option<f64> {
+        let allocations = self.allocations?;
+        (self.run_steps > 0).then(|| allocations as f64 / self.run_steps as f64)
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
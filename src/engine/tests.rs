@@ -0,0 +1,47 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn engine_stats() {
+    let mut s = Simulation::new();
+    for i in 0..5 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                for _ in 0..i {
+                    yield Effect::TimeOut(1.0);
+                }
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    assert_eq!(s.engine_stats().max_pending_events(), 5);
+    assert_eq!(s.engine_stats().events_per_second(), 0.0);
+    // the steps executed one at a time are not timed
+    s.step().unwrap();
+    s = s.run(EndCondition::NoEvents);
+    let stats = s.engine_stats();
+    assert_eq!(stats.steps(), 15);
+    assert_eq!(stats.pending_events(), 0);
+    assert_eq!(stats.max_pending_events(), 5);
+    assert_eq!(stats.events_per_time_unit(), Some(15.0 / 4.0));
+    assert!(stats.wall_time() > std::time::Duration::ZERO);
+    assert!(stats.events_per_second() > 0.0);
+    // the test harness does not use the counting allocator
+    assert_eq!(stats.allocations(), None);
+    assert_eq!(stats.allocations_per_step(), None);
+}
@@ -0,0 +1,168 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Tracking the entities flowing through a model.
+//!
+//! An entity is what flows through the model, e.g. a customer or a part, and is not
+//! necessarily a process: a single process may work on many parts, one after the other,
+//! and a part may be handed from a process to another. A process carries at most one
+//! entity at a time: it creates a new one yielding `Effect::CreateEntity`, takes over an
+//! existing one with `Effect::Carry`, and the entity leaves the model when the process
+//! carrying it yields `Effect::Depart`.
+//!
+//! The events of a process are tagged with the entity it carried when it was resumed,
+//! returned by `Event::entity`, so the history of an entity can be extracted from the log
+//! with `EventLog::events_for_entity`. The simulation records the arrival and departure
+//! of each entity: `Simulation::sojourn_times` returns the time spent in the model by the
+//! entities that left it.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, EntityId, SimContext, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! // a machine that processes three parts, each for 2.0
+//! let machine = sim.create_process(Box::new(#[coroutine] |_| {
+//!     for _ in 0..3 {
+//!         let ctx: SimContext<Effect> = yield Effect::CreateEntity;
+//!         assert!(ctx.entity().is_some());
+//!         yield Effect::TimeOut(2.0);
+//!         yield Effect::Depart;
+//!     }
+//! }));
+//! sim.schedule_event(0.0, machine, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::NoEvents);
+//! let sojourns: Vec<f64> = sim.sojourn_times().iter().map(|(_, t)| *t).collect();
+//! assert_eq!(sojourns, [2.0, 2.0, 2.0]);
+//! let second = EntityId::from_raw(1);
+//! assert_eq!(sim.entity(second).unwrap().arrival(), 2.0);
+//! assert_eq!(sim.log().events_for_entity(second).count(), 2);
+//! ```
+use crate::error::SimError;
+use crate::{EntityId, ProcessId, SimState, Simulation};
+
+/// The arrival and departure of an entity, see `Simulation::entity`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityRecord {
+    arrival: f64,
+    departure: Option<f64>,
+}
+
+impl EntityRecord {
+    /// Returns the time the entity was created
+    pub fn arrival(&self) -> f64 {
+        self.arrival
+    }
+
+    /// Returns the time the entity left the model, if it did
+    pub fn departure(&self) -> Option<f64> {
+        self.departure
+    }
+
+    /// Returns the time spent by the entity in the model, if it left it
+    pub fn sojourn_time(&self) -> Option<f64> {
+        self.departure.map(|departure| departure - self.arrival)
+    }
+}
+
+/// The entities of a simulation.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Entities {
+    records: Vec<EntityRecord>,
+}
+
+impl Entities {
+    pub(crate) fn create(&mut self, time: f64) -> EntityId {
+        self.records.push(EntityRecord {
+            arrival: time,
+            departure: None,
+        });
+        EntityId(self.records.len() - 1)
+    }
+
+    /// Record the departure of `entity`, unless it already left.
+    pub(crate) fn depart(&mut self, entity: EntityId, time: f64) {
+        let record = &mut self.records[entity.0];
+        record.departure.get_or_insert(time);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Create an entity arriving at the current time.
+    /// See the [`entity`](crate::entity) module.
+    pub fn create_entity(&mut self) -> EntityId {
+        self.entities.create(self.time)
+    }
+
+    /// Set the entity carried by a process, or clear it with `None`. Unlike with
+    /// `Effect::Carry`, the entity carried before, if any, is replaced.
+    ///
+    /// Returns an error if the process does not exist or already completed, or if the
+    /// entity does not exist.
+    pub fn set_entity(
+        &mut self,
+        process: ProcessId,
+        entity: Option<EntityId>,
+    ) -> Result<(), SimError> {
+        if let Some(entity) = entity.filter(|e| self.entity(*e).is_none()) {
+            return Err(SimError::UnknownEntity(entity));
+        }
+        self.process_entry(process)?.entity = entity;
+        Ok(())
+    }
+
+    /// Returns the entity carried by a process, if any and if the process did not complete
+    pub fn entity_of(&self, process: ProcessId) -> Option<EntityId> {
+        self.processes.get(process)?.entity
+    }
+
+    /// Record that `entity` left the model at the current time, like `Effect::Depart`.
+    /// Nothing happens if it already left.
+    ///
+    /// # Panics
+    /// Panics if the entity does not exist.
+    pub fn depart_entity(&mut self, entity: EntityId) {
+        self.entities.depart(entity, self.time);
+    }
+
+    /// Returns the arrival and departure of an entity, if it exists
+    pub fn entity(&self, entity: EntityId) -> Option<&EntityRecord> {
+        self.entities.records.get(entity.0)
+    }
+
+    /// Returns all the entities, in the order they were created
+    pub fn entities(&self) -> impl Iterator<Item = (EntityId, &EntityRecord)> {
+        self.entities
+            .records
+            .iter()
+            .enumerate()
+            .map(|(i, record)| (EntityId(i), record))
+    }
+
+    /// Returns the time spent in the model by each entity that left it, in the order the
+    /// entities were created
+    pub fn sojourn_times(&self) -> Vec<(EntityId, f64)> {
+        self.entities()
+            .filter_map(|(entity, record)| Some((entity, record.sojourn_time()?)))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,116 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::error::SimError;
+use crate::{Effect, EndCondition, EntityId, SimContext, Simulation};
+
+#[test]
+fn entities() {
+    let mut s = Simulation::new();
+    let worker = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            let ctx: SimContext<Effect> = yield Effect::CreateEntity;
+            assert_eq!(ctx.created(), Some(0));
+            assert_eq!(ctx.entity(), Some(EntityId::from_raw(0)));
+            yield Effect::TimeOut(2.0);
+            let ctx: SimContext<Effect> = yield Effect::Depart;
+            assert_eq!(ctx.entity(), None);
+            yield Effect::CreateEntity;
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, worker, Effect::TimeOut(0.)).unwrap();
+    s = s.run(EndCondition::NoEvents);
+    let (first, second) = (EntityId::from_raw(0), EntityId::from_raw(1));
+    assert_eq!(s.sojourn_times(), [(first, 2.0)]);
+    assert_eq!(s.entities().count(), 2);
+    assert_eq!(s.entity(second).unwrap().arrival(), 2.0);
+    assert_eq!(s.entity(second).unwrap().departure(), None);
+    let history: Vec<f64> = s
+        .log()
+        .events_for_entity(first)
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(history, [0.0, 2.0]);
+
+    // another process takes the second entity out of the model
+    let courier = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Carry(second);
+            yield Effect::TimeOut(4.0);
+            yield Effect::Depart;
+        },
+    ));
+    assert_eq!(s.entity_of(courier), None);
+    s.schedule_event(s.time(), courier, Effect::TimeOut(0.))
+        .unwrap();
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.entity(second).unwrap().sojourn_time(), Some(5.0));
+    assert!(s.set_entity(courier, Some(second)).is_err());
+
+    let parked = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::Wait;
+        },
+    ));
+    let late = s.create_entity();
+    s.set_entity(parked, Some(late)).unwrap();
+    assert_eq!(s.entity_of(parked), Some(late));
+    s.depart_entity(late);
+    s.depart_entity(late);
+    assert_eq!(s.entity(late).unwrap().sojourn_time(), Some(0.0));
+    let unknown = EntityId::from_raw(10);
+    assert_eq!(
+        s.set_entity(parked, Some(unknown)),
+        Err(SimError::UnknownEntity(unknown))
+    );
+}
+
+#[test]
+fn invalid_carry() {
+    for unknown in [false, true] {
+        let mut s = Simulation::new();
+        let other = s.create_entity();
+        let target = if unknown {
+            EntityId::from_raw(5)
+        } else {
+            other
+        };
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::CreateEntity;
+                yield Effect::TimeOut(1.0);
+                yield Effect::Carry(target);
+                yield Effect::TimeOut(1.0);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        s = s.run(EndCondition::NoEvents);
+        let error = if unknown {
+            SimError::UnknownEntity(target)
+        } else {
+            SimError::AlreadyCarrying {
+                process: p,
+                entity: EntityId::from_raw(1),
+            }
+        };
+        assert_eq!(s.error(), Some(&error));
+        assert_eq!(s.entity_of(p), Some(EntityId::from_raw(1)));
+    }
+}
@@ -0,0 +1,116 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Errors reported by the simulation.
+use crate::{EntityId, FactoryId, GroupId, ProcessId, ResourceId, StatId};
+use std::error::Error;
+use std::fmt;
+
+/// An error reported by a `Simulation`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SimError {
+    /// There is no process with this id in the simulation
+    UnknownProcess(ProcessId),
+    /// The process already completed
+    ProcessCompleted(ProcessId),
+    /// The process is not waiting to be resumed
+    ProcessNotWaiting(ProcessId),
+    /// An event for the process was scheduled at an invalid time, e.g. NaN
+    InvalidTime(ProcessId),
+    /// Too many consecutive steps were executed at the same time, see `ZeroDelayPolicy`.
+    /// `processes` are the ones resumed by those steps.
+    ZeroDelayLoop {
+        process: ProcessId,
+        time: f64,
+        processes: Vec<ProcessId>,
+    },
+    /// The process released a resource it does not hold, see `ReleasePolicy`
+    NotHolding {
+        process: ProcessId,
+        resource: ResourceId,
+    },
+    /// The process requested no units, more units than the capacity of a resource, or
+    /// multiple units of a resource that grants them one at a time, see
+    /// `Resource::supports_units`
+    InvalidUnits {
+        process: ProcessId,
+        resource: ResourceId,
+        units: usize,
+    },
+    /// There is no process factory with this name
+    UnknownFactory(String),
+    /// There is no spawner, process factory, resource factory or store factory with this
+    /// id, see the [`factory`](crate::factory) module
+    UnknownFactoryId(FactoryId),
+    /// There is no entity with this id in the simulation
+    UnknownEntity(EntityId),
+    /// There is no group of resources with this id, or it has no members
+    UnknownGroup(GroupId),
+    /// There is no accumulator of statistics with this id, see `Effect::Record`
+    UnknownStat(StatId),
+    /// The process tried to carry an entity while carrying another one, see `Effect::Carry`
+    AlreadyCarrying {
+        process: ProcessId,
+        entity: EntityId,
+    },
+    /// An instance of a module with this path already exists, see the
+    /// [`module`](crate::module) module
+    DuplicateInstance(String),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::UnknownProcess(p) => write!(f, "unknown process {}", p),
+            SimError::ProcessCompleted(p) => write!(f, "process {} already completed", p),
+            SimError::ProcessNotWaiting(p) => write!(f, "process {} is not waiting", p),
+            SimError::InvalidTime(p) => write!(f, "invalid time for an event of process {}", p),
+            SimError::ZeroDelayLoop {
+                process,
+                time,
+                processes,
+            } => write!(
+                f,
+                "zero-delay loop at t={} among processes {:?}, stopped before resuming process {}",
+                time, processes, process
+            ),
+            SimError::NotHolding { process, resource } => write!(
+                f,
+                "process {} released resource {} that it does not hold",
+                process, resource
+            ),
+            SimError::InvalidUnits {
+                process,
+                resource,
+                units,
+            } => write!(
+                f,
+                "process {} requested {} units of resource {}",
+                process, units, resource
+            ),
+            SimError::UnknownFactory(name) => write!(f, "unknown process factory {}", name),
+            SimError::UnknownFactoryId(id) => write!(f, "unknown process factory {}", id),
+            SimError::UnknownEntity(e) => write!(f, "unknown entity {}", e),
+            SimError::UnknownGroup(g) => write!(f, "unknown or empty resource group {}", g),
+            SimError::UnknownStat(s) => write!(f, "unknown statistic {}", s),
+            SimError::AlreadyCarrying { process, entity } => {
+                write!(f, "process {} already carries entity {}", process, entity)
+            }
+            SimError::DuplicateInstance(path) => write!(f, "instance {} already exists", path),
+        }
+    }
+}
+
+impl Error for SimError {}
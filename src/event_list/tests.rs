@@ -0,0 +1,71 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{CalendarQueue, FutureEventList, HeapEventList};
+use crate::rng::{Philox4x32, StreamRng};
+use crate::{Effect, Event, ProcessId};
+
+#[test]
+fn calendar_queue_order() {
+    let mut rng = Philox4x32::from_stream(3, 0, 0);
+    let mut heap = HeapEventList::new();
+    let mut calendar = CalendarQueue::new();
+    let mut now = 0.0;
+    let mut popped = 0;
+    for id in 0..20_000 {
+        // mostly the near future, with some ties, far events and late events
+        let time = match rng.next_u32() % 10 {
+            0 => now,
+            1 => now + 1000.0 * rng.next_f64(),
+            2 => now - rng.next_f64(),
+            _ => now + rng.next_f64(),
+        };
+        let mut event = Event::new(time, ProcessId(0), Effect::Trace);
+        event.id = id;
+        event.set_priority((rng.next_u32() % 3) as i32);
+        heap.push(event);
+        calendar.push(event);
+        assert_eq!(calendar.len(), heap.len());
+        assert_eq!(calendar.peek().map(Event::id), heap.peek().map(Event::id));
+        // pop less than what is pushed, to grow the queue, then drain it
+        if id % 3 != 0 || id > 15_000 {
+            while id > 15_000 && !heap.is_empty() || popped < id * 2 / 3 {
+                let expected = heap.pop().unwrap();
+                assert_eq!(calendar.pop().map(|e| e.id()), Some(expected.id()));
+                now = expected.time();
+                popped += 1;
+            }
+        }
+    }
+    assert!(calendar.is_empty());
+    assert_eq!(calendar.pop().map(|e| e.id()), None);
+    assert_eq!(calendar.buckets(), 2);
+}
+
+#[test]
+fn infinite_times() {
+    let mut calendar = CalendarQueue::new();
+    for (id, time) in [f64::INFINITY, 1.0, f64::NEG_INFINITY, 2.0]
+        .into_iter()
+        .enumerate()
+    {
+        let mut event = Event::new(time, ProcessId(0), Effect::Trace);
+        event.id = id;
+        calendar.push(event);
+    }
+    assert_eq!(calendar.iter().count(), 4);
+    let times: Vec<f64> = std::iter::from_fn(|| calendar.pop().map(|e| e.time())).collect();
+    assert_eq!(times, [f64::NEG_INFINITY, 1.0, 2.0, f64::INFINITY]);
+}
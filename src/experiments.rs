@@ -0,0 +1,213 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Parameter sweeps.
+//!
+//! Capacity planning studies run the same model with different parameters, e.g. the number
+//! of servers and the arrival rate, and compare some output metrics. An `Experiment` holds
+//! the parameter sets of its scenarios, that can be the combinations of some values built
+//! with `grid`. `Experiment::run` builds the simulation of each scenario with a setup
+//! closure, runs it and collects the metrics returned by another closure in a
+//! `ResultTable`, with a row for each scenario and a column for each metric.
+//!
+//! Scenarios are run one after the other, unless the experiment is made `parallel`.
+//! As for [`replications`](crate::replications), the simulations never leave their thread.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::experiments::{grid, Experiment};
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let experiment = Experiment::new(grid(&[1.0, 2.0], &[1, 3])).parallel();
+//! let table = experiment.run(
+//!     EndCondition::NoEvents,
+//!     |&(delay, n)| {
+//!         let mut sim = Simulation::new();
+//!         let p = sim.create_process(Box::new(#[coroutine] move |_| {
+//!             for _ in 0..n {
+//!                 yield Effect::TimeOut(delay);
+//!             }
+//!         }));
+//!         sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!         sim
+//!     },
+//!     |_, sim| vec![("end", sim.time())],
+//! );
+//! assert_eq!(table.columns(), ["end"]);
+//! assert_eq!(table.get(3, "end"), Some(6.0));
+//! ```
+use crate::replications::run_parallel;
+use crate::{EndCondition, SimState, Simulation};
+use std::io::{self, Write};
+use std::thread;
+
+/// Returns all the combinations of a value of `a` and one of `b`.
+pub fn grid<A: Clone, B: Clone>(a: &[A], b: &[B]) -> Vec<(A, B)> {
+    a.iter()
+        .flat_map(|x| b.iter().map(move |y| (x.clone(), y.clone())))
+        .collect()
+}
+
+/// A set of scenarios, each with its parameters of type `P`.
+#[derive(Debug, Clone)]
+pub struct Experiment<P> {
+    scenarios: Vec<P>,
+    threads: usize,
+}
+
+impl<P: Sync> Experiment<P> {
+    /// Create an experiment with a scenario for each parameter set
+    pub fn new(scenarios: Vec<P>) -> Experiment<P> {
+        Experiment {
+            scenarios,
+            threads: 1,
+        }
+    }
+
+    /// Run the scenarios on as many threads as the available parallelism of the machine
+    pub fn parallel(self) -> Self {
+        let threads = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        self.threads(threads)
+    }
+
+    /// Run the scenarios on at most `threads` threads
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Returns the parameters of the scenarios
+    pub fn scenarios(&self) -> &[P] {
+        &self.scenarios
+    }
+
+    /// Run each scenario until `until` is met.
+    ///
+    /// The simulation of a scenario is created by `setup` and, at the end of its run,
+    /// passed to `metrics` with the parameters, that returns the named output metrics.
+    /// The index of the scenario is recorded in the metadata of the simulation.
+    ///
+    /// # Panics
+    /// Panics if a scenario panics.
+    pub fn run<T, S, F, M, N>(&self, until: EndCondition, setup: S, metrics: F) -> ResultTable
+    where
+        T: 'static + SimState + Clone,
+        S: Fn(&P) -> Simulation<T> + Sync,
+        F: Fn(&P, Simulation<T>) -> M + Sync,
+        M: IntoIterator<Item = (N, f64)>,
+        N: Into<String>,
+    {
+        let rows = run_parallel(self.scenarios.len(), self.threads, |index| {
+            let params = &self.scenarios[index];
+            let mut sim = setup(params);
+            sim.metadata_mut().set_parameter("scenario", index);
+            metrics(params, sim.run(until.clone()))
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect::<Vec<(String, f64)>>()
+        });
+        let mut table = ResultTable::default();
+        for row in rows {
+            table.push(row);
+        }
+        table
+    }
+}
+
+/// The output metrics of the scenarios of an experiment.
+///
+/// The columns are the names of the metrics, in the order they were first returned.
+/// A scenario that did not return a metric has no value in its column.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResultTable {
+    columns: Vec<String>,
+    rows: Vec<Vec<Option<f64>>>,
+}
+
+impl ResultTable {
+    /// Returns the names of the metrics
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Returns the number of scenarios
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns `true` if there are no scenarios
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the metrics of a scenario, in the order of the columns
+    pub fn row(&self, scenario: usize) -> &[Option<f64>] {
+        &self.rows[scenario]
+    }
+
+    /// Returns the value of a metric for a scenario, if any
+    pub fn get(&self, scenario: usize, metric: &str) -> Option<f64> {
+        let column = self.columns.iter().position(|c| c == metric)?;
+        self.rows.get(scenario)?.get(column).copied().flatten()
+    }
+
+    /// Returns the values of a metric, one for each scenario
+    pub fn column(&self, metric: &str) -> Vec<Option<f64>> {
+        (0..self.len()).map(|s| self.get(s, metric)).collect()
+    }
+
+    /// Write the table in CSV format, with the index of the scenario in the first column.
+    /// Missing values are left empty.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "scenario")?;
+        for column in self.columns.iter() {
+            write!(writer, ",{}", column)?;
+        }
+        writeln!(writer)?;
+        for (scenario, row) in self.rows.iter().enumerate() {
+            write!(writer, "{}", scenario)?;
+            for value in row.iter() {
+                match value {
+                    Some(v) => write!(writer, ",{}", v)?,
+                    None => write!(writer, ",")?,
+                }
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, metrics: Vec<(String, f64)>) {
+        let mut row = vec![None; self.columns.len()];
+        for (name, value) in metrics {
+            match self.columns.iter().position(|c| *c == name) {
+                Some(column) => row[column] = Some(value),
+                None => {
+                    self.columns.push(name);
+                    for previous in self.rows.iter_mut() {
+                        previous.push(None);
+                    }
+                    row.push(Some(value));
+                }
+            }
+        }
+        self.rows.push(row);
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,54 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{grid, Experiment};
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn experiment() {
+    let scenarios = grid(&[1.0, 2.0], &[false, true]);
+    let table = Experiment::new(scenarios).threads(3).run(
+        EndCondition::NoEvents,
+        |&(delay, _)| {
+            let mut sim = Simulation::new();
+            let p = sim.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::TimeOut(delay);
+                },
+            ));
+            sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+            sim
+        },
+        |&(_, extra), sim| {
+            let scenario = sim.metadata().parameter_as::<f64>("scenario").unwrap();
+            let mut metrics = vec![("scenario", scenario), ("end", sim.time())];
+            if extra {
+                metrics.push(("events", sim.processed_events().len() as f64));
+            }
+            metrics
+        },
+    );
+    assert_eq!(table.columns(), ["scenario", "end", "events"]);
+    assert_eq!(
+        table.column("scenario"),
+        [Some(0.0), Some(1.0), Some(2.0), Some(3.0)]
+    );
+    assert_eq!(table.row(2), [Some(2.0), Some(2.0), None]);
+    let mut csv = Vec::new();
+    table.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("scenario,scenario,end,events\n0,0,1,\n1,1,1,1\n"));
+}
@@ -0,0 +1,483 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Exporting the log of a simulation.
+//!
+//! The states of a model are arbitrary types, so exporters need to know which attributes
+//! of a state are interesting and how to represent them. An `AttributeSchema` lists the
+//! attributes, each with a name, a type and a function that extracts its value from a
+//! state. The schema is registered once with `Simulation::set_attribute_schema`, and every
+//! exporter emits a typed column for each attribute.
+//!
+//! `write_csv` writes the processed events in CSV format, preceded by the metadata of the
+//! run as comment lines.
+//!
+//! `write_chrome_trace` writes the processed events in the Chrome trace event format, to
+//! visualize the activity of the processes with Perfetto.
+//!
+//! `write_gantt_csv` writes the Gantt chart of the resources, see the
+//! [`gantt`](crate::gantt) module, in CSV format preceded by the metadata of the run.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::export::{write_csv, AttributeSchema, AttributeType, Value};
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut schema = AttributeSchema::new();
+//! schema.add_attribute(
+//!     "timeout",
+//!     AttributeType::Float,
+//!     Box::new(|e: &Effect| match e {
+//!         Effect::TimeOut(t) => Value::Float(*t),
+//!         _ => Value::Missing,
+//!     }),
+//! );
+//! let mut sim = Simulation::new();
+//! sim.set_attribute_schema(schema);
+//! let p = sim.create_process(Box::new(#[coroutine] |_| {
+//!     yield Effect::TimeOut(1.5);
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::NoEvents);
+//!
+//! let mut csv = Vec::new();
+//! write_csv(&sim, &mut csv).unwrap();
+//! let csv = String::from_utf8(csv).unwrap();
+//! assert!(csv.ends_with("time,process,timeout\n0,0,1.5\n"));
+//! ```
+use crate::metadata::RunMetadata;
+use crate::{Effect, Event, ProcessId, ResourceId, SimState, Simulation};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io::{self, Write};
+
+/// The type of an attribute.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AttributeType {
+    Integer,
+    Float,
+    Boolean,
+    Text,
+}
+
+/// The value of an attribute of a state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Text(String),
+    /// The attribute does not apply to the state
+    Missing,
+}
+
+/// Extracts the value of an attribute from a state
+#[cfg(not(feature = "send"))]
+pub type Extractor<T> = Box<dyn Fn(&T) -> Value>;
+/// Extracts the value of an attribute from a state
+#[cfg(feature = "send")]
+pub type Extractor<T> = Box<dyn Fn(&T) -> Value + Send>;
+
+/// The attributes of the states of a model that are exported.
+pub struct AttributeSchema<T> {
+    attributes: Vec<(String, AttributeType, Extractor<T>)>,
+}
+
+impl Value {
+    /// Returns `true` if the value can be stored in an attribute of type `ty`
+    pub fn has_type(&self, ty: AttributeType) -> bool {
+        matches!(
+            (self, ty),
+            (Value::Integer(_), AttributeType::Integer)
+                | (Value::Float(_), AttributeType::Float)
+                | (Value::Boolean(_), AttributeType::Boolean)
+                | (Value::Text(_), AttributeType::Text)
+                | (Value::Missing, _)
+        )
+    }
+}
+
+impl<T> AttributeSchema<T> {
+    /// Create an empty schema
+    pub fn new() -> AttributeSchema<T> {
+        AttributeSchema {
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Add an attribute. Its values are computed applying `extractor` to the states.
+    pub fn add_attribute(
+        &mut self,
+        name: impl Into<String>,
+        ty: AttributeType,
+        extractor: Extractor<T>,
+    ) {
+        self.attributes.push((name.into(), ty, extractor));
+    }
+
+    /// Returns the names and types of the attributes
+    pub fn columns(&self) -> impl Iterator<Item = (&str, AttributeType)> {
+        self.attributes.iter().map(|(n, ty, _)| (n.as_str(), *ty))
+    }
+
+    /// Returns the values of the attributes of `state`.
+    ///
+    /// Returns an error if a value does not match the type of its attribute.
+    pub fn values(&self, state: &T) -> Result<Vec<Value>, SchemaError> {
+        self.attributes
+            .iter()
+            .map(|(name, ty, extractor)| {
+                let value = extractor(state);
+                if value.has_type(*ty) {
+                    Ok(value)
+                } else {
+                    Err(SchemaError {
+                        attribute: name.clone(),
+                        expected: *ty,
+                        found: value,
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+impl<T: fmt::Debug + 'static> AttributeSchema<T> {
+    /// Create a schema with a single text attribute, `state`, holding the `Debug`
+    /// representation of the states.
+    pub fn debug() -> AttributeSchema<T> {
+        let mut schema = AttributeSchema::new();
+        schema.add_attribute(
+            "state",
+            AttributeType::Text,
+            Box::new(|s: &T| Value::Text(format!("{:?}", s))),
+        );
+        schema
+    }
+}
+
+impl<T> Default for AttributeSchema<T> {
+    fn default() -> Self {
+        AttributeSchema::new()
+    }
+}
+
+/// An extractor returned a value of the wrong type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    /// Name of the attribute
+    pub attribute: String,
+    /// Type of the attribute
+    pub expected: AttributeType,
+    /// Value returned by the extractor
+    pub found: Value,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "attribute `{}` of type {:?} has value {:?}",
+            self.attribute, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Write the processed events of `sim` in CSV format.
+///
+/// The first lines are comments, starting with `#`, with the metadata of the run.
+/// Then there is a header line, and a line for each event with its time, the process and
+/// the attributes of the state yielded by the process, as defined by the attribute schema
+/// of the simulation. Missing values are left empty.
+///
+/// Returns an error if the simulation logs to a sink that does not keep the events in
+/// memory, see the [`sink`](crate::sink) module.
+pub fn write_csv<T, W>(sim: &Simulation<T>, mut writer: W) -> io::Result<()>
+where
+    T: 'static + SimState + Clone,
+    W: Write,
+{
+    let events = logged_events(sim)?;
+    write_metadata(&mut writer, sim.metadata())?;
+    let empty = AttributeSchema::new();
+    let schema = sim.attribute_schema().unwrap_or(&empty);
+    write_csv_header(&mut writer, schema)?;
+    for (event, state) in events {
+        write_csv_record(&mut writer, event, state, schema)?;
+    }
+    Ok(())
+}
+
+/// Write the Gantt chart of the resources of `sim` in CSV format.
+///
+/// The first lines are comments, starting with `#`, with the metadata of the run, followed
+/// by the segments as written by `Gantt::write_csv`.
+pub fn write_gantt_csv<T, W>(sim: &Simulation<T>, mut writer: W) -> io::Result<()>
+where
+    T: 'static + SimState + Clone,
+    W: Write,
+{
+    write_metadata(&mut writer, sim.metadata())?;
+    sim.gantt().write_csv(writer)
+}
+
+/// Write the metadata of the run as CSV comment lines.
+pub(crate) fn write_metadata<W: Write>(writer: &mut W, metadata: &RunMetadata) -> io::Result<()> {
+    for (key, value) in metadata.entries() {
+        writeln!(writer, "# {}={}", key, value)?;
+    }
+    Ok(())
+}
+
+/// Returns the metadata of the run as a JSON object.
+pub(crate) fn json_metadata(metadata: &RunMetadata) -> String {
+    let mut object = String::from("{");
+    for (i, (key, value)) in metadata.entries().into_iter().enumerate() {
+        if i > 0 {
+            object.push(',');
+        }
+        json_string(&mut object, &key);
+        object.push(':');
+        json_string(&mut object, &value);
+    }
+    object.push('}');
+    object
+}
+
+/// Returns the processed events of `sim`, or an error if they are streamed to a sink that
+/// does not keep them in memory.
+pub(crate) fn logged_events<T>(sim: &Simulation<T>) -> io::Result<&[(Event<T>, T)]>
+where
+    T: 'static + SimState + Clone,
+{
+    if !sim.keeps_log() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the log is written to a sink that does not keep the events in memory",
+        ));
+    }
+    Ok(sim.processed_events())
+}
+
+/// Write the header line of a CSV log.
+pub(crate) fn write_csv_header<T, W: Write>(
+    writer: &mut W,
+    schema: &AttributeSchema<T>,
+) -> io::Result<()> {
+    write!(writer, "time,process")?;
+    for (name, _) in schema.columns() {
+        write!(writer, ",{}", quote(name))?;
+    }
+    writeln!(writer)
+}
+
+/// Write a line of a CSV log, for an event and the state yielded by the process.
+pub(crate) fn write_csv_record<T, W: Write>(
+    writer: &mut W,
+    event: &Event<T>,
+    state: &T,
+    schema: &AttributeSchema<T>,
+) -> io::Result<()> {
+    write!(writer, "{},{}", event.time(), event.process())?;
+    let values = schema
+        .values(state)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    for value in values {
+        match value {
+            Value::Integer(i) => write!(writer, ",{}", i)?,
+            Value::Float(x) => write!(writer, ",{}", x)?,
+            Value::Boolean(b) => write!(writer, ",{}", b)?,
+            Value::Text(s) => write!(writer, ",{}", quote(&s))?,
+            Value::Missing => write!(writer, ",")?,
+        }
+    }
+    writeln!(writer)
+}
+
+/// Write the processed events of `sim` in the Chrome trace event format, that can be
+/// visualized with Perfetto or `chrome://tracing`.
+///
+/// Each process is a track, labelled with its name if set with
+/// `Simulation::set_process_name`. A process that yields `Effect::TimeOut` gets a span
+/// for the duration of the timeout, and a process that requests a resource gets a span
+/// from the request to the release, labelled with the name of the resource if set with
+/// `Simulation::set_resource_name`. A request with `Effect::RequestTimeout` gets a span
+/// only if the resource is granted before the deadline. Resources that are never released
+/// are closed at the current time of the simulation. The metadata of the run is written in `otherData`.
+///
+/// Returns an error if the simulation logs to a sink that does not keep the events in
+/// memory, see the [`sink`](crate::sink) module.
+///
+/// `time_scale` is the number of microseconds in a time unit of the simulation, the unit
+/// of the trace format.
+pub fn write_chrome_trace<T, W>(
+    sim: &Simulation<T>,
+    mut writer: W,
+    time_scale: f64,
+) -> io::Result<()>
+where
+    T: 'static + SimState + Clone,
+    W: Write,
+{
+    let mut spans = Vec::new();
+    let mut open: HashMap<(ProcessId, ResourceId), Vec<f64>> = HashMap::new();
+    let mut processes = BTreeSet::new();
+    // requests with a timeout, opened when the process is resumed with the resource
+    let mut timed_requests = HashMap::new();
+    for (event, state) in logged_events(sim)? {
+        let (time, process) = (event.time(), event.process());
+        processes.insert(process);
+        if let Some((r, start)) = timed_requests.remove(&process) {
+            if event.granted_in_time() {
+                open.entry((process, r)).or_default().push(start);
+            }
+        }
+        let released = match state.get_effect() {
+            Effect::TimeOut(d) if d > 0.0 => {
+                spans.push(("TimeOut".to_string(), "timeout", process, time, time + d));
+                None
+            }
+            Effect::Request(r) => {
+                open.entry((process, r)).or_default().push(time);
+                None
+            }
+            Effect::RequestTimeout(r, _) => {
+                timed_requests.insert(process, (r, time));
+                None
+            }
+            Effect::RequestN(r, n) => {
+                let starts = open.entry((process, r)).or_default();
+                starts.extend(std::iter::repeat_n(time, n));
+                None
+            }
+            Effect::Release(r) => Some((r, 1)),
+            Effect::ReleaseN(r, n) => Some((r, n)),
+            Effect::ReleaseAll(r) => Some((r, usize::MAX)),
+            _ => None,
+        };
+        if let Some((r, n)) = released {
+            let starts = open.entry((process, r)).or_default();
+            for _ in 0..n {
+                match starts.pop() {
+                    Some(start) => {
+                        spans.push((resource_label(sim, r), "resource", process, start, time))
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+    let mut unreleased: Vec<_> = open.into_iter().collect();
+    unreleased.sort_by_key(|(key, _)| *key);
+    for ((process, r), starts) in unreleased {
+        for start in starts {
+            spans.push((
+                resource_label(sim, r),
+                "resource",
+                process,
+                start,
+                sim.time(),
+            ));
+        }
+    }
+
+    write!(writer, "{{\"traceEvents\":[")?;
+    let mut first = true;
+    for process in processes {
+        let mut name = String::new();
+        match sim.process_name(process) {
+            Some(n) => json_string(&mut name, n),
+            None => json_string(&mut name, &format!("process {}", process)),
+        }
+        write!(
+            writer,
+            "{}\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":{}}}}}",
+            if first { "" } else { "," },
+            process,
+            name
+        )?;
+        first = false;
+    }
+    for (name, category, process, start, end) in spans {
+        let mut quoted = String::new();
+        json_string(&mut quoted, &name);
+        write!(
+            writer,
+            "{}\n{{\"name\":{},\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":{}}}",
+            if first { "" } else { "," },
+            quoted,
+            category,
+            json_number(start * time_scale),
+            json_number((end - start) * time_scale),
+            process
+        )?;
+        first = false;
+    }
+    writeln!(
+        writer,
+        "\n],\"otherData\":{}}}",
+        json_metadata(sim.metadata())
+    )
+}
+
+pub(crate) fn resource_label<T: 'static + SimState + Clone>(
+    sim: &Simulation<T>,
+    resource: ResourceId,
+) -> String {
+    match sim.resource_name(resource) {
+        Some(name) => name.to_string(),
+        None => format!("resource {}", resource),
+    }
+}
+
+/// Quote a CSV field, if needed.
+pub(crate) fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Format a number for JSON, where infinities and NaN are not allowed.
+pub(crate) fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Append a quoted and escaped JSON string.
+pub(crate) fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,123 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{AttributeSchema, AttributeType, Value};
+
+#[test]
+fn schema() {
+    let mut schema = AttributeSchema::new();
+    schema.add_attribute(
+        "id",
+        AttributeType::Integer,
+        Box::new(|s: &(i64, &str)| Value::Integer(s.0)),
+    );
+    schema.add_attribute(
+        "name",
+        AttributeType::Text,
+        Box::new(|s: &(i64, &str)| Value::Text(s.1.to_string())),
+    );
+    assert_eq!(
+        schema.columns().collect::<Vec<_>>(),
+        [
+            ("id", AttributeType::Integer),
+            ("name", AttributeType::Text)
+        ]
+    );
+    assert_eq!(
+        schema.values(&(1, "a")).unwrap(),
+        [Value::Integer(1), Value::Text("a".to_string())]
+    );
+    schema.add_attribute(
+        "wrong",
+        AttributeType::Boolean,
+        Box::new(|_: &(i64, &str)| Value::Float(1.0)),
+    );
+    assert_eq!(schema.values(&(1, "a")).unwrap_err().attribute, "wrong");
+    assert_eq!(super::quote("a,\"b\""), "\"a,\"\"b\"\"\"");
+}
+
+#[test]
+fn chrome_trace() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    s.set_resource_name(r, "server");
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(2.0);
+            yield Effect::Release(r);
+        },
+    ));
+    s.set_process_name(p, "customer \"1\"");
+    s.schedule_event(1.0, p, Effect::TimeOut(0.)).unwrap();
+    let s = s.run(EndCondition::NoEvents);
+    let mut out = Vec::new();
+    super::write_chrome_trace(&s, &mut out, 1000.0).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(
+        "{\"traceEvents\":[\n{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":0,\
+         \"args\":{\"name\":\"customer \\\"1\\\"\"}},\n\
+         {\"name\":\"TimeOut\",\"cat\":\"timeout\",\"ph\":\"X\",\"ts\":1000,\"dur\":2000,\
+         \"pid\":0,\"tid\":0},\n\
+         {\"name\":\"server\",\"cat\":\"resource\",\"ph\":\"X\",\"ts\":1000,\"dur\":2000,\
+         \"pid\":0,\"tid\":0}\n],\"otherData\":{\"desim_version\":"
+    ));
+    // each unit of a request of multiple units is a span
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(2)));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::RequestN(r, 2);
+            yield Effect::TimeOut(2.0);
+            yield Effect::ReleaseN(r, 2);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let s = s.run(EndCondition::NoEvents);
+    let mut out = Vec::new();
+    super::write_chrome_trace(&s, &mut out, 1.0).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.matches("\"cat\":\"resource\"").count(), 2);
+
+    // a request with a timeout is a span only if it is granted in time
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    for _ in 0..2 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_: SimContext<Effect>| {
+                let ctx = yield Effect::RequestTimeout(r, 1.0);
+                if !ctx.timed_out() {
+                    yield Effect::TimeOut(2.0);
+                    yield Effect::Release(r);
+                }
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    let s = s.run(EndCondition::NoEvents);
+    let mut out = Vec::new();
+    super::write_chrome_trace(&s, &mut out, 1.0).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.matches("\"cat\":\"resource\"").count(), 1);
+    assert!(
+        out.contains("\"cat\":\"resource\",\"ph\":\"X\",\"ts\":0,\"dur\":2,\"pid\":0,\"tid\":0")
+    );
+}
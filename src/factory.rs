@@ -0,0 +1,284 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Named factories of processes.
+//!
+//! Models with many similar entities, e.g. cars, packets or patients, build them all
+//! with the same code. A process factory, registered with
+//! `Simulation::register_process_factory`, builds a process from arguments of any type
+//! chosen by the model, e.g. the route of a car. `Simulation::spawn_from_factory` creates
+//! a process with a factory and schedules it, from outside the simulation.
+//!
+//! The factories share a single registry with the spawners registered with
+//! `Simulation::register_spawner`, that build a process from the state yielded by its
+//! parent: both are identified by a `FactoryId`, that `Effect::Spawn` and
+//! `Simulation::spawn_process` accept, and the factories by their name too. A factory used
+//! through its id receives `()` as arguments. The processes created from outside the
+//! simulation with `spawn_from_factory` or `spawn_process` are created again by
+//! `Simulation::reset`, with the same arguments.
+//!
+//! Processes can spawn other processes from a factory with `SimContext::spawn_from_factory`.
+//! The new process is created when the calling process yields, and starts with the state
+//! it yields, like with `Effect::Spawn`, or with the state the calling process was resumed
+//! with if it completes. The calling process is recorded as its parent.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, SimContext, Simulation};
+//!
+//! #[derive(Clone)]
+//! struct Car {
+//!     length: f64,
+//! }
+//!
+//! let mut sim = Simulation::new();
+//! sim.register_process_factory("car", Box::new(|car: Car| {
+//!     Box::new(#[coroutine] move |_| {
+//!         yield Effect::TimeOut(car.length);
+//!     })
+//! }));
+//! sim.spawn_from_factory("car", Car { length: 3.0 }, 0.0, Effect::TimeOut(0.0))
+//!     .unwrap();
+//! let source = sim.create_process(Box::new(#[coroutine] |mut ctx: SimContext<Effect>| {
+//!     for i in 1..=3 {
+//!         let length = i as f64;
+//!         ctx.spawn_from_factory("car", Car { length }, ctx.time() + 1.0);
+//!         ctx = yield Effect::TimeOut(1.0);
+//!     }
+//! }));
+//! sim.schedule_event(0.0, source, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::NoEvents);
+//! // the last car arrives at 3.0 and stays for 3.0
+//! assert_eq!(sim.time(), 6.0);
+//! ```
+use crate::error::SimError;
+use crate::shared::SharedValue;
+use crate::{Event, FactoryId, Process, ProcessId, SimState, Simulation, Spawner};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Builds a process from its arguments, see `Simulation::register_process_factory`
+#[cfg(not(feature = "send"))]
+pub type ProcessFactory<T, A> = Box<dyn FnMut(A) -> Box<Process<T>>>;
+/// Builds a process from its arguments, see `Simulation::register_process_factory`
+#[cfg(feature = "send")]
+pub type ProcessFactory<T, A> = Box<dyn FnMut(A) -> Box<Process<T>> + Send>;
+
+/// A process factory, whatever the type of its arguments.
+#[cfg(not(feature = "send"))]
+type ErasedFactory<T> = Box<dyn FnMut(Box<dyn Any>) -> Box<Process<T>>>;
+/// A process factory, whatever the type of its arguments.
+#[cfg(feature = "send")]
+type ErasedFactory<T> = Box<dyn FnMut(Box<dyn Any>) -> Box<Process<T>> + Send>;
+
+/// Makes a copy of the arguments of a process, to create it again.
+#[cfg(not(feature = "send"))]
+pub(crate) type Arguments = Box<dyn Fn() -> Box<dyn SharedValue>>;
+/// Makes a copy of the arguments of a process, to create it again.
+#[cfg(feature = "send")]
+pub(crate) type Arguments = Box<dyn Fn() -> Box<dyn SharedValue> + Send>;
+
+enum Factory<T> {
+    /// Builds a process from the state of its parent
+    Spawner(Spawner<T>),
+    /// Builds a process from its arguments
+    Arguments(ErasedFactory<T>),
+}
+
+/// The process factories and spawners of a simulation.
+pub(crate) struct ProcessFactories<T> {
+    factories: Vec<Factory<T>>,
+    names: HashMap<String, FactoryId>,
+}
+
+impl<T> Default for ProcessFactories<T> {
+    fn default() -> Self {
+        ProcessFactories {
+            factories: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+}
+
+impl<T: 'static> ProcessFactories<T> {
+    pub(crate) fn insert<A: SharedValue>(
+        &mut self,
+        name: String,
+        mut factory: ProcessFactory<T, A>,
+    ) -> FactoryId {
+        let message = format!("ERROR. The arguments do not match the process factory {name}.");
+        let erased: ErasedFactory<T> =
+            Box::new(move |args: Box<dyn Any>| factory(*args.downcast::<A>().expect(&message)));
+        match self.names.get(&name) {
+            Some(&id) => {
+                self.factories[id] = Factory::Arguments(erased);
+                id
+            }
+            None => {
+                self.factories.push(Factory::Arguments(erased));
+                self.names.insert(name, self.factories.len() - 1);
+                self.factories.len() - 1
+            }
+        }
+    }
+
+    pub(crate) fn insert_spawner(&mut self, spawner: Spawner<T>) -> FactoryId {
+        self.factories.push(Factory::Spawner(spawner));
+        self.factories.len() - 1
+    }
+
+    /// Returns the id of the factory named `name`
+    pub(crate) fn id(&self, name: &str) -> Result<FactoryId, SimError> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| SimError::UnknownFactory(name.to_string()))
+    }
+
+    /// Returns `true` if `id` is the id of a factory or spawner
+    pub(crate) fn contains(&self, id: FactoryId) -> bool {
+        id < self.factories.len()
+    }
+
+    /// Build a process with the factory `id`, passing it `args`, or `()` if there are none,
+    /// or with the spawner `id`, passing it `state`.
+    pub(crate) fn build(
+        &mut self,
+        id: FactoryId,
+        args: Option<Box<dyn SharedValue>>,
+        state: &T,
+    ) -> Result<Box<Process<T>>, SimError> {
+        match self.factories.get_mut(id) {
+            Some(Factory::Spawner(spawner)) => Ok(spawner(state)),
+            Some(Factory::Arguments(factory)) => {
+                let args: Box<dyn Any> = match args {
+                    Some(args) => args,
+                    None => Box::new(()),
+                };
+                Ok(factory(args))
+            }
+            None => Err(SimError::UnknownFactoryId(id)),
+        }
+    }
+}
+
+/// A process created from outside the simulation with a factory, to be created again by
+/// `Simulation::reset`.
+pub(crate) struct InitialProcess<T> {
+    pub(crate) factory: FactoryId,
+    pub(crate) args: Option<Arguments>,
+    pub(crate) time: f64,
+    pub(crate) state: T,
+}
+
+/// A process requested by a process with `SimContext::spawn_from_factory`.
+pub(crate) struct SpawnRequest {
+    pub(crate) factory: String,
+    pub(crate) args: Box<dyn SharedValue>,
+    pub(crate) time: f64,
+}
+
+impl fmt::Debug for SpawnRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnRequest")
+            .field("factory", &self.factory)
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Register a factory of processes named `name`, that builds them from arguments of
+    /// type `A`, replacing the factory with the same name, if any.
+    /// See the [`factory`](crate::factory) module.
+    ///
+    /// Returns the id of the factory, that is the same when the factory is replaced.
+    pub fn register_process_factory<A: SharedValue>(
+        &mut self,
+        name: impl Into<String>,
+        factory: ProcessFactory<T, A>,
+    ) -> FactoryId {
+        self.process_factories.insert(name.into(), factory)
+    }
+
+    /// Returns the id of the process factory named `name`, if any
+    pub fn process_factory(&self, name: &str) -> Option<FactoryId> {
+        self.process_factories.id(name).ok()
+    }
+
+    /// Create a process with the factory named `name`, passing it `args`, and schedule it
+    /// at the absolute `time` with `state`.
+    ///
+    /// Unlike the processes created with `create_process`, the process is created again
+    /// by `reset`, with a copy of `args`.
+    ///
+    /// Returns the id of the new process, or an error if there is no factory named `name`,
+    /// or if `time` is NaN or in the past.
+    ///
+    /// # Panics
+    /// Panics if the factory takes arguments of another type.
+    pub fn spawn_from_factory<A: SharedValue + Clone>(
+        &mut self,
+        name: &str,
+        args: A,
+        time: f64,
+        state: T,
+    ) -> Result<ProcessId, SimError> {
+        let factory = self.process_factories.id(name)?;
+        let copy = args.clone();
+        let process = self.spawn_at(factory, Some(Box::new(args)), time, state.clone())?;
+        self.initial_processes.push(InitialProcess {
+            factory,
+            args: Some(Box::new(move || Box::new(copy.clone()))),
+            time,
+            state,
+        });
+        Ok(process)
+    }
+
+    /// Create a process with the factory or spawner `factory`, and schedule it at the
+    /// absolute `time` with `state`.
+    pub(crate) fn spawn_at(
+        &mut self,
+        factory: FactoryId,
+        args: Option<Box<dyn SharedValue>>,
+        time: f64,
+        state: T,
+    ) -> Result<ProcessId, SimError> {
+        let process = self.process_factories.build(factory, args, &state)?;
+        let process = self.create_process(process);
+        if time.is_nan() || time < self.time {
+            self.processes.remove(process);
+            return Err(SimError::InvalidTime(process));
+        }
+        self.schedule(Event::new(time, process, state));
+        Ok(process)
+    }
+
+    /// Create the processes requested by `parent` during its last step, starting them
+    /// with `state`.
+    pub(crate) fn spawn_requested(&mut self, parent: ProcessId, state: &T) -> Result<(), SimError> {
+        for request in self.shared.take_spawns() {
+            let factory = self.process_factories.id(&request.factory)?;
+            let child = self.spawn_at(factory, Some(request.args), request.time, state.clone())?;
+            self.parents.insert(child, parent);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
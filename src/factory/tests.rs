@@ -0,0 +1,168 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::error::SimError;
+use crate::{Effect, EndCondition, ProcessId, SimContext, Simulation};
+
+#[derive(Clone, Copy)]
+struct Packet {
+    size: usize,
+}
+
+#[test]
+fn process_factories() {
+    let mut s = Simulation::new();
+    s.register_process_factory(
+        "packet",
+        Box::new(|packet: Packet| {
+            Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::TimeOut(packet.size as f64);
+                },
+            )
+        }),
+    );
+    assert_eq!(
+        s.spawn_from_factory("frame", Packet { size: 1 }, 0.0, Effect::Trace),
+        Err(SimError::UnknownFactory("frame".to_string()))
+    );
+    let first = s
+        .spawn_from_factory("packet", Packet { size: 2 }, 1.0, Effect::TimeOut(0.))
+        .unwrap();
+    let router = s.create_process(Box::new(
+        #[coroutine]
+        |mut ctx: SimContext<Effect>| {
+            for size in [1, 4] {
+                ctx.spawn_from_factory("packet", Packet { size }, ctx.time());
+                ctx = yield Effect::TimeOut(1.0);
+            }
+            ctx.spawn_from_factory("packet", Packet { size: 8 }, ctx.time() + 0.5);
+        },
+    ));
+    s.schedule_event(0.0, router, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    let child = ProcessId::from_raw(2);
+    assert_eq!(s.parent(child), Some(router));
+    assert_eq!(s.parent(first), None);
+    s = s.run(EndCondition::NoEvents);
+    // the last packet is spawned at 2.5 when the router completes
+    assert_eq!(s.time(), 10.5);
+    assert!(matches!(
+        s.spawn_from_factory("packet", Packet { size: 2 }, f64::NAN, Effect::Trace),
+        Err(SimError::InvalidTime(_))
+    ));
+    assert!(matches!(
+        s.spawn_from_factory("packet", Packet { size: 2 }, 1.0, Effect::Trace),
+        Err(SimError::InvalidTime(_))
+    ));
+
+    // a spawn in the past fails the step of the parent
+    let mut s = Simulation::new();
+    s.register_process_factory(
+        "packet",
+        Box::new(|_: Packet| {
+            Box::new(
+                #[coroutine]
+                |_| {
+                    yield Effect::Wait;
+                },
+            )
+        }),
+    );
+    let late = s.create_process(Box::new(
+        #[coroutine]
+        |mut ctx: SimContext<Effect>| {
+            ctx.spawn_from_factory("packet", Packet { size: 1 }, -1.0);
+            yield Effect::Wait;
+        },
+    ));
+    s.schedule_event(0.0, late, Effect::TimeOut(0.)).unwrap();
+    assert!(matches!(s.step(), Err(SimError::InvalidTime(_))));
+}
+
+#[test]
+#[should_panic(expected = "do not match the process factory packet")]
+fn factory_arguments() {
+    let mut s = Simulation::new();
+    s.register_process_factory(
+        "packet",
+        Box::new(|packet: Packet| {
+            Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::TimeOut(packet.size as f64);
+                },
+            )
+        }),
+    );
+    let _ = s.spawn_from_factory("packet", 3usize, 0.0, Effect::Trace);
+}
+
+#[test]
+fn factory_registry() {
+    let mut s = Simulation::new();
+    let packet = s.register_process_factory(
+        "packet",
+        Box::new(|packet: Packet| {
+            Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::TimeOut(packet.size as f64);
+                },
+            )
+        }),
+    );
+    let ack = s.register_spawner(Box::new(|_: &Effect| {
+        Box::new(
+            #[coroutine]
+            |_| {
+                yield Effect::TimeOut(1.0);
+            },
+        )
+    }));
+    let beacon = s.register_process_factory(
+        "beacon",
+        Box::new(|()| {
+            Box::new(
+                #[coroutine]
+                |_| {
+                    yield Effect::TimeOut(5.0);
+                },
+            )
+        }),
+    );
+    assert_ne!(packet, ack);
+    assert_eq!(s.process_factory("beacon"), Some(beacon));
+    assert_eq!(s.process_factory("frame"), None);
+    let sender = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Spawn(ack);
+            yield Effect::Spawn(beacon);
+        },
+    ));
+    s.schedule_event(0.0, sender, Effect::TimeOut(0.)).unwrap();
+    s.spawn_from_factory("packet", Packet { size: 3 }, 1.0, Effect::TimeOut(0.))
+        .unwrap();
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.time(), 5.0);
+
+    // the packet is created again, with its arguments, the sender is not
+    s.reset(Effect::Trace);
+    assert_eq!(s.active_processes(), 1);
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.time(), 4.0);
+}
@@ -0,0 +1,207 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Gantt charts of the resources.
+//!
+//! The Chrome trace written by `export::write_chrome_trace` shows what each process did.
+//! A Gantt chart shows the same run from the side of the resources: `Simulation::gantt`
+//! returns a row for each resource, with a segment for each unit held by a process, from
+//! the time it was acquired to the time it was released. Each segment reports the
+//! process, the entity it carried when it acquired the unit and a label, ready to be drawn
+//! with a plotting library or written in CSV format with `Gantt::write_csv`, or with
+//! `export::write_gantt_csv` to precede it with the metadata of the run.
+//!
+//! The segments are taken from the occupancy recorded in the statistics of the resources,
+//! that must be tracked with `Simulation::track_occupancy` before the run: otherwise, only
+//! the units still in use are reported. The segments start at the end of the warm-up
+//! period, if any, and the units still in use are reported as released at the current time.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::resources::SimpleResource;
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let r = sim.create_resource(Box::new(SimpleResource::new(1)));
+//! sim.set_resource_name(r, "lathe");
+//! sim.track_occupancy(r);
+//! for name in ["first", "second"] {
+//!     let p = sim.create_process(Box::new(#[coroutine] move |_| {
+//!         yield Effect::Request(r);
+//!         yield Effect::TimeOut(5.0);
+//!         yield Effect::Release(r);
+//!     }));
+//!     sim.set_process_name(p, name);
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! }
+//! let sim = sim.run(EndCondition::NoEvents);
+//!
+//! let gantt = sim.gantt();
+//! let lathe = gantt.get(r).unwrap();
+//! assert_eq!(lathe.label(), "lathe");
+//! let segments: Vec<_> = lathe
+//!     .segments()
+//!     .iter()
+//!     .map(|s| (s.start(), s.end(), s.label()))
+//!     .collect();
+//! assert_eq!(segments, [(0.0, 5.0, "first"), (5.0, 10.0, "second")]);
+//!
+//! let mut csv = Vec::new();
+//! gantt.write_csv(&mut csv).unwrap();
+//! let csv = String::from_utf8(csv).unwrap();
+//! assert!(csv.starts_with("resource,start,end,process,entity,label\nlathe,0,5,"));
+//! ```
+use crate::export::{quote, resource_label};
+use crate::{EntityId, ProcessId, ResourceId, SimState, Simulation};
+use std::io::{self, Write};
+
+/// The time a process held a unit of a resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GanttSegment {
+    start: f64,
+    end: f64,
+    process: ProcessId,
+    entity: Option<EntityId>,
+    label: String,
+}
+
+impl GanttSegment {
+    /// Returns the time the unit was acquired
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    /// Returns the time the unit was released
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    /// Returns the process that held the unit
+    pub fn process(&self) -> ProcessId {
+        self.process
+    }
+
+    /// Returns the entity carried by the process when it acquired the unit, if any
+    pub fn entity(&self) -> Option<EntityId> {
+        self.entity
+    }
+
+    /// Returns the name of the process, if set with `Simulation::set_process_name`, or
+    /// `process <id>`
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// The segments of a resource, sorted by the time of acquisition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GanttRow {
+    resource: ResourceId,
+    label: String,
+    segments: Vec<GanttSegment>,
+}
+
+impl GanttRow {
+    /// Returns the resource
+    pub fn resource(&self) -> ResourceId {
+        self.resource
+    }
+
+    /// Returns the name of the resource, if set with `Simulation::set_resource_name`, or
+    /// `resource <id>`
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the segments, sorted by the time of acquisition
+    pub fn segments(&self) -> &[GanttSegment] {
+        &self.segments
+    }
+}
+
+/// A Gantt chart of the resources of a simulation, see `Simulation::gantt`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gantt {
+    rows: Vec<GanttRow>,
+}
+
+impl Gantt {
+    /// Returns the rows, one for each resource in the order they were created
+    pub fn rows(&self) -> &[GanttRow] {
+        &self.rows
+    }
+
+    /// Returns the row of a resource, if it exists
+    pub fn get(&self, resource: ResourceId) -> Option<&GanttRow> {
+        self.rows.get(resource.0)
+    }
+
+    /// Write the segments in CSV format, with a header line and a line for each segment
+    /// with the label of the resource, the start and end times, the process, the entity
+    /// and the label of the segment. The entity is left empty if the process carried none.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "resource,start,end,process,entity,label")?;
+        for row in &self.rows {
+            for segment in &row.segments {
+                write!(
+                    writer,
+                    "{},{},{},{},",
+                    quote(&row.label),
+                    segment.start,
+                    segment.end,
+                    segment.process
+                )?;
+                if let Some(entity) = segment.entity {
+                    write!(writer, "{}", entity)?;
+                }
+                writeln!(writer, ",{}", quote(&segment.label))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Returns the Gantt chart of the resources, up to the current time.
+    /// See the [`gantt`](crate::gantt) module.
+    pub fn gantt(&self) -> Gantt {
+        let rows = (0..self.resource_stats.len())
+            .map(ResourceId)
+            .map(|resource| GanttRow {
+                resource,
+                label: resource_label(self, resource),
+                segments: self
+                    .resource_stats(resource)
+                    .segments()
+                    .into_iter()
+                    .map(|(start, end, process, entity)| GanttSegment {
+                        start,
+                        end,
+                        process,
+                        entity,
+                        label: match self.process_name(process) {
+                            Some(name) => name.to_string(),
+                            None => format!("process {}", process),
+                        },
+                    })
+                    .collect(),
+            })
+            .collect();
+        Gantt { rows }
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,93 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::resources::SimpleResource;
+use crate::{Effect, EndCondition, EntityId, Simulation};
+
+#[test]
+fn gantt() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(2)));
+    let idle = s.create_resource(Box::new(SimpleResource::new(1)));
+    s.set_resource_name(r, "a, b");
+    s.track_occupancy(r);
+    let mut processes = Vec::new();
+    for i in 0..3 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::CreateEntity;
+                yield Effect::Request(r);
+                yield Effect::TimeOut(2.0 + i as f64);
+                if i < 2 {
+                    yield Effect::Release(r);
+                }
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        processes.push(p);
+    }
+    s.set_process_name(processes[0], "first");
+    s = s.run(EndCondition::NoEvents);
+
+    let gantt = s.gantt();
+    assert_eq!(gantt.rows().len(), 2);
+    assert!(gantt.get(idle).unwrap().segments().is_empty());
+    assert_eq!(gantt.get(idle).unwrap().label(), "resource 1");
+    let row = gantt.get(r).unwrap();
+    let segments: Vec<_> = row
+        .segments()
+        .iter()
+        .map(|s| (s.start(), s.end(), s.process(), s.entity()))
+        .collect();
+    // the third process never releases the resource, so its segment ends at the
+    // current time
+    assert_eq!(
+        segments,
+        [
+            (0.0, 2.0, processes[0], Some(EntityId::from_raw(0))),
+            (0.0, 3.0, processes[1], Some(EntityId::from_raw(1))),
+            (2.0, 6.0, processes[2], Some(EntityId::from_raw(2))),
+        ]
+    );
+    assert_eq!(row.segments()[0].label(), "first");
+    assert_eq!(
+        row.segments()[1].label(),
+        format!("process {}", processes[1])
+    );
+
+    let mut csv = Vec::new();
+    gantt.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("resource,start,end,process,entity,label")
+    );
+    assert_eq!(
+        lines.next(),
+        Some(format!("\"a, b\",0,2,{},0,first", processes[0]).as_str())
+    );
+    assert_eq!(lines.count(), 2);
+
+    // the metadata of the run precede the chart
+    let mut csv = Vec::new();
+    crate::export::write_gantt_csv(&s, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let (comments, chart): (Vec<_>, Vec<_>) = csv.lines().partition(|l| l.starts_with('#'));
+    assert!(comments[0].starts_with("# desim_version="));
+    assert_eq!(chart[0], "resource,start,end,process,entity,label");
+    assert_eq!(chart.len(), 4);
+}
@@ -0,0 +1,106 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Injection of external events.
+//!
+//! An `EventInjector`, obtained with `Simulation::injector`, schedules events in a
+//! simulation from outside of it, e.g. from another thread while the simulation runs with
+//! `nonblocking_run`, or from a callback receiving stimuli from a live system.
+//! Injectors can be cloned and, if the state type is `Send`, sent to other threads.
+//!
+//! Injected events are received by the simulation at the beginning of each step.
+//! Their time is absolute: an event injected for a time that the simulation already passed
+//! is executed at the current time. If an event is injected for an unknown or completed
+//! process, or at a NaN time, the step fails with an error for the first of them, but the
+//! other events received are scheduled anyway. Note that a run with `EndCondition::NoEvents` stops as
+//! soon as there are no events scheduled, even if more could be injected later.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let p = sim.create_process(Box::new(#[coroutine] |_| {
+//!     yield Effect::Wait;
+//! }));
+//! let injector = sim.injector();
+//! injector.inject(3.0, p, Effect::TimeOut(0.0)).unwrap();
+//! sim.step().unwrap();
+//! assert_eq!(sim.time(), 3.0);
+//! ```
+use crate::ProcessId;
+use std::sync::mpsc::{channel, Receiver, Sender, TryIter};
+
+/// An event injected from outside of the simulation.
+pub(crate) struct Injection<T> {
+    pub time: f64,
+    pub process: ProcessId,
+    pub state: T,
+}
+
+/// A handle to inject events in a simulation.
+pub struct EventInjector<T> {
+    sender: Sender<Injection<T>>,
+}
+
+/// The receiving end of the injectors of a simulation.
+pub(crate) struct Injections<T> {
+    sender: Sender<Injection<T>>,
+    receiver: Receiver<Injection<T>>,
+}
+
+impl<T> EventInjector<T> {
+    /// Schedule `process` to be resumed with `state` at the absolute simulation `time`.
+    ///
+    /// Returns the state back if the simulation was dropped.
+    pub fn inject(&self, time: f64, process: ProcessId, state: T) -> Result<(), T> {
+        self.sender
+            .send(Injection {
+                time,
+                process,
+                state,
+            })
+            .map_err(|e| e.0.state)
+    }
+}
+
+impl<T> Clone for EventInjector<T> {
+    fn clone(&self) -> Self {
+        EventInjector {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<T> Injections<T> {
+    pub fn new() -> Injections<T> {
+        let (sender, receiver) = channel();
+        Injections { sender, receiver }
+    }
+
+    pub fn injector(&self) -> EventInjector<T> {
+        EventInjector {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Returns the events injected so far, without blocking
+    pub fn received(&self) -> TryIter<'_, Injection<T>> {
+        self.receiver.try_iter()
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,57 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::error::SimError;
+use crate::{Effect, EndCondition, ProcessId, Simulation};
+use std::thread;
+
+#[test]
+fn injector() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::Wait;
+        },
+    ));
+    s.schedule_event(2.0, p, Effect::TimeOut(0.)).unwrap();
+    let injector = s.injector();
+    thread::spawn(move || {
+        // in the past when it is received
+        injector.inject(1.0, p, Effect::TimeOut(0.)).unwrap();
+        injector.inject(5.0, p, Effect::TimeOut(0.)).unwrap();
+    })
+    .join()
+    .unwrap();
+    s.step().unwrap();
+    s.step().unwrap();
+    assert_eq!(s.time(), 2.0);
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.time(), 5.0);
+    assert_eq!(s.processed_events().len(), 3);
+
+    let injector = s.injector();
+    injector
+        .inject(6.0, ProcessId(42), Effect::TimeOut(0.))
+        .unwrap();
+    injector.inject(f64::NAN, p, Effect::TimeOut(0.)).unwrap();
+    injector.inject(7.0, p, Effect::TimeOut(0.)).unwrap();
+    assert_eq!(
+        s.step().unwrap_err(),
+        SimError::UnknownProcess(ProcessId(42))
+    );
+    // the valid injection is scheduled anyway
+    assert_eq!(s.peek_next_time(), Some(7.0));
+}
@@ -17,7 +17,8 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! inspired by the SimPy library for Python. It uses the coroutine
 //! feature that is nightly. Once the feature is stabilized, also this
 //! crate will use stable. Coroutines will be the only nightly feature
-//! used in this crate.
+//! used in this crate. Without the default `coroutines` feature, the crate
+//! builds on stable, with processes written as state machines.
 //!
 //! The examples directory in this repository contains full usage examples
 //! of the desim crate as a simulation framework.
@@ -35,12 +36,16 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! The simulation will generate a log of all the events with a state that
 //! returns `true` to `should_log`.
 //!
-/*
-//! `nonblocking_run` lets you run the simulation in another thread
+//! The events scheduled and not yet executed are kept in a binary heap. Models with many
+//! events in the near future may run faster with the calendar queue of the
+//! [`event_list`](crate::event_list) module, set with `set_future_event_list`.
+//!
+//! With the `send` feature, `nonblocking_run` lets you run the simulation in another thread
 //! so that your program can go on without waiting for the simulation
-//! to finish.
+//! to finish. The feature requires processes, resources, stores and callbacks to be `Send`.
+//! It also enables the `parallel` module, that runs the partitions of a
+//! large model on several threads.
 //!
-*/
 //! # Process
 //! A process is implemented using the rust coroutines syntax.
 //! This let us avoid the overhead of spawning a new thread for each
@@ -55,6 +60,26 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! For example, a coroutine can set a timeout after which it is executed again.
 //! The process may also return. In that case it can not be resumed anymore.
 //!
+//! Processes can also be written without coroutines, as state machines implementing
+//! `process::ProcessFn` or as async functions wrapped in a `task::AsyncProcess`.
+//! Disabling the default `coroutines` feature, processes can only be written this way,
+//! and the crate builds on stable Rust.
+//!
+//! A process that is waiting, e.g. for a timeout or in the queue of a resource, can be
+//! woken up early with `interrupt` or by another process yielding `Effect::Interrupt`.
+//!
+//! Timeouts, requests of resources and store operations can be combined in a condition
+//! with `create_condition`. Yielding `Effect::AnyOf` the process waits for the first one
+//! to complete, e.g. a request with a deadline, while with `Effect::AllOf` it waits for
+//! all of them.
+//!
+//! With `Effect::WaitUntil`, a process waits until a predicate over the state of the
+//! simulation holds, e.g. the level of a store. See the [`predicate`](crate::predicate)
+//! module.
+//!
+//! Processes can share state through a blackboard owned by the simulation, that they read
+//! and modify from their `SimContext`. See the [`shared`](crate::shared) module.
+//!
 //!
 //! # Resource
 //! A resource is a finite amount of entities, eachone of which can be used by one process
@@ -63,23 +88,139 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! A resource can be created in the simulation using the `create_resource`
 //! method, which requires the resource to add to the simulation and returns an identifier
 //! for that resource that can be used to require and release it.
+//! Resources and stores can also be added while the simulation runs, by the owner of the
+//! simulation between two steps or by a process yielding `Effect::AddResource` or
+//! `Effect::AddStore` with a factory registered beforehand. Ids are never reused, so the
+//! ones held by processes stay valid.
 //!
 //! A resource can be required and reelased by a process yielding
-//! the corresponding `Effect`. There is no check on the fact that a process
-//! yielding `Release` was holding a resource with that ID.
+//! the corresponding `Effect`. The simulation counts the units granted to each process:
+//! a process can give back all of them at once yielding `ReleaseAll`, and by default the
+//! simulation stops with an error if a process releases a resource it does not hold
+//! (see `set_release_policy`). The holders of a resource are returned by `resource_holders`.
 //!
 //! For more information about the `Resource` trait and the `SimpleResource` implementation,
 //! see the [`resources`](crate::resources) module.
+//!
+//! # Soft limits
+//! Thresholds on queue lengths, waiting times and store levels can be added to the
+//! simulation with `add_soft_limit`. Crossing one of them does not stop the simulation,
+//! but records a warning and calls the hooks registered with `on_warning`.
+//! See the [`limits`](crate::limits) module.
+//!
+//! # Modules
+//! A sub-model, like a workstation with its machine and operator, can be written once as a
+//! `Module` and instantiated several times with `instantiate`, each instance naming its
+//! processes, resources and stores under its own path. See the [`module`](crate::module)
+//! module.
+//!
+//! # Co-simulation
+//! Models built separately, e.g. a network and the servers it connects, can be advanced in
+//! lock-step by a `CoSim`, exchanging timestamped messages at synchronization points.
+//! See the [`cosim`](crate::cosim) module.
+//!
+//! # Trace-driven simulation
+//! Processes can enter the simulation from a stream of `Arrival`s, e.g. read from
+//! an external trace, with `add_arrivals`. See the [`trace`](crate::trace) module.
 
-#![feature(coroutines, coroutine_trait)]
-use std::cmp::{Ordering, Reverse};
-use std::collections::BinaryHeap;
-use std::ops::{Coroutine, CoroutineState};
-use std::pin::Pin;
+#![cfg_attr(feature = "coroutines", feature(coroutine_trait))]
+#![cfg_attr(all(test, feature = "coroutines"), feature(coroutines))]
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+#[cfg(feature = "coroutines")]
+use std::ops::Coroutine;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+#[cfg(feature = "send")]
+pub mod background;
+pub mod breakdown;
+pub mod builder;
+pub mod bus;
+pub mod control;
+pub mod cosim;
+pub mod deadlock;
+pub mod debug;
+pub mod engine;
+pub mod entity;
+pub mod error;
+pub mod event_list;
+pub mod experiments;
+pub mod export;
+pub mod factory;
+pub mod gantt;
+pub mod injector;
+pub mod limits;
+pub mod log;
+pub mod mailbox;
+pub mod metadata;
+pub mod module;
+pub mod observer;
+#[cfg(feature = "send")]
+pub mod parallel;
+pub mod predicate;
 pub mod prelude;
+pub mod process;
+pub mod progress;
+pub mod reference;
+pub mod replay;
+pub mod replications;
+pub mod reset;
 pub mod resources;
-use resources::{Resource, Store};
+pub mod rng;
+pub mod rollback;
+pub mod shared;
+pub mod signal;
+pub mod sink;
+mod slab;
+pub mod soak;
+pub mod sources;
+pub mod stats;
+pub mod steps;
+pub mod task;
+pub mod time;
+pub mod trace;
+use builder::SimulationBuilder;
+use bus::{Listener, Topic};
+use deadlock::{BlockedProcess, WaitingFor};
+use engine::{EngineCounters, EngineStats};
+use entity::Entities;
+use error::SimError;
+use event_list::{BoxedEventList, FutureEventList, FutureEvents};
+use export::AttributeSchema;
+use factory::{InitialProcess, ProcessFactories, SpawnRequest};
+use injector::{EventInjector, Injections};
+use limits::{
+    BudgetPolicy, BudgetViolation, ReleasePolicy, SoftLimit, SoftLimits, Warning, WarningHook,
+    ZeroDelayPolicy,
+};
+use log::{EventLog, LogIndex};
+use mailbox::Mailbox;
+use metadata::RunMetadata;
+use module::{Module, Scope};
+use observer::{BoxedObserver, ResourceChange, StatUpdate};
+use predicate::{Predicate, SimView, WaitUntil};
+use process::ProcessStep;
+use progress::{Progress, ProgressHook, ProgressReport};
+use reference::{AnalyticReference, ComparisonReport};
+use replay::{Input, Journal};
+use resources::{
+    BoxedResource, BoxedStore, ReleaseOutcome, Resource, ResourceEntry, ResourceFactory,
+    ResourceGroup, ResourceInspect, ResourceSnapshot, StandardResource, Store, StoreFactory,
+};
+use rng::{Philox4x32, Streams};
+use rollback::Rollback;
+use shared::{Shared, SharedValue};
+use signal::{Gate, Signal};
+use sink::{BoxedLogSink, LogFilter};
+use slab::Slab;
+use soak::{CheckInterval, Invariant, InvariantCheck, InvariantViolation, SoakError};
+use stats::{Counter, Histogram, ResourceStats, Statistic, Stats, Tally, TimeWeighted};
+use time::SplitTime;
+use trace::Arrival;
 
 /// Data structures implementing this trait can be yielded from the coroutine
 /// associated with a `Process`. This allows attaching application-specific data
@@ -91,7 +232,8 @@ use resources::{Resource, Store};
 ///
 /// A process can then yield `ItemState` instead of `Effect` types:
 ///
-/// ```
+#[cfg_attr(feature = "coroutines", doc = "```")]
+#[cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
 /// #![feature (coroutines, coroutine_trait)]
 /// use desim::{Effect, SimState, Simulation};
 ///
@@ -139,6 +281,7 @@ pub trait SimState {
 /// The effect is yelded by a process coroutine to
 /// interact with the simulation environment.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[non_exhaustive]
 pub enum Effect {
     /// The process that yields this effect will be resumed
@@ -151,28 +294,276 @@ pub enum Effect {
         /// Process to execute when the event occur
         process: ProcessId,
     },
+    /// Like `Event`, with a priority. Among the events scheduled at the same time, the ones
+    /// with higher priority are executed first. The priority of other events is 0.
+    EventWithPriority {
+        /// Time interval between the current simulation time and the event schedule
+        time: f64,
+        /// Process to execute when the event occur
+        process: ProcessId,
+        /// Priority of the event
+        priority: i32,
+    },
+    /// Like `Event`, at an absolute time. The time must not be in the past.
+    EventAt {
+        /// Time of the event
+        time: f64,
+        /// Process to execute when the event occur
+        process: ProcessId,
+    },
     /// This effect is yielded to request a resource
     Request(ResourceId),
+    /// Request the specified number of units of a resource at once. The process waits
+    /// until all of them are granted together, and can release them with `ReleaseN`.
+    RequestN(ResourceId, usize),
+    /// Request a resource, waiting at most the specified time.
+    /// If the deadline expires first, the process leaves the queue of the resource and it is
+    /// resumed with `SimContext::timed_out()` set, otherwise it holds the resource.
+    RequestTimeout(ResourceId, f64),
     /// This effect is yielded to release a resource that is not needed anymore.
     Release(ResourceId),
+    /// Release the specified number of units of a resource at once.
+    /// As many waiting processes as the freed units allow are granted the resource in the
+    /// same step, then the process is resumed.
+    ReleaseN(ResourceId, usize),
+    /// Release all the units of a resource held by the process, like `ReleaseN`.
+    ReleaseAll(ResourceId),
+    /// Request a unit of one of the resources of a group, see
+    /// `Simulation::create_resource_group`: the first member with an available unit, or
+    /// the one with the shortest queue. `SimContext::outcome()` tells which one was granted.
+    RequestGroup(GroupId),
+    /// Release a unit of the first resource of a group held by the process, like `Release`.
+    ReleaseGroup(GroupId),
     /// This effect is yielded to push into a store
     Push(StoreId),
     /// This effect is yielded to pull out of a store
     Pull(StoreId),
     /// Keep the process' state until it is resumed by another event.
     Wait,
+    /// Like `Wait`, but the process is resumed after the specified time if no other event
+    /// resumed it first. In that case `SimContext::timed_out()` returns `true`.
+    WaitTimeout(f64),
+    /// Add a resource built by a factory, see `Simulation::register_resource_factory`.
+    /// The process is resumed immediately and `SimContext::created` returns the id of the
+    /// new resource.
+    AddResource(FactoryId),
+    /// Add a store built by a factory, see `Simulation::register_store_factory`.
+    /// The process is resumed immediately and `SimContext::created` returns the id of the
+    /// new store.
+    AddStore(FactoryId),
+    /// Create a new process with a spawner, see `Simulation::register_spawner`, or with a
+    /// process factory, that receives `()` as arguments. The new process is started at the
+    /// current time with the yielded state, and the process that yields this effect is
+    /// resumed immediately: `SimContext::created` returns the id of the new process.
+    Spawn(FactoryId),
+    /// Terminate a process, see `Simulation::terminate_process`.
+    /// The process that yields this effect is resumed immediately, unless it terminated
+    /// itself. Terminating a process that already completed does nothing.
+    Kill(ProcessId),
+    /// Take a resource offline, e.g. because it broke down. The processes holding it are
+    /// interrupted with the yielded state as cause and lose their units, and no unit is
+    /// granted until the resource is repaired. The process that yields this effect is
+    /// resumed immediately. See the [`breakdown`](crate::breakdown) module.
+    Fail(ResourceId),
+    /// Bring a resource back online, granting it to the processes that were waiting.
+    /// The process that yields this effect is resumed immediately.
+    Repair(ResourceId),
+    /// Wait until the first of the effects of a condition completes.
+    /// The other effects are cancelled: the process leaves the queues it entered and the
+    /// resources granted to it are released.
+    /// See `Simulation::create_condition`.
+    AnyOf(ConditionId),
+    /// Wait until all the effects of a condition complete.
+    /// See `Simulation::create_condition`.
+    AllOf(ConditionId),
+    /// Wait until one of the effects of a condition completes, like `AnyOf`, but complete
+    /// at most one of them besides the timeouts: e.g. pull a single item out of the first of
+    /// several stores that has one, or request the first of several resources that is free.
+    /// The effects are tried in order, and the others are withdrawn as soon as one completes.
+    /// `SimContext::fired()` tells which one.
+    Select(ConditionId),
+    /// Cancel a scheduled event, so that it never fires.
+    /// The process that yields this effect is resumed immediately.
+    /// See `Simulation::cancel_event`.
+    Cancel(EventId),
+    /// Interrupt the specified process, that is resumed with the yielded state as cause.
+    /// The process that yields this effect is resumed immediately, as interrupted if it
+    /// interrupted itself.
+    /// See `Simulation::interrupt`.
+    Interrupt(ProcessId),
     /// Logs the event and resume the process immediately.
     Trace,
+    /// Publish a domain event on a topic, with the yielded state as payload.
+    /// The process that yields this effect is resumed immediately.
+    /// See the [`bus`](crate::bus) module.
+    Publish(TopicId),
+    /// Wait for the next domain event published on a topic. The process is resumed with
+    /// the state of the publisher.
+    Subscribe(TopicId),
+    /// Wait until a signal is fired. The process is resumed with the yielded state.
+    /// See `Simulation::create_signal`.
+    WaitSignal(SignalId),
+    /// Wake all the processes waiting for a signal.
+    /// The process that yields this effect is resumed immediately.
+    Fire(SignalId),
+    /// Wake the process that has been waiting for a signal the longest, if any.
+    /// The process that yields this effect is resumed immediately.
+    FireOne(SignalId),
+    /// Pass a gate: the process is resumed immediately if the gate is open, otherwise
+    /// when it is opened. See the [`signal`](crate::signal) module.
+    WaitGate(GateId),
+    /// Open a gate, releasing the processes waiting at it.
+    /// The process that yields this effect is resumed immediately.
+    OpenGate(GateId),
+    /// Close a gate. The process that yields this effect is resumed immediately.
+    CloseGate(GateId),
+    /// Wait until a predicate over the state of the simulation holds. The process is
+    /// resumed immediately if it already does. See the [`predicate`](crate::predicate)
+    /// module.
+    WaitUntil(PredicateId),
+    /// Send the yielded state as a message to a mailbox. The process is resumed
+    /// immediately, or when there is space if the mailbox is full.
+    /// See the [`mailbox`](crate::mailbox) module.
+    Send(MailboxId),
+    /// Receive the oldest message of a mailbox, waiting for one if it is empty.
+    /// The process is resumed with the message as state.
+    Receive(MailboxId),
+    /// Record a value in an accumulator of statistics: add it to a counter, observe it in a
+    /// tally or a histogram, or set it as the level of a time-weighted accumulator.
+    /// The process that yields this effect is resumed immediately.
+    /// See the [`stats`](crate::stats) module.
+    Record(StatId, f64),
+    /// Create an entity arriving at the current time, carried by the process from now on.
+    /// The process is resumed immediately: `SimContext::created` returns the raw id of the
+    /// new entity. See the [`entity`](crate::entity) module.
+    CreateEntity,
+    /// Carry an existing entity, e.g. handed over by another process. The process is
+    /// resumed immediately.
+    ///
+    /// The step fails if the entity does not exist, or if the process already carries
+    /// another entity: it must leave it with `Effect::Depart` first.
+    Carry(EntityId),
+    /// The entity carried by the process leaves the model at the current time, and the
+    /// process carries no entity anymore. The process is resumed immediately.
+    Depart,
+}
+
+/// Defines a typed identifier wrapping an index.
+macro_rules! id_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(transparent)
+        )]
+        pub struct $name(pub(crate) usize);
+
+        impl $name {
+            /// Create an id from its raw value
+            pub fn from_raw(raw: usize) -> $name {
+                $name(raw)
+            }
+
+            /// Returns the raw value of the id
+            pub fn into_raw(self) -> usize {
+                self.0
+            }
+        }
+
+        impl From<usize> for $name {
+            fn from(raw: usize) -> $name {
+                $name(raw)
+            }
+        }
+
+        impl From<$name> for usize {
+            fn from(id: $name) -> usize {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
 }
 
-/// Identifies a process. Can be used to resume it from another one and to schedule it.
-pub type ProcessId = usize;
-/// Identifies a resource. Can be used to request and release it.
-pub type ResourceId = usize;
-/// Identifies a store. Can be used to push into and pull out of it.
-pub type StoreId = usize;
+id_type!(
+    /// Identifies a process. Can be used to resume it from another one and to schedule it.
+    ///
+    /// The id of a process that completed is not valid anymore, even if the process that
+    /// takes its place reuses its slot.
+    ProcessId
+);
+id_type!(
+    /// Identifies a resource. Can be used to request and release it.
+    ResourceId
+);
+/// Identifies a group of resources.
+pub type GroupId = usize;
+id_type!(
+    /// Identifies a store. Can be used to push into and pull out of it.
+    StoreId
+);
+id_type!(
+    /// Identifies an entity flowing through the model, e.g. a customer served by several
+    /// processes. See the [`entity`](crate::entity) module.
+    EntityId
+);
+/// Identifies a scheduled event. Can be used to cancel it.
+pub type EventId = usize;
+/// Identifies a topic of domain events. Can be used to publish and subscribe.
+pub type TopicId = usize;
+/// Identifies a signal. Can be used to wait for it and to fire it.
+pub type SignalId = usize;
+/// Identifies a gate. Can be used to pass, open and close it.
+pub type GateId = usize;
+/// Identifies a mailbox. Can be used to send and receive messages.
+pub type MailboxId = usize;
+/// Identifies an accumulator of statistics. Can be used to record values.
+pub type StatId = usize;
+/// Identifies a combination of effects that can be waited with `AnyOf` and `AllOf`.
+pub type ConditionId = usize;
+/// Identifies a factory of resources, stores or processes, used to add them while the
+/// simulation runs.
+pub type FactoryId = usize;
+/// Identifies a series of periodic events. Can be used to cancel it.
+pub type SeriesId = usize;
+/// Identifies a predicate over the state of the simulation. Can be used to wait until it
+/// holds.
+pub type PredicateId = usize;
 /// The type of each `Process` coroutine
+#[cfg(all(feature = "coroutines", not(feature = "send")))]
 pub type Process<T> = dyn Coroutine<SimContext<T>, Yield = T, Return = ()> + Unpin;
+/// The type of each `Process` coroutine
+#[cfg(all(feature = "coroutines", feature = "send"))]
+pub type Process<T> = dyn Coroutine<SimContext<T>, Yield = T, Return = ()> + Unpin + Send;
+/// The type of each `Process`, without the `coroutines` feature
+#[cfg(all(not(feature = "coroutines"), not(feature = "send")))]
+pub type Process<T> = dyn process::ProcessFn<T>;
+/// The type of each `Process`, without the `coroutines` feature
+#[cfg(all(not(feature = "coroutines"), feature = "send"))]
+pub type Process<T> = dyn process::ProcessFn<T> + Send;
+/// The old name of `Process`, from when processes were generators
+#[deprecated(since = "0.4.0", note = "use `Process` instead")]
+pub type SimGen<T> = Process<T>;
+
+/// Creates the processes spawned by other processes, see `Simulation::register_spawner`
+#[cfg(not(feature = "send"))]
+pub type Spawner<T> = Box<dyn FnMut(&T) -> Box<Process<T>>>;
+/// Creates the processes spawned by other processes, see `Simulation::register_spawner`
+#[cfg(feature = "send")]
+pub type Spawner<T> = Box<dyn FnMut(&T) -> Box<Process<T>> + Send>;
+/// A source of arrivals, see `Simulation::add_arrivals`
+#[cfg(not(feature = "send"))]
+pub type ArrivalSource<T> = Box<dyn Iterator<Item = Arrival<T>>>;
+/// A source of arrivals, see `Simulation::add_arrivals`
+#[cfg(feature = "send")]
+pub type ArrivalSource<T> = Box<dyn Iterator<Item = Arrival<T>> + Send>;
 
 /// This struct provides the methods to create and run the simulation
 /// in a single thread.
@@ -184,13 +575,264 @@ pub type Process<T> = dyn Coroutine<SimContext<T>, Yield = T, Return = ()> + Unp
 /// simulation framework works
 pub struct Simulation<T: SimState + Clone> {
     time: f64,
+    /// The current time without the rounding of `time`, if it fits in a `SplitTime`
+    clock: Option<SplitTime>,
+    start_time: f64,
     steps: usize,
-    processes: Vec<Option<Box<Process<T>>>>,
-    future_events: BinaryHeap<Reverse<Event<T>>>,
+    processes: Slab<ProcessEntry<T>>,
+    streams: Streams<Philox4x32>,
+    created_processes: u32,
+    process_names: HashMap<ProcessId, String>,
+    /// The processes with each name, in the order they were given it
+    named_processes: HashMap<String, Vec<ProcessId>>,
+    /// The paths of the instances of modules
+    instances: HashSet<String>,
+    parents: HashMap<ProcessId, ProcessId>,
+    future_events: FutureEvents<T>,
     processed_events: Vec<(Event<T>, T)>,
-    resources: Vec<Box<dyn Resource<T>>>,
-    stores: Vec<Box<dyn Store<T>>>,
+    log_index: LogIndex,
+    log_sink: Option<BoxedLogSink<T>>,
+    /// The metadata of the run was passed to the log sink
+    log_begun: bool,
+    log_filter: Option<LogFilter<T>>,
+    muted_processes: HashSet<ProcessId>,
+    resources: Vec<ResourceEntry<T>>,
+    stores: Vec<BoxedStore<T>>,
     future_events_buffer: Vec<Event<T>>,
+    request_times: HashMap<(ResourceId, ProcessId), f64>,
+    held: HashMap<(ResourceId, ProcessId), usize>,
+    /// Units requested with `RequestN` and not granted yet
+    requested_units: HashMap<(ResourceId, ProcessId), usize>,
+    /// The resources that are offline, with the grants deferred until their repair
+    offline: HashMap<ResourceId, Vec<Event<T>>>,
+    /// The next change of the capacity of the resources whose capacity changes over time
+    capacity_changes: Vec<(f64, ResourceId, usize)>,
+    soft_limits: SoftLimits,
+    event_budget: Option<(usize, BudgetPolicy)>,
+    budget_violations: Vec<BudgetViolation>,
+    arrival_sources: Vec<ArrivalSource<T>>,
+    metadata: RunMetadata,
+    conditions: Vec<Vec<Effect>>,
+    /// Branches of completed conditions, reused by the next ones to save allocations
+    branch_buffers: Vec<Vec<(Effect, BranchStatus)>>,
+    /// Events granted by a resource, reused by every request and release to save allocations
+    grants_buffer: Vec<Event<T>>,
+    resource_factories: Vec<ResourceFactory<T>>,
+    store_factories: Vec<StoreFactory<T>>,
+    entities: Entities,
+    process_factories: ProcessFactories<T>,
+    /// The processes created with `spawn_process`, created again by `reset`
+    initial_processes: Vec<InitialProcess<T>>,
+    next_event_id: EventId,
+    cancelled_events: HashSet<EventId>,
+    reference: Option<AnalyticReference>,
+    resource_stats: Vec<ResourceStats>,
+    stats: Stats,
+    /// The end of the warm-up period, until it is reached
+    warm_up: Option<f64>,
+    resource_names: Vec<Option<String>>,
+    store_names: Vec<Option<String>>,
+    /// The occupancy of the resources, shared with the contexts of the processes
+    resource_snapshots: Arc<Mutex<Vec<ResourceSnapshot>>>,
+    groups: Vec<ResourceGroup>,
+    topics: Vec<Topic<T>>,
+    signals: Vec<Signal<T>>,
+    predicates: Vec<WaitUntil<T>>,
+    /// Set when a resource or a store changed, to evaluate the predicates again
+    state_changed: bool,
+    shared: Shared,
+    gates: Vec<Gate<T>>,
+    mailboxes: Vec<Mailbox<T>>,
+    invariants: Vec<Invariant<Simulation<T>>>,
+    progress: Option<Progress>,
+    /// Set by the `StopHandle`s to stop the run in progress
+    stop: Arc<AtomicBool>,
+    observers: Vec<BoxedObserver<T>>,
+    error: Option<SimError>,
+    zero_delay_policy: ZeroDelayPolicy,
+    release_policy: ReleasePolicy,
+    zero_delay_steps: usize,
+    /// The number of steps and the wall-clock time at the start of the current run
+    run_start: (usize, Instant),
+    engine: EngineCounters,
+    /// The processes resumed by the current chain of steps at the same time, tracked with
+    /// `ZeroDelayPolicy::Fail`
+    zero_delay_processes: Vec<ProcessId>,
+    end_reason: Option<EndReason>,
+    end_condition: Option<EndCondition>,
+    attribute_schema: Option<AttributeSchema<T>>,
+    injections: Injections<T>,
+    series: Vec<Option<Series<T>>>,
+    /// The inputs recorded so far
+    journal: Option<Journal<T>>,
+    /// The inputs left to replay
+    replaying: Option<VecDeque<(usize, Input<T>)>>,
+    rollback: Option<Rollback<T>>,
+}
+
+/// A series of periodic events.
+struct Series<T> {
+    period: f64,
+    process: ProcessId,
+    state: T,
+    /// The next event of the series
+    next: EventId,
+}
+
+/// The bookkeeping the simulation keeps for each process.
+struct ProcessEntry<T> {
+    /// The coroutine, or `None` if the process completed
+    coroutine: Option<Box<Process<T>>>,
+    /// Number of events scheduled by the effects yielded by the process
+    scheduled_events: usize,
+    /// Set when the process exceeds the event budget with `BudgetPolicy::Suspend`
+    suspended: bool,
+    /// Set while the process is parked after yielding `Effect::Wait`
+    waiting: bool,
+    /// The event that resumes the process after yielding `Effect::WaitTimeout`
+    deadline: Option<EventId>,
+    /// What the process is waiting for, if it is parked in a queue
+    blocked_on: Option<Blocked>,
+    /// Incremented when the process is interrupted, to discard its pending events
+    epoch: usize,
+    /// The condition the process is waiting for, after yielding `AnyOf` or `AllOf`
+    condition: Option<ActiveCondition>,
+    /// The random stream of the process, in order of creation
+    stream: u32,
+    /// Number of times the process was resumed, each with its own substream
+    resumes: u32,
+    /// The effect yielded last, that tells why the process is resumed
+    last_effect: Option<Effect>,
+    /// The entity carried by the process
+    entity: Option<EntityId>,
+    /// The last event scheduled with `Effect::Event`, `EventWithPriority` or `EventAt`
+    /// since the process was resumed
+    scheduled: Option<EventId>,
+}
+
+/// A condition a process is waiting for.
+struct ActiveCondition {
+    /// `true` for `AnyOf` and `Select`, `false` for `AllOf`
+    any: bool,
+    /// `true` for `Select`: at most one effect other than timeouts completes
+    exclusive: bool,
+    branches: Vec<(Effect, BranchStatus)>,
+}
+
+/// The progress of one of the effects of a condition.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BranchStatus {
+    /// The process is in a queue
+    Pending,
+    /// The event that completes the effect is scheduled
+    Ready,
+    /// The effect completed
+    Done,
+}
+
+/// Where a process is parked.
+#[derive(Debug, Copy, Clone)]
+enum Blocked {
+    /// In the queue of a resource
+    Resource(ResourceId),
+    /// Granted units of a resource, but not yet resumed
+    Granted(ResourceId, usize),
+    /// In a queue of a store
+    Store(StoreId),
+    /// Subscribed to a topic
+    Topic(TopicId),
+    /// Waiting for a signal
+    Signal(SignalId),
+    /// Waiting at a closed gate
+    Gate(GateId),
+    /// Waiting to send to or receive from a mailbox
+    Mailbox(MailboxId),
+    /// Waiting for a predicate to hold
+    Predicate(PredicateId),
+}
+
+/// Why a process was resumed, returned by `SimContext::outcome`.
+///
+/// Besides interrupts and expired deadlines, a process resumed by the completion of the
+/// effect it yielded last gets the outcome of that effect: e.g. a process that yielded
+/// `Effect::Request` is resumed with `Granted`, unless the resource rejected the request
+/// with `Event::reject`. A process resumed by an event scheduled by the owner of the
+/// simulation or by another process, e.g. with `Simulation::resume_process` or
+/// `Effect::Event`, is resumed with `Resumed` whatever it was waiting for.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The process was resumed for the first time
+    Started,
+    /// The timeout yielded by the process expired
+    Elapsed,
+    /// The process was granted the resource it requested
+    Granted(ResourceId),
+    /// The resource rejected the request of the process, e.g. because its queue was full
+    Rejected(ResourceId),
+    /// The store was full and rejected the push of the process
+    Full(StoreId),
+    /// The deadline of a request, a wait or a condition expired
+    TimedOut,
+    /// The process released the resource
+    Released(ResourceId),
+    /// The process pushed into the store
+    Pushed(StoreId),
+    /// The process pulled out of the store
+    Pulled(StoreId),
+    /// The process was interrupted, and the state is the cause
+    Interrupted,
+    /// The signal the process was waiting for was fired
+    Signaled(SignalId),
+    /// The gate the process was waiting at is open
+    Passed(GateId),
+    /// A domain event was published on the topic, and the state is that of the publisher
+    Published(TopicId),
+    /// The process sent a message to the mailbox
+    Sent(MailboxId),
+    /// The process received a message from the mailbox, that is the state
+    Received(MailboxId),
+    /// The predicate the process was waiting for holds
+    Satisfied(PredicateId),
+    /// The condition the process was waiting for completed. For `AnyOf` and `Select`,
+    /// `fired` is the index of the effect that completed first.
+    Condition {
+        condition: ConditionId,
+        fired: Option<usize>,
+    },
+    /// The process was resumed by another event, e.g. after yielding `Effect::Wait`, or
+    /// immediately after an effect that does not wait
+    Resumed,
+}
+
+impl Outcome {
+    /// Returns the outcome of the `effect` yielded by a process, if it was not interrupted,
+    /// its deadline did not expire and its request was not rejected
+    fn of(effect: Option<Effect>, fired: Option<usize>) -> Outcome {
+        match effect {
+            None => Outcome::Started,
+            Some(Effect::TimeOut(_)) => Outcome::Elapsed,
+            Some(Effect::Request(r) | Effect::RequestN(r, _) | Effect::RequestTimeout(r, _)) => {
+                Outcome::Granted(r)
+            }
+            Some(Effect::Release(r) | Effect::ReleaseN(r, _) | Effect::ReleaseAll(r)) => {
+                Outcome::Released(r)
+            }
+            Some(Effect::Push(s)) => Outcome::Pushed(s),
+            Some(Effect::Pull(s)) => Outcome::Pulled(s),
+            Some(Effect::WaitSignal(s)) => Outcome::Signaled(s),
+            Some(Effect::WaitGate(g)) => Outcome::Passed(g),
+            Some(Effect::Subscribe(t)) => Outcome::Published(t),
+            Some(Effect::Send(m)) => Outcome::Sent(m),
+            Some(Effect::Receive(m)) => Outcome::Received(m),
+            Some(Effect::WaitUntil(p)) => Outcome::Satisfied(p),
+            Some(Effect::AnyOf(c) | Effect::AllOf(c) | Effect::Select(c)) => Outcome::Condition {
+                condition: c,
+                fired,
+            },
+            Some(_) => Outcome::Resumed,
+        }
+    }
 }
 
 /// The Simulation Context is the argument used to resume the coroutine.
@@ -199,17 +841,24 @@ pub struct Simulation<T: SimState + Clone> {
 pub struct SimContext<T> {
     time: f64,
     state: T,
+    outcome: Outcome,
+    interrupted: bool,
+    fired: Option<usize>,
+    timed_out: bool,
+    release: Option<ReleaseOutcome>,
+    created: Option<usize>,
+    event_id: EventId,
+    scheduled: Option<EventId>,
+    resources: Arc<Mutex<Vec<ResourceSnapshot>>>,
+    shared: Shared,
+    rng: Philox4x32,
+    entity: Option<EntityId>,
 }
 
-/*
-pub struct ParallelSimulation {
-    processes: Vec<Box<Coroutine<Yield = Effect, Return = ()>>>
-}
- */
-
 /// An event that can be scheduled by a process, yelding the `Event` `Effect`
 /// or by the owner of a `Simulation` through the `schedule` method
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Event<T> {
     /// Time interval between the current simulation time and the event schedule
     time: f64,
@@ -217,16 +866,127 @@ pub struct Event<T> {
     process: ProcessId,
     /// Effect that generated the event
     state: T,
+    /// Source of arrivals that created the process, if this is its first event
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<usize>,
+    /// Series of periodic events the event belongs to
+    #[cfg_attr(feature = "serde", serde(skip))]
+    series: Option<SeriesId>,
+    /// Incarnation of the process the event was scheduled for
+    #[cfg_attr(feature = "serde", serde(skip))]
+    epoch: usize,
+    /// Set if the event interrupts the process
+    interrupted: bool,
+    /// Index of the effect of a condition that is completed by this event
+    #[cfg_attr(feature = "serde", serde(skip))]
+    branch: Option<usize>,
+    /// Assigned when the event is scheduled
+    id: EventId,
+    /// What happened when the process released a resource
+    #[cfg_attr(feature = "serde", serde(skip))]
+    release: Option<ReleaseOutcome>,
+    /// The resource or store created by the process
+    #[cfg_attr(feature = "serde", serde(skip))]
+    created: Option<usize>,
+    /// Set if a resource rejected the request of the process
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rejected: bool,
+    /// The entity carried by the process when it was resumed
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    entity: Option<EntityId>,
+    /// Events at the same time are executed by decreasing priority
+    priority: i32,
+    /// The time without rounding, if it fits in a `SplitTime`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    exact: Option<SplitTime>,
+    /// Why the process is resumed, if not by the completion of the effect it yielded
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cause: Option<Outcome>,
 }
 
 /// Specify which condition must be met for the simulation to stop.
+#[derive(Clone)]
 pub enum EndCondition {
     /// Run the simulation until a certain point in time is reached.
     Time(f64),
     /// Run the simulation until there are no more events scheduled.
     NoEvents,
-    /// Execute exactly N steps of the simulation.
+    /// Run until N steps of the simulation were executed since the run started.
     NSteps(usize),
+    /// Run until the run lasted for the specified wall-clock time.
+    MaxWallClock(Duration),
+    /// Stop when both conditions are met.
+    And(Box<EndCondition>, Box<EndCondition>),
+    /// Stop when either condition is met.
+    Or(Box<EndCondition>, Box<EndCondition>),
+}
+
+/// What happened in a step of the simulation, returned by `Simulation::step`.
+#[derive(Debug, Clone)]
+pub enum StepResult<T> {
+    /// An event resumed a process, that yielded `state`
+    Executed { event: Event<T>, state: T },
+    /// An event resumed a process, that completed
+    Completed { event: Event<T> },
+    /// There were no events to execute
+    Idle,
+}
+
+impl<T> StepResult<T> {
+    /// Returns the time of the executed event, if any
+    pub fn time(&self) -> Option<f64> {
+        self.event().map(Event::time)
+    }
+
+    /// Returns the process resumed by the step, if any
+    pub fn process(&self) -> Option<ProcessId> {
+        self.event().map(Event::process)
+    }
+
+    /// Returns the executed event, if any
+    pub fn event(&self) -> Option<&Event<T>> {
+        match self {
+            StepResult::Executed { event, .. } | StepResult::Completed { event } => Some(event),
+            StepResult::Idle => None,
+        }
+    }
+}
+
+/// Why a run of the simulation stopped.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum EndReason {
+    /// The time of an `EndCondition::Time` was reached
+    TimeReached,
+    /// There were no more events scheduled
+    NoEvents,
+    /// The steps of an `EndCondition::NSteps` were executed
+    StepLimit,
+    /// The wall-clock time of an `EndCondition::MaxWallClock` elapsed
+    WallClock,
+    /// The condition of `run_while` did not hold anymore
+    Condition,
+    /// The progress hook aborted the run. See the [`progress`](crate::progress) module.
+    Aborted,
+    /// The run was stopped with a `StopHandle`. See the [`control`](crate::control) module.
+    Stopped,
+    /// A step failed
+    Error(SimError),
+    /// There were no more events scheduled, but some processes were still parked.
+    /// See the [`deadlock`](crate::deadlock) module.
+    Deadlock(Vec<BlockedProcess>),
+}
+
+impl EndCondition {
+    /// Stop when both this condition and `other` are met.
+    pub fn and(self, other: EndCondition) -> EndCondition {
+        EndCondition::And(Box::new(self), Box::new(other))
+    }
+
+    /// Stop when either this condition or `other` is met.
+    pub fn or(self, other: EndCondition) -> EndCondition {
+        EndCondition::Or(Box::new(self), Box::new(other))
+    }
 }
 
 impl<T: 'static + SimState + Clone> Simulation<T> {
@@ -235,418 +995,3074 @@ impl<T: 'static + SimState + Clone> Simulation<T> {
         Simulation::<T>::default()
     }
 
-    /// Returns the current simulation time
-    pub fn time(&self) -> f64 {
-        self.time
+    /// Create a new `Simulation` environment with room for `events` scheduled events,
+    /// `processes` processes and `log` processed events, so that the simulation does not
+    /// need to grow them while it runs.
+    pub fn with_capacity(events: usize, processes: usize, log: usize) -> Simulation<T> {
+        let mut sim = Simulation::new();
+        sim.reserve_events(events);
+        sim.processes.reserve(processes);
+        sim.processed_events.reserve(log);
+        sim
     }
 
-    /// Returns the log of processed events
-    pub fn processed_events(&self) -> &[(Event<T>, T)] {
-        self.processed_events.as_slice()
+    /// Create a new `Simulation` environment, whose processes draw random numbers from
+    /// streams derived from `seed`. See `SimContext::rng`.
+    ///
+    /// The seed is recorded in the metadata. `Simulation::new` uses the seed 0.
+    pub fn with_seed(seed: u64) -> Simulation<T> {
+        let mut sim = Simulation::new();
+        sim.set_seed(seed, 0);
+        sim
     }
 
-    /// Create a process.
-    ///
-    /// For more information about a process, see the crate level documentation
-    ///
-    /// Returns the identifier of the process.
-    pub fn create_process(
-        &mut self,
-        process: Box<dyn Coroutine<SimContext<T>, Yield = T, Return = ()> + Unpin>,
-    ) -> ProcessId {
-        let id = self.processes.len();
-        self.processes.push(Some(process));
-        id
+    /// Returns a builder to configure a new `Simulation`.
+    /// See the [`builder`](crate::builder) module.
+    pub fn builder() -> SimulationBuilder<T> {
+        SimulationBuilder::new()
     }
 
-    /// Create a new resource.
-    ///
-    /// For more information about a resource, see the crate level documentation
-    /// and the documentation of the [`resources`](crate::resources) module.
-    ///
-    /// Returns the identifier of the resource
-    pub fn create_resource(&mut self, resource: Box<dyn Resource<T>>) -> ResourceId {
-        let id = self.resources.len();
-        self.resources.push(resource);
-        id
+    /// Set the seed of the random streams of the processes, and the replication of the
+    /// experiment, that selects independent streams for the same seed.
+    pub fn set_seed(&mut self, seed: u64, replication: u32) {
+        self.streams = Streams::new(seed, replication);
+        self.metadata.set_seed(seed);
     }
 
-    /// Create a new store.
-    ///
-    /// For more information about a store, see the crate level documentation
-    /// and the documentation of the [`resources`](crate::resources) module.
-    ///
-    /// Returns the identifier of the store
-    pub fn create_store(&mut self, store: Box<dyn Store<T>>) -> StoreId {
-        let id = self.stores.len();
-        self.stores.push(store);
-        id
+    /// Returns the random streams of the processes
+    pub fn streams(&self) -> &Streams<Philox4x32> {
+        &self.streams
     }
 
-    /// Schedule a process to be executed after `time` time instants.
-    /// Another way to schedule events is
-    /// yielding `Effect::Event` from a process during the simulation.
-    // TODO: Review this API
-    pub fn schedule_event(&mut self, time: f64, process: ProcessId, state: T) {
-        self.future_events
-            .push(Reverse(Event::new(time, process, state)));
+    /// Returns the current simulation time
+    pub fn time(&self) -> f64 {
+        self.time
     }
 
-    fn log_processed_event(&mut self, event: &Event<T>, sim_state: T) {
-        if sim_state.should_log() {
-            self.processed_events.push((event.clone(), sim_state));
-        }
+    /// Returns the current simulation time without rounding, or `None` if it does not fit
+    /// in a `SplitTime`, e.g. because it is negative. See the [`time`](crate::time) module.
+    pub fn split_time(&self) -> Option<SplitTime> {
+        self.clock
     }
 
-    /// Proceed in the simulation by 1 step
-    pub fn step(&mut self) {
-        self.steps += 1;
-        if let Some(Reverse(event)) = self.future_events.pop() {
-            self.time = event.time();
-            let gstatepin = Pin::new(
-                self.processes[event.process]
-                    .as_mut()
-                    .expect("ERROR. Tried to resume a completed process."),
-            )
-            .resume(SimContext {
-                time: self.time,
-                state: event.state().clone(),
-            });
-            // log event
-            // logging needs to happen before the processing because processing
-            // can add further events (such as resource acquired/released) and
-            // it becomes confusing if you first get a resource acquired event
-            // and only log the request for it afterwards.
-            match gstatepin.clone() {
-                CoroutineState::Yielded(y) => {
-                    self.log_processed_event(&event, y);
-                }
-                CoroutineState::Complete(_) => {}
-            }
-            // process event
-            match gstatepin {
-                CoroutineState::Yielded(y) => {
-                    let effect = y.get_effect();
-                    match effect {
-                        Effect::TimeOut(t) => self.future_events.push(Reverse(Event {
-                            time: self.time + t,
-                            process: event.process(),
-                            state: y,
-                        })),
-                        Effect::Event { time, process } => {
-                            let e = Event::new(time + self.time, process, y);
-                            self.future_events.push(Reverse(e))
-                        }
-                        Effect::Request(r) => {
-                            let res = &mut self.resources[r];
-                            let request_event = Event::new(self.time, event.process(), y);
-                            if let Some(e) = res.allocate_or_enqueue(request_event) {
-                                self.future_events.push(Reverse(e))
-                            }
-                        }
-                        Effect::Release(r) => {
-                            let res = &mut self.resources[r];
-                            let release_event = Event::new(self.time, event.process(), y);
-                            if let Some(e) = res.release_and_schedule_next(release_event.clone()) {
-                                self.future_events.push(Reverse(e));
-                            }
-                            // after releasing the resource the process
-                            // can be resumed
-                            self.future_events.push(Reverse(release_event));
-                        }
-                        Effect::Wait => {}
-                        Effect::Trace => {
-                            // this event is only for tracing, reschedule
-                            // immediately'
-                            let e = Event::new(self.time, event.process(), y);
-                            self.future_events.push(Reverse(e));
-                        }
-                        Effect::Push(s) => {
-                            let store = &mut self.stores[s];
-                            let request_event = Event::new(self.time, event.process(), y);
-                            store.push_or_enqueue_and_schedule_next(
-                                request_event,
-                                &mut self.future_events_buffer,
-                            );
-                            self.future_events
-                                .extend(self.future_events_buffer.drain(..).map(Reverse));
-                        }
-                        Effect::Pull(s) => {
-                            let store = &mut self.stores[s];
-                            let request_event = Event::new(self.time, event.process(), y);
-                            store.pull_or_enqueue_and_schedule_next(
-                                request_event,
-                                &mut self.future_events_buffer,
-                            );
-                            self.future_events
-                                .extend(self.future_events_buffer.drain(..).map(Reverse));
-                        }
-                    }
-                }
-                CoroutineState::Complete(_) => {
-                    // FIXME: removing the process from the vector would invalidate
-                    // all existing `ProcessId`s, but keeping it would be a
-                    // waste of space since it is completed.
-                    // May be worth to use another data structure.
-                    // At least let's remove the coroutine itself.
-                    self.processes[event.process()].take();
-                }
+    /// Set the clock to `time`.
+    fn set_clock(&mut self, time: f64) {
+        self.time = time;
+        self.clock = SplitTime::checked_from_f64(time);
+    }
+
+    /// Move the clock to the time of `event`. The clock keeps its precision if the event
+    /// was scheduled after a delay, or if it is at the current time.
+    fn advance_clock(&mut self, event: &Event<T>) {
+        match event.exact {
+            Some(exact) => {
+                self.time = event.time;
+                self.clock = Some(exact);
             }
+            None if event.time != self.time => self.set_clock(event.time),
+            None => {}
         }
     }
 
-    /// Run the simulation until and ending condition is met.
-    pub fn run(mut self, until: EndCondition) -> Simulation<T> {
-        while !self.check_ending_condition(&until) {
-            self.step();
+    /// Set the time of `event` to `delay` time units after the current time, adding the
+    /// delay to the clock without rounding.
+    fn set_delay(&self, event: &mut Event<T>, delay: f64) {
+        match self.clock.and_then(|clock| clock.checked_add(delay)) {
+            Some(exact) => {
+                event.time = exact.as_f64();
+                event.exact = Some(exact);
+            }
+            None => event.set_time(self.time + delay),
         }
-        self
     }
-    /*
-        pub fn nonblocking_run(mut self, until: EndCondition) -> thread::JoinHandle<Simulation> {
-            thread::spawn(move || {
-                self.run(until)
-            })
-        }
-    */
 
-    /// Return `true` if the ending condition was met, `false` otherwise.
-    fn check_ending_condition(&self, ending_condition: &EndCondition) -> bool {
-        match &ending_condition {
-            EndCondition::Time(t) => self.time >= *t,
-            EndCondition::NoEvents => self.future_events.is_empty(),
-            EndCondition::NSteps(n) => self.steps == *n,
-        }
+    /// Returns an event for `process` at `delay` time units after the current time.
+    fn delayed_event(&self, delay: f64, process: ProcessId, state: T) -> Event<T> {
+        let mut event = Event::new(self.time, process, state);
+        self.set_delay(&mut event, delay);
+        event
     }
-}
 
-impl<T> SimContext<T> {
-    /// Returns current simulation time.
-    pub fn time(&self) -> f64 {
-        self.time
+    /// Start the simulation at `time` instead of 0, e.g. at a timestamp.
+    ///
+    /// The statistics collected so far start from `time`.
+    ///
+    /// # Panics
+    /// Panics if the simulation already executed some steps, or if an event is scheduled
+    /// before `time`.
+    pub fn set_start_time(&mut self, time: f64) {
+        assert_eq!(
+            self.steps, 0,
+            "ERROR. The start time can be set only before the first step."
+        );
+        assert!(
+            self.future_events.iter().all(|e| e.time() >= time),
+            "ERROR. An event is scheduled before the start time."
+        );
+        self.set_clock(time);
+        self.start_time = time;
+        self.reset_stats_at(time);
+        self.collect_capacity_changes(time);
     }
 
-    /// Returns the `Effect` that caused the process to wake up
-    pub fn state(&self) -> &T {
-        &self.state
+    /// Collect the next capacity change of every resource after `time`
+    fn collect_capacity_changes(&mut self, time: f64) {
+        self.capacity_changes = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(id, resource)| {
+                let (t, capacity) = resource.next_capacity_change(time)?;
+                Some((t, ResourceId(id), capacity))
+            })
+            .collect();
     }
-}
 
-impl<T> Event<T> {
-    pub fn new(time: f64, process: ProcessId, state: T) -> Event<T> {
-        Event {
-            time,
-            process,
-            state,
-        }
+    /// Returns the time the simulation started at
+    pub fn start_time(&self) -> f64 {
+        self.start_time
     }
-    pub fn time(&self) -> f64 {
-        self.time
+
+    /// Returns the time elapsed since the start of the simulation
+    pub fn elapsed(&self) -> f64 {
+        self.time - self.start_time
     }
-    pub fn set_time(&mut self, time: f64) {
-        self.time = time;
+
+    /// Returns the log of processed events.
+    ///
+    /// The log is empty if the simulation logs to a sink that does not keep the events
+    /// in memory, see `set_log_sink` and `keeps_log`.
+    pub fn processed_events(&self) -> &[(Event<T>, T)] {
+        match &self.log_sink {
+            Some(sink) => sink.events(),
+            None => self.processed_events.as_slice(),
+        }
     }
-    pub fn process(&self) -> ProcessId {
-        self.process
+
+    /// Remove the processed events from the log and return them, without copying them.
+    /// The events processed from now on are logged as usual.
+    ///
+    /// The result is empty if the simulation logs to a sink that does not keep the events
+    /// in memory, see `set_log_sink`.
+    pub fn take_processed_events(&mut self) -> Vec<(Event<T>, T)> {
+        self.log_index.clear();
+        match &mut self.log_sink {
+            Some(sink) => sink.take_events(),
+            None => std::mem::take(&mut self.processed_events),
+        }
     }
-    pub fn set_process(&mut self, process: ProcessId) {
-        self.process = process;
+
+    /// Returns `true` if the processed events are kept in memory, either by the simulation
+    /// or by its log sink, so that they can be read with `processed_events`.
+    pub fn keeps_log(&self) -> bool {
+        self.log_sink
+            .as_ref()
+            .is_none_or(|sink| sink.keeps_events())
     }
-    pub fn state(&self) -> &T {
-        &self.state
+
+    /// Consume the simulation, returning the log of processed events,
+    /// like `take_processed_events`.
+    pub fn into_log(mut self) -> Vec<(Event<T>, T)> {
+        self.take_processed_events()
     }
-    pub fn state_mut(&mut self) -> &mut T {
-        &mut self.state
+
+    /// Returns a view of the log of processed events, to query it.
+    /// See the [`log`](crate::log) module.
+    pub fn log(&self) -> EventLog<'_, T> {
+        let index = self.log_sink.is_none().then_some(&self.log_index);
+        EventLog::new(self.processed_events(), index)
     }
-    pub fn set_state(&mut self, state: T) {
-        self.state = state;
+
+    /// Write the log of processed events in JSON format: an object with the metadata of the
+    /// run in `metadata`, as written by `export::write_chrome_trace`, and the events in
+    /// `events`, as an array of pairs of an event and the state yielded by the process.
+    ///
+    /// Returns an error if the simulation logs to a sink that does not keep the events in
+    /// memory, see `set_log_sink`.
+    ///
+    /// Available with the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn export_log<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()>
+    where
+        T: serde::Serialize,
+    {
+        let events = export::logged_events(self)?;
+        let metadata = export::json_metadata(&self.metadata);
+        write!(writer, "{{\"metadata\":{},\"events\":", metadata)?;
+        serde_json::to_writer(&mut writer, events)?;
+        write!(writer, "}}")
     }
-}
 
-impl<T: SimState> Event<T> {
-    pub fn effect(&self) -> Effect {
-        self.state.get_effect()
+    /// Set the destination of the log of processed events, replacing the current one.
+    /// See the [`sink`](crate::sink) module.
+    pub fn set_log_sink(&mut self, sink: BoxedLogSink<T>) {
+        self.log_sink = Some(sink);
+        self.log_begun = false;
+        self.log_index.clear();
     }
-    pub fn set_effect(&mut self, effect: Effect) {
-        self.state.set_effect(effect)
+
+    /// Set a filter deciding which events are logged, besides `SimState::should_log`.
+    /// See the [`sink`](crate::sink) module.
+    pub fn set_log_filter(&mut self, filter: LogFilter<T>) {
+        self.log_filter = Some(filter);
     }
-}
 
-impl<T: SimState + Clone> Default for Simulation<T> {
-    fn default() -> Self {
-        Simulation::<T> {
-            time: 0.0,
-            steps: 0,
-            processes: Vec::default(),
-            future_events: BinaryHeap::default(),
-            processed_events: Vec::default(),
-            resources: Vec::default(),
-            stores: Vec::default(),
-            future_events_buffer: Vec::default(),
+    /// Enable or disable the logging of the events of a process. It is enabled by default.
+    pub fn set_process_logging(&mut self, process: ProcessId, enabled: bool) {
+        if enabled {
+            self.muted_processes.remove(&process);
+        } else {
+            self.muted_processes.insert(process);
         }
     }
-}
 
-impl<T> PartialEq for Event<T> {
-    fn eq(&self, other: &Event<T>) -> bool {
-        self.time == other.time
+    /// Flush the log sink, returning the first error it encountered, if any.
+    pub fn flush_log(&mut self) -> std::io::Result<()> {
+        match &mut self.log_sink {
+            Some(sink) => sink.flush(),
+            None => Ok(()),
+        }
     }
-}
-
-impl<T> Eq for Event<T> {}
 
-impl<T> PartialOrd for Event<T> {
-    #[allow(clippy::incorrect_partial_ord_impl_on_ord_type)]
-    fn partial_cmp(&self, other: &Event<T>) -> Option<Ordering> {
-        self.time.partial_cmp(&other.time)
+    /// Returns the metadata describing this run
+    pub fn metadata(&self) -> &RunMetadata {
+        &self.metadata
     }
-}
 
-impl<T> Ord for Event<T> {
-    fn cmp(&self, other: &Event<T>) -> Ordering {
-        match self.time.partial_cmp(&other.time) {
-            Some(o) => o,
-            None => panic!("Event time was uncomparable. Maybe a NaN"),
-        }
+    /// Returns the metadata describing this run, to record the seed, the parameters
+    /// of the model and so on. See the [`metadata`](crate::metadata) module.
+    pub fn metadata_mut(&mut self) -> &mut RunMetadata {
+        &mut self.metadata
     }
-}
 
-impl SimState for Effect {
-    fn get_effect(&self) -> Effect {
-        *self
+    /// Register the attributes of the states that are written by the exporters.
+    /// See the [`export`](crate::export) module.
+    pub fn set_attribute_schema(&mut self, schema: AttributeSchema<T>) {
+        self.attribute_schema = Some(schema);
     }
-    fn set_effect(&mut self, e: Effect) {
-        *self = e;
+
+    /// Returns the attribute schema, if registered
+    pub fn attribute_schema(&self) -> Option<&AttributeSchema<T>> {
+        self.attribute_schema.as_ref()
     }
-    fn should_log(&self) -> bool {
-        true
+
+    /// Attach an analytic reference model to the simulation.
+    /// See the [`reference`](crate::reference) module.
+    pub fn set_reference(&mut self, reference: AnalyticReference) {
+        self.reference = Some(reference);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        use crate::{Effect, Simulation};
+    /// Compare the `empirical` values of some metrics with the analytic reference, using the
+    /// parameters recorded in the metadata of the run.
+    ///
+    /// Returns `None` if no reference is attached.
+    pub fn compare_with_reference(&self, empirical: &[(&str, f64)]) -> Option<ComparisonReport> {
+        self.reference
+            .as_ref()
+            .map(|reference| reference.compare(&self.metadata, empirical))
+    }
 
-        let mut s = Simulation::new();
-        let p = s.create_process(Box::new(
-            #[coroutine]
-            |_| {
-                let mut a = 0.0;
-                loop {
-                    a += 1.0;
+    /// Create a process.
+    ///
+    /// For more information about a process, see the crate level documentation
+    ///
+    /// Returns the identifier of the process.
+    ///
+    /// When a process completes, its id becomes invalid and its slot is reused by the
+    /// processes created later, that get different ids.
+    pub fn create_process(&mut self, process: Box<Process<T>>) -> ProcessId {
+        let id = self.processes.insert(ProcessEntry {
+            coroutine: Some(process),
+            scheduled_events: 0,
+            suspended: false,
+            waiting: false,
+            deadline: None,
+            blocked_on: None,
+            epoch: 0,
+            condition: None,
+            stream: self.created_processes,
+            resumes: 0,
+            last_effect: None,
+            entity: None,
+            scheduled: None,
+        });
+        self.created_processes = self.created_processes.wrapping_add(1);
+        id
+    }
 
-                    yield Effect::TimeOut(a);
-                }
-            },
-        ));
-        s.schedule_event(0.0, p, Effect::TimeOut(0.));
-        s.step();
-        s.step();
-        assert_eq!(s.time(), 1.0);
-        s.step();
-        assert_eq!(s.time(), 3.0);
-        s.step();
-        assert_eq!(s.time(), 6.0);
-    }
-
-    #[test]
-    fn run() {
-        use crate::{Effect, EndCondition, Simulation};
-
-        let mut s = Simulation::new();
-        let p = s.create_process(Box::new(
-            #[coroutine]
-            |_| {
-                let tik = 0.7;
-                loop {
-                    println!("tik");
-                    yield Effect::TimeOut(tik);
-                }
-            },
-        ));
-        s.schedule_event(0.0, p, Effect::TimeOut(0.));
-        let s = s.run(EndCondition::Time(10.0));
-        println!("{}", s.time());
-        assert!(s.time() >= 10.0);
-    }
-
-    #[test]
-    fn resource() {
-        use crate::resources::SimpleResource;
-        use crate::{Effect, EndCondition::NoEvents, Simulation};
-
-        let mut s = Simulation::new();
-        let r = s.create_resource(Box::new(SimpleResource::new(1)));
-
-        // simple process that lock the resource for 7 time units
-        let p1 = s.create_process(Box::new(
-            #[coroutine]
-            move |_| {
-                yield Effect::Request(r);
-                yield Effect::TimeOut(7.0);
-                yield Effect::Release(r);
-            },
-        ));
-        // simple process that holds the resource for 3 time units
-        let p2 = s.create_process(Box::new(
-            #[coroutine]
-            move |_| {
-                yield Effect::Request(r);
-                yield Effect::TimeOut(3.0);
-                yield Effect::Release(r);
-            },
-        ));
+    /// Returns the number of processes that did not complete yet
+    pub fn active_processes(&self) -> usize {
+        self.processes.len()
+    }
 
-        // let p1 start immediately...
-        s.schedule_event(0.0, p1, Effect::TimeOut(0.));
-        // let p2 start after 2 t.u., when r is not available
-        s.schedule_event(2.0, p2, Effect::TimeOut(2.));
-        // p2 will wait r to be free (time 7.0) and its timeout
-        // of 3.0 t.u. The simulation will end at time 10.0
+    /// Returns the entry of a process that did not complete.
+    fn process_entry(&mut self, process: ProcessId) -> Result<&mut ProcessEntry<T>, SimError> {
+        if self.processes.was_removed(process) {
+            return Err(SimError::ProcessCompleted(process));
+        }
+        self.processes
+            .get_mut(process)
+            .ok_or(SimError::UnknownProcess(process))
+    }
 
-        let s = s.run(NoEvents);
-        println!("{:?}", s.processed_events());
-        assert_eq!(s.time(), 10.0);
+    /// Register a spawner, used by processes to create new processes yielding
+    /// `Effect::Spawn`.
+    ///
+    /// The spawner receives the state yielded by the parent process, so that it can
+    /// configure the new process, e.g. the arrivals of a source process. The spawners
+    /// share their ids with the process factories, see the [`factory`](crate::factory)
+    /// module.
+    pub fn register_spawner(&mut self, spawner: Spawner<T>) -> FactoryId {
+        self.process_factories.insert_spawner(spawner)
     }
 
-    #[test]
-    fn store() {
-        use crate::resources::SimpleStore;
-        use crate::{Effect, EndCondition::NoEvents, Simulation};
+    /// Returns the process that spawned `process` with `Effect::Spawn`, if any
+    ///
+    /// The parent of a completed process is kept only while the simulation keeps the log
+    /// in memory, see `keeps_log`.
+    pub fn parent(&self, process: ProcessId) -> Option<ProcessId> {
+        self.parents.get(&process).copied()
+    }
 
-        let mut sim = Simulation::new();
-        let store = sim.create_store(Box::new(SimpleStore::new(1)));
-
-        // simple process that pulls out of the store immediately and after 7 time units
-        let p1 = sim.create_process(Box::new(
-            #[coroutine]
-            move |_| {
-                yield Effect::Pull(store);
-                yield Effect::TimeOut(7.0);
-                yield Effect::Pull(store);
-            },
-        ));
-        // simple process that pushes into the store immediately and after 3 time units
-        let p2 = sim.create_process(Box::new(
-            #[coroutine]
-            move |_| {
-                yield Effect::Push(store);
-                yield Effect::TimeOut(3.0);
-                yield Effect::Push(store);
+    /// Give a name to a process, used by exporters to label it
+    ///
+    /// The name of a completed process is kept only while the simulation keeps the log in
+    /// memory, to label the log; otherwise it is dropped with the process.
+    pub fn set_process_name(&mut self, process: ProcessId, name: impl Into<String>) {
+        let name = name.into();
+        if let Some(old) = self.process_names.insert(process, name.clone()) {
+            self.forget_name(process, &old);
+        }
+        self.named_processes.entry(name).or_default().push(process);
+    }
+
+    /// Drop `process` from the index of the names.
+    fn forget_name(&mut self, process: ProcessId, name: &str) {
+        if let Some(processes) = self.named_processes.get_mut(name) {
+            processes.retain(|p| *p != process);
+            if processes.is_empty() {
+                self.named_processes.remove(name);
+            }
+        }
+    }
+
+    /// Returns the name of a process, if set
+    pub fn process_name(&self, process: ProcessId) -> Option<&str> {
+        self.process_names.get(&process).map(String::as_str)
+    }
+
+    /// Give a name to a resource, used by exporters to label it
+    pub fn set_resource_name(&mut self, resource: ResourceId, name: impl Into<String>) {
+        self.resource_names[resource.0] = Some(name.into());
+    }
+
+    /// Returns the name of a resource, if set
+    pub fn resource_name(&self, resource: ResourceId) -> Option<&str> {
+        self.resource_names.get(resource.0)?.as_deref()
+    }
+
+    /// Give a name to a store
+    pub fn set_store_name(&mut self, store: StoreId, name: impl Into<String>) {
+        self.store_names[store.0] = Some(name.into());
+    }
+
+    /// Returns the name of a store, if set
+    pub fn store_name(&self, store: StoreId) -> Option<&str> {
+        self.store_names.get(store.0)?.as_deref()
+    }
+
+    /// Returns the process that was given the name `name` last, if any. It may have
+    /// completed, see `set_process_name`.
+    pub fn process_by_name(&self, name: &str) -> Option<ProcessId> {
+        self.named_processes.get(name)?.last().copied()
+    }
+
+    /// Returns the resource named `name`, if any
+    pub fn resource_by_name(&self, name: &str) -> Option<ResourceId> {
+        self.resource_names
+            .iter()
+            .position(|n| n.as_deref() == Some(name))
+            .map(ResourceId)
+    }
+
+    /// Returns the store named `name`, if any
+    pub fn store_by_name(&self, name: &str) -> Option<StoreId> {
+        self.store_names
+            .iter()
+            .position(|n| n.as_deref() == Some(name))
+            .map(StoreId)
+    }
+
+    /// Build an instance of `module` named `name`, e.g. one of the identical workstations
+    /// of a factory, returning the ids it exposes.
+    /// See the [`module`](crate::module) module.
+    ///
+    /// Returns an error if an instance named `name` already exists.
+    pub fn instantiate<M: Module<T>>(
+        &mut self,
+        name: &str,
+        module: &M,
+    ) -> Result<M::Ports, SimError> {
+        self.build_instance(name.to_string(), module)
+    }
+
+    /// Build an instance of `module` at `path`, that must not be in use.
+    pub(crate) fn build_instance<M: Module<T>>(
+        &mut self,
+        path: String,
+        module: &M,
+    ) -> Result<M::Ports, SimError> {
+        if !self.instances.insert(path.clone()) {
+            return Err(SimError::DuplicateInstance(path));
+        }
+        Ok(module.build(&mut Scope::new(self, path)))
+    }
+
+    /// Create a new resource.
+    ///
+    /// For more information about a resource, see the crate level documentation
+    /// and the documentation of the [`resources`](crate::resources) module.
+    ///
+    /// Returns the identifier of the resource
+    pub fn create_resource(&mut self, resource: BoxedResource<T>) -> ResourceId {
+        self.add_resource(ResourceEntry::Custom(resource))
+    }
+
+    /// Create a new resource of one of the types of the [`resources`](crate::resources)
+    /// module, e.g. a `SimpleResource`, whose methods are called without dynamic dispatch.
+    /// See `StandardResource`.
+    ///
+    /// Returns the identifier of the resource
+    pub fn create_standard_resource(
+        &mut self,
+        resource: impl Into<StandardResource<T>>,
+    ) -> ResourceId {
+        self.add_resource(ResourceEntry::Standard(resource.into()))
+    }
+
+    fn add_resource(&mut self, resource: ResourceEntry<T>) -> ResourceId {
+        let id = self.resources.len();
+        let mut stats = ResourceStats::with_capacity(Resource::capacity(&resource));
+        stats.reset(self.start_time);
+        self.resource_stats.push(stats);
+        if let Some((time, capacity)) = resource.next_capacity_change(self.time) {
+            self.capacity_changes.push((time, ResourceId(id), capacity));
+        }
+        self.resource_snapshots
+            .lock()
+            .unwrap()
+            .push(ResourceSnapshot::of(&resource));
+        self.resources.push(resource);
+        self.resource_names.push(None);
+        ResourceId(id)
+    }
+
+    /// Create a named group of resources, e.g. all the checkout counters of a shop.
+    ///
+    /// The statistics of the group pool the ones of its members. Processes can request
+    /// any of the members with `Effect::RequestGroup` and release it with
+    /// `Effect::ReleaseGroup`.
+    ///
+    /// # Panics
+    /// Panics if one of the members is not a resource of the simulation.
+    pub fn create_resource_group(
+        &mut self,
+        name: impl Into<String>,
+        members: Vec<ResourceId>,
+    ) -> GroupId {
+        assert!(
+            members.iter().all(|r| r.0 < self.resources.len()),
+            "ERROR. A group can only contain resources of the simulation."
+        );
+        let id = self.groups.len();
+        self.groups.push(ResourceGroup::new(name.into(), members));
+        id
+    }
+
+    /// Create a topic of domain events.
+    /// See the [`bus`](crate::bus) module.
+    ///
+    /// Returns the identifier of the topic.
+    pub fn create_topic(&mut self, name: impl Into<String>) -> TopicId {
+        let id = self.topics.len();
+        self.topics.push(Topic::new(name.into()));
+        id
+    }
+
+    /// Returns the topic with the given name, if any
+    pub fn topic(&self, name: &str) -> Option<TopicId> {
+        self.topics.iter().position(|t| t.name() == name)
+    }
+
+    /// Register a listener called with the time and the payload of every domain event
+    /// published on `topic`.
+    pub fn subscribe(&mut self, topic: TopicId, listener: Listener<T>) {
+        self.topics[topic].add_listener(listener);
+    }
+
+    /// Create a signal processes can wait for, see the [`signal`](crate::signal) module.
+    ///
+    /// Returns the identifier of the signal.
+    pub fn create_signal(&mut self) -> SignalId {
+        self.signals.push(Signal::new());
+        self.signals.len() - 1
+    }
+
+    /// Create a gate, open or closed. See the [`signal`](crate::signal) module.
+    ///
+    /// Returns the identifier of the gate.
+    pub fn create_gate(&mut self, open: bool) -> GateId {
+        self.gates.push(Gate::new(open));
+        self.gates.len() - 1
+    }
+
+    /// Returns `true` if the gate is open
+    pub fn is_gate_open(&self, gate: GateId) -> bool {
+        self.gates[gate].is_open()
+    }
+
+    /// Create a predicate over the state of the simulation, that processes can wait for.
+    /// See the [`predicate`](crate::predicate) module.
+    ///
+    /// Returns the identifier of the predicate.
+    pub fn create_predicate(&mut self, predicate: Predicate<T>) -> PredicateId {
+        self.predicates.push(WaitUntil::new(predicate));
+        self.predicates.len() - 1
+    }
+
+    /// Insert a value in the blackboard shared by the processes, replacing and returning
+    /// the previous value of the same type. See the [`shared`](crate::shared) module.
+    pub fn insert_shared<S: SharedValue>(&mut self, value: S) -> Option<S> {
+        self.shared.insert(value)
+    }
+
+    /// Call `f` with the value of type `S` of the blackboard, if there is one
+    pub fn with_shared<S: SharedValue, R>(&self, f: impl FnOnce(&S) -> R) -> Option<R> {
+        self.shared.with(f)
+    }
+
+    /// Call `f` with the value of type `S` of the blackboard, to modify it, if there is one
+    pub fn with_shared_mut<S: SharedValue, R>(&mut self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        self.shared.with_mut(f)
+    }
+
+    /// Resume the processes waiting for the predicates that hold, if a resource, a store
+    /// or the blackboard changed since the last time.
+    fn wake_waiting_until(&mut self) {
+        let shared_changed = self.shared.take_changed();
+        if !std::mem::take(&mut self.state_changed) && !shared_changed {
+            return;
+        }
+        let mut predicates = std::mem::take(&mut self.predicates);
+        let view = SimView { sim: self };
+        let woken: Vec<_> = predicates.iter_mut().flat_map(|p| p.wake(&view)).collect();
+        self.predicates = predicates;
+        for e in woken {
+            // the process left the queue of the predicate
+            if let Some(entry) = self.processes.get_mut(e.process()) {
+                entry.blocked_on = None;
+            }
+            self.schedule(e);
+        }
+    }
+
+    /// Create a mailbox, holding at most `capacity` messages if set.
+    /// See the [`mailbox`](crate::mailbox) module.
+    ///
+    /// Returns the identifier of the mailbox.
+    pub fn create_mailbox(&mut self, capacity: Option<usize>) -> MailboxId {
+        self.mailboxes.push(Mailbox::new(capacity));
+        self.mailboxes.len() - 1
+    }
+
+    /// Returns the number of messages waiting to be received from a mailbox
+    pub fn mailbox_len(&self, mailbox: MailboxId) -> usize {
+        self.mailboxes[mailbox].len()
+    }
+
+    /// Register an observer, notified while the simulation runs.
+    /// See the [`observer`](crate::observer) module.
+    pub fn add_observer(&mut self, observer: BoxedObserver<T>) {
+        self.observers.push(observer);
+    }
+
+    /// Returns a read-only view of a resource of the simulation, e.g. to inspect it in an
+    /// invariant or after the run
+    pub fn resource(&self, resource: ResourceId) -> &dyn ResourceInspect<T> {
+        &self.resources[resource.0]
+    }
+
+    /// Returns a store of the simulation, e.g. to inspect it in an invariant
+    pub fn store(&self, store: StoreId) -> &dyn Store<T> {
+        self.stores[store.0].as_ref()
+    }
+
+    /// Returns the number of steps executed
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns a group of resources
+    pub fn resource_group(&self, group: GroupId) -> &ResourceGroup {
+        &self.groups[group]
+    }
+
+    /// Returns the statistics of a resource, up to the current time.
+    /// See the [`stats`](crate::stats) module.
+    pub fn resource_stats(&self, resource: ResourceId) -> ResourceStats {
+        let mut stats = self.resource_stats[resource.0].clone();
+        stats.advance(self.time);
+        stats
+    }
+
+    /// Record which process held each unit of `resource` and when, from now on, returned
+    /// by `ResourceStats::occupancy` and drawn by `gantt`. It is off by default, since the
+    /// record grows with every release of the resource.
+    pub fn track_occupancy(&mut self, resource: ResourceId) {
+        self.resource_stats[resource.0].track_occupancy();
+    }
+
+    /// Returns the statistics of a group of resources, pooling the ones of its members.
+    pub fn group_stats(&self, group: GroupId) -> ResourceStats {
+        let mut stats = ResourceStats::default();
+        for r in self.groups[group].members() {
+            stats.merge(&self.resource_stats(*r));
+        }
+        stats
+    }
+
+    /// Create a counter, that sums the recorded values.
+    /// See the [`stats`](crate::stats) module.
+    pub fn create_counter(&mut self, name: impl Into<String>) -> StatId {
+        self.stats
+            .add(name.into(), Statistic::Counter(Counter::default()))
+    }
+
+    /// Create a tally, that summarizes the recorded observations.
+    pub fn create_tally(&mut self, name: impl Into<String>) -> StatId {
+        self.stats
+            .add(name.into(), Statistic::Tally(Tally::default()))
+    }
+
+    /// Create a time-weighted accumulator, that summarizes a level changing over time.
+    /// The level is 0 from the current time until the first value is recorded.
+    pub fn create_time_weighted(&mut self, name: impl Into<String>) -> StatId {
+        let statistic = Statistic::TimeWeighted(TimeWeighted::new(self.time));
+        self.stats.add(name.into(), statistic)
+    }
+
+    /// Create a histogram of the recorded observations, with the bins of `histogram`, e.g.
+    /// to report percentiles of the sojourn times.
+    pub fn create_histogram(&mut self, name: impl Into<String>, histogram: Histogram) -> StatId {
+        self.stats.add(name.into(), Statistic::Histogram(histogram))
+    }
+
+    /// Record a value in an accumulator at the current time, like `Effect::Record`.
+    pub fn record(&mut self, stat: StatId, value: f64) {
+        self.stats.record(stat, self.time, value);
+    }
+
+    /// Returns the accumulators of statistics of the model, up to the current time.
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats.clone();
+        stats.advance(self.time);
+        stats
+    }
+
+    /// Set a warm-up period, that ends at `time`.
+    ///
+    /// The events processed during the warm-up are not logged, and when it ends the
+    /// statistics of the resources and of the model are reset, so that they describe the
+    /// steady state of the model without the transient from its initial state.
+    pub fn set_warm_up(&mut self, time: f64) {
+        self.warm_up = Some(time);
+    }
+
+    /// Discard the statistics of the resources and of the model collected so far.
+    /// The current queue lengths, units in use and time-weighted levels are kept.
+    pub fn reset_stats(&mut self) {
+        self.reset_stats_at(self.time);
+    }
+
+    fn reset_stats_at(&mut self, time: f64) {
+        for stats in self.resource_stats.iter_mut() {
+            stats.advance(time);
+            stats.reset(time);
+        }
+        self.stats.advance(time);
+        self.stats.reset(time);
+    }
+
+    /// Register a factory of resources.
+    ///
+    /// While the simulation runs, a process can add a resource built by the factory
+    /// yielding `Effect::AddResource`. Resources can also be added between two steps with
+    /// `create_resource`. The ids of the existing resources never change.
+    pub fn register_resource_factory(&mut self, factory: ResourceFactory<T>) -> FactoryId {
+        self.resource_factories.push(factory);
+        self.resource_factories.len() - 1
+    }
+
+    /// Register a factory of stores, used by processes yielding `Effect::AddStore`.
+    /// See `register_resource_factory`.
+    pub fn register_store_factory(&mut self, factory: StoreFactory<T>) -> FactoryId {
+        self.store_factories.push(factory);
+        self.store_factories.len() - 1
+    }
+
+    /// Create a new store.
+    ///
+    /// For more information about a store, see the crate level documentation
+    /// and the documentation of the [`resources`](crate::resources) module.
+    ///
+    /// Returns the identifier of the store
+    pub fn create_store(&mut self, store: BoxedStore<T>) -> StoreId {
+        let id = self.stores.len();
+        self.stores.push(store);
+        self.store_names.push(None);
+        StoreId(id)
+    }
+
+    /// Add a source of arrivals to the simulation.
+    ///
+    /// The iterator is consumed lazily: the next arrival is created and scheduled only
+    /// when the previous one from the same source enters the simulation.
+    /// Arrivals should be sorted by time, arrivals in the past enter the simulation
+    /// immediately.
+    /// See the [`trace`](crate::trace) module for more information.
+    pub fn add_arrivals(&mut self, arrivals: ArrivalSource<T>) {
+        self.arrival_sources.push(arrivals);
+        self.schedule_next_arrival(self.arrival_sources.len() - 1);
+    }
+
+    /// Create the process of the next arrival from `source` and schedule it.
+    fn schedule_next_arrival(&mut self, source: usize) {
+        if let Some(arrival) = self.arrival_sources[source].next() {
+            let (time, process, state) = arrival.into_parts();
+            let process = self.create_process(process);
+            let mut event = Event::new(time.max(self.time), process, state);
+            event.source = Some(source);
+            self.schedule(event);
+        }
+    }
+
+    /// Resume a process that is waiting after yielding `Effect::Wait`.
+    ///
+    /// The process is resumed at the current simulation time and `state` is passed to it
+    /// through the `SimContext`. This lets code outside the model, e.g. a user or another
+    /// simulator, take decisions between calls to `step` or `run`.
+    ///
+    /// Returns an error if the process does not exist, already completed or is not waiting.
+    /// The call is ignored while replaying a journal, see the [`replay`](crate::replay) module.
+    pub fn resume_process(&mut self, process: ProcessId, state: T) -> Result<(), SimError> {
+        if self.replaying.is_some() {
+            return Ok(());
+        }
+        self.resume_input(process, state)
+    }
+
+    /// Resume a process waiting after yielding `Effect::Wait` on behalf of the owner of
+    /// the simulation, recording it in the journal.
+    pub(crate) fn resume_input(&mut self, process: ProcessId, state: T) -> Result<(), SimError> {
+        let entry = self.process_entry(process)?;
+        if !entry.waiting {
+            return Err(SimError::ProcessNotWaiting(process));
+        }
+        entry.waiting = false;
+        if let Some(journal) = &mut self.journal {
+            let input = Input::Resumed {
+                process,
+                state: state.clone(),
+            };
+            journal.push(self.steps, input);
+        }
+        let mut event = Event::new(self.time, process, state);
+        event.cause = Some(Outcome::Resumed);
+        self.schedule(event);
+        Ok(())
+    }
+
+    /// Returns `true` if the process is waiting after yielding `Effect::Wait`.
+    pub fn is_waiting(&self, process: ProcessId) -> bool {
+        self.processes
+            .get(process)
+            .is_some_and(|entry| entry.waiting)
+    }
+
+    /// Returns the processes that are parked and what they are waiting for.
+    /// See the [`deadlock`](crate::deadlock) module.
+    pub fn blocked_processes(&self) -> Vec<BlockedProcess> {
+        self.processes
+            .iter()
+            .filter_map(|(process, entry)| {
+                let waiting_for = match entry.blocked_on {
+                    Some(Blocked::Resource(r)) => WaitingFor::Resource(r),
+                    // the grant is scheduled
+                    Some(Blocked::Granted(..)) => return None,
+                    Some(Blocked::Store(s)) => WaitingFor::Store(s),
+                    Some(Blocked::Topic(t)) => WaitingFor::Topic(t),
+                    Some(Blocked::Signal(s)) => WaitingFor::Signal(s),
+                    Some(Blocked::Gate(g)) => WaitingFor::Gate(g),
+                    Some(Blocked::Mailbox(m)) => WaitingFor::Mailbox(m),
+                    Some(Blocked::Predicate(p)) => WaitingFor::Predicate(p),
+                    None if entry.condition.is_some() => WaitingFor::Condition,
+                    None if entry.waiting => WaitingFor::Resume,
+                    None => return None,
+                };
+                Some(BlockedProcess {
+                    process,
+                    waiting_for,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns a handle that can be used to schedule events from outside of the simulation,
+    /// also from other threads. See the [`injector`](crate::injector) module.
+    pub fn injector(&self) -> EventInjector<T> {
+        self.injections.injector()
+    }
+
+    /// Schedule the events received from the injectors.
+    fn receive_injections(&mut self) -> Result<(), SimError> {
+        let received = self
+            .injections
+            .received()
+            .map(|i| (i.time, i.process, i.state));
+        let injected: Vec<_> = if self.replaying.is_some() {
+            // the events sent while replaying are discarded
+            received.for_each(drop);
+            self.replayed_injections()
+        } else {
+            received.collect()
+        };
+        // the first invalid injection is reported, the valid ones are scheduled anyway
+        let mut result = Ok(());
+        for (time, process, state) in injected {
+            let valid = self.process_entry(process).and_then(|_| {
+                if time.is_nan() {
+                    Err(SimError::InvalidTime(process))
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = valid {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+                continue;
+            }
+            if let Some(journal) = &mut self.journal {
+                let input = Input::Injected {
+                    time,
+                    process,
+                    state: state.clone(),
+                };
+                journal.push(self.steps, input);
+            }
+            let mut event = Event::new(time.max(self.time), process, state);
+            event.cause = Some(Outcome::Resumed);
+            self.schedule(event);
+        }
+        result
+    }
+
+    /// Schedule a process to be executed at `time`.
+    /// Another way to schedule events is
+    /// yielding `Effect::Event` from a process during the simulation.
+    ///
+    /// `time` is not checked against the current time. To make it explicit whether it is
+    /// absolute or relative to the current time, and to reject events in the past, use
+    /// `schedule_at` or `schedule_in`.
+    ///
+    /// Returns an id that can be used to cancel the event.
+    /// Returns an error if `time` is NaN.
+    pub fn schedule_event(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+    ) -> Result<EventId, SimError> {
+        self.schedule_event_with_priority(time, process, state, 0)
+    }
+
+    /// Schedule a process to be executed at time `time`, with a priority.
+    ///
+    /// Among the events scheduled at the same time, the ones with higher priority are
+    /// executed first. Events scheduled with `schedule_event` have priority 0.
+    pub fn schedule_event_with_priority(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+        priority: i32,
+    ) -> Result<EventId, SimError> {
+        if time.is_nan() {
+            return Err(SimError::InvalidTime(process));
+        }
+        if let Some(journal) = &mut self.journal {
+            let input = Input::Scheduled {
+                time,
+                process,
+                state: state.clone(),
+                priority,
+            };
+            journal.push(self.steps, input);
+        }
+        let mut event = Event::new(time, process, state);
+        event.set_priority(priority);
+        event.cause = Some(Outcome::Resumed);
+        let id = self.schedule(event);
+        // the event may be for a completed process
+        self.discard_stale_events();
+        Ok(id)
+    }
+
+    /// Schedule a process to be executed at the absolute time `time`.
+    ///
+    /// Returns an id that can be used to cancel the event.
+    /// Returns an error if `time` is NaN or in the past.
+    pub fn schedule_at(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+    ) -> Result<EventId, SimError> {
+        if time < self.time {
+            return Err(SimError::InvalidTime(process));
+        }
+        self.schedule_event(time, process, state)
+    }
+
+    /// Schedule a process to be executed after `delay` time instants from now.
+    ///
+    /// Returns an id that can be used to cancel the event.
+    /// Returns an error if `delay` is NaN or negative.
+    pub fn schedule_in(
+        &mut self,
+        delay: f64,
+        process: ProcessId,
+        state: T,
+    ) -> Result<EventId, SimError> {
+        if delay.is_nan() || delay < 0.0 {
+            return Err(SimError::InvalidTime(process));
+        }
+        if let Some(journal) = &mut self.journal {
+            let input = Input::Delayed {
+                delay,
+                process,
+                state: state.clone(),
+            };
+            journal.push(self.steps, input);
+        }
+        // the delay is added to the clock without rounding
+        let mut event = self.delayed_event(delay, process, state);
+        event.cause = Some(Outcome::Resumed);
+        let id = self.schedule(event);
+        self.discard_stale_events();
+        Ok(id)
+    }
+
+    /// Schedule a process to be executed at `start` and then every `period` time instants,
+    /// e.g. a monitor sampling the state of the model. Each event passes `state` to the
+    /// process, that should wait between events, e.g. yielding `Effect::Wait`.
+    ///
+    /// The series goes on until it is cancelled with `cancel_series` or the process
+    /// completes, so a run with `EndCondition::NoEvents` does not end before that.
+    ///
+    /// Returns an error if the process does not exist or completed, if `start` is NaN
+    /// or `period` is not positive.
+    pub fn schedule_periodic(
+        &mut self,
+        start: f64,
+        period: f64,
+        process: ProcessId,
+        state: T,
+    ) -> Result<SeriesId, SimError> {
+        self.process_entry(process)?;
+        if start.is_nan() || period.is_nan() || period <= 0.0 {
+            return Err(SimError::InvalidTime(process));
+        }
+        let id = self.series.len();
+        let mut event = Event::new(start, process, state.clone());
+        event.series = Some(id);
+        event.cause = Some(Outcome::Resumed);
+        let next = self.schedule(event);
+        self.series.push(Some(Series {
+            period,
+            process,
+            state,
+            next,
+        }));
+        Ok(id)
+    }
+
+    /// Cancel a series of periodic events. Its next event never fires.
+    pub fn cancel_series(&mut self, series: SeriesId) {
+        if let Some(Some(s)) = self.series.get_mut(series).map(Option::take) {
+            self.cancel_event(s.next);
+        }
+    }
+
+    /// Schedule the event of `series` following the one at `time`.
+    fn schedule_next_occurrence(&mut self, series: SeriesId, time: f64) {
+        let Some(s) = &self.series[series] else {
+            return;
+        };
+        if self.processes.get(s.process).is_none() {
+            self.series[series] = None;
+            return;
+        }
+        let mut event = Event::new(time + s.period, s.process, s.state.clone());
+        event.series = Some(series);
+        event.cause = Some(Outcome::Resumed);
+        let next = self.schedule(event);
+        if let Some(s) = &mut self.series[series] {
+            s.next = next;
+        }
+    }
+
+    /// Cancel an event, so that it never fires.
+    ///
+    /// Processes can cancel events yielding `Effect::Cancel`. Cancelling an event that
+    /// already fired has no effect.
+    /// The call is ignored while replaying a journal, see the [`replay`](crate::replay) module.
+    pub fn cancel_event(&mut self, event: EventId) {
+        if self.replaying.is_some() {
+            return;
+        }
+        self.cancel_input(event);
+    }
+
+    /// Cancel an event on behalf of the owner of the simulation, recording it in the
+    /// journal.
+    pub(crate) fn cancel_input(&mut self, event: EventId) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(self.steps, Input::Cancelled { event });
+        }
+        self.cancel(event);
+    }
+
+    /// Cancel an event, without recording it in the journal.
+    fn cancel(&mut self, event: EventId) {
+        if event < self.next_event_id {
+            self.cancelled_events.insert(event);
+            // the ids of the events that already fired are never popped, drop them once
+            // they outnumber the pending events
+            if self.cancelled_events.len() > 2 * self.future_events.len() + 16 {
+                let pending: HashSet<EventId> = self.future_events.iter().map(|e| e.id).collect();
+                self.cancelled_events.retain(|id| pending.contains(id));
+            }
+            self.discard_stale_events();
+        }
+    }
+
+    /// Add a soft limit to the simulation.
+    ///
+    /// When the limit is crossed, a `Warning` is recorded and passed to the hooks
+    /// registered with `on_warning`, but the simulation goes on.
+    /// See the [`limits`](crate::limits) module for more information.
+    pub fn add_soft_limit(&mut self, limit: SoftLimit) {
+        self.soft_limits.add(limit);
+    }
+
+    /// Register a callback that is invoked every time a soft limit is crossed.
+    pub fn on_warning(&mut self, hook: WarningHook) {
+        self.soft_limits.add_hook(hook);
+    }
+
+    /// Returns the warnings raised by the soft limits so far
+    pub fn warnings(&self) -> &[Warning] {
+        self.soft_limits.warnings()
+    }
+
+    /// Set how the simulation handles long chains of steps at the same time, e.g. processes
+    /// that keep yielding zero timeouts.
+    ///
+    /// The default is `ZeroDelayPolicy::Unbounded`.
+    pub fn set_zero_delay_policy(&mut self, policy: ZeroDelayPolicy) {
+        self.zero_delay_policy = policy;
+    }
+
+    /// Count the consecutive steps at the same time and apply the zero-delay policy to
+    /// the next `event`.
+    fn check_zero_delay(
+        &mut self,
+        previous_time: f64,
+        event: &mut Event<T>,
+    ) -> Result<(), SimError> {
+        if event.time() <= previous_time {
+            self.zero_delay_steps += 1;
+        } else {
+            self.zero_delay_steps = 0;
+            self.zero_delay_processes.clear();
+        }
+        match self.zero_delay_policy {
+            ZeroDelayPolicy::Unbounded => {}
+            ZeroDelayPolicy::Fail(max) => {
+                if !self.zero_delay_processes.contains(&event.process()) {
+                    self.zero_delay_processes.push(event.process());
+                }
+                if self.zero_delay_steps > max {
+                    return Err(SimError::ZeroDelayLoop {
+                        process: event.process(),
+                        time: event.time(),
+                        processes: std::mem::take(&mut self.zero_delay_processes),
+                    });
+                }
+            }
+            ZeroDelayPolicy::Advance { max_steps, epsilon } => {
+                if self.zero_delay_steps > max_steps {
+                    // move the clock forward, the events left behind are executed late
+                    self.set_clock(previous_time + epsilon);
+                    self.zero_delay_steps = 0;
+                }
+                if event.time() < self.time {
+                    event.set_time(self.time);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Limit the number of events that each process can schedule.
+    ///
+    /// When a process schedules more than `max_events` events, it is reported in
+    /// `budget_violations` and, depending on `policy`, it is suspended: its pending
+    /// events are discarded and it will not be resumed anymore.
+    pub fn set_event_budget(&mut self, max_events: usize, policy: BudgetPolicy) {
+        self.event_budget = Some((max_events, policy));
+    }
+
+    /// Returns the processes that exceeded the event budget
+    pub fn budget_violations(&self) -> &[BudgetViolation] {
+        self.budget_violations.as_slice()
+    }
+
+    /// Account `count` new events to the budget of `process`.
+    fn charge_event_budget(&mut self, process: ProcessId, count: usize) {
+        let entry = &mut self.processes[process];
+        let before = entry.scheduled_events;
+        entry.scheduled_events += count;
+        if let Some((max_events, policy)) = self.event_budget {
+            if before <= max_events && entry.scheduled_events > max_events {
+                entry.suspended = policy == BudgetPolicy::Suspend;
+                self.budget_violations.push(BudgetViolation {
+                    process,
+                    time: self.time,
+                    events: entry.scheduled_events,
+                });
+            }
+        }
+    }
+
+    /// Called whenever a resource returns an event for a process that requested it.
+    fn resource_granted(&mut self, resource: ResourceId, event: &Event<T>) {
+        let key = (resource, event.process());
+        let units = self.requested_units.remove(&key).unwrap_or(1);
+        *self.held.entry(key).or_default() += units;
+        let entity = self.processes.get(event.process()).and_then(|e| e.entity);
+        // a deferred grant is in use from the time the process is resumed
+        let start = event.time().max(self.time);
+        for _ in 0..units {
+            self.resource_stats[resource.0].record_use(start, event.process(), entity, true);
+        }
+        let process = event.process();
+        self.notify_resource(resource, ResourceChange::Acquired { process, units });
+        self.notify_stat(StatUpdate::InUse {
+            resource,
+            time: start,
+            delta: units as isize,
+        });
+        match event.branch {
+            Some(branch) => self.set_branch_status(event.process(), branch, BranchStatus::Ready),
+            None => {
+                self.processes[event.process()].blocked_on = Some(Blocked::Granted(resource, units))
+            }
+        }
+        if let Some(request_time) = self.request_times.remove(&(resource, event.process())) {
+            // the grant may be deferred, e.g. by a `ScheduledResource`
+            let wait = event.time() - request_time;
+            self.resource_stats[resource.0].record_grant(wait);
+            self.notify_stat(StatUpdate::WaitTime {
+                resource,
+                time: self.time,
+                wait,
+            });
+            self.soft_limits
+                .check_wait(self.time, event.process(), resource, wait);
+        }
+    }
+
+    /// Remove `process` from the queue of `resource`.
+    fn withdraw_request(&mut self, process: ProcessId, resource: ResourceId) {
+        let deferred = self.offline.get_mut(&resource).and_then(|grants| {
+            let i = grants.iter().position(|e| e.process() == process)?;
+            Some(grants.remove(i))
+        });
+        match deferred {
+            // the resource already granted the units, give them back
+            Some(e) => {
+                let units = self
+                    .requested_units
+                    .remove(&(resource, process))
+                    .unwrap_or(1);
+                for _ in 0..units {
+                    let release_event = Event::new(self.time, process, e.state().clone());
+                    let mut granted = std::mem::take(&mut self.grants_buffer);
+                    self.resources[resource.0]
+                        .release_and_schedule_next_into(release_event, &mut granted);
+                    self.schedule_grants(resource, &mut granted);
+                    self.grants_buffer = granted;
+                }
+            }
+            None => {
+                self.resources[resource.0].cancel_request(process);
+                self.requested_units.remove(&(resource, process));
+            }
+        }
+        self.request_times.remove(&(resource, process));
+        self.resource_changed(process, resource);
+    }
+
+    /// Release `resource`, that was granted to `process` before it could be resumed.
+    fn give_back(&mut self, process: ProcessId, resource: ResourceId, state: T) {
+        self.release_unit(process, resource, state);
+        self.resource_changed(process, resource);
+    }
+
+    /// Release a unit of `resource` held by `process`, granting it to the next waiting
+    /// processes, if any. Returns the first process that was granted the resource.
+    fn release_unit(
+        &mut self,
+        process: ProcessId,
+        resource: ResourceId,
+        state: T,
+    ) -> Option<ProcessId> {
+        if let Some(held) = self.held.get_mut(&(resource, process)) {
+            *held -= 1;
+            if *held == 0 {
+                self.held.remove(&(resource, process));
+            }
+            let stats = &mut self.resource_stats[resource.0];
+            if let Some(time) = stats.record_use(self.time, process, None, false) {
+                self.notify_stat(StatUpdate::InUse {
+                    resource,
+                    time,
+                    delta: -1,
+                });
+            }
+            self.notify_resource(resource, ResourceChange::Released { process });
+        }
+        let release_event = Event::new(self.time, process, state);
+        let mut granted = std::mem::take(&mut self.grants_buffer);
+        self.resources[resource.0].release_and_schedule_next_into(release_event, &mut granted);
+        let woken = granted.first().map(Event::process);
+        self.schedule_grants(resource, &mut granted);
+        self.grants_buffer = granted;
+        woken
+    }
+
+    /// Schedule the events returned by a resource for the processes it granted, leaving
+    /// `granted` empty.
+    fn schedule_grants(&mut self, resource: ResourceId, granted: &mut Vec<Event<T>>) {
+        if let Some(deferred) = self.offline.get_mut(&resource) {
+            deferred.append(granted);
+            return;
+        }
+        for e in granted.drain(..) {
+            if e.rejected {
+                let key = (resource, e.process());
+                self.request_times.remove(&key);
+                self.requested_units.remove(&key);
+            } else {
+                self.resource_granted(resource, &e);
+            }
+            self.schedule(e);
+        }
+    }
+
+    /// Returns the number of units of `resource` currently held by `process`
+    pub fn held(&self, process: ProcessId, resource: ResourceId) -> usize {
+        self.held.get(&(resource, process)).copied().unwrap_or(0)
+    }
+
+    /// Returns the processes holding `resource`, with the number of units each of them
+    /// holds, sorted by process
+    pub fn resource_holders(&self, resource: ResourceId) -> Vec<(ProcessId, usize)> {
+        let mut holders: Vec<_> = self
+            .held
+            .iter()
+            .filter(|((r, _), _)| *r == resource)
+            .map(|((_, p), units)| (*p, *units))
+            .collect();
+        holders.sort_unstable();
+        holders
+    }
+
+    /// Returns `true` if the resource is offline, after `Effect::Fail`
+    pub fn is_offline(&self, resource: ResourceId) -> bool {
+        self.offline.contains_key(&resource)
+    }
+
+    /// Take `resource` offline on behalf of `process`, preempting its holders.
+    fn fail_resource(&mut self, process: ProcessId, resource: ResourceId, cause: T) {
+        if self.offline.contains_key(&resource) {
+            return;
+        }
+        self.offline.insert(resource, Vec::new());
+        self.notify_resource(resource, ResourceChange::Failed);
+        for (holder, _) in self.resource_holders(resource) {
+            if holder != process {
+                // the holder may be parked elsewhere, it is removed from there as well
+                if self.processes.get(holder).is_some() {
+                    self.interrupt_process(holder, cause.clone());
+                }
+            }
+            for _ in 0..self.held(holder, resource) {
+                self.release_unit(holder, resource, cause.clone());
+            }
+        }
+        self.resource_changed(process, resource);
+    }
+
+    /// Bring `resource` back online, resuming the processes it granted while offline.
+    fn repair_resource(&mut self, resource: ResourceId) {
+        if let Some(deferred) = self.offline.remove(&resource) {
+            self.notify_resource(resource, ResourceChange::Repaired);
+            let mut granted = deferred;
+            for e in &mut granted {
+                e.set_time(self.time);
+            }
+            self.schedule_grants(resource, &mut granted);
+        }
+    }
+
+    /// Set what happens when a process releases a resource it does not hold.
+    ///
+    /// The default is `ReleasePolicy::Fail`.
+    pub fn set_release_policy(&mut self, policy: ReleasePolicy) {
+        self.release_policy = policy;
+    }
+
+    /// Called whenever the queue of a resource may have changed after an operation of `process`.
+    fn resource_changed(&mut self, process: ProcessId, resource: ResourceId) {
+        self.state_changed = true;
+        let snapshot = ResourceSnapshot::of(&self.resources[resource.0]);
+        self.resource_snapshots.lock().unwrap()[resource.0] = snapshot;
+        let queue_len = snapshot.queue_len();
+        if queue_len != self.resource_stats[resource.0].queue_len() {
+            self.notify_stat(StatUpdate::QueueLength {
+                resource,
+                time: self.time,
+                len: queue_len,
+            });
+        }
+        self.resource_stats[resource.0].record_queue(self.time, queue_len);
+        self.soft_limits
+            .check_queue(self.time, process, resource, queue_len);
+    }
+
+    fn notify_resource(&mut self, resource: ResourceId, change: ResourceChange) {
+        for observer in self.observers.iter_mut() {
+            observer.on_resource_state_change(resource, self.time, &change);
+        }
+    }
+
+    /// Notify the observers of the changes of capacity of the resources up to `time`.
+    fn notify_capacity_changes(&mut self, time: f64) {
+        while let Some(i) = self
+            .capacity_changes
+            .iter()
+            .enumerate()
+            .filter(|(_, (t, _, _))| *t <= time)
+            .min_by(|(_, a), (_, b)| a.0.total_cmp(&b.0))
+            .map(|(i, _)| i)
+        {
+            let (t, resource, capacity) = self.capacity_changes[i];
+            let change = ResourceChange::CapacityChanged { capacity };
+            for observer in self.observers.iter_mut() {
+                observer.on_resource_state_change(resource, t, &change);
+            }
+            match self.resources[resource.0].next_capacity_change(t) {
+                Some((next, capacity)) => self.capacity_changes[i] = (next, resource, capacity),
+                None => {
+                    self.capacity_changes.swap_remove(i);
+                }
+            }
+        }
+    }
+
+    fn notify_complete(&mut self, process: ProcessId) {
+        for observer in self.observers.iter_mut() {
+            observer.on_process_complete(process, self.time);
+        }
+    }
+
+    fn notify_stat(&mut self, update: StatUpdate) {
+        for observer in self.observers.iter_mut() {
+            observer.on_stat_update(&update);
+        }
+    }
+
+    fn should_log(&self, event: &Event<T>, state: &T) -> bool {
+        state.should_log()
+            && self.warm_up.is_none()
+            && !self.muted_processes.contains(&event.process())
+            && self
+                .log_filter
+                .as_ref()
+                .is_none_or(|filter| filter(event, state))
+    }
+
+    fn log_processed_event(&mut self, event: &Event<T>, sim_state: T) {
+        for observer in self.observers.iter_mut() {
+            observer.on_event_fired(event, &sim_state);
+        }
+        if self.should_log(event, &sim_state) {
+            match &mut self.log_sink {
+                Some(sink) => {
+                    if !self.log_begun {
+                        self.log_begun = true;
+                        sink.begin(&self.metadata);
+                    }
+                    sink.log_with_schema(event, &sim_state, self.attribute_schema.as_ref());
+                }
+                None => {
+                    self.log_index
+                        .entry(event.process())
+                        .or_default()
+                        .push(self.processed_events.len());
+                    self.processed_events.push((event.clone(), sim_state));
+                }
+            }
+        }
+    }
+
+    /// Interrupt a process, resuming it immediately with `cause` as state.
+    ///
+    /// All the events that were scheduled to resume the process are discarded. If the process
+    /// was waiting in the queue of a resource or store, it is removed from the queue, and if it
+    /// was granted a resource but not yet resumed, the resource is released.
+    /// The process can check if it was interrupted with `SimContext::interrupted()`.
+    ///
+    /// Returns an error if the process does not exist or already completed.
+    /// The call is ignored while replaying a journal, see the [`replay`](crate::replay) module.
+    pub fn interrupt(&mut self, process: ProcessId, cause: T) -> Result<(), SimError> {
+        if self.replaying.is_some() {
+            return Ok(());
+        }
+        self.interrupt_input(process, cause)
+    }
+
+    /// Interrupt a process on behalf of the owner of the simulation, recording it in the
+    /// journal.
+    pub(crate) fn interrupt_input(&mut self, process: ProcessId, cause: T) -> Result<(), SimError> {
+        self.process_entry(process)?;
+        if let Some(journal) = &mut self.journal {
+            let input = Input::Interrupted {
+                process,
+                state: cause.clone(),
+            };
+            journal.push(self.steps, input);
+        }
+        self.interrupt_process(process, cause);
+        Ok(())
+    }
+
+    /// Interrupt a process that did not complete.
+    fn interrupt_process(&mut self, process: ProcessId, cause: T) {
+        self.unblock(process, &cause);
+        let mut event = Event::new(self.time, process, cause);
+        event.interrupted = true;
+        self.schedule(event);
+        self.discard_stale_events();
+    }
+
+    /// Terminate a process, dropping its coroutine without resuming it.
+    ///
+    /// All the events that were scheduled to resume the process are discarded, and the
+    /// process leaves the queues it entered, like with `interrupt`. The units of resources
+    /// it holds are released; `state` is used for the release events.
+    /// A process can also terminate another one, or itself, by yielding `Effect::Kill`.
+    ///
+    /// Returns an error if the process does not exist or already completed.
+    /// The call is ignored while replaying a journal, see the [`replay`](crate::replay) module.
+    pub fn terminate_process(&mut self, process: ProcessId, state: T) -> Result<(), SimError> {
+        if self.replaying.is_some() {
+            return Ok(());
+        }
+        self.terminate_input(process, state)
+    }
+
+    /// Terminate a process on behalf of the owner of the simulation, recording it in the
+    /// journal.
+    pub(crate) fn terminate_input(&mut self, process: ProcessId, state: T) -> Result<(), SimError> {
+        self.process_entry(process)?;
+        if let Some(journal) = &mut self.journal {
+            let input = Input::Terminated {
+                process,
+                state: state.clone(),
+            };
+            journal.push(self.steps, input);
+        }
+        self.terminate(process, state)
+    }
+
+    /// Terminate a process, without recording it in the journal.
+    pub(crate) fn terminate(&mut self, process: ProcessId, state: T) -> Result<(), SimError> {
+        self.process_entry(process)?;
+        self.unblock(process, &state);
+        let mut held: Vec<_> = self
+            .held
+            .iter()
+            .filter(|((_, p), _)| *p == process)
+            .map(|(&(r, _), &n)| (r, n))
+            .collect();
+        held.sort_unstable();
+        for (resource, n) in held {
+            for _ in 0..n {
+                self.release_unit(process, resource, state.clone());
+            }
+            self.resource_changed(process, resource);
+        }
+        self.processes.remove(process);
+        self.forget_process(process);
+        self.discard_stale_events();
+        self.notify_complete(process);
+        Ok(())
+    }
+
+    /// Drop what is recorded about a process that completed.
+    ///
+    /// Its name and parent are kept while the log is kept in memory, to label it.
+    fn forget_process(&mut self, process: ProcessId) {
+        for r in 0..self.resources.len() {
+            let key = (ResourceId(r), process);
+            self.held.remove(&key);
+            self.request_times.remove(&key);
+        }
+        self.muted_processes.remove(&process);
+        if !self.keeps_log() {
+            if let Some(name) = self.process_names.remove(&process) {
+                self.forget_name(process, &name);
+            }
+            self.parents.remove(&process);
+        }
+    }
+
+    /// Discard the pending events of `process` and remove it from the queues it is in.
+    fn unblock(&mut self, process: ProcessId, state: &T) {
+        let entry = &mut self.processes[process];
+        entry.epoch += 1;
+        entry.waiting = false;
+        if let Some(condition) = entry.condition.take() {
+            self.cancel_branches(process, condition, state);
+        }
+        let entry = &mut self.processes[process];
+        match entry.blocked_on.take() {
+            Some(Blocked::Resource(r)) => self.withdraw_request(process, r),
+            Some(Blocked::Granted(r, units)) => {
+                for _ in 0..units {
+                    self.give_back(process, r, state.clone());
+                }
+            }
+            Some(Blocked::Store(s)) => {
+                self.stores[s.0].cancel(process);
+            }
+            Some(Blocked::Topic(t)) => self.topics[t].cancel(process),
+            Some(Blocked::Signal(s)) => self.signals[s].cancel(process),
+            Some(Blocked::Gate(g)) => self.gates[g].cancel(process),
+            Some(Blocked::Mailbox(m)) => self.mailboxes[m].cancel(process),
+            Some(Blocked::Predicate(p)) => self.predicates[p].cancel(process),
+            None => {}
+        }
+    }
+
+    /// Push an event in the future events, on behalf of the current incarnation
+    /// of its process.
+    fn schedule(&mut self, mut event: Event<T>) -> EventId {
+        // the events are ordered by their exact time, when they have one
+        if event.exact.is_none() {
+            event.exact = if event.time == self.time {
+                self.clock
+            } else {
+                SplitTime::checked_from_f64(event.time)
+            };
+        }
+        let id = self.next_event_id;
+        self.next_event_id += 1;
+        event.id = id;
+        // events for completed processes are discarded by `next_event`
+        event.epoch = self.processes.get(event.process()).map_or(0, |e| e.epoch);
+        for observer in self.observers.iter_mut() {
+            observer.on_event_scheduled(&event);
+        }
+        self.future_events.push(event);
+        self.engine.record_pending(self.future_events.len());
+        id
+    }
+
+    /// Pop the next event, discarding the ones that are not valid anymore.
+    ///
+    /// Events that complete only a part of an `AllOf` condition are consumed as well.
+    /// Returns the event and, if it completed an `AnyOf` condition, the effect that fired.
+    fn next_event(&mut self) -> Option<(Event<T>, Option<Effect>)> {
+        while let Some(event) = self.future_events.pop() {
+            if let Some(source) = event.source {
+                self.schedule_next_arrival(source);
+            }
+            if let Some(series) = event.series {
+                self.schedule_next_occurrence(series, event.time());
+            }
+            if self.cancelled_events.remove(&event.id) {
+                continue;
+            }
+            match self.processes.get(event.process()) {
+                Some(entry) if !entry.suspended && entry.epoch == event.epoch => {}
+                _ => continue,
+            }
+            if let Some(branch) = event.branch {
+                self.advance_clock(&event);
+                match self.complete_branch(event.process(), branch, event.state()) {
+                    Some(fired) => return Some((event, fired)),
+                    None => continue,
+                }
+            }
+            return Some((event, None));
+        }
+        None
+    }
+
+    /// Returns `true` if `event` was not cancelled and its process can still be resumed by it.
+    fn is_pending(&self, event: &Event<T>) -> bool {
+        self.processes.get(event.process()).is_some_and(|entry| {
+            !self.cancelled_events.contains(&event.id)
+                && !entry.suspended
+                && entry.epoch == event.epoch
+        })
+    }
+
+    /// Pop the events at the head of the future events that are not valid anymore, so that
+    /// the head is always the next event to execute.
+    ///
+    /// Called after every operation that can invalidate scheduled events.
+    fn discard_stale_events(&mut self) {
+        while self
+            .future_events
+            .peek()
+            .is_some_and(|e| !self.is_pending(e))
+        {
+            let event = self.future_events.pop().unwrap();
+            if let Some(source) = event.source {
+                self.schedule_next_arrival(source);
+            }
+            if let Some(series) = event.series {
+                self.schedule_next_occurrence(series, event.time());
+            }
+            self.cancelled_events.remove(&event.id);
+        }
+    }
+
+    /// Returns the next event that will be executed, without executing it.
+    ///
+    /// Events injected with an `EventInjector` are not considered until the next step
+    /// receives them.
+    pub fn peek_next_event(&self) -> Option<&Event<T>> {
+        // the stale events at the head are discarded as soon as they become stale
+        self.future_events.peek()
+    }
+
+    /// Returns the time of the next event that will be executed, see `peek_next_event`.
+    pub fn peek_next_time(&self) -> Option<f64> {
+        self.peek_next_event().map(Event::time)
+    }
+
+    /// Create a condition, i.e. a combination of effects that a process can wait for
+    /// yielding `Effect::AnyOf`, `Effect::AllOf` or `Effect::Select`.
+    ///
+    /// A process waiting for `AnyOf` is resumed as soon as one of the effects completes,
+    /// with the state it yielded, and can find out which one with `SimContext::fired()`.
+    /// A process waiting for `AllOf` is resumed when the last one completes.
+    ///
+    /// When an `AnyOf` condition completes, the process leaves the queues it is still in
+    /// and releases the resources it was granted by the other effects. Items already
+    /// pushed to or pulled from a store are not given back.
+    ///
+    /// # Panics
+    /// Only `TimeOut`, `Request`, `Push` and `Pull` effects can be combined in a condition.
+    pub fn create_condition(&mut self, effects: Vec<Effect>) -> ConditionId {
+        assert!(
+            effects.iter().all(|e| matches!(
+                e,
+                Effect::TimeOut(_) | Effect::Request(_) | Effect::Push(_) | Effect::Pull(_)
+            )),
+            "ERROR. Only TimeOut, Request, Push and Pull effects can be combined in a condition."
+        );
+        let id = self.conditions.len();
+        self.conditions.push(effects);
+        id
+    }
+
+    /// Start the effects of a condition on behalf of `process`, whose `branches` are all
+    /// pending.
+    ///
+    /// If the condition is `exclusive`, the effects are started in order until one of them
+    /// completes, and the others are withdrawn.
+    fn start_condition(
+        &mut self,
+        process: ProcessId,
+        branches: Vec<(Effect, BranchStatus)>,
+        any: bool,
+        exclusive: bool,
+        y: T,
+    ) {
+        let len = branches.len();
+        self.processes[process].condition = Some(ActiveCondition {
+            any,
+            exclusive,
+            branches,
+        });
+        for i in 0..len {
+            if exclusive && self.branch_status(process, i) != Some(BranchStatus::Pending) {
+                // another effect already completed
+                continue;
+            }
+            let Some(effect) = self.processes[process]
+                .condition
+                .as_ref()
+                .map(|c| c.branches[i].0)
+            else {
+                break;
+            };
+            let mut e = Event::new(self.time, process, y.clone());
+            e.branch = Some(i);
+            match effect {
+                Effect::TimeOut(t) => {
+                    self.set_delay(&mut e, t);
+                    self.set_branch_status(process, i, BranchStatus::Ready);
+                    self.schedule(e);
+                }
+                Effect::Request(r) => {
+                    self.request_times.insert((r, process), self.time);
+                    self.resource_stats[r.0].record_request();
+                    let mut granted = std::mem::take(&mut self.grants_buffer);
+                    self.resources[r.0].allocate_n_or_enqueue_into(e, 1, &mut granted);
+                    self.schedule_grants(r, &mut granted);
+                    self.grants_buffer = granted;
+                    self.resource_changed(process, r);
+                }
+                Effect::Push(s) => {
+                    self.stores[s.0]
+                        .push_or_enqueue_and_schedule_next(e, &mut self.future_events_buffer);
+                    self.schedule_store_events(process, s);
+                }
+                Effect::Pull(s) => {
+                    self.stores[s.0]
+                        .pull_or_enqueue_and_schedule_next(e, &mut self.future_events_buffer);
+                    self.schedule_store_events(process, s);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    fn set_branch_status(&mut self, process: ProcessId, branch: usize, status: BranchStatus) {
+        let Some(condition) = self.processes[process].condition.as_mut() else {
+            return;
+        };
+        condition.branches[branch].1 = status;
+        let effect = condition.branches[branch].0;
+        if !condition.exclusive || status != BranchStatus::Ready {
+            return;
+        }
+        if let Effect::TimeOut(_) = effect {
+            return;
+        }
+        // withdraw the other effects before they complete as well
+        let mut others = Vec::new();
+        for (i, (effect, status)) in condition.branches.iter_mut().enumerate() {
+            if i != branch && *status == BranchStatus::Pending {
+                *status = BranchStatus::Done;
+                others.push(*effect);
+            }
+        }
+        for effect in others {
+            match effect {
+                Effect::Request(r) => self.withdraw_request(process, r),
+                Effect::Push(s) | Effect::Pull(s) => {
+                    self.stores[s.0].cancel(process);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn branch_status(&self, process: ProcessId, branch: usize) -> Option<BranchStatus> {
+        let condition = self.processes[process].condition.as_ref()?;
+        Some(condition.branches[branch].1)
+    }
+
+    /// Mark an effect of the condition of `process` as completed.
+    ///
+    /// Returns `Some` if the process must be resumed, with the effect that fired if the
+    /// condition is an `AnyOf`.
+    fn complete_branch(
+        &mut self,
+        process: ProcessId,
+        branch: usize,
+        state: &T,
+    ) -> Option<Option<Effect>> {
+        let entry = &mut self.processes[process];
+        let condition = entry.condition.as_mut()?;
+        condition.branches[branch].1 = BranchStatus::Done;
+        if condition.any {
+            let condition = entry.condition.take().unwrap();
+            let fired = condition.branches[branch].0;
+            // discard the events of the other effects
+            entry.epoch += 1;
+            self.cancel_branches(process, condition, state);
+            Some(Some(fired))
+        } else if condition
+            .branches
+            .iter()
+            .all(|(_, status)| *status == BranchStatus::Done)
+        {
+            let condition = entry.condition.take().unwrap();
+            self.recycle_branches(condition.branches);
+            Some(None)
+        } else {
+            None
+        }
+    }
+
+    /// Withdraw the effects of a condition that did not complete.
+    ///
+    /// The events of the process must be discarded by the caller.
+    /// Items pushed or pulled by a completed store operation are not given back.
+    fn cancel_branches(&mut self, process: ProcessId, mut condition: ActiveCondition, state: &T) {
+        for (effect, status) in condition.branches.drain(..) {
+            match (effect, status) {
+                (Effect::Request(r), BranchStatus::Pending) => self.withdraw_request(process, r),
+                (Effect::Request(r), BranchStatus::Ready) => {
+                    self.give_back(process, r, state.clone())
+                }
+                (Effect::Push(s) | Effect::Pull(s), BranchStatus::Pending) => {
+                    self.stores[s.0].cancel(process);
+                }
+                _ => {}
+            }
+        }
+        self.recycle_branches(condition.branches);
+    }
+
+    /// Returns the pending branches of condition `c`, in a recycled vector if possible.
+    fn condition_branches(&mut self, c: ConditionId) -> Vec<(Effect, BranchStatus)> {
+        let mut branches = self.branch_buffers.pop().unwrap_or_default();
+        branches.extend(
+            self.conditions[c]
+                .iter()
+                .map(|e| (*e, BranchStatus::Pending)),
+        );
+        branches
+    }
+
+    /// Keep the vector of the branches of a condition for the next one.
+    fn recycle_branches(&mut self, mut branches: Vec<(Effect, BranchStatus)>) {
+        branches.clear();
+        self.branch_buffers.push(branches);
+    }
+
+    /// Proceed in the simulation by 1 step
+    ///
+    /// Returns what happened: the event that resumed a process, and what the process did,
+    /// or `StepResult::Idle` if there were no events to execute.
+    ///
+    /// Returns an error if the resumed process yields an effect with an invalid time, e.g.
+    /// `TimeOut(f64::NAN)`. In that case the effect is discarded and the process is not
+    /// resumed anymore.
+    pub fn step(&mut self) -> Result<StepResult<T>, SimError> {
+        let result = self.execute_step();
+        self.discard_stale_events();
+        result
+    }
+
+    fn execute_step(&mut self) -> Result<StepResult<T>, SimError> {
+        if self.metadata.started_at().is_none() {
+            self.metadata.set_started_at(SystemTime::now());
+        }
+        self.replay_calls()?;
+        self.steps += 1;
+        self.receive_injections()?;
+        let previous_time = self.time;
+        if let Some((mut event, fired)) = self.next_event() {
+            self.check_zero_delay(previous_time, &mut event)?;
+            if !self.capacity_changes.is_empty() {
+                self.notify_capacity_changes(event.time());
+            }
+            if let Some(warm_up) = self.warm_up.filter(|w| event.time() >= *w) {
+                self.warm_up = None;
+                self.reset_stats_at(warm_up);
+            }
+            self.advance_clock(&event);
+            if let Some(rollback) = &mut self.rollback {
+                rollback.record(self.time, self.steps);
+            }
+            let process = event.process();
+            let entry = &mut self.processes[process];
+            entry.waiting = false;
+            entry.blocked_on = None;
+            // a process waiting with a deadline may be woken up by another event first
+            let deadline = entry.deadline.take();
+            let deadline_expired = deadline == Some(event.id);
+            if let Some(id) = deadline.filter(|_| !deadline_expired) {
+                self.cancel(id);
+            }
+            let entry = &mut self.processes[process];
+            event.entity = entry.entity;
+            let rng = Philox4x32::from_substream(&self.streams, entry.stream, entry.resumes);
+            entry.resumes = entry.resumes.wrapping_add(1);
+            let fired_branch = event.branch.filter(|_| fired.is_some());
+            let timed_out = deadline_expired || matches!(fired, Some(Effect::TimeOut(_)));
+            let outcome = match (entry.last_effect, event.cause) {
+                _ if event.interrupted => Outcome::Interrupted,
+                _ if timed_out => Outcome::TimedOut,
+                (None, _) => Outcome::Started,
+                // resumed by someone else, before the effect completed
+                (Some(_), Some(cause)) => cause,
+                (
+                    Some(
+                        Effect::Request(r) | Effect::RequestN(r, _) | Effect::RequestTimeout(r, _),
+                    ),
+                    None,
+                ) if event.rejected => Outcome::Rejected(r),
+                (Some(Effect::Push(s)), None) if event.rejected => Outcome::Full(s),
+                (effect, None) => Outcome::of(effect, fired_branch),
+            };
+            let scheduled = entry.scheduled.take();
+            let step = process::resume(
+                entry
+                    .coroutine
+                    .as_mut()
+                    .expect("ERROR. Tried to resume a completed process."),
+                SimContext {
+                    rng,
+                    time: self.time,
+                    state: event.state().clone(),
+                    outcome,
+                    interrupted: event.interrupted,
+                    fired: fired_branch,
+                    timed_out,
+                    release: event.release,
+                    created: event.created,
+                    event_id: event.id,
+                    scheduled,
+                    resources: Arc::clone(&self.resource_snapshots),
+                    shared: self.shared.clone(),
+                    entity: event.entity,
+                },
+            );
+            match step {
+                ProcessStep::Yield(y) => {
+                    // log event
+                    // logging needs to happen before the processing because processing
+                    // can add further events (such as resource acquired/released) and
+                    // it becomes confusing if you first get a resource acquired event
+                    // and only log the request for it afterwards.
+                    self.log_processed_event(&event, y.clone());
+                    // process event
+                    self.check_effect(process, y.get_effect())?;
+                    self.spawn_requested(process, &y)?;
+                    self.processes[process].last_effect = Some(y.get_effect());
+                    let scheduled_before = self.future_events.len();
+                    self.process_effect(process, y.clone());
+                    let scheduled = self.future_events.len() - scheduled_before;
+                    if self.processes.get(process).is_some() {
+                        self.charge_event_budget(process, scheduled);
+                    }
+                    self.wake_waiting_until();
+                    Ok(StepResult::Executed { event, state: y })
+                }
+                ProcessStep::Complete => {
+                    // free the slot, the ids of the other processes stay valid
+                    self.processes.remove(process);
+                    self.forget_process(process);
+                    self.notify_complete(process);
+                    self.spawn_requested(process, event.state())?;
+                    self.wake_waiting_until();
+                    Ok(StepResult::Completed { event })
+                }
+            }
+        } else {
+            Ok(StepResult::Idle)
+        }
+    }
+
+    /// Check that the time of an effect yielded by `process` is valid.
+    fn check_effect(&self, process: ProcessId, effect: Effect) -> Result<(), SimError> {
+        let valid = match effect {
+            Effect::TimeOut(time)
+            | Effect::Event { time, .. }
+            | Effect::EventWithPriority { time, .. }
+            | Effect::RequestTimeout(_, time)
+            | Effect::WaitTimeout(time) => !time.is_nan(),
+            Effect::EventAt { time, .. } => time >= self.time,
+            Effect::AnyOf(c) | Effect::AllOf(c) | Effect::Select(c) => self.conditions[c]
+                .iter()
+                .all(|e| !matches!(e, Effect::TimeOut(t) if t.is_nan())),
+            _ => true,
+        };
+        if !valid {
+            return Err(SimError::InvalidTime(process));
+        }
+        if let Effect::RequestN(resource, units) = effect {
+            let capacity = Resource::capacity(&self.resources[resource.0]);
+            if units == 0
+                || capacity.is_some_and(|c| units > c)
+                || !self.resources[resource.0].supports_units(units)
+            {
+                return Err(SimError::InvalidUnits {
+                    process,
+                    resource,
+                    units,
+                });
+            }
+        }
+        if let Effect::RequestGroup(g) | Effect::ReleaseGroup(g) = effect {
+            if self.groups.get(g).is_none_or(|g| g.members().is_empty()) {
+                return Err(SimError::UnknownGroup(g));
+            }
+        }
+        if let Effect::Kill(target) = effect {
+            if self.processes.get(target).is_none() && !self.processes.was_removed(target) {
+                return Err(SimError::UnknownProcess(target));
+            }
+        }
+        let factory = match effect {
+            Effect::Spawn(f) => Some((f, self.process_factories.contains(f))),
+            Effect::AddResource(f) => Some((f, f < self.resource_factories.len())),
+            Effect::AddStore(f) => Some((f, f < self.store_factories.len())),
+            _ => None,
+        };
+        if let Some((f, false)) = factory {
+            return Err(SimError::UnknownFactoryId(f));
+        }
+        if let Effect::Record(stat, _) = effect {
+            if stat >= self.stats.len() {
+                return Err(SimError::UnknownStat(stat));
+            }
+        }
+        if let Effect::Carry(entity) = effect {
+            if self.entity(entity).is_none() {
+                return Err(SimError::UnknownEntity(entity));
+            }
+            match self.processes[process].entity {
+                Some(carried) if carried != entity => {
+                    return Err(SimError::AlreadyCarrying {
+                        process,
+                        entity: carried,
+                    })
+                }
+                _ => {}
+            }
+        }
+        let (resource, units) = match effect {
+            Effect::Release(r) => (r, 1),
+            Effect::ReleaseN(r, n) => (r, n),
+            Effect::ReleaseGroup(g) => (self.held_group_member(process, g), 1),
+            _ => return Ok(()),
+        };
+        if self.held(process, resource) >= units {
+            return Ok(());
+        }
+        match self.release_policy {
+            ReleasePolicy::Ignore => Ok(()),
+            ReleasePolicy::Fail => Err(SimError::NotHolding { process, resource }),
+            ReleasePolicy::Panic => panic!(
+                "ERROR. Process {} released resource {} that it does not hold.",
+                process, resource
+            ),
+        }
+    }
+
+    /// Release `n` units of `resource` held by `process` and resume it.
+    ///
+    /// The units that the process does not hold, allowed by `ReleasePolicy::Ignore`, are
+    /// not released.
+    fn release_units(&mut self, process: ProcessId, resource: ResourceId, n: usize, y: T) {
+        let held = self.held(process, resource);
+        for _ in 0..n.min(held) {
+            self.release_unit(process, resource, y.clone());
+        }
+        self.resource_changed(process, resource);
+        let mut e = Event::new(self.time, process, y);
+        if held < n {
+            e.release = Some(ReleaseOutcome::NotHeld);
+        }
+        self.schedule(e);
+    }
+
+    /// Schedule the events returned by a mailbox, parking `process` if it is not resumed.
+    fn schedule_mailbox_events(
+        &mut self,
+        process: ProcessId,
+        mailbox: MailboxId,
+        events: Vec<Event<T>>,
+    ) {
+        if events.iter().all(|e| e.process() != process) {
+            self.processes[process].blocked_on = Some(Blocked::Mailbox(mailbox));
+        }
+        for e in events {
+            self.schedule(e);
+        }
+    }
+
+    /// Request `units` units of `resource` on behalf of `process`.
+    fn request(&mut self, process: ProcessId, resource: ResourceId, units: usize, y: T) {
+        let request_event = Event::new(self.time, process, y);
+        self.request_times.insert((resource, process), self.time);
+        if units != 1 {
+            self.requested_units.insert((resource, process), units);
+        }
+        self.resource_stats[resource.0].record_request();
+        let mut granted = std::mem::take(&mut self.grants_buffer);
+        self.resources[resource.0].allocate_n_or_enqueue_into(request_event, units, &mut granted);
+        if granted.iter().all(|e| e.process() != process) {
+            self.processes[process].blocked_on = Some(Blocked::Resource(resource));
+        }
+        self.schedule_grants(resource, &mut granted);
+        self.grants_buffer = granted;
+        self.resource_changed(process, resource);
+    }
+
+    /// Release a unit of the resource `r` on behalf of `process`, then resume it.
+    fn release(&mut self, process: ProcessId, r: ResourceId, y: T) {
+        if self.held(process, r) == 0 {
+            self.release_units(process, r, 1, y);
+            return;
+        }
+        let mut release_event = Event::new(self.time, process, y.clone());
+        let outcome = match self.release_unit(process, r, y) {
+            Some(woken) => ReleaseOutcome::Woke(woken),
+            None => ReleaseOutcome::Freed,
+        };
+        self.resource_changed(process, r);
+        // after releasing the resource the process
+        // can be resumed
+        release_event.release = Some(outcome);
+        self.schedule(release_event);
+    }
+
+    /// Returns the member of a group requested by `Effect::RequestGroup`: the first one
+    /// with an available unit, or the one with the shortest queue.
+    fn group_member(&self, group: GroupId) -> ResourceId {
+        self.groups[group]
+            .members()
+            .iter()
+            .copied()
+            .min_by_key(|r| {
+                let resource = &self.resources[r.0];
+                let free = Resource::available(resource).is_some_and(|a| a > 0);
+                (!free, Resource::queue_len(resource))
+            })
+            .expect("the groups requested are not empty")
+    }
+
+    /// Returns the member of a group released by `process` with `Effect::ReleaseGroup`:
+    /// the first one it holds, or the first member if it holds none.
+    fn held_group_member(&self, process: ProcessId, group: GroupId) -> ResourceId {
+        let members = self.groups[group].members();
+        members
+            .iter()
+            .copied()
+            .find(|r| self.held(process, *r) > 0)
+            .unwrap_or(members[0])
+    }
+
+    /// Apply the effect of the state `y` yielded by `process`.
+    fn process_effect(&mut self, process: ProcessId, y: T) {
+        match y.get_effect() {
+            Effect::TimeOut(t) => {
+                self.schedule(self.delayed_event(t, process, y));
+            }
+            Effect::Event {
+                time,
+                process: target,
+            } => {
+                let mut e = self.delayed_event(time, target, y);
+                e.cause = Some(Outcome::Resumed);
+                self.processes[process].scheduled = Some(self.schedule(e));
+            }
+            Effect::EventWithPriority {
+                time,
+                process: target,
+                priority,
+            } => {
+                let mut e = self.delayed_event(time, target, y);
+                e.set_priority(priority);
+                e.cause = Some(Outcome::Resumed);
+                self.processes[process].scheduled = Some(self.schedule(e));
+            }
+            Effect::EventAt {
+                time,
+                process: target,
+            } => {
+                let mut e = Event::new(time, target, y);
+                e.cause = Some(Outcome::Resumed);
+                self.processes[process].scheduled = Some(self.schedule(e));
+            }
+            Effect::Request(r) => self.request(process, r, 1, y),
+            Effect::RequestN(r, n) => self.request(process, r, n, y),
+            Effect::Release(r) => self.release(process, r, y),
+            Effect::RequestGroup(g) => {
+                let r = self.group_member(g);
+                // the outcome reports the member that was requested
+                self.processes[process].last_effect = Some(Effect::Request(r));
+                self.request(process, r, 1, y);
+            }
+            Effect::ReleaseGroup(g) => {
+                let r = self.held_group_member(process, g);
+                self.processes[process].last_effect = Some(Effect::Release(r));
+                self.release(process, r, y);
+            }
+            Effect::ReleaseN(r, n) => self.release_units(process, r, n, y),
+            Effect::ReleaseAll(r) => {
+                let n = self.held(process, r);
+                self.release_units(process, r, n, y);
+            }
+            Effect::AddResource(f) => {
+                let resource = (self.resource_factories[f])();
+                let mut e = Event::new(self.time, process, y);
+                e.created = Some(self.create_resource(resource).0);
+                self.schedule(e);
+            }
+            Effect::AddStore(f) => {
+                let store = (self.store_factories[f])();
+                let mut e = Event::new(self.time, process, y);
+                e.created = Some(self.create_store(store).0);
+                self.schedule(e);
+            }
+            Effect::Spawn(f) => {
+                // the id was checked with the effect
+                let child = self.process_factories.build(f, None, &y).unwrap();
+                let child = self.create_process(child);
+                self.parents.insert(child, process);
+                self.schedule(Event::new(self.time, child, y.clone()));
+                let mut e = Event::new(self.time, process, y);
+                e.created = Some(child.0);
+                self.schedule(e);
+            }
+            Effect::Kill(target) => {
+                // the target may have completed, e.g. a customer that already left
+                let _ = self.terminate(target, y.clone());
+                if target != process {
+                    self.schedule(Event::new(self.time, process, y));
+                }
+            }
+            Effect::Fail(r) => {
+                self.fail_resource(process, r, y.clone());
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Repair(r) => {
+                self.repair_resource(r);
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Wait => self.processes[process].waiting = true,
+            Effect::WaitTimeout(t) => {
+                let deadline = self.schedule(self.delayed_event(t, process, y));
+                let entry = &mut self.processes[process];
+                entry.waiting = true;
+                entry.deadline = Some(deadline);
+            }
+            Effect::Trace => {
+                // this event is only for tracing, reschedule
+                // immediately'
+                let e = Event::new(self.time, process, y);
+                self.schedule(e);
+            }
+            Effect::Push(s) => {
+                let store = &mut self.stores[s.0];
+                let request_event = Event::new(self.time, process, y);
+                store.push_or_enqueue_and_schedule_next(
+                    request_event,
+                    &mut self.future_events_buffer,
+                );
+                self.schedule_store_events(process, s);
+            }
+            Effect::Pull(s) => {
+                let store = &mut self.stores[s.0];
+                let request_event = Event::new(self.time, process, y);
+                store.pull_or_enqueue_and_schedule_next(
+                    request_event,
+                    &mut self.future_events_buffer,
+                );
+                self.schedule_store_events(process, s);
+            }
+            Effect::AnyOf(c) => {
+                let branches = self.condition_branches(c);
+                self.start_condition(process, branches, true, false, y);
+            }
+            Effect::AllOf(c) => {
+                let branches = self.condition_branches(c);
+                self.start_condition(process, branches, false, false, y);
+            }
+            Effect::Select(c) => {
+                let branches = self.condition_branches(c);
+                self.start_condition(process, branches, true, true, y);
+            }
+            Effect::RequestTimeout(r, t) => {
+                let mut branches = self.branch_buffers.pop().unwrap_or_default();
+                branches.push((Effect::Request(r), BranchStatus::Pending));
+                branches.push((Effect::TimeOut(t), BranchStatus::Pending));
+                self.start_condition(process, branches, true, false, y);
+            }
+            Effect::Cancel(event) => {
+                self.cancel(event);
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Publish(t) => {
+                for e in self.topics[t].publish(self.time, &y) {
+                    self.schedule(e);
+                }
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Subscribe(t) => {
+                self.topics[t].wait(Event::new(self.time, process, y));
+                self.processes[process].blocked_on = Some(Blocked::Topic(t));
+            }
+            Effect::WaitSignal(s) => {
+                self.signals[s].wait(Event::new(self.time, process, y));
+                self.processes[process].blocked_on = Some(Blocked::Signal(s));
+            }
+            Effect::WaitUntil(p) => {
+                let event = Event::new(self.time, process, y);
+                if self.predicates[p].holds(&SimView { sim: self }) {
+                    self.schedule(event);
+                } else {
+                    self.predicates[p].wait(event);
+                    self.processes[process].blocked_on = Some(Blocked::Predicate(p));
+                }
+            }
+            Effect::Fire(s) | Effect::FireOne(s) => {
+                let all = matches!(y.get_effect(), Effect::Fire(_));
+                for e in self.signals[s].fire(self.time, all) {
+                    self.schedule(e);
+                }
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::WaitGate(g) => match self.gates[g].wait(Event::new(self.time, process, y)) {
+                Some(e) => {
+                    self.schedule(e);
+                }
+                None => self.processes[process].blocked_on = Some(Blocked::Gate(g)),
             },
-        ));
+            Effect::OpenGate(g) => {
+                for e in self.gates[g].open(self.time) {
+                    self.schedule(e);
+                }
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::CloseGate(g) => {
+                self.gates[g].close();
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Send(m) => {
+                let events = self.mailboxes[m].send(Event::new(self.time, process, y));
+                self.schedule_mailbox_events(process, m, events);
+            }
+            Effect::Receive(m) => {
+                let events = self.mailboxes[m].receive(Event::new(self.time, process, y));
+                self.schedule_mailbox_events(process, m, events);
+            }
+            Effect::Record(stat, value) => {
+                self.stats.record(stat, self.time, value);
+                self.notify_stat(StatUpdate::Sample {
+                    stat,
+                    time: self.time,
+                    value,
+                });
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::CreateEntity => {
+                let entity = self.create_entity();
+                self.processes[process].entity = Some(entity);
+                let mut e = Event::new(self.time, process, y);
+                e.created = Some(entity.0);
+                self.schedule(e);
+            }
+            Effect::Carry(entity) => {
+                self.processes[process].entity = Some(entity);
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Depart => {
+                if let Some(entity) = self.processes[process].entity.take() {
+                    self.entities.depart(entity, self.time);
+                }
+                self.schedule(Event::new(self.time, process, y));
+            }
+            Effect::Interrupt(target) => {
+                // interrupting a completed process has no effect
+                if self.processes.get(target).is_some() {
+                    self.interrupt_process(target, y.clone());
+                }
+                // a process that interrupts itself is resumed once, as interrupted
+                if target != process {
+                    self.schedule(Event::new(self.time, process, y));
+                }
+            }
+        }
+    }
+
+    /// Schedule the events returned by store `s` after an operation of `process`.
+    fn schedule_store_events(&mut self, process: ProcessId, s: StoreId) {
+        let mut buffer = std::mem::take(&mut self.future_events_buffer);
+        if self.processes[process].condition.is_none()
+            && !buffer.iter().any(|e| e.process() == process)
+        {
+            self.processes[process].blocked_on = Some(Blocked::Store(s));
+        }
+        for e in buffer.drain(..) {
+            if let Some(branch) = e.branch {
+                self.set_branch_status(e.process(), branch, BranchStatus::Ready);
+            }
+            self.schedule(e);
+        }
+        self.future_events_buffer = buffer;
+        self.state_changed = true;
+        let level = self.stores[s.0].level();
+        self.soft_limits.check_store(self.time, process, s, level);
+    }
+
+    /// Set the end condition used by `run_to_end`
+    pub fn set_end_condition(&mut self, until: EndCondition) {
+        self.end_condition = Some(until);
+    }
+
+    /// Returns the end condition used by `run_to_end`, if set
+    pub fn end_condition(&self) -> Option<&EndCondition> {
+        self.end_condition.as_ref()
+    }
+
+    /// Replace the data structure of the future events, moving the events already
+    /// scheduled into `list`. See the [`event_list`](crate::event_list) module.
+    pub fn set_future_event_list(&mut self, mut list: BoxedEventList<T>) {
+        list.reserve(self.future_events.len());
+        while let Some(event) = self.future_events.pop() {
+            list.push(event);
+        }
+        self.future_events = FutureEvents::Custom(list);
+    }
+
+    /// Reserve room for at least `additional` more scheduled events
+    pub fn reserve_events(&mut self, additional: usize) {
+        self.future_events.reserve(additional);
+    }
+
+    /// Run the simulation until the end condition set with `set_end_condition` is met,
+    /// or until there are no more events if it is not set.
+    pub fn run_to_end(self) -> Simulation<T> {
+        let until = self.end_condition.clone().unwrap_or(EndCondition::NoEvents);
+        self.run(until)
+    }
+
+    /// Run the simulation until and ending condition is met.
+    ///
+    /// The simulation also stops if a step fails. The error can be retrieved with `error()`.
+    ///
+    /// Why the simulation stopped is returned by `end_reason()`.
+    pub fn run(mut self, until: EndCondition) -> Simulation<T> {
+        self.start_run();
+        while !self.should_stop(&until) {
+            if let Err(e) = self.step() {
+                self.fail(e);
+                break;
+            }
+        }
+        self.end_run();
+        self
+    }
+
+    /// Run the simulation while `condition` holds, until an ending condition is met.
+    ///
+    /// `condition` is checked before each step, e.g. to stop when a number of entities
+    /// were served or a queue grew too long. Like `run`, the run stops if a step fails.
+    /// If the run stops because `condition` does not hold, the end reason is
+    /// `EndReason::Condition`.
+    pub fn run_while<F>(mut self, until: EndCondition, mut condition: F) -> Simulation<T>
+    where
+        F: FnMut(&Simulation<T>) -> bool,
+    {
+        self.start_run();
+        while !self.should_stop(&until) {
+            if !condition(&self) {
+                self.end_reason = Some(EndReason::Condition);
+                break;
+            }
+            if let Err(e) = self.step() {
+                self.fail(e);
+                break;
+            }
+        }
+        self.end_run();
+        self
+    }
+
+    /// Run the simulation until an ending condition is met, pacing it with the wall clock.
+    ///
+    /// Before each step, the thread sleeps until the wall-clock time corresponding to the
+    /// next event, so that the simulation time advances in sync with the real time.
+    /// `scale` is the number of wall-clock seconds for a simulation time unit: 1.0 runs in
+    /// real time if the time unit is the second, 0.5 twice as fast. If a step takes longer
+    /// than the time available, the simulation falls behind and catches up without sleeping.
+    ///
+    /// Like `run`, the run stops if a step fails.
+    pub fn run_realtime(mut self, until: EndCondition, scale: f64) -> Simulation<T> {
+        self.start_run();
+        let start = Instant::now();
+        let start_time = self.time;
+        while !self.should_stop(&until) {
+            if let Some(mut time) = self.peek_next_time() {
+                if let EndCondition::Time(t) = until {
+                    time = time.min(t);
+                }
+                let delay = ((time - start_time) * scale).max(0.0);
+                if let Some(wait) = Duration::try_from_secs_f64(delay)
+                    .ok()
+                    .and_then(|d| (start + d).checked_duration_since(Instant::now()))
+                {
+                    thread::sleep(wait);
+                }
+            }
+            if let Err(e) = self.step() {
+                self.fail(e);
+                break;
+            }
+        }
+        self.end_run();
+        self
+    }
+
+    /// Returns the error that stopped the last run, if any
+    pub fn error(&self) -> Option<&SimError> {
+        self.error.as_ref()
+    }
+
+    /// Returns why the last run stopped, or `None` if the simulation was never run
+    pub fn end_reason(&self) -> Option<&EndReason> {
+        self.end_reason.as_ref()
+    }
+
+    /// Record that the run stopped because of an error.
+    fn fail(&mut self, e: SimError) {
+        self.end_reason = Some(EndReason::Error(e.clone()));
+        self.error = Some(e);
+    }
+
+    /// Record the start of a run, for the end conditions relative to it.
+    fn start_run(&mut self) {
+        self.run_start = (self.steps, Instant::now());
+        self.engine.start_run();
+    }
+
+    /// Record the end of a run in the performance counters of the engine.
+    fn end_run(&mut self) {
+        let (steps, start) = self.run_start;
+        self.engine.end_run(self.steps - steps, start.elapsed());
+    }
+
+    /// Returns the performance counters of the engine, see the
+    /// [`engine`](crate::engine) module.
+    pub fn engine_stats(&self) -> EngineStats {
+        self.engine
+            .stats(self.steps, self.future_events.len(), self.elapsed())
+    }
+
+    /// Return `true` and record the end reason if the ending condition was met.
+    fn should_stop(&mut self, until: &EndCondition) -> bool {
+        let report = ProgressReport {
+            time: self.time,
+            steps: self.steps,
+            pending: self.future_events.len(),
+        };
+        if self.progress.as_mut().is_some_and(|p| p.report(report)) {
+            self.end_reason = Some(EndReason::Aborted);
+            return true;
+        }
+        if self.stop.load(AtomicOrdering::Relaxed) && self.stop.swap(false, AtomicOrdering::Relaxed)
+        {
+            self.end_reason = Some(EndReason::Stopped);
+            return true;
+        }
+        match self.ending_reason(until) {
+            Some(reason) => {
+                self.end_reason = Some(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set a hook called with the progress of the runs at the given interval, that can
+    /// abort them. See the [`progress`](crate::progress) module.
+    ///
+    /// # Panics
+    /// Panics if the interval is not positive.
+    pub fn set_progress_hook(&mut self, interval: CheckInterval, hook: ProgressHook) {
+        assert!(
+            interval.first() > 0.0,
+            "ERROR. The interval of the progress reports must be positive."
+        );
+        self.progress = Some(Progress::new(interval, hook));
+    }
+
+    /// Remove the progress hook, if set
+    pub fn remove_progress_hook(&mut self) {
+        self.progress = None;
+    }
+
+    /// Register an invariant of the model, checked by `soak` at the given interval.
+    /// See the [`soak`](crate::soak) module.
+    ///
+    /// # Panics
+    /// Panics if the interval is not positive.
+    pub fn add_invariant(
+        &mut self,
+        name: impl Into<String>,
+        interval: CheckInterval,
+        check: InvariantCheck<Simulation<T>>,
+    ) {
+        assert!(
+            interval.first() > 0.0,
+            "ERROR. The interval of the invariant checks must be positive."
+        );
+        self.invariants
+            .push(Invariant::new(name.into(), interval, check));
+    }
+
+    /// Run the simulation until the ending condition is met, checking the invariants.
+    ///
+    /// Stops at the first invariant that does not hold, reporting it together with the
+    /// state of the resources and stores, or at the first step that fails, reporting its
+    /// error.
+    pub fn soak(&mut self, until: EndCondition) -> Result<(), SoakError> {
+        self.start_run();
+        let mut result = Ok(());
+        while !self.should_stop(&until) {
+            if let Err(e) = self.step() {
+                self.fail(e.clone());
+                result = Err(SoakError::Step(e));
+                break;
+            }
+            if let Err(violation) = self.check_invariants() {
+                result = Err(SoakError::Invariant(violation));
+                break;
+            }
+        }
+        self.end_run();
+        result
+    }
+
+    fn check_invariants(&mut self) -> Result<(), InvariantViolation> {
+        let mut invariants = std::mem::take(&mut self.invariants);
+        let mut result = Ok(());
+        for invariant in invariants.iter_mut() {
+            if !invariant.is_due(self.steps, self.time) {
+                continue;
+            }
+            if let Err(message) = (invariant.check)(self) {
+                result = Err(InvariantViolation {
+                    invariant: invariant.name.clone(),
+                    message,
+                    time: self.time,
+                    step: self.steps,
+                    queues: self
+                        .resources
+                        .iter()
+                        .enumerate()
+                        .map(|(r, resource)| (ResourceId(r), Resource::queue_len(resource)))
+                        .collect(),
+                    levels: self
+                        .stores
+                        .iter()
+                        .enumerate()
+                        .map(|(s, store)| (StoreId(s), store.level()))
+                        .collect(),
+                });
+                break;
+            }
+        }
+        self.invariants = invariants;
+        result
+    }
+
+    /// Return why the ending condition was met, or `None` if it was not.
+    fn ending_reason(&self, ending_condition: &EndCondition) -> Option<EndReason> {
+        match &ending_condition {
+            EndCondition::Time(t) => (self.time >= *t).then_some(EndReason::TimeReached),
+            // the replayed inputs left may schedule more events
+            EndCondition::NoEvents => {
+                (self.future_events.is_empty() && !self.is_replaying()).then(|| {
+                    let blocked = self.blocked_processes();
+                    if blocked.is_empty() {
+                        EndReason::NoEvents
+                    } else {
+                        EndReason::Deadlock(blocked)
+                    }
+                })
+            }
+            EndCondition::NSteps(n) => {
+                (self.steps - self.run_start.0 >= *n).then_some(EndReason::StepLimit)
+            }
+            EndCondition::MaxWallClock(d) => {
+                (self.run_start.1.elapsed() >= *d).then_some(EndReason::WallClock)
+            }
+            EndCondition::And(a, b) => {
+                let reason = self.ending_reason(a)?;
+                self.ending_reason(b).map(|_| reason)
+            }
+            EndCondition::Or(a, b) => self.ending_reason(a).or_else(|| self.ending_reason(b)),
+        }
+    }
+}
+
+impl<T> SimContext<T> {
+    /// Returns current simulation time.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the `Effect` that caused the process to wake up
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// Returns the random number generator of the process for this step.
+    ///
+    /// Each process draws from its own stream, selected by the order in which processes were
+    /// created, and each step of the process from its own substream, so that the numbers
+    /// drawn depend only on the seed of the simulation and on the model.
+    pub fn rng(&mut self) -> &mut Philox4x32 {
+        &mut self.rng
+    }
+
+    /// Returns why the process was resumed
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Returns `true` if the process was woken up by an interrupt.
+    ///
+    /// In this case, the state is the cause of the interruption.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// If the process was waiting for `Effect::AnyOf`, returns the index of the effect
+    /// of the condition that completed first.
+    pub fn fired(&self) -> Option<usize> {
+        self.fired
+    }
+
+    /// Returns `true` if the process was resumed because its deadline expired, e.g. after
+    /// yielding `Effect::RequestTimeout` or `Effect::WaitTimeout`, or by the timeout of an
+    /// `AnyOf` condition.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// If the process was resumed after yielding `Effect::Release`, returns what the release did.
+    pub fn release_outcome(&self) -> Option<ReleaseOutcome> {
+        self.release
+    }
+
+    /// If the process was resumed after yielding `Effect::AddResource`, `Effect::AddStore`,
+    /// `Effect::Spawn` or `Effect::CreateEntity`, returns the raw id of the new resource,
+    /// store, process or entity, that can be converted with `from_raw`.
+    pub fn created(&self) -> Option<usize> {
+        self.created
+    }
+
+    /// Returns the id of the event that resumed the process
+    pub fn event_id(&self) -> EventId {
+        self.event_id
+    }
+
+    /// Returns the id of the last event scheduled by the process with `Effect::Event`,
+    /// `Effect::EventWithPriority` or `Effect::EventAt` since it was resumed the previous
+    /// time, if any, e.g. to cancel with `Effect::Cancel` an event scheduled for another
+    /// process.
+    pub fn scheduled(&self) -> Option<EventId> {
+        self.scheduled
+    }
+
+    /// Returns the occupancy of a resource. It does not change while the process runs.
+    ///
+    /// # Panics
+    /// If the resource does not exist.
+    pub fn resource(&self, resource: ResourceId) -> ResourceSnapshot {
+        self.resources.lock().unwrap()[resource.0]
+    }
+
+    /// Returns the entity carried by the process when it was resumed, if any.
+    /// See the [`entity`](crate::entity) module.
+    pub fn entity(&self) -> Option<EntityId> {
+        self.entity
+    }
+
+    /// Call `f` with the value of type `S` of the blackboard, if there is one.
+    /// See the [`shared`](crate::shared) module.
+    pub fn with_shared<S: SharedValue, R>(&self, f: impl FnOnce(&S) -> R) -> Option<R> {
+        self.shared.with(f)
+    }
+
+    /// Call `f` with the value of type `S` of the blackboard, to modify it, if there is one.
+    /// The predicates the processes are waiting for are evaluated again after the step.
+    pub fn with_shared_mut<S: SharedValue, R>(&mut self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        self.shared.with_mut(f)
+    }
+
+    /// Request a process from the factory named `name`, scheduled at the absolute `time`.
+    /// See the [`factory`](crate::factory) module.
+    ///
+    /// The process is created when this process yields or completes. The step fails if
+    /// there is no factory named `name`, or if `time` is NaN or in the past.
+    pub fn spawn_from_factory<A: SharedValue>(
+        &mut self,
+        name: impl Into<String>,
+        args: A,
+        time: f64,
+    ) {
+        self.shared.request_spawn(SpawnRequest {
+            factory: name.into(),
+            args: Box::new(args),
+            time,
+        });
+    }
+}
+
+impl<T> Event<T> {
+    pub fn new(time: f64, process: ProcessId, state: T) -> Event<T> {
+        Event {
+            time,
+            process,
+            state,
+            source: None,
+            series: None,
+            epoch: 0,
+            interrupted: false,
+            branch: None,
+            id: 0,
+            release: None,
+            created: None,
+            rejected: false,
+            entity: None,
+            priority: 0,
+            exact: None,
+            cause: None,
+        }
+    }
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+        self.exact = None;
+    }
+    pub fn process(&self) -> ProcessId {
+        self.process
+    }
+    /// Returns the priority of the event
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+    /// Set the priority of the event.
+    ///
+    /// Among the events scheduled at the same time, the ones with higher priority are
+    /// executed first, e.g. a resource may give a higher priority to the events of the
+    /// processes it grants.
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+    /// Order of simultaneous events: higher priority first, then in the order they were
+    /// scheduled.
+    fn tie_break(&self, other: &Event<T>) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then(self.id.cmp(&other.id))
+    }
+    /// Returns `true` if the event resumes a process that yielded `Effect::RequestTimeout`
+    /// because it was granted the resource, rather than because the deadline expired or it
+    /// was interrupted or rejected.
+    pub(crate) fn granted_in_time(&self) -> bool {
+        self.branch == Some(0) && !self.interrupted && !self.rejected
+    }
+    /// Returns the id assigned to the event when it was scheduled.
+    ///
+    /// Ids are increasing, and events scheduled at the same time are executed in the order
+    /// of their ids.
+    pub fn id(&self) -> EventId {
+        self.id
+    }
+    pub fn set_process(&mut self, process: ProcessId) {
+        self.process = process;
+    }
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+    pub fn state_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+    pub fn set_state(&mut self, state: T) {
+        self.state = state;
+    }
+    /// Reject the request of the process. A resource returns the rejected event from
+    /// `allocate_or_enqueue` to resume the process immediately without granting it the
+    /// resource, e.g. because the queue is full, and the process is resumed with
+    /// `Outcome::Rejected`. Likewise, a full store rejects a push, and the process is
+    /// resumed with `Outcome::Full`.
+    pub fn reject(&mut self) {
+        self.rejected = true;
+    }
+    /// Returns `true` if the request of the process was rejected
+    pub fn is_rejected(&self) -> bool {
+        self.rejected
+    }
+    /// Returns the entity carried by the process when it was resumed by this event, if
+    /// any. See the [`entity`](crate::entity) module.
+    pub fn entity(&self) -> Option<EntityId> {
+        self.entity
+    }
+}
+
+impl<T: SimState> Event<T> {
+    pub fn effect(&self) -> Effect {
+        self.state.get_effect()
+    }
+    pub fn set_effect(&mut self, effect: Effect) {
+        self.state.set_effect(effect)
+    }
+}
+
+impl<T: SimState + Clone> Default for Simulation<T> {
+    fn default() -> Self {
+        Simulation::<T> {
+            time: 0.0,
+            clock: Some(SplitTime::default()),
+            start_time: 0.0,
+            steps: 0,
+            processes: Slab::default(),
+            streams: Streams::new(0, 0),
+            created_processes: 0,
+            process_names: HashMap::default(),
+            named_processes: HashMap::default(),
+            instances: HashSet::default(),
+            parents: HashMap::default(),
+            future_events: FutureEvents::default(),
+            processed_events: Vec::default(),
+            log_index: LogIndex::default(),
+            log_sink: None,
+            log_begun: false,
+            log_filter: None,
+            muted_processes: HashSet::default(),
+            resources: Vec::default(),
+            stores: Vec::default(),
+            future_events_buffer: Vec::default(),
+            request_times: HashMap::default(),
+            held: HashMap::default(),
+            requested_units: HashMap::default(),
+            offline: HashMap::default(),
+            capacity_changes: Vec::default(),
+            soft_limits: SoftLimits::default(),
+            event_budget: None,
+            budget_violations: Vec::default(),
+            arrival_sources: Vec::default(),
+            metadata: RunMetadata::default(),
+            conditions: Vec::default(),
+            branch_buffers: Vec::default(),
+            grants_buffer: Vec::default(),
+            resource_factories: Vec::default(),
+            store_factories: Vec::default(),
+            entities: Entities::default(),
+            process_factories: ProcessFactories::default(),
+            initial_processes: Vec::default(),
+            next_event_id: 0,
+            cancelled_events: HashSet::default(),
+            reference: None,
+            resource_stats: Vec::default(),
+            stats: Stats::default(),
+            warm_up: None,
+            resource_names: Vec::default(),
+            store_names: Vec::default(),
+            resource_snapshots: Arc::default(),
+            groups: Vec::default(),
+            topics: Vec::default(),
+            signals: Vec::default(),
+            predicates: Vec::default(),
+            state_changed: false,
+            shared: Shared::default(),
+            gates: Vec::default(),
+            mailboxes: Vec::default(),
+            invariants: Vec::default(),
+            progress: None,
+            stop: Arc::default(),
+            observers: Vec::default(),
+            error: None,
+            zero_delay_policy: ZeroDelayPolicy::Unbounded,
+            release_policy: ReleasePolicy::default(),
+            zero_delay_steps: 0,
+            run_start: (0, Instant::now()),
+            engine: EngineCounters::default(),
+            zero_delay_processes: Vec::default(),
+            end_reason: None,
+            end_condition: None,
+            attribute_schema: None,
+            injections: Injections::new(),
+            series: Vec::default(),
+            journal: None,
+            replaying: None,
+            rollback: None,
+        }
+    }
+}
+
+impl<T> PartialEq for Event<T> {
+    fn eq(&self, other: &Event<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Event<T> {}
+
+impl<T> PartialOrd for Event<T> {
+    fn partial_cmp(&self, other: &Event<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        // let p1 start immediately...
-        sim.schedule_event(0.0, p1, Effect::TimeOut(0.));
-        // let p2 start after 2 t.u., when r is not available
-        sim.schedule_event(2.0, p2, Effect::TimeOut(2.));
-        // p2 will wait r to be free (time 7.0) and its timeout
-        // of 3.0 t.u. The simulation will end at time 10.0
+impl<T> Ord for Event<T> {
+    fn cmp(&self, other: &Event<T>) -> Ordering {
+        // times are validated when events are scheduled, the total order only prevents
+        // an invalid event from corrupting the heap
+        let time = match (self.exact, other.exact) {
+            (Some(exact), Some(other)) => exact.cmp(&other),
+            _ => self.time.total_cmp(&other.time),
+        };
+        time.then_with(|| self.tie_break(other))
+    }
+}
 
-        let s = sim.run(NoEvents);
-        println!("{:?}", s.processed_events());
-        assert_eq!(s.time(), 9.0);
+impl SimState for Effect {
+    fn get_effect(&self) -> Effect {
+        *self
+    }
+    fn set_effect(&mut self, e: Effect) {
+        *self = e;
+    }
+    fn should_log(&self) -> bool {
+        true
     }
 }
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
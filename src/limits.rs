@@ -0,0 +1,267 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Soft limits and warnings.
+//!
+//! A soft limit is a threshold on some observable quantity of the simulation,
+//! like the length of the queue of a resource or the number of items in a store.
+//! Differently from an ending condition, crossing a soft limit does not stop the
+//! simulation: a `Warning` is recorded and every hook registered with
+//! `Simulation::on_warning` is invoked.
+//!
+//! Limits on levels (queue length and store level) fire once when the threshold is
+//! crossed and are armed again when the level goes back below the threshold.
+//! Limits on waiting times fire for every request that waited too long.
+//!
+//! An event budget limits the number of events that a single process may schedule.
+//! It is useful to find out which process is responsible of a runaway simulation,
+//! e.g. a zero-delay loop. See `Simulation::set_event_budget`.
+//! Zero-delay loops can also be bounded globally with `Simulation::set_zero_delay_policy`.
+//! Releases of resources that a process does not hold are handled according to
+//! `Simulation::set_release_policy`.
+use crate::{ProcessId, ResourceId, StoreId};
+use std::fmt;
+
+/// A threshold that generates a `Warning` when crossed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SoftLimit {
+    /// The number of requests waiting in the queue of `resource` grows above `max`.
+    QueueLength { resource: ResourceId, max: usize },
+    /// A process waited more than `max` time units to be granted `resource`.
+    WaitTime { resource: ResourceId, max: f64 },
+    /// The number of items stored in `store` grows above `max`.
+    StoreLevel { store: StoreId, max: usize },
+}
+
+/// Describes a soft limit that was crossed during the simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Simulation time at which the limit was crossed
+    pub time: f64,
+    /// Process whose effect caused the limit to be crossed
+    pub process: ProcessId,
+    /// The limit that was crossed
+    pub limit: SoftLimit,
+    /// The observed value of the monitored quantity
+    pub value: f64,
+}
+
+/// What the simulation does when a process exceeds its event budget.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BudgetPolicy {
+    /// Report the process and let it continue
+    Flag,
+    /// Report the process and stop resuming it
+    Suspend,
+}
+
+/// What the simulation does with long chains of steps at the same time.
+///
+/// A process that keeps yielding zero timeouts, or a set of processes that keep waking each
+/// other up, stops the simulation clock forever. A policy bounds the number of consecutive
+/// steps at the same time. Note that many processes legitimately active at the same time
+/// count as a chain too, so the bound should be larger than the number of processes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ZeroDelayPolicy {
+    /// Never interfere with the simulation
+    Unbounded,
+    /// Stop with an error after the given number of consecutive steps at the same time
+    Fail(usize),
+    /// After `max_steps` consecutive steps at the same time, advance the clock by `epsilon`.
+    /// The events that were left behind are executed at the new time.
+    Advance { max_steps: usize, epsilon: f64 },
+}
+
+/// What the simulation does when a process releases a resource it does not hold.
+///
+/// The simulation counts the units of each resource granted to each process. A process that
+/// yields `Effect::Release` or `Effect::ReleaseN` for more units than it holds has a bug,
+/// that would otherwise corrupt the state of the resource.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ReleasePolicy {
+    /// Skip the release of the units that are not held and resume the process, that finds
+    /// `ReleaseOutcome::NotHeld` in `SimContext::release_outcome`
+    Ignore,
+    /// Stop with `SimError::NotHolding`
+    #[default]
+    Fail,
+    /// Panic, e.g. to get a backtrace while debugging
+    Panic,
+}
+
+/// Reports a process that scheduled more events than its budget allows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetViolation {
+    /// The offending process
+    pub process: ProcessId,
+    /// Simulation time at which the budget was exceeded
+    pub time: f64,
+    /// Number of events scheduled by the process at that time
+    pub events: usize,
+}
+
+/// A user callback invoked whenever a soft limit is crossed
+#[cfg(not(feature = "send"))]
+pub type WarningHook = Box<dyn FnMut(&Warning)>;
+/// A user callback invoked whenever a soft limit is crossed
+#[cfg(feature = "send")]
+pub type WarningHook = Box<dyn FnMut(&Warning) + Send>;
+
+/// Keeps the configured limits, the warnings that were raised and the hooks to call.
+#[derive(Default)]
+pub(crate) struct SoftLimits {
+    limits: Vec<(SoftLimit, bool)>,
+    warnings: Vec<Warning>,
+    hooks: Vec<WarningHook>,
+}
+
+impl SoftLimits {
+    pub(crate) fn add(&mut self, limit: SoftLimit) {
+        self.limits.push((limit, false));
+    }
+
+    pub(crate) fn add_hook(&mut self, hook: WarningHook) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn warnings(&self) -> &[Warning] {
+        self.warnings.as_slice()
+    }
+
+    /// Forget the warnings raised, so that the limits can be crossed again.
+    pub(crate) fn clear_warnings(&mut self) {
+        self.warnings.clear();
+        for (_, exceeded) in self.limits.iter_mut() {
+            *exceeded = false;
+        }
+    }
+
+    /// Check the limits on the queue of `resource`, given its current length.
+    pub(crate) fn check_queue(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        resource: ResourceId,
+        len: usize,
+    ) {
+        self.check_level(time, process, len, |limit| match limit {
+            SoftLimit::QueueLength { resource: r, max } if *r == resource => Some(*max),
+            _ => None,
+        });
+    }
+
+    /// Check the limits on the level of `store`, given the number of stored items.
+    pub(crate) fn check_store(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        store: StoreId,
+        len: usize,
+    ) {
+        self.check_level(time, process, len, |limit| match limit {
+            SoftLimit::StoreLevel { store: s, max } if *s == store => Some(*max),
+            _ => None,
+        });
+    }
+
+    /// Check the limits on the waiting time for `resource`.
+    pub(crate) fn check_wait(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        resource: ResourceId,
+        wait: f64,
+    ) {
+        let mut crossed = Vec::new();
+        for (limit, _) in &self.limits {
+            if let SoftLimit::WaitTime { resource: r, max } = limit {
+                if *r == resource && wait > *max {
+                    crossed.push(*limit);
+                }
+            }
+        }
+        for limit in crossed {
+            self.raise(Warning {
+                time,
+                process,
+                limit,
+                value: wait,
+            });
+        }
+    }
+
+    fn check_level<F>(&mut self, time: f64, process: ProcessId, len: usize, threshold: F)
+    where
+        F: Fn(&SoftLimit) -> Option<usize>,
+    {
+        let mut crossed = Vec::new();
+        for (limit, exceeded) in self.limits.iter_mut() {
+            if let Some(max) = threshold(limit) {
+                if len > max && !*exceeded {
+                    *exceeded = true;
+                    crossed.push(*limit);
+                } else if len <= max {
+                    *exceeded = false;
+                }
+            }
+        }
+        for limit in crossed {
+            self.raise(Warning {
+                time,
+                process,
+                limit,
+                value: len as f64,
+            });
+        }
+    }
+
+    fn raise(&mut self, warning: Warning) {
+        for hook in self.hooks.iter_mut() {
+            hook(&warning);
+        }
+        self.warnings.push(warning);
+    }
+}
+
+impl fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "t={}: process {} exceeded its event budget scheduling {} events",
+            self.time, self.process, self.events
+        )
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            SoftLimit::QueueLength { resource, max } => write!(
+                f,
+                "t={}: queue of resource {} reached length {} (limit {}) after a request of process {}",
+                self.time, resource, self.value, max, self.process
+            ),
+            SoftLimit::WaitTime { resource, max } => write!(
+                f,
+                "t={}: process {} waited {} for resource {} (limit {})",
+                self.time, self.process, self.value, resource, max
+            ),
+            SoftLimit::StoreLevel { store, max } => write!(
+                f,
+                "t={}: store {} reached level {} (limit {}) after an operation of process {}",
+                self.time, store, self.value, max, self.process
+            ),
+        }
+    }
+}
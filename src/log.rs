@@ -0,0 +1,132 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Queries over the log of processed events.
+//!
+//! `Simulation::log` returns an `EventLog`, a view of the processed events that can be
+//! filtered by process, by entity, by time and by state without scanning the log by hand.
+//! When the simulation keeps the log itself, without a sink, it indexes the events by process, so
+//! that the events of a process are found without a scan. The log is ordered by time, so the events in an interval are
+//! found with a binary search.
+//!
+//! The log can also be moved out of the simulation, without copying it, with
+//! `Simulation::take_processed_events` or `Simulation::into_log`.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let mut processes = Vec::new();
+//! for delay in [1.0, 2.0] {
+//!     let p = sim.create_process(Box::new(#[coroutine] move |_| {
+//!         yield Effect::TimeOut(delay);
+//!         yield Effect::Trace;
+//!     }));
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!     processes.push(p);
+//! }
+//! let sim = sim.run(EndCondition::NoEvents);
+//! let log = sim.log();
+//! assert_eq!(log.events_for_process(processes[1]).count(), 2);
+//! assert_eq!(log.events_between(1.0, 3.0).len(), 2);
+//! assert_eq!(log.events_matching(|s| matches!(s, Effect::Trace)).count(), 2);
+//! ```
+use crate::{EntityId, Event, ProcessId};
+use std::collections::HashMap;
+
+/// The positions in the log of the events of each process.
+pub(crate) type LogIndex = HashMap<ProcessId, Vec<usize>>;
+
+/// A view of the log of processed events, see `Simulation::log`.
+pub struct EventLog<'a, T> {
+    events: &'a [(Event<T>, T)],
+    index: Option<&'a LogIndex>,
+}
+
+impl<'a, T> EventLog<'a, T> {
+    pub(crate) fn new(events: &'a [(Event<T>, T)], index: Option<&'a LogIndex>) -> EventLog<'a, T> {
+        EventLog { events, index }
+    }
+
+    /// Returns all the events, with the states yielded by the processes
+    pub fn events(&self) -> &'a [(Event<T>, T)] {
+        self.events
+    }
+
+    /// Returns the number of events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if no event was logged
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns the events of a process, in the order they were executed
+    pub fn events_for_process(
+        &self,
+        process: ProcessId,
+    ) -> impl Iterator<Item = &'a (Event<T>, T)> + 'a {
+        let events = self.events;
+        // without an index, scan the log
+        let indexed = self
+            .index
+            .map(|index| index.get(&process).into_iter().flatten());
+        let scanned = self.index.is_none().then(|| events.iter());
+        indexed
+            .into_iter()
+            .flatten()
+            .filter_map(move |i| events.get(*i))
+            .chain(
+                scanned
+                    .into_iter()
+                    .flatten()
+                    .filter(move |(e, _)| e.process() == process),
+            )
+    }
+
+    /// Returns the events of the processes carrying an entity, in the order they were
+    /// executed. See the [`entity`](crate::entity) module.
+    pub fn events_for_entity(
+        &self,
+        entity: EntityId,
+    ) -> impl Iterator<Item = &'a (Event<T>, T)> + 'a {
+        self.events
+            .iter()
+            .filter(move |(e, _)| e.entity() == Some(entity))
+    }
+
+    /// Returns the events executed from `start` included to `end` excluded
+    pub fn events_between(&self, start: f64, end: f64) -> &'a [(Event<T>, T)] {
+        let from = self.events.partition_point(|(e, _)| e.time() < start);
+        let to = self.events.partition_point(|(e, _)| e.time() < end);
+        &self.events[from..to.max(from)]
+    }
+
+    /// Returns the events whose state satisfies `predicate`
+    pub fn events_matching<P>(&self, mut predicate: P) -> impl Iterator<Item = &'a (Event<T>, T)>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.events
+            .iter()
+            .filter(move |(_, state)| predicate(state))
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,70 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::sink::MemorySink;
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn event_log() {
+    let mut s = Simulation::new();
+    s.set_log_sink(Box::new(MemorySink::new()));
+    let mut processes = Vec::new();
+    for delay in [1.0, 2.0, 3.0] {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::TimeOut(delay);
+                yield Effect::TimeOut(delay);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+        processes.push(p);
+    }
+    let s = s.run(EndCondition::NoEvents);
+    let log = s.log();
+    assert_eq!(log.len(), 6);
+    let times: Vec<_> = log
+        .events_for_process(processes[2])
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(times, [0.0, 3.0]);
+    assert_eq!(log.events_between(1.0, 3.0).len(), 2);
+    assert!(log.events_between(3.0, 1.0).is_empty());
+    let long = log.events_matching(|s| matches!(s, Effect::TimeOut(t) if *t > 1.0));
+    assert_eq!(long.count(), 4);
+}
+
+#[test]
+fn take_log() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            for _ in 0..4 {
+                yield Effect::TimeOut(1.0);
+            }
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+    let mut s = s.run(EndCondition::NSteps(2));
+    let first = s.take_processed_events();
+    assert_eq!(first.len(), 2);
+    assert!(s.processed_events().is_empty());
+    s.step().unwrap();
+    assert_eq!(s.log().events_for_process(p).count(), 1);
+    let rest = s.run(EndCondition::NoEvents).into_log();
+    let times: Vec<_> = rest.iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, [2.0, 3.0]);
+}
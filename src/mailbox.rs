@@ -0,0 +1,109 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Point-to-point messaging between processes.
+//!
+//! A mailbox, created with `Simulation::create_mailbox`, holds the messages sent to it in
+//! order. The message is the state yielded by the sender with `Effect::Send`. A process
+//! yielding `Effect::Receive` takes the oldest message, and is resumed with it as state;
+//! if the mailbox is empty it waits for the next message.
+//!
+//! A mailbox can have a bounded capacity: when it is full, senders wait until a message
+//! is received. With no capacity, each sender waits for a receiver to take its message,
+//! like in a rendezvous.
+use crate::{Event, ProcessId};
+use std::collections::VecDeque;
+
+/// A queue of messages between processes.
+pub(crate) struct Mailbox<T> {
+    capacity: Option<usize>,
+    messages: VecDeque<T>,
+    /// Events of the processes waiting for a message
+    receivers: VecDeque<Event<T>>,
+    /// Events of the processes waiting for space, with their messages
+    senders: VecDeque<Event<T>>,
+}
+
+impl<T: Clone> Mailbox<T> {
+    pub(crate) fn new(capacity: Option<usize>) -> Mailbox<T> {
+        Mailbox {
+            capacity,
+            messages: VecDeque::new(),
+            receivers: VecDeque::new(),
+            senders: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of messages waiting to be received
+    pub(crate) fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Send the state of `event`, returning the events to schedule: the sender, unless the
+    /// mailbox is full, and the receiver, if one was waiting.
+    pub(crate) fn send(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        if let Some(mut receiver) = self.receivers.pop_front() {
+            receiver.set_time(event.time());
+            receiver.set_state(event.state().clone());
+            return vec![receiver, event];
+        }
+        if self.capacity.is_some_and(|c| self.messages.len() >= c) {
+            self.senders.push_back(event);
+            return Vec::new();
+        }
+        self.messages.push_back(event.state().clone());
+        vec![event]
+    }
+
+    /// Receive a message on behalf of the process of `event`, returning the events to
+    /// schedule: the receiver, if a message was available, and a sender that was waiting
+    /// for space.
+    pub(crate) fn receive(&mut self, mut event: Event<T>) -> Vec<Event<T>> {
+        let Some(message) = self.messages.pop_front() else {
+            // a mailbox with no capacity passes the messages from hand to hand
+            return match self.senders.pop_front() {
+                Some(mut sender) => {
+                    event.set_state(sender.state().clone());
+                    sender.set_time(event.time());
+                    vec![event, sender]
+                }
+                None => {
+                    self.receivers.push_back(event);
+                    Vec::new()
+                }
+            };
+        };
+        event.set_state(message);
+        let mut events = vec![event];
+        if let Some(mut sender) = self.senders.pop_front() {
+            self.messages.push_back(sender.state().clone());
+            sender.set_time(events[0].time());
+            events.push(sender);
+        }
+        events
+    }
+
+    /// Discard the messages and the waiting processes
+    pub(crate) fn clear(&mut self) {
+        self.messages.clear();
+        self.receivers.clear();
+        self.senders.clear();
+    }
+
+    /// Remove a waiting process, e.g. because it was interrupted.
+    pub(crate) fn cancel(&mut self, process: ProcessId) {
+        self.receivers.retain(|e| e.process() != process);
+        self.senders.retain(|e| e.process() != process);
+    }
+}
@@ -0,0 +1,142 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Metadata describing a simulation run.
+//!
+//! Each `Simulation` carries a `RunMetadata` block, accessible with
+//! `Simulation::metadata_mut`, where the user can record the random seed, the parameters
+//! of the model, the version of the model source code and so on. The version of desim
+//! and the wall-clock time at which the run started are recorded automatically.
+//!
+//! Exporters embed the metadata in their output, so that archived results can be
+//! interpreted and reproduced later: `export::write_csv` and the CSV log sink write it as
+//! comment lines, `export::write_chrome_trace`, `Simulation::export_log` and the JSON lines
+//! log sink as a JSON object.
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Provenance information of a simulation run.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RunMetadata {
+    seed: Option<u64>,
+    git_hash: Option<String>,
+    started_at: Option<SystemTime>,
+    parameters: Vec<(String, String)>,
+}
+
+impl RunMetadata {
+    /// Create an empty metadata block
+    pub fn new() -> RunMetadata {
+        RunMetadata::default()
+    }
+
+    /// Returns the version of desim used for the run
+    pub fn crate_version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    /// Returns the seed of the random number generators, if recorded
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Record the seed of the random number generators
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Returns the version of the source code of the model, if recorded
+    pub fn git_hash(&self) -> Option<&str> {
+        self.git_hash.as_deref()
+    }
+
+    /// Record the version of the source code of the model, e.g. a git commit hash
+    pub fn set_git_hash(&mut self, hash: impl Into<String>) {
+        self.git_hash = Some(hash.into());
+    }
+
+    /// Returns the wall-clock time at which the run started
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.started_at
+    }
+
+    /// Record the wall-clock time at which the run started.
+    ///
+    /// The simulation calls it with the current time on its first step, if it was not set.
+    pub fn set_started_at(&mut self, time: SystemTime) {
+        self.started_at = Some(time);
+    }
+
+    /// Record a parameter of the model. If the parameter was already recorded, its value
+    /// is replaced.
+    pub fn set_parameter(&mut self, name: impl Into<String>, value: impl fmt::Display) {
+        let name = name.into();
+        let value = value.to_string();
+        match self.parameters.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = value,
+            None => self.parameters.push((name, value)),
+        }
+    }
+
+    /// Returns the value of a parameter of the model
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Parse the value of a parameter of the model, e.g. to compute analytic expectations
+    pub fn parameter_as<F: FromStr>(&self, name: &str) -> Option<F> {
+        self.parameter(name).and_then(|v| v.parse().ok())
+    }
+
+    /// Returns all the metadata as a list of key-value pairs.
+    ///
+    /// This is the representation used by exporters. Parameters of the model are
+    /// prefixed with `param.`, the start time is in seconds since the UNIX epoch.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![(
+            "desim_version".to_string(),
+            self.crate_version().to_string(),
+        )];
+        if let Some(seed) = self.seed {
+            entries.push(("seed".to_string(), seed.to_string()));
+        }
+        if let Some(hash) = &self.git_hash {
+            entries.push(("git_hash".to_string(), hash.clone()));
+        }
+        if let Some(started_at) = self.started_at {
+            let secs = started_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or_default();
+            entries.push(("started_at".to_string(), secs.to_string()));
+        }
+        for (name, value) in &self.parameters {
+            entries.push((format!("param.{}", name), value.clone()));
+        }
+        entries
+    }
+}
+
+impl fmt::Display for RunMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in self.entries() {
+            writeln!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Composition of a model from reusable sub-models.
+//!
+//! A `Module` builds a sub-model, e.g. a workstation with its buffer, its machine and the
+//! operator running it, in a `Scope` of the parent simulation. Each instance, created with
+//! `Simulation::instantiate`, gets its own processes, resources and stores, named under the
+//! path of the instance, e.g. `ws2/machine`. Each path can be used by a single instance.
+//! Their ids are valid in the whole simulation:
+//! the module returns the ones the parent needs to wire the instances together, and the
+//! others can be found by name with `Simulation::process_by_name`,
+//! `Simulation::resource_by_name` and `Simulation::store_by_name`.
+//!
+//! A module can instantiate other modules in its scope, whose paths are nested in its own,
+//! and a closure taking the scope is a module as well.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::module::{Module, Scope};
+//! use desim::resources::{SimpleResource, SimpleStore};
+//! use desim::{Effect, EndCondition, Simulation, StoreId};
+//!
+//! /// A machine processing the parts that arrive in its buffer
+//! struct Workstation {
+//!     process_time: f64,
+//! }
+//!
+//! impl Module<Effect> for Workstation {
+//!     /// The buffer of the workstation
+//!     type Ports = StoreId;
+//!
+//!     fn build(&self, scope: &mut Scope<'_, Effect>) -> StoreId {
+//!         let buffer = scope.create_store("buffer", Box::new(SimpleStore::new(5)));
+//!         let machine = scope.create_resource("machine", Box::new(SimpleResource::new(1)));
+//!         let process_time = self.process_time;
+//!         let operator = scope.create_process("operator", Box::new(#[coroutine] move |_| loop {
+//!             yield Effect::Pull(buffer);
+//!             yield Effect::Request(machine);
+//!             yield Effect::TimeOut(process_time);
+//!             yield Effect::Release(machine);
+//!         }));
+//!         scope.schedule_event(0.0, operator, Effect::TimeOut(0.0)).unwrap();
+//!         buffer
+//!     }
+//! }
+//!
+//! let mut sim = Simulation::new();
+//! let buffers: Vec<StoreId> = (1..=3)
+//!     .map(|i| sim.instantiate(&format!("ws{}", i), &Workstation { process_time: 2.0 }))
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert!(sim.instantiate("ws1", &Workstation { process_time: 1.0 }).is_err());
+//! let supplier = sim.create_process(Box::new(#[coroutine] move |_| {
+//!     for i in 0..3 {
+//!         yield Effect::Push(buffers[i]);
+//!     }
+//! }));
+//! sim.schedule_event(0.0, supplier, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(sim.time(), 2.0);
+//! let machine = sim.resource_by_name("ws2/machine").unwrap();
+//! assert_eq!(sim.resource_stats(machine).grants(), 1);
+//! ```
+use crate::error::SimError;
+use crate::resources::{BoxedResource, BoxedStore};
+use crate::{EventId, Process, ProcessId, ResourceId, SimState, Simulation, StoreId};
+
+/// A sub-model that can be instantiated several times in a simulation.
+pub trait Module<T: SimState + Clone> {
+    /// The ids of an instance that the parent needs, e.g. the store of its input
+    type Ports;
+
+    /// Create the processes, resources and stores of an instance in `scope`
+    fn build(&self, scope: &mut Scope<'_, T>) -> Self::Ports;
+}
+
+impl<T: SimState + Clone, P, F: Fn(&mut Scope<'_, T>) -> P> Module<T> for F {
+    type Ports = P;
+
+    fn build(&self, scope: &mut Scope<'_, T>) -> P {
+        self(scope)
+    }
+}
+
+/// The part of a simulation where an instance of a module is built.
+///
+/// Everything created in the scope is named under its path.
+pub struct Scope<'a, T: SimState + Clone> {
+    sim: &'a mut Simulation<T>,
+    path: String,
+}
+
+impl<'a, T: 'static + SimState + Clone> Scope<'a, T> {
+    pub(crate) fn new(sim: &'a mut Simulation<T>, path: String) -> Scope<'a, T> {
+        Scope { sim, path }
+    }
+
+    /// Returns the path of the instance, e.g. `line/ws1`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns `name` qualified by the path of the instance
+    pub fn qualify(&self, name: &str) -> String {
+        format!("{}/{}", self.path, name)
+    }
+
+    /// Create a process of the instance, named `name` under its path
+    pub fn create_process(&mut self, name: &str, process: Box<Process<T>>) -> ProcessId {
+        let process = self.sim.create_process(process);
+        self.sim.set_process_name(process, self.qualify(name));
+        process
+    }
+
+    /// Create a resource of the instance, named `name` under its path
+    pub fn create_resource(&mut self, name: &str, resource: BoxedResource<T>) -> ResourceId {
+        let resource = self.sim.create_resource(resource);
+        self.sim.set_resource_name(resource, self.qualify(name));
+        resource
+    }
+
+    /// Create a store of the instance, named `name` under its path
+    pub fn create_store(&mut self, name: &str, store: BoxedStore<T>) -> StoreId {
+        let store = self.sim.create_store(store);
+        self.sim.set_store_name(store, self.qualify(name));
+        store
+    }
+
+    /// Schedule a process to be executed at `time`. See `Simulation::schedule_event`.
+    pub fn schedule_event(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+    ) -> Result<EventId, SimError> {
+        self.sim.schedule_event(time, process, state)
+    }
+
+    /// Build an instance of `module` named `name`, nested in this one.
+    ///
+    /// Returns an error if an instance with the same path already exists.
+    pub fn instantiate<M: Module<T>>(
+        &mut self,
+        name: &str,
+        module: &M,
+    ) -> Result<M::Ports, SimError> {
+        let path = self.qualify(name);
+        self.sim.build_instance(path, module)
+    }
+
+    /// Returns the simulation, e.g. to create conditions or signals for the instance
+    pub fn simulation(&mut self) -> &mut Simulation<T> {
+        self.sim
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
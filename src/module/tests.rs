@@ -0,0 +1,80 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::Scope;
+use crate::error::SimError;
+use crate::resources::{SimpleResource, SimpleStore};
+use crate::{Effect, EndCondition::NoEvents, ResourceId, Simulation, StoreId};
+
+#[test]
+fn nested_modules() {
+    let station = |scope: &mut Scope<'_, Effect>| {
+        let machine = scope.create_resource("machine", Box::new(SimpleResource::new(1)));
+        let operator = scope.create_process(
+            "operator",
+            Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::Request(machine);
+                    yield Effect::TimeOut(1.0);
+                    yield Effect::Release(machine);
+                },
+            ),
+        );
+        scope
+            .schedule_event(0.0, operator, Effect::TimeOut(0.))
+            .unwrap();
+        machine
+    };
+    let line = |scope: &mut Scope<'_, Effect>| {
+        assert_eq!(scope.path(), "line");
+        let output = scope.create_store("output", Box::new(SimpleStore::new(1)));
+        let stations: Vec<ResourceId> = (1..=2)
+            .map(|i| scope.instantiate(&format!("ws{}", i), &station).unwrap())
+            .collect();
+        assert_eq!(
+            scope.instantiate("ws1", &station),
+            Err(SimError::DuplicateInstance("line/ws1".to_string()))
+        );
+        (output, stations)
+    };
+
+    let mut s = Simulation::new();
+    let (output, stations) = s.instantiate("line", &line).unwrap();
+    assert_ne!(stations[0], stations[1]);
+    assert_eq!(s.resource_by_name("line/ws2/machine"), Some(stations[1]));
+    assert_eq!(s.resource_name(stations[0]), Some("line/ws1/machine"));
+    assert_eq!(s.store_by_name("line/output"), Some(output));
+    assert_eq!(s.store_by_name("output"), None::<StoreId>);
+    let operator = s.process_by_name("line/ws1/operator").unwrap();
+    assert_eq!(s.process_name(operator), Some("line/ws1/operator"));
+    // a name given to several processes finds the last one
+    let last = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::Wait;
+        },
+    ));
+    s.set_process_name(last, "line/ws1/operator");
+    assert_eq!(s.process_by_name("line/ws1/operator"), Some(last));
+    s.set_process_name(last, "spare");
+    assert_eq!(s.process_by_name("line/ws1/operator"), Some(operator));
+    assert_eq!(s.process_by_name("spare"), Some(last));
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 1.0);
+    for machine in stations {
+        assert_eq!(s.resource_stats(machine).grants(), 1);
+    }
+}
@@ -0,0 +1,105 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Observers of the simulation.
+//!
+//! An observer registered with `Simulation::add_observer` is notified while the simulation
+//! runs, e.g. to feed a live dashboard or a debugger. It is told when events are scheduled
+//! and executed, when processes complete and when resources are acquired, released, fail,
+//! are repaired or change capacity. It also receives the updates of the statistics as soon
+//! as they are recorded, so that aggregates can be maintained incrementally instead of
+//! being recomputed from the events.
+//!
+//! All the methods of `SimulationObserver` have an empty default implementation, so an
+//! observer only implements the notifications it is interested in.
+use crate::{Event, ProcessId, ResourceId, StatId};
+
+/// An incremental update of the statistics collected by the simulation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum StatUpdate {
+    /// The length of the queue of a resource changed
+    QueueLength {
+        resource: ResourceId,
+        time: f64,
+        len: usize,
+    },
+    /// A request of a resource was granted after waiting `wait` time units
+    WaitTime {
+        resource: ResourceId,
+        time: f64,
+        wait: f64,
+    },
+    /// The number of units in use of a resource changed by `delta` from `time` on.
+    /// Integrating the units in use over time gives the utilization of the resource.
+    ///
+    /// A unit granted at the next opening of a `ScheduledResource` is in use from then,
+    /// so `time` may be later than the current time.
+    InUse {
+        resource: ResourceId,
+        time: f64,
+        delta: isize,
+    },
+    /// A process recorded `value` in a statistic of the model with `Effect::Record`, e.g.
+    /// a new observation of a histogram
+    Sample { stat: StatId, time: f64, value: f64 },
+}
+
+/// A change of the state of a resource.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResourceChange {
+    /// Some units of the resource were granted to a process
+    Acquired { process: ProcessId, units: usize },
+    /// A process released a unit of the resource
+    Released { process: ProcessId },
+    /// The resource went offline, see `Effect::Fail`
+    Failed,
+    /// The resource was repaired, see `Effect::Repair`
+    Repaired,
+    /// The number of units that can be granted changed, e.g. at the opening or closing of
+    /// a shift of a `ScheduledResource`. See `Resource::next_capacity_change`.
+    CapacityChanged { capacity: usize },
+}
+
+/// An observer registered on a simulation
+#[cfg(not(feature = "send"))]
+pub type BoxedObserver<T> = Box<dyn SimulationObserver<T>>;
+/// An observer registered on a simulation
+#[cfg(feature = "send")]
+pub type BoxedObserver<T> = Box<dyn SimulationObserver<T> + Send>;
+
+/// Receives notifications from a running simulation.
+pub trait SimulationObserver<T> {
+    /// Called when `event` is added to the queue of future events
+    fn on_event_scheduled(&mut self, _event: &Event<T>) {}
+
+    /// Called when a process is resumed by `event` and yields `state`
+    fn on_event_fired(&mut self, _event: &Event<T>, _state: &T) {}
+
+    /// Called when a process completes or is terminated, at `time`
+    fn on_process_complete(&mut self, _process: ProcessId, _time: f64) {}
+
+    /// Called when the state of a resource changes at `time`
+    fn on_resource_state_change(
+        &mut self,
+        _resource: ResourceId,
+        _time: f64,
+        _change: &ResourceChange,
+    ) {
+    }
+
+    /// Called when a statistic is updated
+    fn on_stat_update(&mut self, _update: &StatUpdate) {}
+}
@@ -0,0 +1,259 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Running a model partitioned over several threads.
+//!
+//! Available with the `send` feature. A `ParallelSimulation` splits a large model, e.g.
+//! the nodes of a network, in partitions: each of them is a `Simulation` with its own
+//! processes, resources and stores, executed by its own thread. The processes of a
+//! partition interact with the other partitions only through messages, sent with the
+//! `Link` returned when the receiving partition was added, as in the
+//! [`cosim`](crate::cosim) module.
+//!
+//! The synchronization is conservative: a message must be timestamped at least the
+//! lookahead of the simulation later than the time it is sent, e.g. the minimum latency
+//! of the network. Then, if the earliest event of all the partitions is at `t`, no message
+//! can arrive before `t + lookahead`, and all the partitions execute their events before
+//! that time in parallel, then exchange the messages sent meanwhile. A message that
+//! arrives before the last event executed by its partition violates the lookahead: the
+//! run stops with `SimError::InvalidTime`.
+//!
+//! ```
+//! #![feature(coroutines)]
+//! use desim::parallel::ParallelSimulation;
+//! use desim::{Effect, SimContext, Simulation};
+//!
+//! // a packet crosses two hops with a latency of 1.0 each
+//! let mut psim = ParallelSimulation::new(1.0);
+//! let source = psim.add_partition(Simulation::new());
+//! let relay = psim.add_partition(Simulation::new());
+//! let sink = psim.add_partition(Simulation::new());
+//! let destination = psim.partition_mut(&sink).create_process(Box::new(#[coroutine] |_| loop {
+//!     yield Effect::Wait;
+//! }));
+//! let to_sink = sink.clone();
+//! let forwarder = psim.partition_mut(&relay).create_process(Box::new(
+//!     #[coroutine]
+//!     move |mut ctx: SimContext<Effect>| loop {
+//!         to_sink.send(ctx.time() + 1.0, destination, Effect::TimeOut(0.0)).unwrap();
+//!         ctx = yield Effect::Wait;
+//!     },
+//! ));
+//! let to_relay = relay.clone();
+//! let generator = psim.partition_mut(&source).create_process(Box::new(
+//!     #[coroutine]
+//!     move |mut ctx: SimContext<Effect>| {
+//!         for _ in 0..5 {
+//!             to_relay.send(ctx.time() + 1.0, forwarder, Effect::TimeOut(0.0)).unwrap();
+//!             ctx = yield Effect::TimeOut(1.0);
+//!         }
+//!     },
+//! ));
+//! psim.partition_mut(&source)
+//!     .schedule_event(0.0, generator, Effect::TimeOut(0.0))
+//!     .unwrap();
+//!
+//! psim.run(10.0).unwrap();
+//! let received: Vec<f64> = psim
+//!     .partition(&sink)
+//!     .processed_events()
+//!     .iter()
+//!     .map(|(event, _)| event.time())
+//!     .collect();
+//! assert_eq!(received, [2.0, 3.0, 4.0, 5.0, 6.0]);
+//! ```
+use crate::cosim::Link;
+use crate::error::SimError;
+use crate::{SimState, Simulation};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+/// What a worker thread is asked to do with its partition.
+#[derive(Copy, Clone)]
+enum Command {
+    /// Schedule the messages received, and report the time of the next event
+    Receive,
+    /// Execute the events before `bound`, or up to it if `inclusive`
+    Advance { bound: f64, inclusive: bool },
+}
+
+/// A partition, with the link of the messages sent to it.
+struct Partition<T: SimState + Clone> {
+    sim: Simulation<T>,
+    link: Link<T>,
+}
+
+impl<T: 'static + SimState + Clone> Partition<T> {
+    fn receive(&mut self) -> Result<Option<f64>, SimError> {
+        for (time, process, state) in self.link.take_sent() {
+            if time < self.sim.time() {
+                return Err(SimError::InvalidTime(process));
+            }
+            self.sim.schedule_event(time, process, state)?;
+        }
+        Ok(self.sim.peek_next_time())
+    }
+
+    fn advance(&mut self, bound: f64, inclusive: bool) -> Result<Option<f64>, SimError> {
+        while let Some(next) = self.sim.peek_next_time() {
+            if next > bound || (next == bound && !inclusive) {
+                break;
+            }
+            if let Err(e) = self.sim.step() {
+                self.sim.fail(e.clone());
+                return Err(e);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Execute the commands of the coordinator, until it hangs up.
+    fn work(&mut self, commands: Receiver<Command>, replies: Sender<Reply>) {
+        for command in commands {
+            let reply = match command {
+                Command::Receive => self.receive(),
+                Command::Advance { bound, inclusive } => self.advance(bound, inclusive),
+            };
+            if replies.send(reply).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// The reply of a worker: the time of the next event of its partition, if asked for it.
+type Reply = Result<Option<f64>, SimError>;
+
+/// A model partitioned over several threads, see the [`parallel`](crate::parallel) module.
+pub struct ParallelSimulation<T: SimState + Clone> {
+    partitions: Vec<Partition<T>>,
+    lookahead: f64,
+    time: f64,
+}
+
+impl<T: 'static + SimState + Clone + Send> ParallelSimulation<T> {
+    /// Create a simulation whose messages are timestamped at least `lookahead` later than
+    /// the time they are sent.
+    ///
+    /// # Panics
+    /// Panics if `lookahead` is not positive and finite.
+    pub fn new(lookahead: f64) -> ParallelSimulation<T> {
+        assert!(
+            lookahead > 0.0 && lookahead.is_finite(),
+            "ERROR. The lookahead must be positive and finite."
+        );
+        ParallelSimulation {
+            partitions: Vec::new(),
+            lookahead,
+            time: 0.0,
+        }
+    }
+
+    /// Add a partition, returning the link to send messages to it.
+    pub fn add_partition(&mut self, sim: Simulation<T>) -> Link<T> {
+        let link = Link::new(self.partitions.len());
+        self.partitions.push(Partition {
+            sim,
+            link: link.clone(),
+        });
+        link
+    }
+
+    /// Returns the partition that `link` sends messages to
+    pub fn partition(&self, link: &Link<T>) -> &Simulation<T> {
+        &self.partitions[link.model()].sim
+    }
+
+    /// Returns the partition that `link` sends messages to, e.g. to create its processes
+    pub fn partition_mut(&mut self, link: &Link<T>) -> &mut Simulation<T> {
+        &mut self.partitions[link.model()].sim
+    }
+
+    /// Returns the lookahead of the messages
+    pub fn lookahead(&self) -> f64 {
+        self.lookahead
+    }
+
+    /// Returns the time up to which all the partitions executed their events
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Run all the partitions in parallel, until there are no events up to `until`.
+    ///
+    /// Returns the error of the first partition whose step failed, if any. The error is
+    /// also recorded by the partition, and returned by its `error()`.
+    pub fn run(&mut self, until: f64) -> Result<(), SimError> {
+        let lookahead = self.lookahead;
+        let result = thread::scope(|scope| {
+            let (replies, received) = channel();
+            let commands: Vec<Sender<Command>> = self
+                .partitions
+                .iter_mut()
+                .map(|partition| {
+                    let (commands, incoming) = channel();
+                    let replies = replies.clone();
+                    scope.spawn(move || partition.work(incoming, replies));
+                    commands
+                })
+                .collect();
+            // send a command to all the workers and wait for all of them
+            let broadcast = |command: Command| -> Result<Option<f64>, SimError> {
+                for worker in commands.iter() {
+                    worker.send(command).unwrap();
+                }
+                let mut next: Option<f64> = None;
+                let mut error = None;
+                for reply in received.iter().take(commands.len()) {
+                    match reply {
+                        Ok(time) => next = next.into_iter().chain(time).min_by(f64::total_cmp),
+                        Err(e) => error = error.or(Some(e)),
+                    }
+                }
+                error.map_or(Ok(next), Err)
+            };
+            loop {
+                match broadcast(Command::Receive)? {
+                    Some(next) if next <= until => {
+                        let bound = next + lookahead;
+                        if bound > until {
+                            broadcast(Command::Advance {
+                                bound: until,
+                                inclusive: true,
+                            })?;
+                            return Ok(());
+                        }
+                        broadcast(Command::Advance {
+                            bound,
+                            inclusive: false,
+                        })?;
+                    }
+                    _ => return Ok(()),
+                }
+            }
+        });
+        if result.is_ok() {
+            self.time = self.time.max(until);
+        }
+        result
+    }
+
+    /// Returns the partitions, in the order they were added
+    pub fn into_partitions(self) -> Vec<Simulation<T>> {
+        self.partitions.into_iter().map(|p| p.sim).collect()
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,73 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::ParallelSimulation;
+use crate::error::SimError;
+use crate::{Effect, SimContext, Simulation};
+
+#[test]
+fn lookahead() {
+    let run = |latency: f64| {
+        let mut psim = ParallelSimulation::new(1.0);
+        let a = psim.add_partition(Simulation::new());
+        let b = psim.add_partition(Simulation::new());
+        // b has events of its own, that pass the messages sent too late
+        let clock = psim.partition_mut(&b).create_process(Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::TimeOut(0.25);
+            },
+        ));
+        let receiver = psim.partition_mut(&b).create_process(Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::Wait;
+            },
+        ));
+        let to_b = b.clone();
+        let sender = psim.partition_mut(&a).create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                for _ in 0..4 {
+                    let ctx: SimContext<Effect> = yield Effect::TimeOut(1.0);
+                    to_b.send(ctx.time() + latency, receiver, Effect::Trace)
+                        .unwrap();
+                }
+            },
+        ));
+        for (link, p) in [(&a, sender), (&b, clock)] {
+            psim.partition_mut(link)
+                .schedule_event(0.0, p, Effect::TimeOut(0.))
+                .unwrap();
+        }
+        let result = psim.run(10.0);
+        (result, psim.into_partitions(), receiver)
+    };
+
+    let (result, partitions, _) = run(1.5);
+    assert_eq!(result, Ok(()));
+    assert_eq!(partitions[0].time(), 4.0);
+    assert_eq!(partitions[1].time(), 10.0);
+    let received = partitions[1]
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| matches!(e.state(), Effect::Trace))
+        .count();
+    assert_eq!(received, 4);
+
+    let (result, partitions, receiver) = run(0.5);
+    assert_eq!(result, Err(SimError::InvalidTime(receiver)));
+    assert!(partitions[1].time() > 1.5);
+}
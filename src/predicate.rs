@@ -0,0 +1,136 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Waiting for a condition on the state of the simulation.
+//!
+//! A predicate, created with `Simulation::create_predicate`, is a function of a `SimView`,
+//! a read-only view of the simulation that exposes the occupancy of the resources, the
+//! levels of the stores and the blackboard of the [`shared`](crate::shared) module.
+//! A process yielding `Effect::WaitUntil` with the id of a predicate is resumed immediately
+//! if the predicate holds, otherwise it is parked until it does, e.g. to start packing when
+//! 10 items accumulated in a store.
+//!
+//! The predicates with waiting processes are evaluated again at the end of each step that
+//! changed a resource, a store or the blackboard. When a predicate holds, all the processes
+//! waiting for it are resumed at the current time, with the state they yielded.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::resources::SimpleStore;
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let store = sim.create_store(Box::new(SimpleStore::new(100)));
+//! let full_box = sim.create_predicate(Box::new(move |view| view.store_level(store) >= 10));
+//! let producer = sim.create_process(Box::new(#[coroutine] move |_| loop {
+//!     yield Effect::TimeOut(1.0);
+//!     yield Effect::Push(store);
+//! }));
+//! let packer = sim.create_process(Box::new(#[coroutine] move |_| {
+//!     yield Effect::WaitUntil(full_box);
+//!     yield Effect::Trace;
+//! }));
+//! sim.schedule_event(0.0, producer, Effect::TimeOut(0.0)).unwrap();
+//! sim.schedule_event(0.0, packer, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::Time(20.0));
+//! let (packed, _) = sim
+//!     .processed_events()
+//!     .iter()
+//!     .find(|(_, state)| matches!(state, Effect::Trace))
+//!     .unwrap();
+//! assert_eq!(packed.time(), 10.0);
+//! ```
+use crate::resources::ResourceInspect;
+use crate::shared::SharedValue;
+use crate::{Event, ProcessId, ResourceId, SimState, Simulation, StoreId};
+
+/// A predicate over the state of the simulation
+#[cfg(not(feature = "send"))]
+pub type Predicate<T> = Box<dyn Fn(&SimView<'_, T>) -> bool>;
+/// A predicate over the state of the simulation
+#[cfg(feature = "send")]
+pub type Predicate<T> = Box<dyn Fn(&SimView<'_, T>) -> bool + Send>;
+
+/// A read-only view of a simulation, passed to the predicates.
+pub struct SimView<'a, T: SimState + Clone> {
+    pub(crate) sim: &'a Simulation<T>,
+}
+
+impl<T: 'static + SimState + Clone> SimView<'_, T> {
+    /// Returns the current simulation time
+    pub fn time(&self) -> f64 {
+        self.sim.time()
+    }
+
+    /// Returns the occupancy of a resource
+    pub fn resource(&self, resource: ResourceId) -> &dyn ResourceInspect<T> {
+        self.sim.resource(resource)
+    }
+
+    /// Returns the number of items in a store
+    pub fn store_level(&self, store: StoreId) -> usize {
+        self.sim.store(store).level()
+    }
+
+    /// Call `f` with the value of type `S` of the blackboard, if there is one.
+    /// See the [`shared`](crate::shared) module.
+    pub fn with_shared<S: SharedValue, R>(&self, f: impl FnOnce(&S) -> R) -> Option<R> {
+        self.sim.with_shared(f)
+    }
+}
+
+/// A predicate and the processes waiting for it.
+pub(crate) struct WaitUntil<T: SimState + Clone> {
+    predicate: Predicate<T>,
+    waiting: Vec<Event<T>>,
+}
+
+impl<T: 'static + SimState + Clone> WaitUntil<T> {
+    pub(crate) fn new(predicate: Predicate<T>) -> WaitUntil<T> {
+        WaitUntil {
+            predicate,
+            waiting: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the predicate holds
+    pub(crate) fn holds(&self, view: &SimView<'_, T>) -> bool {
+        (self.predicate)(view)
+    }
+
+    /// Park the event of a process until the predicate holds.
+    pub(crate) fn wait(&mut self, event: Event<T>) {
+        self.waiting.push(event);
+    }
+
+    /// Remove a waiting process, e.g. because it was interrupted.
+    pub(crate) fn cancel(&mut self, process: ProcessId) {
+        self.waiting.retain(|e| e.process() != process);
+    }
+
+    /// Returns the events that resume the waiting processes at the current time, if the
+    /// predicate holds.
+    pub(crate) fn wake(&mut self, view: &SimView<'_, T>) -> Vec<Event<T>> {
+        if self.waiting.is_empty() || !self.holds(view) {
+            return Vec::new();
+        }
+        let time = view.time();
+        let mut woken = std::mem::take(&mut self.waiting);
+        for event in woken.iter_mut() {
+            event.set_time(time);
+        }
+        woken
+    }
+}
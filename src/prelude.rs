@@ -28,10 +28,13 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 
 pub use crate::Effect;
 pub use crate::EndCondition;
+pub use crate::EndReason;
 pub use crate::Event;
+pub use crate::Outcome;
 pub use crate::Process;
 pub use crate::ProcessId;
 pub use crate::ResourceId;
 pub use crate::SimContext;
 pub use crate::SimState;
 pub use crate::Simulation;
+pub use crate::StepResult;
@@ -0,0 +1,144 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Processes written as state machines.
+//!
+//! Processes are usually coroutines, that need the unstable `coroutines` feature in the
+//! crate that defines them. A process can also be a type implementing `ProcessFn`, that
+//! is resumed with the context of each step and returns a `ProcessStep`: either the state
+//! to yield or the completion of the process. The states between two steps are kept
+//! explicitly in the fields of the type, or a closure can be used for simple processes.
+//!
+//! Wrapped in a `StateMachine`, a `ProcessFn` can be used wherever a `Process` is, so a
+//! model can be written without enabling any unstable feature. Disabling the default
+//! `coroutines` feature of desim, a `Process` is a `ProcessFn` instead of a coroutine and
+//! desim builds on stable Rust too:
+//!
+//! ```toml
+//! [dependencies]
+//! desim = { version = "0.4", default-features = false }
+//! ```
+//!
+//! ```
+//! use desim::process::{ProcessFn, ProcessStep, StateMachine};
+//! use desim::{Effect, EndCondition, SimContext, Simulation};
+//!
+//! /// Waits `period` time units `n` times
+//! struct Ticker {
+//!     period: f64,
+//!     n: usize,
+//! }
+//!
+//! impl ProcessFn<Effect> for Ticker {
+//!     fn resume(&mut self, _ctx: SimContext<Effect>) -> ProcessStep<Effect> {
+//!         if self.n == 0 {
+//!             return ProcessStep::Complete;
+//!         }
+//!         self.n -= 1;
+//!         ProcessStep::Yield(Effect::TimeOut(self.period))
+//!     }
+//! }
+//!
+//! let mut sim = Simulation::new();
+//! let ticker = StateMachine::new(Ticker { period: 2.0, n: 3 });
+//! let p = sim.create_process(Box::new(ticker));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(sim.time(), 6.0);
+//! ```
+use crate::{Process, SimContext};
+#[cfg(feature = "coroutines")]
+use std::ops::{Coroutine, CoroutineState};
+#[cfg(feature = "coroutines")]
+use std::pin::Pin;
+
+/// What a `ProcessFn` does after being resumed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessStep<T> {
+    /// Yield the state, like a coroutine
+    Yield(T),
+    /// Complete the process
+    Complete,
+}
+
+/// A process written as a state machine.
+pub trait ProcessFn<T> {
+    /// Resume the process with the context of the current step
+    fn resume(&mut self, ctx: SimContext<T>) -> ProcessStep<T>;
+}
+
+impl<T, F> ProcessFn<T> for F
+where
+    F: FnMut(SimContext<T>) -> ProcessStep<T>,
+{
+    fn resume(&mut self, ctx: SimContext<T>) -> ProcessStep<T> {
+        self(ctx)
+    }
+}
+
+/// Adapts a `ProcessFn` to be used as a `Process`.
+///
+/// Once the process completed, it is never resumed again by the simulation.
+#[derive(Debug, Clone)]
+pub struct StateMachine<P>(P);
+
+impl<P> StateMachine<P> {
+    /// Wrap `process`
+    pub fn new(process: P) -> StateMachine<P> {
+        StateMachine(process)
+    }
+
+    /// Returns the wrapped process
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+#[cfg(feature = "coroutines")]
+impl<T, P: ProcessFn<T> + Unpin> Coroutine<SimContext<T>> for StateMachine<P> {
+    type Yield = T;
+    type Return = ();
+
+    fn resume(self: Pin<&mut Self>, ctx: SimContext<T>) -> CoroutineState<T, ()> {
+        match self.get_mut().0.resume(ctx) {
+            ProcessStep::Yield(state) => CoroutineState::Yielded(state),
+            ProcessStep::Complete => CoroutineState::Complete(()),
+        }
+    }
+}
+
+#[cfg(not(feature = "coroutines"))]
+impl<T, P: ProcessFn<T>> ProcessFn<T> for StateMachine<P> {
+    fn resume(&mut self, ctx: SimContext<T>) -> ProcessStep<T> {
+        self.0.resume(ctx)
+    }
+}
+
+/// Resume `process` with the context of the current step.
+#[cfg(feature = "coroutines")]
+pub(crate) fn resume<T>(process: &mut Box<Process<T>>, ctx: SimContext<T>) -> ProcessStep<T> {
+    match Pin::new(process).resume(ctx) {
+        CoroutineState::Yielded(state) => ProcessStep::Yield(state),
+        CoroutineState::Complete(()) => ProcessStep::Complete,
+    }
+}
+
+/// Resume `process` with the context of the current step.
+#[cfg(not(feature = "coroutines"))]
+pub(crate) fn resume<T>(process: &mut Box<Process<T>>, ctx: SimContext<T>) -> ProcessStep<T> {
+    process.resume(ctx)
+}
+
+#[cfg(test)]
+mod tests;
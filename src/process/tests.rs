@@ -0,0 +1,44 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{ProcessStep, StateMachine};
+use crate::resources::SimpleResource;
+use crate::{Effect, EndCondition, SimContext, Simulation};
+
+#[test]
+fn state_machine() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let mut processes = Vec::new();
+    for _ in 0..2 {
+        let mut step = 0;
+        let process = move |ctx: SimContext<Effect>| {
+            step += 1;
+            match step {
+                1 => ProcessStep::Yield(Effect::Request(r)),
+                2 => ProcessStep::Yield(Effect::TimeOut(ctx.time() + 1.0)),
+                3 => ProcessStep::Yield(Effect::Release(r)),
+                _ => ProcessStep::Complete,
+            }
+        };
+        let p = s.create_process(Box::new(StateMachine::new(process)));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+        processes.push(p);
+    }
+    let s = s.run(EndCondition::NoEvents);
+    // the second process is granted the resource at 1 and holds it for 2
+    assert_eq!(s.time(), 3.0);
+    assert_eq!(s.active_processes(), 0);
+}
@@ -0,0 +1,107 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Progress reports of long runs.
+//!
+//! A hook set with `Simulation::set_progress_hook` is called during the runs every `n`
+//! steps or every time the simulation time advanced by a given amount, with the current
+//! time, the number of steps executed and the number of events scheduled, e.g. to draw a
+//! progress bar. It can also abort the run, returning `ControlFlow::Break`: the run stops
+//! before the next step, with `EndReason::Aborted`.
+//!
+//! The hook is called between the steps by all the runs, `run` and its variants, the
+//! iterator returned by `iter_steps` and the controlled runs.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use std::ops::ControlFlow;
+//! use desim::soak::CheckInterval;
+//! use desim::{Effect, EndCondition, EndReason, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+//!     yield Effect::TimeOut(1.0);
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! sim.set_progress_hook(
+//!     CheckInterval::Time(10.0),
+//!     Box::new(|report| {
+//!         println!("t={} after {} steps", report.time, report.steps);
+//!         // abort the run if it takes too long
+//!         if report.steps >= 25 {
+//!             ControlFlow::Break(())
+//!         } else {
+//!             ControlFlow::Continue(())
+//!         }
+//!     }),
+//! );
+//! let sim = sim.run(EndCondition::Time(100.0));
+//! assert_eq!(sim.end_reason(), Some(&EndReason::Aborted));
+//! // reported at 10.0, 20.0 and 30.0, after 31 steps
+//! assert_eq!(sim.time(), 30.0);
+//! ```
+use crate::soak::CheckInterval;
+use std::ops::ControlFlow;
+
+/// The progress of a run, passed to the progress hook.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProgressReport {
+    /// Current simulation time
+    pub time: f64,
+    /// Number of steps executed
+    pub steps: usize,
+    /// Number of events scheduled and not executed yet
+    pub pending: usize,
+}
+
+/// Called with the progress of a run, returns `ControlFlow::Break` to abort it
+#[cfg(not(feature = "send"))]
+pub type ProgressHook = Box<dyn FnMut(&ProgressReport) -> ControlFlow<()>>;
+/// Called with the progress of a run, returns `ControlFlow::Break` to abort it
+#[cfg(feature = "send")]
+pub type ProgressHook = Box<dyn FnMut(&ProgressReport) -> ControlFlow<()> + Send>;
+
+/// A progress hook set on a simulation.
+pub(crate) struct Progress {
+    interval: CheckInterval,
+    hook: ProgressHook,
+    /// Step or time of the next report
+    next: f64,
+}
+
+impl Progress {
+    pub(crate) fn new(interval: CheckInterval, hook: ProgressHook) -> Progress {
+        Progress {
+            interval,
+            hook,
+            next: interval.first(),
+        }
+    }
+
+    /// Start counting the interval again, for a new run from the start.
+    pub(crate) fn restart(&mut self) {
+        self.next = self.interval.first();
+    }
+
+    /// Call the hook if a report is due, returning `true` if the run must be aborted.
+    pub(crate) fn report(&mut self, report: ProgressReport) -> bool {
+        self.interval
+            .is_due(&mut self.next, report.steps, report.time)
+            && (self.hook)(&report).is_break()
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
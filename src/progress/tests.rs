@@ -0,0 +1,76 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::soak::CheckInterval;
+use crate::{Effect, EndCondition, EndReason, Simulation};
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn progress_reports() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&reports);
+    let mut s = Simulation::builder()
+        .progress_hook(
+            CheckInterval::Steps(4),
+            Box::new(move |report| {
+                recorded.lock().unwrap().push(*report);
+                ControlFlow::Continue(())
+            }),
+        )
+        .build();
+    for _ in 0..2 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            |_| {
+                for _ in 0..4 {
+                    yield Effect::TimeOut(1.0);
+                }
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.end_reason(), Some(&EndReason::NoEvents));
+    let reports: Vec<_> = reports
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| (r.time, r.steps, r.pending))
+        .collect();
+    assert_eq!(reports, [(1.0, 4, 2), (3.0, 8, 2)]);
+
+    // a runaway run is aborted
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(0.0);
+        },
+    ));
+    s.schedule_event(s.time(), p, Effect::TimeOut(0.)).unwrap();
+    s.set_progress_hook(
+        CheckInterval::Steps(100),
+        Box::new(|report| {
+            if report.steps > 1000 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }),
+    );
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.end_reason(), Some(&EndReason::Aborted));
+    assert_eq!(s.steps(), 1100);
+}
@@ -0,0 +1,208 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Verification against analytic models.
+//!
+//! Many queueing models have a closed-form solution, at least for some of their metrics.
+//! Comparing the results of the simulation with the analytic values is the usual way to
+//! verify that the model is implemented correctly.
+//!
+//! An `AnalyticReference` holds the expected value of some metrics as functions of the
+//! parameters of the model, read from the `RunMetadata` of the simulation. Once attached to
+//! a simulation with `Simulation::set_reference`, `Simulation::compare_with_reference`
+//! produces a report with the empirical and analytic value of each metric, flagging the
+//! ones that deviate more than the tolerance.
+//!
+//! ```
+//! use desim::metadata::RunMetadata;
+//! use desim::reference::AnalyticReference;
+//!
+//! // M/M/1 queue: the mean number of customers in the system is rho / (1 - rho)
+//! let mut reference = AnalyticReference::new(0.05);
+//! reference.add_expectation(
+//!     "customers",
+//!     Box::new(|m: &RunMetadata| {
+//!         let rho = m.parameter_as::<f64>("lambda")? / m.parameter_as::<f64>("mu")?;
+//!         Some(rho / (1.0 - rho))
+//!     }),
+//! );
+//! let mut metadata = RunMetadata::new();
+//! metadata.set_parameter("lambda", 0.5);
+//! metadata.set_parameter("mu", 1.0);
+//! let report = reference.compare(&metadata, &[("customers", 0.98)]);
+//! assert!(!report.has_deviations());
+//! println!("{}", report);
+//! ```
+use crate::metadata::RunMetadata;
+use std::fmt;
+
+/// Computes the expected value of a metric from the metadata of the run.
+///
+/// Returns `None` if the value is not known, e.g. because a parameter is missing.
+#[cfg(not(feature = "send"))]
+pub type Expectation = Box<dyn Fn(&RunMetadata) -> Option<f64>>;
+/// Computes the expected value of a metric from the metadata of the run.
+///
+/// Returns `None` if the value is not known, e.g. because a parameter is missing.
+#[cfg(feature = "send")]
+pub type Expectation = Box<dyn Fn(&RunMetadata) -> Option<f64> + Send>;
+
+/// A set of analytic expectations, with the tolerance allowed on each one.
+pub struct AnalyticReference {
+    expectations: Vec<(String, Expectation)>,
+    tolerance: f64,
+}
+
+/// The comparison of a metric with its analytic value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// Name of the metric
+    pub metric: String,
+    /// Value measured in the simulation
+    pub empirical: f64,
+    /// Expected value, if known
+    pub analytic: Option<f64>,
+    /// Relative deviation of the empirical value from the analytic one
+    pub deviation: Option<f64>,
+    /// Set if the deviation is larger than the tolerance
+    pub flagged: bool,
+}
+
+/// The comparison of the results of a run with an `AnalyticReference`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonReport {
+    tolerance: f64,
+    comparisons: Vec<Comparison>,
+}
+
+impl AnalyticReference {
+    /// Create an empty reference.
+    ///
+    /// `tolerance` is the largest relative deviation of an empirical value from the
+    /// analytic one that is not flagged.
+    pub fn new(tolerance: f64) -> AnalyticReference {
+        AnalyticReference {
+            expectations: Vec::new(),
+            tolerance,
+        }
+    }
+
+    /// Returns the tolerance
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Add the analytic expectation of `metric`.
+    pub fn add_expectation(&mut self, metric: impl Into<String>, expectation: Expectation) {
+        self.expectations.push((metric.into(), expectation));
+    }
+
+    /// Returns the expected value of `metric` for the run described by `metadata`
+    pub fn expected(&self, metric: &str, metadata: &RunMetadata) -> Option<f64> {
+        self.expectations
+            .iter()
+            .find(|(m, _)| m == metric)
+            .and_then(|(_, expectation)| expectation(metadata))
+    }
+
+    /// Compare the `empirical` values of some metrics, given as `(metric, value)` pairs,
+    /// with their expected values for the run described by `metadata`.
+    ///
+    /// Metrics without an analytic value are reported but never flagged.
+    pub fn compare(&self, metadata: &RunMetadata, empirical: &[(&str, f64)]) -> ComparisonReport {
+        let comparisons = empirical
+            .iter()
+            .map(|&(metric, empirical)| {
+                let analytic = self.expected(metric, metadata);
+                let deviation = analytic.map(|a| relative_deviation(empirical, a));
+                Comparison {
+                    metric: metric.to_string(),
+                    empirical,
+                    analytic,
+                    deviation,
+                    flagged: deviation.is_some_and(|d| d > self.tolerance || d.is_nan()),
+                }
+            })
+            .collect();
+        ComparisonReport {
+            tolerance: self.tolerance,
+            comparisons,
+        }
+    }
+}
+
+/// The deviation of `empirical` relative to `analytic`, or the absolute one if the
+/// analytic value is zero.
+fn relative_deviation(empirical: f64, analytic: f64) -> f64 {
+    let difference = (empirical - analytic).abs();
+    if analytic == 0.0 {
+        difference
+    } else {
+        difference / analytic.abs()
+    }
+}
+
+impl ComparisonReport {
+    /// Returns the comparisons of all the metrics
+    pub fn comparisons(&self) -> &[Comparison] {
+        self.comparisons.as_slice()
+    }
+
+    /// Returns `true` if any metric deviates more than the tolerance
+    pub fn has_deviations(&self) -> bool {
+        self.comparisons.iter().any(|c| c.flagged)
+    }
+
+    /// Returns the comparisons of the metrics that deviate more than the tolerance
+    pub fn deviations(&self) -> impl Iterator<Item = &Comparison> {
+        self.comparisons.iter().filter(|c| c.flagged)
+    }
+}
+
+impl fmt::Display for ComparisonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .comparisons
+            .iter()
+            .map(|c| c.metric.len())
+            .chain(Some("metric".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:width$}  {:>12}  {:>12}  {:>10}  flag",
+            "metric", "empirical", "analytic", "deviation"
+        )?;
+        for c in &self.comparisons {
+            let analytic = c.analytic.map(|a| format!("{:.6}", a)).unwrap_or_default();
+            let deviation = c
+                .deviation
+                .map(|d| format!("{:.2}%", d * 100.0))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "{:width$}  {:>12.6}  {:>12}  {:>10}  {}",
+                c.metric,
+                c.empirical,
+                analytic,
+                deviation,
+                if c.flagged { "*" } else { "" }
+            )?;
+        }
+        write!(f, "tolerance: {:.2}%", self.tolerance * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests;
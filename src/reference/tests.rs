@@ -0,0 +1,45 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::AnalyticReference;
+use crate::metadata::RunMetadata;
+
+#[test]
+fn compare() {
+    let mut reference = AnalyticReference::new(0.1);
+    reference.add_expectation(
+        "utilization",
+        Box::new(|m: &RunMetadata| {
+            Some(m.parameter_as::<f64>("lambda")? / m.parameter_as::<f64>("mu")?)
+        }),
+    );
+    reference.add_expectation("idle", Box::new(|_| Some(0.0)));
+    let mut metadata = RunMetadata::new();
+    metadata.set_parameter("lambda", 0.8);
+    metadata.set_parameter("mu", 1.0);
+
+    let report = reference.compare(
+        &metadata,
+        &[("utilization", 0.6), ("idle", 0.05), ("throughput", 0.8)],
+    );
+    let c = report.comparisons();
+    assert_eq!(c[0].analytic, Some(0.8));
+    assert!(c[0].flagged);
+    assert!(!c[1].flagged);
+    assert_eq!(c[2].analytic, None);
+    assert!(!c[2].flagged);
+    assert_eq!(report.deviations().count(), 1);
+    assert!(report.to_string().contains("utilization"));
+}
@@ -0,0 +1,222 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Recording and replaying runs.
+//!
+//! Given the same model, a run depends only on the seed of the random streams of the
+//! processes and on the inputs received from outside of the simulation: the events
+//! injected with an `EventInjector`, maybe from another thread, and the calls to
+//! `schedule_event`, `cancel_event`, `resume_process`, `interrupt` and `terminate_process`
+//! made between the steps. After `record_inputs`, the simulation keeps a `Journal` of the
+//! seed and of these inputs, together with the step at which each of them was received.
+//! With the `serde` feature, the journal can be saved and loaded again.
+//!
+//! To replay the run, build the same model and pass the journal to `replay`: the inputs
+//! are delivered again at the same steps, so that the run is exactly the same, e.g. to
+//! reproduce a failure seen once. While replaying, the events sent by injectors are
+//! discarded and the calls to `cancel_event`, `resume_process`, `interrupt` and
+//! `terminate_process` are ignored. The calls to `schedule_event` return the id of the
+//! event, so they are not ignored and should be left out.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let build = || {
+//!     let mut sim = Simulation::new();
+//!     let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+//!         yield Effect::Wait;
+//!     }));
+//!     (sim, p)
+//! };
+//! let (mut sim, p) = build();
+//! sim.record_inputs();
+//! sim.injector().inject(2.0, p, Effect::TimeOut(0.0)).unwrap();
+//! sim.step().unwrap();
+//! sim.resume_process(p, Effect::TimeOut(1.0)).unwrap();
+//! let sim = sim.run(EndCondition::NoEvents);
+//! let journal = sim.journal().unwrap().clone();
+//!
+//! let (mut replay, _) = build();
+//! replay.replay(journal);
+//! let replay = replay.run(EndCondition::NoEvents);
+//! assert_eq!(replay.processed_events().len(), sim.processed_events().len());
+//! assert_eq!(replay.time(), 2.0);
+//! ```
+use crate::error::SimError;
+use crate::{EventId, ProcessId, SimState, Simulation};
+use std::collections::VecDeque;
+
+/// An input received by the simulation from outside.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Input<T> {
+    /// An event sent by an `EventInjector`
+    Injected {
+        time: f64,
+        process: ProcessId,
+        state: T,
+    },
+    /// A call to `Simulation::schedule_event` or one of its variants
+    Scheduled {
+        time: f64,
+        process: ProcessId,
+        state: T,
+        priority: i32,
+    },
+    /// A call to `Simulation::schedule_in`
+    Delayed {
+        delay: f64,
+        process: ProcessId,
+        state: T,
+    },
+    /// A call to `Simulation::cancel_event`
+    Cancelled { event: EventId },
+    /// A call to `Simulation::resume_process`
+    Resumed { process: ProcessId, state: T },
+    /// A call to `Simulation::interrupt`
+    Interrupted { process: ProcessId, state: T },
+    /// A call to `Simulation::terminate_process`
+    Terminated { process: ProcessId, state: T },
+}
+
+/// The seed and the inputs of a run, see `Simulation::record_inputs`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Journal<T> {
+    seed: u64,
+    replication: u32,
+    inputs: Vec<(usize, Input<T>)>,
+}
+
+impl<T> Journal<T> {
+    /// Returns the seed of the random streams
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the replication selecting the random streams
+    pub fn replication(&self) -> u32 {
+        self.replication
+    }
+
+    /// Returns the inputs, with the steps at which they were received
+    pub fn inputs(&self) -> &[(usize, Input<T>)] {
+        &self.inputs
+    }
+
+    pub(crate) fn push(&mut self, step: usize, input: Input<T>) {
+        self.inputs.push((step, input));
+    }
+
+    /// Keep only the inputs for which `f` returns `true`
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(usize, &Input<T>) -> bool) {
+        self.inputs.retain(|(step, input)| f(*step, input));
+    }
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Start recording the inputs of the simulation in a journal.
+    /// See the [`replay`](crate::replay) module.
+    pub fn record_inputs(&mut self) {
+        self.journal = Some(Journal {
+            seed: self.streams.seed(),
+            replication: self.streams.replication(),
+            inputs: Vec::new(),
+        });
+    }
+
+    /// Returns the journal of the inputs, if they are recorded
+    pub fn journal(&self) -> Option<&Journal<T>> {
+        self.journal.as_ref()
+    }
+
+    /// Stop recording the inputs and return the journal
+    pub fn take_journal(&mut self) -> Option<Journal<T>> {
+        self.journal.take()
+    }
+
+    /// Replay the inputs of a journal, after setting the seed it was recorded with.
+    /// See the [`replay`](crate::replay) module.
+    pub fn replay(&mut self, journal: Journal<T>) {
+        self.set_seed(journal.seed, journal.replication);
+        self.replaying = Some(journal.inputs.into());
+    }
+
+    /// Returns `true` while replaying a journal that has inputs left
+    pub fn is_replaying(&self) -> bool {
+        self.replaying.as_ref().is_some_and(|r| !r.is_empty())
+    }
+
+    /// Deliver the replayed inputs received between two steps.
+    pub(crate) fn replay_calls(&mut self) -> Result<(), SimError> {
+        while let Some(input) = self.next_replayed(false) {
+            match input {
+                Input::Scheduled {
+                    time,
+                    process,
+                    state,
+                    priority,
+                } => {
+                    self.schedule_event_with_priority(time, process, state, priority)?;
+                }
+                Input::Delayed {
+                    delay,
+                    process,
+                    state,
+                } => {
+                    self.schedule_in(delay, process, state)?;
+                }
+                Input::Cancelled { event } => self.cancel_input(event),
+                Input::Resumed { process, state } => self.resume_input(process, state)?,
+                Input::Interrupted { process, state } => self.interrupt_input(process, state)?,
+                Input::Terminated { process, state } => self.terminate_input(process, state)?,
+                Input::Injected { .. } => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the replayed events injected at the current step.
+    pub(crate) fn replayed_injections(&mut self) -> Vec<(f64, ProcessId, T)> {
+        let mut injected = Vec::new();
+        while let Some(Input::Injected {
+            time,
+            process,
+            state,
+        }) = self.next_replayed(true)
+        {
+            injected.push((time, process, state));
+        }
+        injected
+    }
+
+    /// Pop the next replayed input, if it was received at the current step and it is an
+    /// injected event or not, according to `injected`.
+    fn next_replayed(&mut self, injected: bool) -> Option<Input<T>> {
+        let inputs: &mut VecDeque<_> = self.replaying.as_mut()?;
+        match inputs.front() {
+            Some((step, input))
+                if *step == self.steps && matches!(input, Input::Injected { .. }) == injected =>
+            {
+                inputs.pop_front().map(|(_, input)| input)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
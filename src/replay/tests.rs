@@ -0,0 +1,104 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn replay_interrupts() {
+    let build = || {
+        let mut s = Simulation::new();
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::TimeOut(10.0);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        (s, p)
+    };
+    let (mut s, p) = build();
+    s.record_inputs();
+    for _ in 0..3 {
+        s.step().unwrap();
+        s.interrupt(p, Effect::Trace).unwrap();
+    }
+    let journal = s.take_journal().unwrap();
+    assert_eq!(journal.inputs().len(), 3);
+    // the interrupts by the owner are not driven from the replay
+    let (mut r, _) = build();
+    r.replay(journal);
+    for _ in 0..3 {
+        r.step().unwrap();
+    }
+    // the last interrupt is delivered before the next step
+    assert!(r.is_replaying());
+    r = r.run(EndCondition::NSteps(1));
+    s = s.run(EndCondition::NSteps(1));
+    assert!(!r.is_replaying());
+    let times = |s: &Simulation<Effect>| -> Vec<f64> {
+        s.processed_events().iter().map(|(e, _)| e.time()).collect()
+    };
+    assert_eq!(times(&r), [0.0; 4]);
+    assert_eq!(times(&r), times(&s));
+}
+
+#[test]
+fn replay_calls() {
+    let build = || {
+        let mut s = Simulation::new();
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::Wait;
+            },
+        ));
+        let q = s.create_process(Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::TimeOut(1.0);
+            },
+        ));
+        (s, p, q)
+    };
+    let (mut s, p, q) = build();
+    s.record_inputs();
+    s.schedule_event(0.0, q, Effect::TimeOut(0.)).unwrap();
+    let cancelled = s.schedule_event(5.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    s.cancel_event(cancelled);
+    s.schedule_event(2.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(EndCondition::Time(3.0));
+    s.resume_process(p, Effect::Trace).unwrap();
+    s.terminate_process(q, Effect::Trace).unwrap();
+    s = s.run(EndCondition::NoEvents);
+    let journal = s.take_journal().unwrap();
+    assert_eq!(journal.inputs().len(), 6);
+
+    let (mut r, p, q) = build();
+    r.replay(journal);
+    // the calls of the owner are ignored, the journal drives the run
+    r.terminate_process(p, Effect::Trace).unwrap();
+    r.resume_process(q, Effect::Trace).unwrap();
+    r = r.run(EndCondition::NoEvents);
+    assert!(!r.is_replaying());
+    let events = |s: &Simulation<Effect>| -> Vec<(f64, usize)> {
+        s.processed_events()
+            .iter()
+            .map(|(e, _)| (e.time(), e.process().into_raw()))
+            .collect()
+    };
+    assert_eq!(events(&r), events(&s));
+    assert_eq!(r.time(), s.time());
+}
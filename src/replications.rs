@@ -0,0 +1,447 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Independent replications of a simulation.
+//!
+//! Monte-Carlo studies run many copies of the same model, each with different random
+//! numbers, and aggregate their results. `replicate` builds each copy with a setup closure,
+//! runs the copies on a pool of threads and returns a summary of each run, computed on the
+//! thread that executed it. Since the simulations never leave their thread, the `send`
+//! feature is not required.
+//!
+//! The setup closure receives a `Replication`, holding the base seed and the index of the
+//! copy: together they select independent random streams, see [`rng::Streams`](crate::rng::Streams).
+//! They also seed the streams of the processes of the simulation, see `SimContext::rng`.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::replications::replicate;
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let times = replicate(
+//!     4,
+//!     42,
+//!     EndCondition::NoEvents,
+//!     |replication| {
+//!         let mut sim = Simulation::new();
+//!         let delay = replication.index() as f64;
+//!         let p = sim.create_process(Box::new(#[coroutine] move |_| {
+//!             yield Effect::TimeOut(delay);
+//!         }));
+//!         sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!         sim
+//!     },
+//!     |sim| sim.time(),
+//! );
+//! assert_eq!(times, [0.0, 1.0, 2.0, 3.0]);
+//! ```
+//!
+//! The result of a single run is a random sample: `analyze` runs the replications, collects
+//! some named output metrics from each of them and returns their mean with a confidence
+//! interval. When the interval of a metric is too wide for the study, `Analysis::needs_more`
+//! tells it, and `ConfidenceInterval::required_replications` estimates how many replications
+//! would be enough. The confidence interval of the output of a single long run can be
+//! estimated instead with `batch_means`.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::replications::analyze;
+//! use desim::rng::StreamRng;
+//! use desim::{Effect, EndCondition, SimContext, Simulation};
+//!
+//! let analysis = analyze(
+//!     10,
+//!     42,
+//!     EndCondition::NoEvents,
+//!     0.95,
+//!     |_| {
+//!         let mut sim = Simulation::new();
+//!         let p = sim.create_process(Box::new(#[coroutine] |mut ctx: SimContext<Effect>| {
+//!             let delay = ctx.rng().next_f64();
+//!             yield Effect::TimeOut(delay);
+//!         }));
+//!         sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!         sim
+//!     },
+//!     |sim| vec![("end", sim.time())],
+//! );
+//! let end = analysis.get("end").unwrap();
+//! assert!(end.low() < end.mean() && end.mean() < end.high());
+//! assert!(end.low() > 0.0 && end.high() < 1.0);
+//! ```
+use crate::{EndCondition, SimState, Simulation};
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Identifies one of the copies of a simulation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Replication {
+    seed: u64,
+    index: u32,
+}
+
+impl Replication {
+    /// Returns the seed shared by all the replications
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the index of the replication, starting from 0
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// Run `n` replications of a simulation in parallel, until `until` is met.
+///
+/// Each simulation is created by `setup` and, at the end of its run, passed to `summarize`,
+/// e.g. to compute statistics or to copy its `processed_events`. The seed and the index of
+/// the replication are recorded in the metadata of the simulation. Summaries are returned
+/// in the order of the replications.
+///
+/// The number of threads is the available parallelism of the machine.
+///
+/// # Panics
+/// Panics if a replication panics.
+pub fn replicate<T, S, F, R>(
+    n: u32,
+    seed: u64,
+    until: EndCondition,
+    setup: S,
+    summarize: F,
+) -> Vec<R>
+where
+    T: 'static + SimState + Clone,
+    S: Fn(Replication) -> Simulation<T> + Sync,
+    F: Fn(Simulation<T>) -> R + Sync,
+    R: Send,
+{
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    run_parallel(n as usize, threads, |index| {
+        let replication = Replication {
+            seed,
+            index: index as u32,
+        };
+        let mut sim = setup(replication);
+        sim.set_seed(seed, index as u32);
+        sim.metadata_mut().set_parameter("replication", index);
+        summarize(sim.run(until.clone()))
+    })
+}
+
+/// Run `n` replications of a simulation like `replicate`, collecting the named output
+/// metrics returned by `metrics` at the end of each run, and compute their confidence
+/// intervals at `level`, e.g. 0.95.
+///
+/// # Panics
+/// Panics if a replication panics, or if `level` is not in `(0, 1)`.
+pub fn analyze<T, S, F, M, N>(
+    n: u32,
+    seed: u64,
+    until: EndCondition,
+    level: f64,
+    setup: S,
+    metrics: F,
+) -> Analysis
+where
+    T: 'static + SimState + Clone,
+    S: Fn(Replication) -> Simulation<T> + Sync,
+    F: Fn(Simulation<T>) -> M + Sync,
+    M: IntoIterator<Item = (N, f64)>,
+    N: Into<String>,
+{
+    let runs = replicate(n, seed, until, setup, |sim| {
+        metrics(sim)
+            .into_iter()
+            .map(|(name, value)| (name.into(), value))
+            .collect::<Vec<(String, f64)>>()
+    });
+    let mut samples: Vec<(String, Vec<f64>)> = Vec::new();
+    for (name, value) in runs.into_iter().flatten() {
+        match samples.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, values)) => values.push(value),
+            None => samples.push((name, vec![value])),
+        }
+    }
+    Analysis {
+        metrics: samples
+            .into_iter()
+            .filter_map(|(name, values)| Some((name, confidence_interval(&values, level)?)))
+            .collect(),
+    }
+}
+
+/// The confidence intervals of the output metrics of some replications, see `analyze`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    metrics: Vec<(String, ConfidenceInterval)>,
+}
+
+impl Analysis {
+    /// Returns the confidence interval of a metric, if it was returned by at least two
+    /// replications
+    pub fn get(&self, metric: &str) -> Option<&ConfidenceInterval> {
+        self.metrics
+            .iter()
+            .find_map(|(name, interval)| (name == metric).then_some(interval))
+    }
+
+    /// Returns the metrics with their confidence intervals
+    pub fn metrics(&self) -> impl Iterator<Item = (&str, &ConfidenceInterval)> {
+        self.metrics.iter().map(|(name, ci)| (name.as_str(), ci))
+    }
+
+    /// Returns the metrics whose confidence interval is wider than `precision`, relative to
+    /// their mean, e.g. 0.05 for an half width of 5% of the mean: more replications are
+    /// needed to estimate them.
+    pub fn needs_more(&self, precision: f64) -> Vec<&str> {
+        self.metrics()
+            .filter(|(_, ci)| {
+                let width = ci.relative_half_width();
+                width > precision || width.is_nan()
+            })
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// The mean of some independent samples, with a confidence interval based on the Student's
+/// t distribution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    mean: f64,
+    half_width: f64,
+    level: f64,
+    samples: usize,
+}
+
+impl ConfidenceInterval {
+    /// Returns the mean of the samples
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the half width of the interval
+    pub fn half_width(&self) -> f64 {
+        self.half_width
+    }
+
+    /// Returns the lower bound of the interval
+    pub fn low(&self) -> f64 {
+        self.mean - self.half_width
+    }
+
+    /// Returns the upper bound of the interval
+    pub fn high(&self) -> f64 {
+        self.mean + self.half_width
+    }
+
+    /// Returns the confidence level of the interval
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    /// Returns the number of samples
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Returns the half width of the interval relative to the mean
+    pub fn relative_half_width(&self) -> f64 {
+        self.half_width / self.mean.abs()
+    }
+
+    /// Estimate the number of samples needed for an half width of at most `precision`
+    /// relative to the mean, assuming that the variance of the samples does not change.
+    pub fn required_replications(&self, precision: f64) -> usize {
+        let ratio = self.relative_half_width() / precision;
+        if ratio.is_finite() {
+            ((self.samples as f64 * ratio * ratio).ceil() as usize).max(self.samples)
+        } else {
+            usize::MAX
+        }
+    }
+}
+
+/// Compute the mean of independent `samples` with a confidence interval at `level`, e.g.
+/// 0.95. Returns `None` if there are less than two samples.
+///
+/// # Panics
+/// Panics if `level` is not in `(0, 1)`.
+pub fn confidence_interval(samples: &[f64], level: f64) -> Option<ConfidenceInterval> {
+    assert!(
+        level > 0.0 && level < 1.0,
+        "ERROR. The confidence level must be in (0, 1)."
+    );
+    let n = samples.len();
+    if n < 2 {
+        return None;
+    }
+    let mean = samples.iter().sum::<f64>() / n as f64;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let t = student_t_quantile(1.0 - level, n - 1);
+    Some(ConfidenceInterval {
+        mean,
+        half_width: t * (variance / n as f64).sqrt(),
+        level,
+        samples: n,
+    })
+}
+
+/// Compute a confidence interval at `level` for the mean of the correlated `samples` of a
+/// single run, e.g. the waiting times of the customers, splitting them in `batches`
+/// consecutive batches and treating the means of the batches as independent.
+/// Samples that do not fill the last batch are discarded. Returns `None` if there are less
+/// than two batches.
+///
+/// # Panics
+/// Panics if `level` is not in `(0, 1)`.
+pub fn batch_means(samples: &[f64], batches: usize, level: f64) -> Option<ConfidenceInterval> {
+    let size = samples.len().checked_div(batches)?;
+    if size == 0 {
+        return None;
+    }
+    let means: Vec<f64> = samples
+        .chunks_exact(size)
+        .take(batches)
+        .map(|batch| batch.iter().sum::<f64>() / size as f64)
+        .collect();
+    confidence_interval(&means, level)
+}
+
+/// The value exceeded in absolute value with probability `p` by a variable with the
+/// Student's t distribution with `df` degrees of freedom (Hill, Algorithm 396).
+fn student_t_quantile(p: f64, df: usize) -> f64 {
+    let n = df as f64;
+    if df == 1 {
+        let p = p * PI / 2.0;
+        return p.cos() / p.sin();
+    }
+    if df == 2 {
+        return (2.0 / (p * (2.0 - p)) - 2.0).sqrt();
+    }
+    let a = 1.0 / (n - 0.5);
+    let b = 48.0 / (a * a);
+    let mut c = ((20700.0 * a / b - 98.0) * a - 16.0) * a + 96.36;
+    let d = ((94.5 / (b + c) - 3.0) / b + 1.0) * (a * PI / 2.0).sqrt() * n;
+    let x = d * p;
+    let mut y = x.powf(2.0 / n);
+    if y > 0.05 + a {
+        let x = -normal_quantile(0.5 * p);
+        y = x * x;
+        if df < 5 {
+            c += 0.3 * (n - 4.5) * (x + 0.6);
+        }
+        c += (((0.05 * d * x - 5.0) * x - 7.0) * x - 2.0) * x + b;
+        y = (((((0.4 * y + 6.3) * y + 36.0) * y + 94.5) / c - y - 3.0) / b + 1.0) * x;
+        y = a * y * y;
+        y = if y > 0.002 {
+            y.exp() - 1.0
+        } else {
+            0.5 * y * y + y
+        };
+    } else {
+        y = ((1.0 / (((n + 6.0) / (n * y) - 0.089 * d - 0.822) * (n + 2.0) * 3.0)
+            + 0.5 / (n + 4.0))
+            * y
+            - 1.0)
+            * (n + 1.0)
+            / (n + 2.0)
+            + 1.0 / y;
+    }
+    (n * y).sqrt()
+}
+
+/// The `p`-quantile of the standard normal distribution (Acklam's approximation).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.383_577_518_672_69e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Call `job` with the indices from 0 to `n` on at most `threads` threads, returning the
+/// results in the order of the indices.
+pub(crate) fn run_parallel<J, R>(n: usize, threads: usize, job: J) -> Vec<R>
+where
+    J: Fn(usize) -> R + Sync,
+    R: Send,
+{
+    let threads = threads.clamp(1, n.max(1));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(n));
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= n {
+                    break;
+                }
+                let result = job(index);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,80 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{batch_means, confidence_interval, replicate, student_t_quantile};
+use crate::rng::{Philox4x32, StreamRng};
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn replications() {
+    let run = || {
+        replicate(
+            8,
+            7,
+            EndCondition::NoEvents,
+            |replication| {
+                let mut rng = Philox4x32::from_stream(replication.seed(), replication.index(), 0);
+                let delay = rng.next_f64();
+                let mut sim = Simulation::new();
+                let p = sim.create_process(Box::new(
+                    #[coroutine]
+                    move |_| {
+                        yield Effect::TimeOut(delay);
+                    },
+                ));
+                sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+                sim
+            },
+            |sim| {
+                let replication = sim.metadata().parameter_as::<u32>("replication");
+                (replication, sim.time(), sim.processed_events().len())
+            },
+        )
+    };
+    let results = run();
+    assert_eq!(results.len(), 8);
+    for (i, (replication, time, events)) in results.iter().enumerate() {
+        assert_eq!(*replication, Some(i as u32));
+        assert!(*time > 0.0 && *time < 1.0);
+        assert_eq!(*events, 1);
+    }
+    // replications are reproducible, whatever thread executes them
+    assert_eq!(results, run());
+    assert_ne!(results[0].1, results[1].1);
+}
+
+#[test]
+fn confidence_intervals() {
+    for (df, t) in [
+        (1, 12.706),
+        (2, 4.303),
+        (4, 2.776),
+        (10, 2.228),
+        (30, 2.042),
+    ] {
+        assert!((student_t_quantile(0.05, df) - t).abs() < 1e-3);
+    }
+    assert!((student_t_quantile(0.01, 20) - 2.845).abs() < 1e-3);
+
+    let ci = confidence_interval(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.95).unwrap();
+    assert_eq!(ci.mean(), 3.0);
+    assert!((ci.half_width() - 2.776 * (2.5f64 / 5.0).sqrt()).abs() < 1e-3);
+    assert_eq!(ci.required_replications(ci.relative_half_width() / 2.0), 20);
+    assert!(confidence_interval(&[1.0], 0.95).is_none());
+
+    let batches = batch_means(&[1.0, 3.0, 2.0, 4.0, 3.0, 5.0, 9.0], 3, 0.9).unwrap();
+    assert_eq!((batches.mean(), batches.samples()), (3.0, 3));
+    assert!(batch_means(&[1.0, 2.0], 3, 0.9).is_none());
+}
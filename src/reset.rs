@@ -0,0 +1,181 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Reusing a simulation for repeated runs.
+//!
+//! Building a large model can take longer than running it. Instead of building it again
+//! for each replication, `Simulation::reset` brings a simulation back to its start: the
+//! clock, the steps, the future events, the log and the statistics are cleared, the
+//! resources are emptied, returning to their full capacity, and so are the stores and the
+//! mailboxes.
+//!
+//! The coroutines of the processes cannot be restarted, so the processes are dropped.
+//! The ones created with `spawn_process`, from a spawner registered with
+//! `register_spawner`, or with `spawn_from_factory`, from a process factory, are created
+//! again and scheduled at the same time with the same state and arguments. They get new
+//! ids, since the ids of the dropped processes and of the events are never given out
+//! again, but the same random streams as in the first run: set a different seed or
+//! replication with `set_seed` to draw different random numbers.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::resources::SimpleResource;
+//! use desim::rng::StreamRng;
+//! use desim::{Effect, EndCondition, SimContext, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let counter = sim.create_standard_resource(SimpleResource::new(1));
+//! let customer = sim.register_spawner(Box::new(move |_| {
+//!     Box::new(#[coroutine] move |mut ctx: SimContext<Effect>| {
+//!         yield Effect::Request(counter);
+//!         let service = ctx.rng().next_f64();
+//!         yield Effect::TimeOut(service);
+//!         yield Effect::Release(counter);
+//!     })
+//! }));
+//! for i in 0..3 {
+//!     sim.spawn_process(customer, i as f64, Effect::TimeOut(0.0)).unwrap();
+//! }
+//! let mut sim = sim.run(EndCondition::NoEvents);
+//! let first = sim.time();
+//!
+//! sim.reset(Effect::Trace);
+//! assert_eq!(sim.time(), 0.0);
+//! assert_eq!(sim.active_processes(), 3);
+//! let mut sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(sim.time(), first);
+//!
+//! // the next replication draws other random numbers
+//! sim.reset(Effect::Trace);
+//! sim.set_seed(0, 1);
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_ne!(sim.time(), first);
+//! ```
+use crate::error::SimError;
+use crate::event_list::FutureEventList;
+use crate::factory::InitialProcess;
+use crate::{FactoryId, ProcessId, SimState, Simulation};
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Create a process with a spawner registered with `register_spawner`, and schedule
+    /// it at the absolute `time` with `state`, that is also passed to the spawner. A
+    /// process factory registered with `register_process_factory` can be used as well,
+    /// and receives `()` as arguments.
+    ///
+    /// Unlike the processes created with `create_process`, the process is created again
+    /// by `reset`. See the [`reset`](crate::reset) module.
+    ///
+    /// Returns an error if `spawner` is not a spawner of the simulation or if `time` is NaN
+    /// or in the past.
+    pub fn spawn_process(
+        &mut self,
+        spawner: FactoryId,
+        time: f64,
+        state: T,
+    ) -> Result<ProcessId, SimError> {
+        let process = self.spawn_at(spawner, None, time, state.clone())?;
+        self.initial_processes.push(InitialProcess {
+            factory: spawner,
+            args: None,
+            time,
+            state,
+        });
+        Ok(process)
+    }
+
+    /// Bring the simulation back to its start time, to run it again.
+    /// See the [`reset`](crate::reset) module.
+    ///
+    /// The processes are terminated, releasing the units of resources they hold and
+    /// leaving the queues they are in; `state` is used for the release events, as with
+    /// `terminate_process`. Then the clock, the steps, the future events, the log, the
+    /// entities, the statistics, the engine counters, the warnings and the end reason are
+    /// cleared, and the processes created with `spawn_process` and `spawn_from_factory` are
+    /// created again.
+    ///
+    /// The items in the stores and the messages in the mailboxes are discarded, see
+    /// `Store::clear`. The configuration of the simulation is kept: the resources, stores,
+    /// factories, spawners, hooks, observers, the end condition and the seed. The state of the gates and the
+    /// values on the blackboard are kept as well. The periodic events and
+    /// the arrival sources are dropped with the processes, and a warm-up period set with
+    /// `set_warm_up` ends with the first run: they must be set again if needed, as well as
+    /// the names of the processes.
+    pub fn reset(&mut self, state: T) {
+        let processes: Vec<ProcessId> = self.processes.iter().map(|(id, _)| id).collect();
+        for process in processes {
+            let _ = self.terminate(process, state.clone());
+        }
+        // the grants deferred by the offline resources were given back by the processes
+        self.offline.clear();
+        while self.future_events.pop().is_some() {}
+        self.take_processed_events();
+        // the ids of the dropped processes must not be given out again
+        let processes: Vec<ProcessId> = self.processes.iter().map(|(id, _)| id).collect();
+        for process in processes {
+            self.processes.remove(process);
+        }
+        for store in self.stores.iter_mut() {
+            store.clear();
+        }
+        for mailbox in self.mailboxes.iter_mut() {
+            mailbox.clear();
+        }
+        self.created_processes = 0;
+        self.set_clock(self.start_time);
+        self.collect_capacity_changes(self.start_time);
+        self.steps = 0;
+        self.cancelled_events.clear();
+        self.request_times.clear();
+        self.held.clear();
+        self.requested_units.clear();
+        self.budget_violations.clear();
+        self.soft_limits.clear_warnings();
+        self.zero_delay_steps = 0;
+        self.zero_delay_processes.clear();
+        self.series.clear();
+        self.entities.clear();
+        self.arrival_sources.clear();
+        self.process_names.clear();
+        self.named_processes.clear();
+        self.parents.clear();
+        self.muted_processes.clear();
+        self.state_changed = false;
+        self.shared.take_spawns();
+        self.error = None;
+        self.end_reason = None;
+        self.engine = Default::default();
+        self.reset_stats_at(self.start_time);
+        if let Some(progress) = &mut self.progress {
+            progress.restart();
+        }
+        if let Some(rollback) = &mut self.rollback {
+            rollback.restart();
+        }
+        self.replaying = None;
+        if self.journal.is_some() {
+            self.record_inputs();
+        }
+        let initial = std::mem::take(&mut self.initial_processes);
+        for process in initial.iter() {
+            let args = process.args.as_ref().map(|args| args());
+            // the times were validated when the processes were first spawned
+            let _ = self.spawn_at(process.factory, args, process.time, process.state.clone());
+        }
+        self.initial_processes = initial;
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,168 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::error::SimError;
+use crate::resources::{SimpleResource, SimpleStore};
+use crate::rng::StreamRng;
+use crate::{Effect, EndCondition, EndReason, SimContext, Simulation};
+
+#[test]
+fn reset() {
+    let mut s = Simulation::new();
+    let r = s.create_standard_resource(SimpleResource::new(2));
+    let customer = s.register_spawner(Box::new(move |_| {
+        Box::new(
+            #[coroutine]
+            move |_| {
+                let mut ctx: SimContext<Effect> = yield Effect::Request(r);
+                let service = 1.0 + ctx.rng().next_f64();
+                yield Effect::TimeOut(service);
+                yield Effect::Release(r);
+            },
+        )
+    }));
+    for i in 0..6 {
+        s.spawn_process(customer, i as f64 * 0.5, Effect::TimeOut(0.))
+            .unwrap();
+    }
+    assert!(s.spawn_process(customer, f64::NAN, Effect::Trace).is_err());
+    assert_eq!(
+        s.spawn_process(customer + 1, 0.0, Effect::Trace),
+        Err(SimError::UnknownFactoryId(customer + 1))
+    );
+    let other = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, other, Effect::TimeOut(0.)).unwrap();
+    let store = s.create_store(Box::new(SimpleStore::new(2)));
+    let mailbox = s.create_mailbox(None);
+    let producer = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Push(store);
+            yield Effect::Send(mailbox);
+        },
+    ));
+    s.schedule_event(0.0, producer, Effect::TimeOut(0.))
+        .unwrap();
+
+    // stop while the resource is busy and customers are waiting
+    s = s.run(EndCondition::Time(2.0));
+    assert!(s.resource(r).queue_len() > 0);
+    assert_eq!(s.store(store).level(), 1);
+    assert_eq!(s.mailbox_len(mailbox), 1);
+    s.reset(Effect::Trace);
+    assert_eq!(s.store(store).level(), 0);
+    assert_eq!(s.mailbox_len(mailbox), 0);
+    // the ids of the dropped processes are not given out again
+    assert!(s.processes.was_removed(other));
+    assert!(s.processes.iter().all(|(id, _)| id != producer));
+    assert_eq!(s.time(), 0.0);
+    assert_eq!(s.processed_events().len(), 0);
+    assert_eq!(s.active_processes(), 6);
+    assert_eq!(s.resource(r).available(), Some(2));
+    assert_eq!(s.resource(r).queue_len(), 0);
+    assert_eq!(s.resource_holders(r), []);
+    assert_eq!(s.end_reason(), None);
+
+    s = s.run(EndCondition::NoEvents);
+    let first: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, y)| (e.time(), format!("{y:?}")))
+        .collect();
+    let requests = s.resource_stats(r).requests();
+    s.reset(Effect::Trace);
+    s = s.run(EndCondition::NoEvents);
+    let second: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, y)| (e.time(), format!("{y:?}")))
+        .collect();
+    assert_eq!(first, second);
+    assert_eq!(s.resource_stats(r).requests(), requests);
+    assert_eq!(s.resource(r).available(), Some(2));
+}
+
+#[test]
+fn reset_keeps_configuration() {
+    use crate::observer::{ResourceChange, SimulationObserver};
+    use crate::resources::ScheduledResource;
+    use crate::ResourceId;
+    use std::sync::{Arc, Mutex};
+
+    struct Shifts(Arc<Mutex<Vec<f64>>>);
+    impl SimulationObserver<Effect> for Shifts {
+        fn on_resource_state_change(&mut self, _: ResourceId, time: f64, change: &ResourceChange) {
+            if let ResourceChange::CapacityChanged { .. } = change {
+                self.0.lock().unwrap().push(time);
+            }
+        }
+    }
+
+    let mut s = Simulation::new();
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    s.add_observer(Box::new(Shifts(changes.clone())));
+    s.create_resource(Box::new(ScheduledResource::new(
+        SimpleResource::new(1),
+        vec![(1.0, 2.0), (3.0, 4.0)],
+    )));
+    let idle = s.register_spawner(Box::new(|_| {
+        Box::new(
+            #[coroutine]
+            |_| {
+                yield Effect::TimeOut(5.0);
+            },
+        )
+    }));
+    s.spawn_process(idle, 0.0, Effect::TimeOut(0.)).unwrap();
+    s.set_end_condition(EndCondition::Time(4.5));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    let old = s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run_to_end();
+    let end = s.time();
+    assert_eq!(s.end_reason(), Some(&EndReason::TimeReached));
+    assert_eq!(*changes.lock().unwrap(), [1.0, 2.0, 3.0, 4.0]);
+
+    changes.lock().unwrap().clear();
+    s.reset(Effect::Trace);
+    assert!(matches!(s.end_condition(), Some(EndCondition::Time(t)) if *t == 4.5));
+    // the ids of the events of the first run are not given out again
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    let event = s.schedule_event(0.5, p, Effect::TimeOut(0.)).unwrap();
+    assert!(event > old);
+    s.cancel_event(old);
+    s = s.run_to_end();
+    assert_eq!(s.time(), end);
+    assert_eq!(s.end_reason(), Some(&EndReason::TimeReached));
+    assert_eq!(*changes.lock().unwrap(), [1.0, 2.0, 3.0, 4.0]);
+    assert!(s
+        .processed_events()
+        .iter()
+        .any(|(e, _)| e.process() == p && e.time() == 0.5));
+}
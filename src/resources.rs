@@ -21,21 +21,184 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! The simulation process can request the resource yielding the `Request` effect with the ID.
 //!
 //! The `Resource` trait allow the implementation of custom resource types.
+//! The occupancy it reports can be inspected through the `ResourceInspect` trait with
+//! `Simulation::resource` and, by the processes, through a `ResourceSnapshot` with
+//! `SimContext::resource`.
 //! A `SimpleResource` struct provides a basic but useful implementation of the `Resource` trait.
-use crate::{Event, SimState};
+//! Its `QueueDiscipline` chooses which waiting request is served next, e.g. to compare the
+//! variance of the waiting times under FIFO and LIFO.
+//! `ScheduledResource` makes a resource available only in the intervals of a calendar.
+//! Both can be added as a `StandardResource`, whose methods are called without dynamic
+//! dispatch.
+//! Resources can be collected in a `ResourceGroup`, to analyze them together.
+//!
+//! Similarly, the `Store` trait models buffers where processes push into and pull from.
+//! `SimpleStore` only counts the pushes, while `ItemStore` moves actual items from the
+//! producers to the consumers, using the `Payload` trait implemented by the state.
+//! `FilterStore` additionally lets each consumer choose which items it accepts.
+//! When a `SimpleStore` is full, its `OverflowPolicy` tells whether producers wait for
+//! space, their items are dropped or their pushes are rejected.
+use crate::rng::{Philox4x32, StreamRng};
+use crate::rollback::SavedState;
+use crate::{Event, ProcessId, ResourceId, SimState};
 use std::collections::VecDeque;
 
-/// A simple resource that is allocated based on a first come first served policy.
+/// A simple resource that is allocated based on a first come first served policy, or on
+/// the `QueueDiscipline` it was created with.
 ///
-/// When there are no more instances of the resource available, the processes are enqueued in a
-/// FIFO and triggered as soon as an instance is released.
+/// When there are no more instances of the resource available, the processes are enqueued
+/// and triggered as soon as an instance is released. A process requesting several
+/// instances at once waits until all of them are available, and the processes behind it
+/// wait as well.
 ///
 /// If a resource gets more release then requests, it will panic.
 #[derive(Debug)]
 pub struct SimpleResource<T> {
     quantity: usize,
     available: usize,
-    queue: VecDeque<Event<T>>,
+    /// The requests waiting, with the number of units requested
+    queue: VecDeque<(Event<T>, usize)>,
+    discipline: QueueDiscipline<T>,
+}
+
+/// The order in which a `SimpleResource` serves the waiting requests.
+///
+/// The state yielded with the request is passed to the keys of `ShortestService` and
+/// `Priority`, that serve the waiting requests with equal keys in order of arrival.
+/// Service is never preempted.
+#[derive(Debug, Clone, Default)]
+pub enum QueueDiscipline<T> {
+    /// First come first served
+    #[default]
+    Fifo,
+    /// Last come first served
+    Lifo,
+    /// A waiting request chosen at random with the generator
+    Random(Philox4x32),
+    /// The request with the shortest service time declared by its state first
+    ShortestService(fn(&T) -> f64),
+    /// The request with the highest priority declared by its state first
+    Priority(fn(&T) -> i32),
+}
+
+impl<T> QueueDiscipline<T> {
+    /// Returns the position in `queue` of the request to serve next
+    fn next(&mut self, queue: &VecDeque<(Event<T>, usize)>) -> Option<usize> {
+        if queue.is_empty() {
+            return None;
+        }
+        let requests = queue.iter().map(|(e, _)| e.state()).enumerate();
+        match self {
+            QueueDiscipline::Fifo => Some(0),
+            QueueDiscipline::Lifo => Some(queue.len() - 1),
+            QueueDiscipline::Random(rng) => {
+                Some(((rng.next_f64() * queue.len() as f64) as usize).min(queue.len() - 1))
+            }
+            QueueDiscipline::ShortestService(service) => requests
+                .min_by(|(_, a), (_, b)| service(a).total_cmp(&service(b)))
+                .map(|(i, _)| i),
+            QueueDiscipline::Priority(priority) => requests
+                .min_by(|(_, a), (_, b)| priority(b).cmp(&priority(a)))
+                .map(|(i, _)| i),
+        }
+    }
+}
+
+/// What happened when a process released a resource.
+///
+/// A process can inspect it with `SimContext::release_outcome` after yielding `Effect::Release`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReleaseOutcome {
+    /// The unit returned available
+    Freed,
+    /// The unit was granted to the waiting process
+    Woke(ProcessId),
+    /// The process did not hold the units it released, that were left alone, see
+    /// `ReleasePolicy::Ignore`
+    NotHeld,
+}
+
+/// A resource added to a simulation
+#[cfg(not(feature = "send"))]
+pub type BoxedResource<T> = Box<dyn Resource<T>>;
+/// A resource added to a simulation
+#[cfg(feature = "send")]
+pub type BoxedResource<T> = Box<dyn Resource<T> + Send>;
+/// A store added to a simulation
+#[cfg(not(feature = "send"))]
+pub type BoxedStore<T> = Box<dyn Store<T>>;
+/// A store added to a simulation
+#[cfg(feature = "send")]
+pub type BoxedStore<T> = Box<dyn Store<T> + Send>;
+
+/// Builds the resources added while the simulation runs
+#[cfg(not(feature = "send"))]
+pub type ResourceFactory<T> = Box<dyn FnMut() -> BoxedResource<T>>;
+/// Builds the resources added while the simulation runs
+#[cfg(feature = "send")]
+pub type ResourceFactory<T> = Box<dyn FnMut() -> BoxedResource<T> + Send>;
+
+/// Builds the stores added while the simulation runs
+#[cfg(not(feature = "send"))]
+pub type StoreFactory<T> = Box<dyn FnMut() -> BoxedStore<T>>;
+/// Builds the stores added while the simulation runs
+#[cfg(feature = "send")]
+pub type StoreFactory<T> = Box<dyn FnMut() -> BoxedStore<T> + Send>;
+
+/// Read-only view of the occupancy of a resource.
+///
+/// Returned by `Simulation::resource`, so that the owner of the simulation and the
+/// predicates can inspect a resource without modifying it. It is implemented for every
+/// `Resource`, whose `capacity`, `available` and `queue_len` report the occupancy.
+pub trait ResourceInspect<T>: Resource<T> {
+    /// Returns the number of units held by the processes, if both the capacity and the
+    /// available units are known
+    fn in_use(&self) -> Option<usize> {
+        Some(self.capacity()?.saturating_sub(self.available()?))
+    }
+}
+
+impl<T, R: Resource<T> + ?Sized> ResourceInspect<T> for R {}
+
+/// The occupancy of a resource at a point in time, seen by the processes through
+/// `SimContext::resource`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResourceSnapshot {
+    capacity: Option<usize>,
+    available: Option<usize>,
+    queue_len: usize,
+}
+
+impl ResourceSnapshot {
+    /// Take a snapshot of `resource`
+    pub fn of<T>(resource: &(impl Resource<T> + ?Sized)) -> ResourceSnapshot {
+        ResourceSnapshot {
+            capacity: resource.capacity(),
+            available: resource.available(),
+            queue_len: resource.queue_len(),
+        }
+    }
+
+    /// Returns the capacity of the resource, see `Resource::capacity`
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Returns the available units of the resource, see `Resource::available`
+    pub fn available(&self) -> Option<usize> {
+        self.available
+    }
+
+    /// Returns the number of requests waiting for the resource
+    pub fn queue_len(&self) -> usize {
+        self.queue_len
+    }
+
+    /// Returns the number of units held by the processes, see `ResourceInspect::in_use`
+    pub fn in_use(&self) -> Option<usize> {
+        Some(self.capacity?.saturating_sub(self.available?))
+    }
 }
 
 /// The resource trait implemented by every Resource of the simulation
@@ -45,16 +208,117 @@ pub trait Resource<T> {
     /// It receives an event with current time, the `ProcessId` of the process requesting the
     /// resource and the state generated by the process (with the request effect).
     ///
-    /// It returns an optional `Event` that is added to the simulation.
-    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Option<Event<T>>;
+    /// It returns the events that are added to the simulation, one for each process that is
+    /// granted the resource, usually the requesting one if the resource is available.
+    /// The simulation keeps some bookkeeping in the events, so the event of the process
+    /// should be returned, possibly with a different time or state, rather than a new one.
+    /// A resource that refuses the request, e.g. because its queue is full, returns the
+    /// event marked with `Event::reject`.
+    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Vec<Event<T>>;
+
+    /// This method is called when a process requests `units` units of the resource at once,
+    /// with `Effect::RequestN`. The units must be granted together, returning the event of
+    /// the process once, like `allocate_or_enqueue`. They are released one at a time.
+    ///
+    /// It is only called with a number of units accepted by `supports_units`.
+    /// The default implementation forwards the request to `allocate_or_enqueue`.
+    fn allocate_n_or_enqueue(&mut self, event: Event<T>, units: usize) -> Vec<Event<T>> {
+        debug_assert!(self.supports_units(units));
+        self.allocate_or_enqueue(event)
+    }
+
+    /// Returns `true` if the resource can grant `units` units at once, with
+    /// `allocate_n_or_enqueue`. The simulation rejects the other requests with
+    /// `SimError::InvalidUnits`.
+    ///
+    /// The default implementation only supports single units.
+    fn supports_units(&self, units: usize) -> bool {
+        units == 1
+    }
 
     /// This method is called by the simulator when the resource is released.
     ///
     /// It will receive the release event with current simulation time, the id of the process
     /// that released the request and the state generated by the process (with the release effect)
     ///
-    /// If an optional `Event` is returned, it is scheduled to be simulated.
-    fn release_and_schedule_next(&mut self, event: Event<T>) -> Option<Event<T>>;
+    /// It returns the events of the waiting processes that are granted the resource, that
+    /// are scheduled to be simulated.
+    fn release_and_schedule_next(&mut self, event: Event<T>) -> Vec<Event<T>>;
+
+    /// Like `allocate_n_or_enqueue`, but pushes the events in `granted` instead of
+    /// returning them, so that the simulation can reuse the same vector for every request.
+    ///
+    /// The default implementation extends `granted` with the events returned by
+    /// `allocate_n_or_enqueue`.
+    fn allocate_n_or_enqueue_into(
+        &mut self,
+        event: Event<T>,
+        units: usize,
+        granted: &mut Vec<Event<T>>,
+    ) {
+        granted.extend(self.allocate_n_or_enqueue(event, units));
+    }
+
+    /// Like `release_and_schedule_next`, but pushes the events in `granted` instead of
+    /// returning them, see `allocate_n_or_enqueue_into`.
+    ///
+    /// The default implementation extends `granted` with the events returned by
+    /// `release_and_schedule_next`.
+    fn release_and_schedule_next_into(&mut self, event: Event<T>, granted: &mut Vec<Event<T>>) {
+        granted.extend(self.release_and_schedule_next(event));
+    }
+
+    /// Returns the number of requests waiting for the resource.
+    ///
+    /// It is used by the simulation to monitor the resource, e.g. to check soft limits.
+    /// The default implementation always returns 0.
+    fn queue_len(&self) -> usize {
+        0
+    }
+
+    /// This method is called by the simulator when a process waiting for the resource
+    /// must leave the queue, e.g. because it was interrupted or its request timed out.
+    ///
+    /// It returns the request event of the process, if it was found in the queue.
+    /// The default implementation does not support cancellation and always returns `None`.
+    fn cancel_request(&mut self, _process: ProcessId) -> Option<Event<T>> {
+        None
+    }
+
+    /// Returns the number of units of the resource, used to compute its utilization.
+    /// The default implementation returns `None`.
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the number of units that are not held by any process.
+    /// The default implementation returns `None`.
+    fn available(&self) -> Option<usize> {
+        None
+    }
+
+    /// Save the state of the resource, to be restored with `restore` when the simulation
+    /// is rolled back. See the [`rollback`](crate::rollback) module.
+    ///
+    /// The default implementation returns `None`, for resources that are built again in
+    /// the same state by the factory of the model.
+    fn snapshot(&self) -> Option<SavedState> {
+        None
+    }
+
+    /// Restore a state saved with `snapshot`.
+    /// The default implementation does nothing.
+    fn restore(&mut self, _state: &SavedState) {}
+
+    /// Returns the first change of the number of units that can be granted after `time`,
+    /// with the time of the change and the new number, e.g. at the opening or closing of a
+    /// shift. The simulation notifies the observers of each change with
+    /// `ResourceChange::CapacityChanged`.
+    ///
+    /// The default implementation returns `None`, for resources whose capacity is fixed.
+    fn next_capacity_change(&self, _time: f64) -> Option<(f64, usize)> {
+        None
+    }
 }
 
 /// A type of resource where processes can push into or pull from
@@ -65,7 +329,9 @@ pub trait Store<T> {
     /// resource and the state generated by the process (with the request effect).
     ///
     /// It receives a mutable reference to a vector of `Event`s to push the events that will
-    /// be added to the simulation.
+    /// be added to the simulation. The simulation keeps some bookkeeping in the events, so
+    /// each process should be resumed with its own event, possibly with a different time
+    /// or state, rather than with a new one.
     fn push_or_enqueue_and_schedule_next(
         &mut self,
         event: Event<T>,
@@ -84,48 +350,568 @@ pub trait Store<T> {
         event: Event<T>,
         next_events: &mut Vec<Event<T>>,
     );
+
+    /// Returns the number of items currently in the store.
+    ///
+    /// It is used by the simulation to monitor the store, e.g. to check soft limits.
+    /// The default implementation always returns 0.
+    fn level(&self) -> usize {
+        0
+    }
+
+    /// Returns the number of items discarded because the store was full.
+    /// The default implementation always returns 0.
+    fn dropped(&self) -> usize {
+        0
+    }
+
+    /// This method is called by the simulator when a process waiting to push into or pull
+    /// out of the store must leave the queue, e.g. because it was interrupted.
+    ///
+    /// It returns the event of the process, if it was found in a queue.
+    /// The default implementation does not support cancellation and always returns `None`.
+    fn cancel(&mut self, _process: ProcessId) -> Option<Event<T>> {
+        None
+    }
+
+    /// This method is called by `Simulation::reset` to empty the store, after the waiting
+    /// processes left the queues.
+    ///
+    /// The default implementation does nothing, so the store keeps its items.
+    fn clear(&mut self) {}
 }
 
 impl<T> Resource<T> for SimpleResource<T> {
-    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Option<Event<T>> {
-        if self.available > 0 {
-            self.available -= 1;
-            Some(event)
+    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        self.allocate_n_or_enqueue(event, 1)
+    }
+    fn supports_units(&self, units: usize) -> bool {
+        units <= self.quantity
+    }
+    fn allocate_n_or_enqueue(&mut self, event: Event<T>, units: usize) -> Vec<Event<T>> {
+        let mut granted = Vec::new();
+        self.allocate_n_or_enqueue_into(event, units, &mut granted);
+        granted
+    }
+    fn allocate_n_or_enqueue_into(
+        &mut self,
+        event: Event<T>,
+        units: usize,
+        granted: &mut Vec<Event<T>>,
+    ) {
+        if self.queue.is_empty() && self.available >= units {
+            self.available -= units;
+            granted.push(event);
         } else {
-            self.queue.push_back(event);
-            None
+            self.queue.push_back((event, units));
         }
     }
-    fn release_and_schedule_next(&mut self, event: Event<T>) -> Option<Event<T>> {
-        match self.queue.pop_front() {
-            Some(mut request_event) => {
-                // some is waiting for the request, schedule it! and schedule the self
-                request_event.set_time(event.time());
-                Some(request_event)
-            }
-            None => {
-                // no one is waiting for the resorce, restore the availiable and return self
-                assert!(self.available < self.quantity);
-                self.available += 1;
-                None
+    fn release_and_schedule_next(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        let mut granted = Vec::new();
+        self.release_and_schedule_next_into(event, &mut granted);
+        granted
+    }
+    fn release_and_schedule_next_into(&mut self, event: Event<T>, granted: &mut Vec<Event<T>>) {
+        assert!(self.available < self.quantity);
+        self.available += 1;
+        // schedule the requests that can be satisfied, in the order of the discipline
+        while let Some(i) = self.discipline.next(&self.queue) {
+            if self.queue[i].1 > self.available {
+                break;
             }
+            let (mut request_event, units) = self.queue.remove(i).unwrap();
+            self.available -= units;
+            request_event.set_time(event.time());
+            granted.push(request_event);
         }
     }
+    fn cancel_request(&mut self, process: ProcessId) -> Option<Event<T>> {
+        let i = self
+            .queue
+            .iter()
+            .position(|(e, _)| e.process() == process)?;
+        self.queue.remove(i).map(|(e, _)| e)
+    }
+    fn capacity(&self) -> Option<usize> {
+        Some(self.quantity)
+    }
+    fn available(&self) -> Option<usize> {
+        Some(self.available)
+    }
+    fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
 }
 
 impl<T> SimpleResource<T> {
     /// Create a simple resource of which `quantity` instances are available
     pub fn new(quantity: usize) -> SimpleResource<T> {
+        SimpleResource::with_discipline(quantity, QueueDiscipline::Fifo)
+    }
+
+    /// Create a simple resource of which `quantity` instances are available, that serves
+    /// the waiting requests in the order of `discipline`
+    pub fn with_discipline(quantity: usize, discipline: QueueDiscipline<T>) -> SimpleResource<T> {
         SimpleResource {
             quantity,
             available: quantity,
             queue: VecDeque::new(),
+            discipline,
+        }
+    }
+}
+/// A resource that is available only in the intervals of a calendar, e.g. working shifts.
+///
+/// The calendar is a list of `(open, close)` intervals. The units of the wrapped resource
+/// are granted only while the resource is open: a process granted a unit outside the
+/// calendar is resumed at the next opening, and the unit is in use from then on. Holders
+/// are not preempted when the resource closes. The units granted after the end of the
+/// calendar are given back at once, and their requests wait forever.
+///
+/// The observers of the simulation are notified with `ResourceChange::CapacityChanged`
+/// when the resource opens and closes.
+pub struct ScheduledResource<T, R> {
+    resource: R,
+    calendar: Vec<(f64, f64)>,
+    /// Requests that can never be granted, because the calendar ended
+    stranded: VecDeque<Event<T>>,
+    /// The units of the requests of multiple units, until they are granted
+    units: Vec<(ProcessId, usize)>,
+}
+
+impl<T: Clone, R: Resource<T>> ScheduledResource<T, R> {
+    /// Wrap `resource`, making it available in the intervals of `calendar`.
+    ///
+    /// # Panics
+    /// If the intervals are not sorted and disjoint, or if an interval closes before
+    /// opening.
+    pub fn new(resource: R, calendar: Vec<(f64, f64)>) -> ScheduledResource<T, R> {
+        assert!(
+            calendar.iter().all(|(open, close)| open <= close)
+                && calendar.windows(2).all(|w| w[0].1 <= w[1].0),
+            "ERROR. The intervals of the calendar must be sorted and disjoint."
+        );
+        ScheduledResource {
+            resource,
+            calendar,
+            stranded: VecDeque::new(),
+            units: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the resource is open at `time`
+    pub fn is_open(&self, time: f64) -> bool {
+        self.calendar
+            .iter()
+            .any(|&(open, close)| open <= time && time < close)
+    }
+
+    /// Returns the first time, not before `time`, when the resource is open
+    pub fn next_opening(&self, time: f64) -> Option<f64> {
+        self.calendar
+            .iter()
+            .find(|&&(open, close)| time < close && open < close)
+            .map(|&(open, _)| open.max(time))
+    }
+
+    /// Defer the grants in `granted`, from the index `from`, to the next opening, giving
+    /// back the units granted after the end of the calendar.
+    fn defer(&mut self, granted: &mut Vec<Event<T>>, from: usize) {
+        let mut i = from;
+        while i < granted.len() {
+            if granted[i].is_rejected() {
+                i += 1;
+                continue;
+            }
+            match self.next_opening(granted[i].time()) {
+                Some(time) => {
+                    self.take_units(granted[i].process());
+                    granted[i].set_time(time);
+                    i += 1;
+                }
+                None => {
+                    let e = granted.remove(i);
+                    let end = granted.len();
+                    for _ in 0..self.take_units(e.process()) {
+                        let next = granted.len();
+                        self.resource
+                            .release_and_schedule_next_into(e.clone(), granted);
+                        self.defer(granted, next);
+                    }
+                    // the grants of the units given back take the place of the request
+                    let added = granted.len() - end;
+                    granted[i..].rotate_right(added);
+                    i += added;
+                    self.stranded.push_back(e);
+                }
+            }
+        }
+    }
+
+    /// Returns the number of units requested by `process`, forgetting it
+    fn take_units(&mut self, process: ProcessId) -> usize {
+        match self.units.iter().position(|(p, _)| *p == process) {
+            Some(i) => self.units.swap_remove(i).1,
+            None => 1,
+        }
+    }
+}
+
+impl<T: Clone, R: Resource<T>> Resource<T> for ScheduledResource<T, R> {
+    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        let mut granted = self.resource.allocate_or_enqueue(event);
+        self.defer(&mut granted, 0);
+        granted
+    }
+    fn allocate_n_or_enqueue(&mut self, event: Event<T>, units: usize) -> Vec<Event<T>> {
+        let mut granted = Vec::new();
+        self.allocate_n_or_enqueue_into(event, units, &mut granted);
+        granted
+    }
+    fn allocate_n_or_enqueue_into(
+        &mut self,
+        event: Event<T>,
+        units: usize,
+        granted: &mut Vec<Event<T>>,
+    ) {
+        if units != 1 {
+            self.units.push((event.process(), units));
+        }
+        let from = granted.len();
+        self.resource
+            .allocate_n_or_enqueue_into(event, units, granted);
+        self.defer(granted, from);
+    }
+    fn release_and_schedule_next(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        let mut granted = Vec::new();
+        self.release_and_schedule_next_into(event, &mut granted);
+        granted
+    }
+    fn release_and_schedule_next_into(&mut self, event: Event<T>, granted: &mut Vec<Event<T>>) {
+        let from = granted.len();
+        self.resource.release_and_schedule_next_into(event, granted);
+        self.defer(granted, from);
+    }
+    fn cancel_request(&mut self, process: ProcessId) -> Option<Event<T>> {
+        let cancelled = self
+            .resource
+            .cancel_request(process)
+            .or_else(|| remove_process(&mut self.stranded, process));
+        if cancelled.is_some() {
+            self.take_units(process);
+        }
+        cancelled
+    }
+    fn supports_units(&self, units: usize) -> bool {
+        self.resource.supports_units(units)
+    }
+    fn snapshot(&self) -> Option<SavedState> {
+        self.resource.snapshot()
+    }
+    fn restore(&mut self, state: &SavedState) {
+        self.resource.restore(state)
+    }
+    fn next_capacity_change(&self, time: f64) -> Option<(f64, usize)> {
+        let capacity = self.resource.capacity()?;
+        self.calendar
+            .iter()
+            .flat_map(|&(open, close)| [open, close])
+            .filter(|t| *t > time)
+            .find(|&t| {
+                let before = self
+                    .calendar
+                    .iter()
+                    .any(|&(open, close)| open < t && t <= close);
+                before != self.is_open(t)
+            })
+            .map(|t| (t, if self.is_open(t) { capacity } else { 0 }))
+    }
+    fn capacity(&self) -> Option<usize> {
+        self.resource.capacity()
+    }
+    fn available(&self) -> Option<usize> {
+        self.resource.available()
+    }
+    fn queue_len(&self) -> usize {
+        self.resource.queue_len() + self.stranded.len()
+    }
+}
+
+/// One of the resources of this module, added to a simulation without a box with
+/// `Simulation::create_standard_resource`.
+///
+/// The simulation calls the methods of a boxed resource through a virtual table, while
+/// the ones of a standard resource are selected by a `match` and can be inlined, which
+/// saves some time in models whose resources are requested very often.
+pub enum StandardResource<T> {
+    Simple(SimpleResource<T>),
+    Scheduled(ScheduledResource<T, SimpleResource<T>>),
+}
+
+impl<T> From<SimpleResource<T>> for StandardResource<T> {
+    fn from(resource: SimpleResource<T>) -> Self {
+        StandardResource::Simple(resource)
+    }
+}
+
+impl<T> From<ScheduledResource<T, SimpleResource<T>>> for StandardResource<T> {
+    fn from(resource: ScheduledResource<T, SimpleResource<T>>) -> Self {
+        StandardResource::Scheduled(resource)
+    }
+}
+
+impl<T: Clone> Resource<T> for StandardResource<T> {
+    #[inline]
+    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        match self {
+            StandardResource::Simple(r) => r.allocate_or_enqueue(event),
+            StandardResource::Scheduled(r) => r.allocate_or_enqueue(event),
+        }
+    }
+    #[inline]
+    fn allocate_n_or_enqueue(&mut self, event: Event<T>, units: usize) -> Vec<Event<T>> {
+        match self {
+            StandardResource::Simple(r) => r.allocate_n_or_enqueue(event, units),
+            StandardResource::Scheduled(r) => r.allocate_n_or_enqueue(event, units),
+        }
+    }
+    #[inline]
+    fn release_and_schedule_next(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        match self {
+            StandardResource::Simple(r) => r.release_and_schedule_next(event),
+            StandardResource::Scheduled(r) => r.release_and_schedule_next(event),
+        }
+    }
+    #[inline]
+    fn allocate_n_or_enqueue_into(
+        &mut self,
+        event: Event<T>,
+        units: usize,
+        granted: &mut Vec<Event<T>>,
+    ) {
+        match self {
+            StandardResource::Simple(r) => r.allocate_n_or_enqueue_into(event, units, granted),
+            StandardResource::Scheduled(r) => r.allocate_n_or_enqueue_into(event, units, granted),
+        }
+    }
+    #[inline]
+    fn release_and_schedule_next_into(&mut self, event: Event<T>, granted: &mut Vec<Event<T>>) {
+        match self {
+            StandardResource::Simple(r) => r.release_and_schedule_next_into(event, granted),
+            StandardResource::Scheduled(r) => r.release_and_schedule_next_into(event, granted),
+        }
+    }
+    #[inline]
+    fn cancel_request(&mut self, process: ProcessId) -> Option<Event<T>> {
+        match self {
+            StandardResource::Simple(r) => r.cancel_request(process),
+            StandardResource::Scheduled(r) => r.cancel_request(process),
+        }
+    }
+    fn snapshot(&self) -> Option<SavedState> {
+        match self {
+            StandardResource::Simple(r) => r.snapshot(),
+            StandardResource::Scheduled(r) => r.snapshot(),
+        }
+    }
+    fn restore(&mut self, state: &SavedState) {
+        match self {
+            StandardResource::Simple(r) => r.restore(state),
+            StandardResource::Scheduled(r) => r.restore(state),
+        }
+    }
+    fn supports_units(&self, units: usize) -> bool {
+        match self {
+            StandardResource::Simple(r) => r.supports_units(units),
+            StandardResource::Scheduled(r) => r.supports_units(units),
+        }
+    }
+    fn next_capacity_change(&self, time: f64) -> Option<(f64, usize)> {
+        match self {
+            StandardResource::Simple(r) => r.next_capacity_change(time),
+            StandardResource::Scheduled(r) => r.next_capacity_change(time),
+        }
+    }
+    fn capacity(&self) -> Option<usize> {
+        match self {
+            StandardResource::Simple(r) => r.capacity(),
+            StandardResource::Scheduled(r) => r.capacity(),
+        }
+    }
+    fn available(&self) -> Option<usize> {
+        match self {
+            StandardResource::Simple(r) => r.available(),
+            StandardResource::Scheduled(r) => r.available(),
+        }
+    }
+    fn queue_len(&self) -> usize {
+        match self {
+            StandardResource::Simple(r) => r.queue_len(),
+            StandardResource::Scheduled(r) => r.queue_len(),
+        }
+    }
+}
+
+/// A resource of a simulation: a standard one, or a custom one in a box.
+pub(crate) enum ResourceEntry<T> {
+    Standard(StandardResource<T>),
+    Custom(BoxedResource<T>),
+}
+
+impl<T: Clone> Resource<T> for ResourceEntry<T> {
+    #[inline]
+    fn allocate_or_enqueue(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        match self {
+            ResourceEntry::Standard(r) => r.allocate_or_enqueue(event),
+            ResourceEntry::Custom(r) => r.allocate_or_enqueue(event),
+        }
+    }
+    #[inline]
+    fn allocate_n_or_enqueue(&mut self, event: Event<T>, units: usize) -> Vec<Event<T>> {
+        match self {
+            ResourceEntry::Standard(r) => r.allocate_n_or_enqueue(event, units),
+            ResourceEntry::Custom(r) => r.allocate_n_or_enqueue(event, units),
+        }
+    }
+    #[inline]
+    fn release_and_schedule_next(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        match self {
+            ResourceEntry::Standard(r) => r.release_and_schedule_next(event),
+            ResourceEntry::Custom(r) => r.release_and_schedule_next(event),
+        }
+    }
+    #[inline]
+    fn allocate_n_or_enqueue_into(
+        &mut self,
+        event: Event<T>,
+        units: usize,
+        granted: &mut Vec<Event<T>>,
+    ) {
+        match self {
+            ResourceEntry::Standard(r) => r.allocate_n_or_enqueue_into(event, units, granted),
+            ResourceEntry::Custom(r) => r.allocate_n_or_enqueue_into(event, units, granted),
+        }
+    }
+    #[inline]
+    fn release_and_schedule_next_into(&mut self, event: Event<T>, granted: &mut Vec<Event<T>>) {
+        match self {
+            ResourceEntry::Standard(r) => r.release_and_schedule_next_into(event, granted),
+            ResourceEntry::Custom(r) => r.release_and_schedule_next_into(event, granted),
+        }
+    }
+    #[inline]
+    fn cancel_request(&mut self, process: ProcessId) -> Option<Event<T>> {
+        match self {
+            ResourceEntry::Standard(r) => r.cancel_request(process),
+            ResourceEntry::Custom(r) => r.cancel_request(process),
+        }
+    }
+    fn snapshot(&self) -> Option<SavedState> {
+        match self {
+            ResourceEntry::Standard(r) => r.snapshot(),
+            ResourceEntry::Custom(r) => r.snapshot(),
+        }
+    }
+    fn restore(&mut self, state: &SavedState) {
+        match self {
+            ResourceEntry::Standard(r) => r.restore(state),
+            ResourceEntry::Custom(r) => r.restore(state),
+        }
+    }
+    fn supports_units(&self, units: usize) -> bool {
+        match self {
+            ResourceEntry::Standard(r) => r.supports_units(units),
+            ResourceEntry::Custom(r) => r.supports_units(units),
+        }
+    }
+    fn next_capacity_change(&self, time: f64) -> Option<(f64, usize)> {
+        match self {
+            ResourceEntry::Standard(r) => r.next_capacity_change(time),
+            ResourceEntry::Custom(r) => r.next_capacity_change(time),
+        }
+    }
+    fn capacity(&self) -> Option<usize> {
+        match self {
+            ResourceEntry::Standard(r) => r.capacity(),
+            ResourceEntry::Custom(r) => r.capacity(),
+        }
+    }
+    fn available(&self) -> Option<usize> {
+        match self {
+            ResourceEntry::Standard(r) => r.available(),
+            ResourceEntry::Custom(r) => r.available(),
+        }
+    }
+    fn queue_len(&self) -> usize {
+        match self {
+            ResourceEntry::Standard(r) => r.queue_len(),
+            ResourceEntry::Custom(r) => r.queue_len(),
         }
     }
 }
+
+/// A named group of resources, whose statistics are pooled.
+///
+/// Groups are created with `Simulation::create_resource_group`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceGroup {
+    name: String,
+    members: Vec<ResourceId>,
+}
+
+impl ResourceGroup {
+    pub(crate) fn new(name: String, members: Vec<ResourceId>) -> ResourceGroup {
+        ResourceGroup { name, members }
+    }
+
+    /// Returns the name of the group
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the resources in the group
+    pub fn members(&self) -> &[ResourceId] {
+        self.members.as_slice()
+    }
+}
+
+/// A state that can carry an item of type `I` into and out of a store.
+pub trait Payload<I> {
+    /// Take the item out of the state yielded by a process that pushes into a store.
+    fn take_item(&mut self) -> Option<I>;
+    /// Put the item into the state that resumes a process that pulled it out of a store.
+    fn put_item(&mut self, item: I);
+}
+
+/// A state that selects the items it accepts when pulling out of a `FilterStore`.
+pub trait ItemFilter<I> {
+    /// Returns `true` if the process that yielded this state accepts `item`.
+    fn accepts(&self, item: &I) -> bool;
+}
+
+/// What a `SimpleStore` does when a process pushes into it while it is full.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// The producer waits until there is space
+    #[default]
+    Block,
+    /// The pushed item is discarded and the producer is resumed immediately
+    DropNewest,
+    /// The oldest item in the store is discarded to make space, and the producer is resumed
+    /// immediately
+    DropOldest,
+    /// The push is rejected: the producer is resumed immediately with `Outcome::Full`
+    Reject,
+}
+
 /// a class that implement waiting on both request and release
+///
+/// When the store is full, producers wait for space by default, or see their items dropped
+/// or rejected, depending on its `OverflowPolicy`.
 pub struct SimpleStore<T> {
     capacity: usize,
+    policy: OverflowPolicy,
+    dropped: usize,
     send_waiting_queue: VecDeque<Event<T>>,
     recv_waiting_queue: VecDeque<Event<T>>,
     value_queue: VecDeque<Event<T>>,
@@ -136,12 +922,11 @@ impl<T: SimState + Clone> Store<T> for SimpleStore<T> {
         event: Event<T>,
         next_events: &mut Vec<Event<T>>,
     ) {
-        if let Some(recv_waiting) = self.recv_waiting_queue.pop_front() {
+        if let Some(mut recv_event) = self.recv_waiting_queue.pop_front() {
             // activate both processes
-            // 1. activate the recv_waiting process
-            let recv_id = recv_waiting.process();
-            let mut recv_event = event.clone();
-            recv_event.set_process(recv_id);
+            // 1. activate the recv_waiting process, passing it the state of the sender
+            recv_event.set_state(event.state().clone());
+            recv_event.set_time(event.time());
             next_events.push(recv_event);
 
             // 2. activate the send process
@@ -158,24 +943,39 @@ impl<T: SimState + Clone> Store<T> for SimpleStore<T> {
                 next_events.push(event);
             } else {
                 // queue is full
-                self.send_waiting_queue.push_back(event);
+                match self.policy {
+                    OverflowPolicy::Block => self.send_waiting_queue.push_back(event),
+                    OverflowPolicy::DropOldest if self.capacity > 0 => {
+                        self.value_queue.pop_front();
+                        self.dropped += 1;
+                        self.value_queue.push_back(event.clone());
+                        next_events.push(event);
+                    }
+                    OverflowPolicy::DropNewest | OverflowPolicy::DropOldest => {
+                        self.dropped += 1;
+                        next_events.push(event);
+                    }
+                    OverflowPolicy::Reject => {
+                        let mut event = event;
+                        event.reject();
+                        next_events.push(event);
+                    }
+                }
             }
         }
     }
 
     fn pull_or_enqueue_and_schedule_next(
         &mut self,
-        event: Event<T>,
+        mut event: Event<T>,
         next_events: &mut Vec<Event<T>>,
     ) {
         let current_time = event.time();
-        let current_id = event.process();
 
-        if let Some(mut value) = self.value_queue.pop_front() {
+        if let Some(value) = self.value_queue.pop_front() {
             // At least a value is present, use it to wake up the receiver immediately
-            value.set_process(current_id);
-            value.set_time(current_time);
-            next_events.push(value);
+            event.set_state(value.state().clone());
+            next_events.push(event);
 
             if let Some(mut waiting) = self.send_waiting_queue.pop_front() {
                 // There are waiting senders. Need to do 2 things:
@@ -194,14 +994,305 @@ impl<T: SimState + Clone> Store<T> for SimpleStore<T> {
             self.recv_waiting_queue.push_back(event);
         }
     }
+
+    fn level(&self) -> usize {
+        self.value_queue.len()
+    }
+
+    fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn cancel(&mut self, process: ProcessId) -> Option<Event<T>> {
+        remove_process(&mut self.recv_waiting_queue, process)
+            .or_else(|| remove_process(&mut self.send_waiting_queue, process))
+    }
+
+    fn clear(&mut self) {
+        self.value_queue.clear();
+        self.send_waiting_queue.clear();
+        self.recv_waiting_queue.clear();
+        self.dropped = 0;
+    }
 }
 impl<T> SimpleStore<T> {
     pub fn new(capacity: usize) -> Self {
+        SimpleStore::with_overflow_policy(capacity, OverflowPolicy::Block)
+    }
+
+    /// Create a store that can hold up to `capacity` items, that applies `policy` when it
+    /// is full
+    pub fn with_overflow_policy(capacity: usize, policy: OverflowPolicy) -> Self {
         SimpleStore {
             capacity,
+            policy,
+            dropped: 0,
             send_waiting_queue: VecDeque::default(),
             recv_waiting_queue: VecDeque::default(),
             value_queue: VecDeque::default(),
         }
     }
+
+    /// Create a store without a limit on the number of items, where producers never wait
+    pub fn unbounded() -> Self {
+        SimpleStore::new(usize::MAX)
+    }
+}
+
+/// A store of items of type `I`, with a fixed capacity.
+///
+/// When a process pushes into the store, the item is taken out of its state with
+/// `Payload::take_item`. When a process pulls out of the store, it is resumed with its own
+/// state, where the first item available is put with `Payload::put_item`, so that it can be
+/// retrieved through `SimContext::state()`.
+///
+/// Producers wait when the store is full and consumers wait when it is empty, both in FIFO
+/// order. Pushing a state that does not carry an item panics.
+pub struct ItemStore<T, I> {
+    capacity: usize,
+    items: VecDeque<I>,
+    send_waiting_queue: VecDeque<(Event<T>, I)>,
+    recv_waiting_queue: VecDeque<Event<T>>,
+}
+
+impl<T: Payload<I>, I> Store<T> for ItemStore<T, I> {
+    fn push_or_enqueue_and_schedule_next(
+        &mut self,
+        mut event: Event<T>,
+        next_events: &mut Vec<Event<T>>,
+    ) {
+        let item = event
+            .state_mut()
+            .take_item()
+            .expect("ERROR. Pushed a state without an item into an ItemStore.");
+        if let Some(mut recv_waiting) = self.recv_waiting_queue.pop_front() {
+            // hand the item to the first waiting consumer and resume both
+            recv_waiting.state_mut().put_item(item);
+            recv_waiting.set_time(event.time());
+            next_events.push(recv_waiting);
+            next_events.push(event);
+        } else if self.items.len() < self.capacity {
+            self.items.push_back(item);
+            next_events.push(event);
+        } else {
+            self.send_waiting_queue.push_back((event, item));
+        }
+    }
+
+    fn pull_or_enqueue_and_schedule_next(
+        &mut self,
+        mut event: Event<T>,
+        next_events: &mut Vec<Event<T>>,
+    ) {
+        let item = match self.items.pop_front() {
+            Some(item) => {
+                // a slot is free, let the first waiting producer in
+                if let Some((mut waiting, waiting_item)) = self.send_waiting_queue.pop_front() {
+                    self.items.push_back(waiting_item);
+                    waiting.set_time(event.time());
+                    next_events.push(waiting);
+                }
+                item
+            }
+            None => match self.send_waiting_queue.pop_front() {
+                // a store with no capacity passes the items from hand to hand
+                Some((mut waiting, item)) => {
+                    waiting.set_time(event.time());
+                    next_events.push(waiting);
+                    item
+                }
+                None => {
+                    self.recv_waiting_queue.push_back(event);
+                    return;
+                }
+            },
+        };
+        event.state_mut().put_item(item);
+        next_events.push(event);
+    }
+
+    fn level(&self) -> usize {
+        self.items.len()
+    }
+
+    fn cancel(&mut self, process: ProcessId) -> Option<Event<T>> {
+        remove_process(&mut self.recv_waiting_queue, process).or_else(|| {
+            // the item of the producer is discarded
+            let i = self
+                .send_waiting_queue
+                .iter()
+                .position(|(e, _)| e.process() == process)?;
+            self.send_waiting_queue.remove(i).map(|(e, _)| e)
+        })
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.send_waiting_queue.clear();
+        self.recv_waiting_queue.clear();
+    }
+}
+
+impl<T, I> ItemStore<T, I> {
+    /// Create a store that can hold up to `capacity` items
+    pub fn new(capacity: usize) -> Self {
+        ItemStore {
+            capacity,
+            items: VecDeque::default(),
+            send_waiting_queue: VecDeque::default(),
+            recv_waiting_queue: VecDeque::default(),
+        }
+    }
+
+    /// Returns the items currently in the store, in the order they will be pulled
+    pub fn items(&self) -> impl Iterator<Item = &I> {
+        self.items.iter()
+    }
+}
+
+/// A store of items of type `I` where consumers only pull the items they accept.
+///
+/// It works like `ItemStore`, but when a process pulls out of the store, it receives the
+/// first item for which its state returns `true` to `ItemFilter::accepts`, taking it from
+/// a producer waiting for space if there is none in the store.
+/// If there is no such item, the process waits until a matching item is pushed, while other
+/// consumers may be served before it.
+pub struct FilterStore<T, I> {
+    capacity: usize,
+    items: VecDeque<I>,
+    send_waiting_queue: VecDeque<(Event<T>, I)>,
+    recv_waiting_queue: VecDeque<Event<T>>,
+}
+
+impl<T: Payload<I> + ItemFilter<I>, I> FilterStore<T, I> {
+    /// Give `item` to the first waiting consumer that accepts it.
+    ///
+    /// Returns the item back if no one accepts it.
+    fn offer(&mut self, item: I, time: f64, next_events: &mut Vec<Event<T>>) -> Option<I> {
+        match self
+            .recv_waiting_queue
+            .iter()
+            .position(|e| e.state().accepts(&item))
+        {
+            Some(i) => {
+                let mut consumer = self.recv_waiting_queue.remove(i).unwrap();
+                consumer.state_mut().put_item(item);
+                consumer.set_time(time);
+                next_events.push(consumer);
+                None
+            }
+            None => Some(item),
+        }
+    }
+
+    /// Let waiting producers in while there is room for their items.
+    fn admit_waiting_senders(&mut self, time: f64, next_events: &mut Vec<Event<T>>) {
+        while self.items.len() < self.capacity {
+            let Some((mut sender, item)) = self.send_waiting_queue.pop_front() else {
+                break;
+            };
+            if let Some(item) = self.offer(item, time, next_events) {
+                self.items.push_back(item);
+            }
+            sender.set_time(time);
+            next_events.push(sender);
+        }
+    }
+}
+
+impl<T: Payload<I> + ItemFilter<I>, I> Store<T> for FilterStore<T, I> {
+    fn push_or_enqueue_and_schedule_next(
+        &mut self,
+        mut event: Event<T>,
+        next_events: &mut Vec<Event<T>>,
+    ) {
+        let item = event
+            .state_mut()
+            .take_item()
+            .expect("ERROR. Pushed a state without an item into a FilterStore.");
+        match self.offer(item, event.time(), next_events) {
+            None => next_events.push(event),
+            Some(item) if self.items.len() < self.capacity => {
+                self.items.push_back(item);
+                next_events.push(event);
+            }
+            Some(item) => self.send_waiting_queue.push_back((event, item)),
+        }
+    }
+
+    fn pull_or_enqueue_and_schedule_next(
+        &mut self,
+        mut event: Event<T>,
+        next_events: &mut Vec<Event<T>>,
+    ) {
+        match self.items.iter().position(|i| event.state().accepts(i)) {
+            Some(i) => {
+                let item = self.items.remove(i).unwrap();
+                let time = event.time();
+                event.state_mut().put_item(item);
+                next_events.push(event);
+                self.admit_waiting_senders(time, next_events);
+            }
+            None => match self
+                .send_waiting_queue
+                .iter()
+                .position(|(_, item)| event.state().accepts(item))
+            {
+                // the item is held by a producer waiting for space, e.g. in a store with
+                // no capacity: take it from its hands and resume both
+                Some(i) => {
+                    let (mut sender, item) = self.send_waiting_queue.remove(i).unwrap();
+                    sender.set_time(event.time());
+                    event.state_mut().put_item(item);
+                    next_events.push(event);
+                    next_events.push(sender);
+                }
+                None => self.recv_waiting_queue.push_back(event),
+            },
+        }
+    }
+
+    fn level(&self) -> usize {
+        self.items.len()
+    }
+
+    fn cancel(&mut self, process: ProcessId) -> Option<Event<T>> {
+        remove_process(&mut self.recv_waiting_queue, process).or_else(|| {
+            // the item of the producer is discarded
+            let i = self
+                .send_waiting_queue
+                .iter()
+                .position(|(e, _)| e.process() == process)?;
+            self.send_waiting_queue.remove(i).map(|(e, _)| e)
+        })
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.send_waiting_queue.clear();
+        self.recv_waiting_queue.clear();
+    }
+}
+
+impl<T, I> FilterStore<T, I> {
+    /// Create a store that can hold up to `capacity` items
+    pub fn new(capacity: usize) -> Self {
+        FilterStore {
+            capacity,
+            items: VecDeque::default(),
+            send_waiting_queue: VecDeque::default(),
+            recv_waiting_queue: VecDeque::default(),
+        }
+    }
+
+    /// Returns the items currently in the store
+    pub fn items(&self) -> impl Iterator<Item = &I> {
+        self.items.iter()
+    }
+}
+
+/// Remove the event of `process` from a queue.
+fn remove_process<T>(queue: &mut VecDeque<Event<T>>, process: ProcessId) -> Option<Event<T>> {
+    let i = queue.iter().position(|e| e.process() == process)?;
+    queue.remove(i)
 }
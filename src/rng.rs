@@ -0,0 +1,319 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Random number engines for reproducible simulations.
+//!
+//! A model usually needs many independent streams of random numbers, e.g. one for each
+//! process, and repeats the same run several times with different replications.
+//! Seeding a generator for each of them from another generator makes the streams depend
+//! on the order in which they are created, which is not deterministic when replications
+//! run in parallel.
+//!
+//! Counter-based generators, like `Philox4x32` and `Threefry2x64`, compute the n-th number
+//! of a stream directly from a key and a counter. The streams implemented here are
+//! identified by a `(seed, replication, stream)` triple: the same triple always gives the
+//! same sequence, wherever and whenever it is created, and different triples give
+//! independent sequences.
+//!
+//! A simulation created with `Simulation::with_seed` gives each process its own stream of
+//! `Philox4x32` numbers, accessible with `SimContext::rng`.
+//!
+//! Engines implement the `StreamRng` trait, so that models can be generic over them.
+//! With the `rand_core` feature, they also implement `rand_core::RngCore` and
+//! `rand_core::SeedableRng`, and can be used with the distributions of the `rand` ecosystem.
+//!
+//! ```
+//! use desim::rng::{Philox4x32, StreamRng, Streams};
+//!
+//! let streams = Streams::<Philox4x32>::new(42, 0);
+//! let mut a = streams.stream(1);
+//! let mut b = streams.stream(1);
+//! assert_eq!(a.next_u64(), b.next_u64());
+//! assert!(streams.stream(2).next_f64() < 1.0);
+//! ```
+use std::marker::PhantomData;
+
+/// A generator of random numbers whose streams are indexed by seed, replication and stream.
+pub trait StreamRng {
+    /// Create the generator of `stream` in `replication` of the experiment seeded by `seed`.
+    fn from_stream(seed: u64, replication: u32, stream: u32) -> Self;
+
+    /// Returns the next random `u32`
+    fn next_u32(&mut self) -> u32;
+
+    /// Returns the next random `u64`
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a random `f64` uniformly distributed in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Fill `dest` with random bytes
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// Creates the streams of a replication of an experiment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Streams<R> {
+    seed: u64,
+    replication: u32,
+    _rng: PhantomData<R>,
+}
+
+impl<R: StreamRng> Streams<R> {
+    /// Create the streams of `replication` of the experiment seeded by `seed`.
+    pub fn new(seed: u64, replication: u32) -> Streams<R> {
+        Streams {
+            seed,
+            replication,
+            _rng: PhantomData,
+        }
+    }
+
+    /// Returns the seed of the experiment
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the replication
+    pub fn replication(&self) -> u32 {
+        self.replication
+    }
+
+    /// Create the generator of stream `stream`, e.g. the id of a process.
+    pub fn stream(&self, stream: u32) -> R {
+        R::from_stream(self.seed, self.replication, stream)
+    }
+}
+
+/// The Philox4x32-10 counter-based generator (Salmon et al., 2011).
+///
+/// The seed is the key, the replication and stream are the high words of the counter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Philox4x32 {
+    key: [u32; 2],
+    counter: [u32; 4],
+    buffer: [u32; 4],
+    index: usize,
+}
+
+const PHILOX_M0: u32 = 0xD251_1F53;
+const PHILOX_M1: u32 = 0xCD9E_8D57;
+const PHILOX_W0: u32 = 0x9E37_79B9;
+const PHILOX_W1: u32 = 0xBB67_AE85;
+
+impl Philox4x32 {
+    /// Create a generator from a raw key and counter.
+    pub fn from_key_counter(key: [u32; 2], counter: [u32; 4]) -> Philox4x32 {
+        Philox4x32 {
+            key,
+            counter,
+            buffer: [0; 4],
+            index: 4,
+        }
+    }
+
+    /// Compute the block of random numbers of `counter` with `key`.
+    pub fn block(key: [u32; 2], counter: [u32; 4]) -> [u32; 4] {
+        let mut c = counter;
+        let mut k = key;
+        for round in 0..10 {
+            if round > 0 {
+                k[0] = k[0].wrapping_add(PHILOX_W0);
+                k[1] = k[1].wrapping_add(PHILOX_W1);
+            }
+            let p0 = u64::from(PHILOX_M0) * u64::from(c[0]);
+            let p1 = u64::from(PHILOX_M1) * u64::from(c[2]);
+            c = [
+                (p1 >> 32) as u32 ^ c[1] ^ k[0],
+                p1 as u32,
+                (p0 >> 32) as u32 ^ c[3] ^ k[1],
+                p0 as u32,
+            ];
+        }
+        c
+    }
+
+    fn refill(&mut self) {
+        self.buffer = Philox4x32::block(self.key, self.counter);
+        // the low 64 bits of the counter index the blocks of the stream
+        let (low, carry) = self.counter[0].overflowing_add(1);
+        self.counter[0] = low;
+        if carry {
+            self.counter[1] = self.counter[1].wrapping_add(1);
+        }
+        self.index = 0;
+    }
+}
+
+impl Philox4x32 {
+    /// Create the generator of a substream of a stream, e.g. of a step of a process.
+    ///
+    /// The substream is the high word of the block index.
+    pub(crate) fn from_substream(
+        streams: &Streams<Philox4x32>,
+        stream: u32,
+        substream: u32,
+    ) -> Philox4x32 {
+        let mut rng = streams.stream(stream);
+        rng.counter[1] = substream;
+        rng
+    }
+}
+
+impl StreamRng for Philox4x32 {
+    fn from_stream(seed: u64, replication: u32, stream: u32) -> Philox4x32 {
+        Philox4x32::from_key_counter(
+            [seed as u32, (seed >> 32) as u32],
+            [0, 0, stream, replication],
+        )
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.index == 4 {
+            self.refill();
+        }
+        self.index += 1;
+        self.buffer[self.index - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = u64::from(self.next_u32());
+        let high = u64::from(self.next_u32());
+        (high << 32) | low
+    }
+}
+
+/// The Threefry2x64-20 counter-based generator (Salmon et al., 2011).
+///
+/// The seed is the first word of the key, the replication and stream form the second one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Threefry2x64 {
+    key: [u64; 2],
+    counter: [u64; 2],
+    buffer: [u64; 2],
+    index: usize,
+}
+
+const THREEFRY_PARITY: u64 = 0x1BD1_1BDA_A9FC_1A22;
+const THREEFRY_ROTATIONS: [u32; 8] = [16, 42, 12, 31, 16, 32, 24, 21];
+
+impl Threefry2x64 {
+    /// Create a generator from a raw key and counter.
+    pub fn from_key_counter(key: [u64; 2], counter: [u64; 2]) -> Threefry2x64 {
+        Threefry2x64 {
+            key,
+            counter,
+            buffer: [0; 2],
+            index: 2,
+        }
+    }
+
+    /// Compute the block of random numbers of `counter` with `key`.
+    pub fn block(key: [u64; 2], counter: [u64; 2]) -> [u64; 2] {
+        let ks = [key[0], key[1], THREEFRY_PARITY ^ key[0] ^ key[1]];
+        let mut x0 = counter[0].wrapping_add(ks[0]);
+        let mut x1 = counter[1].wrapping_add(ks[1]);
+        for round in 0..20 {
+            x0 = x0.wrapping_add(x1);
+            x1 = x1.rotate_left(THREEFRY_ROTATIONS[round % 8]) ^ x0;
+            if round % 4 == 3 {
+                let s = round / 4 + 1;
+                x0 = x0.wrapping_add(ks[s % 3]);
+                x1 = x1.wrapping_add(ks[(s + 1) % 3]).wrapping_add(s as u64);
+            }
+        }
+        [x0, x1]
+    }
+
+    fn refill(&mut self) {
+        self.buffer = Threefry2x64::block(self.key, self.counter);
+        self.counter[0] = self.counter[0].wrapping_add(1);
+        if self.counter[0] == 0 {
+            self.counter[1] = self.counter[1].wrapping_add(1);
+        }
+        self.index = 0;
+    }
+}
+
+impl StreamRng for Threefry2x64 {
+    fn from_stream(seed: u64, replication: u32, stream: u32) -> Threefry2x64 {
+        Threefry2x64::from_key_counter(
+            [seed, (u64::from(replication) << 32) | u64::from(stream)],
+            [0, 0],
+        )
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        if self.index == 2 {
+            self.refill();
+        }
+        self.index += 1;
+        self.buffer[self.index - 1]
+    }
+}
+
+#[cfg(feature = "rand_core")]
+mod rand_core_impls {
+    use super::{Philox4x32, StreamRng, Threefry2x64};
+    use rand_core::{impls, Error, RngCore, SeedableRng};
+
+    macro_rules! impl_rand_core {
+        ($rng:ty) => {
+            impl RngCore for $rng {
+                fn next_u32(&mut self) -> u32 {
+                    StreamRng::next_u32(self)
+                }
+
+                fn next_u64(&mut self) -> u64 {
+                    StreamRng::next_u64(self)
+                }
+
+                fn fill_bytes(&mut self, dest: &mut [u8]) {
+                    impls::fill_bytes_via_next(self, dest)
+                }
+
+                fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+                    RngCore::fill_bytes(self, dest);
+                    Ok(())
+                }
+            }
+
+            /// The seed is used as the seed of replication 0, stream 0.
+            impl SeedableRng for $rng {
+                type Seed = [u8; 8];
+
+                fn from_seed(seed: [u8; 8]) -> Self {
+                    <$rng>::from_stream(u64::from_le_bytes(seed), 0, 0)
+                }
+            }
+        };
+    }
+
+    impl_rand_core!(Philox4x32);
+    impl_rand_core!(Threefry2x64);
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,44 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{Philox4x32, StreamRng, Streams, Threefry2x64};
+
+#[test]
+fn known_answers() {
+    // test vectors of the Random123 library
+    assert_eq!(
+        Philox4x32::block([0, 0], [0, 0, 0, 0]),
+        [0x6627e8d5, 0xe169c58d, 0xbc57ac4c, 0x9b00dbd8]
+    );
+    assert_eq!(
+        Philox4x32::block([u32::MAX; 2], [u32::MAX; 4]),
+        [0x408f276d, 0x41c83b0e, 0xa20bc7c6, 0x6d5451fd]
+    );
+    assert_eq!(
+        Threefry2x64::block([0, 0], [0, 0]),
+        [0xc2b6e3a8c2c69865, 0x6f81ed42f350084d]
+    );
+}
+
+#[test]
+fn streams() {
+    let streams = Streams::<Threefry2x64>::new(7, 3);
+    let draw = |mut r: Threefry2x64| (0..5).map(|_| r.next_u64()).collect::<Vec<_>>();
+    let a = draw(streams.stream(0));
+    assert_eq!(a, draw(streams.stream(0)));
+    assert_ne!(a, draw(streams.stream(1)));
+    let mut other = Streams::<Threefry2x64>::new(7, 4).stream(0);
+    assert_ne!(a[0], other.next_u64());
+}
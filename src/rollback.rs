@@ -0,0 +1,181 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Rolling a simulation back to an earlier time.
+//!
+//! After `Simulation::enable_rollback`, a simulation can be brought back to the state it
+//! had at an earlier time with `rollback_to`, e.g. to try a decision and undo it if it
+//! turns out to be bad, or to explore alternative continuations of a run.
+//!
+//! The processes are coroutines, whose state cannot be copied. Instead of saving it, the
+//! simulation records its inputs as in the [`replay`](crate::replay) module, and rolling
+//! back rebuilds the model with the factory passed to `enable_rollback` and executes again
+//! the steps up to the requested time. The factory must build the same model every time,
+//! and the changes made to the simulation from outside other than its inputs, e.g. to the
+//! blackboard, are not restored.
+//!
+//! A resource that the factory cannot build again in the same state, e.g. because it is
+//! connected to an external system, can save its state with `Resource::snapshot`. The
+//! states saved when rollback is enabled are restored with `Resource::restore` in the
+//! rebuilt model, before the steps are executed again.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let build = || {
+//!     let mut sim = Simulation::new();
+//!     let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+//!         yield Effect::TimeOut(1.0);
+//!     }));
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//!     sim
+//! };
+//! let mut sim = build();
+//! sim.enable_rollback(Box::new(build));
+//! let mut sim = sim.run(EndCondition::Time(5.0));
+//! sim.rollback_to(2.5).unwrap();
+//! assert_eq!(sim.time(), 2.0);
+//! assert_eq!(sim.peek_next_time(), Some(3.0));
+//! ```
+use crate::error::SimError;
+use crate::replay::Input;
+use crate::resources::{Resource, ResourceSnapshot};
+use crate::{SimState, Simulation};
+use std::any::Any;
+
+/// The state of a resource saved with `Resource::snapshot`
+#[cfg(not(feature = "send"))]
+pub type SavedState = Box<dyn Any>;
+/// The state of a resource saved with `Resource::snapshot`
+#[cfg(feature = "send")]
+pub type SavedState = Box<dyn Any + Send>;
+
+/// Builds the model of a simulation again, see `Simulation::enable_rollback`
+#[cfg(not(feature = "send"))]
+pub type ModelFactory<T> = Box<dyn FnMut() -> Simulation<T>>;
+/// Builds the model of a simulation again, see `Simulation::enable_rollback`
+#[cfg(feature = "send")]
+pub type ModelFactory<T> = Box<dyn FnMut() -> Simulation<T> + Send>;
+
+/// What a simulation needs to be rolled back.
+pub(crate) struct Rollback<T: SimState + Clone> {
+    factory: ModelFactory<T>,
+    /// The times of the events, with the first step executed at each of them
+    times: Vec<(f64, usize)>,
+    /// The states of the resources saved when rollback was enabled
+    saved: Vec<(usize, SavedState)>,
+}
+
+impl<T: SimState + Clone> Rollback<T> {
+    /// Record that `step` executed an event at `time`.
+    pub(crate) fn record(&mut self, time: f64, step: usize) {
+        if self.times.last().is_none_or(|(last, _)| time > *last) {
+            self.times.push((time, step));
+        }
+    }
+
+    /// Forget the times recorded, for a new run from the start.
+    pub(crate) fn restart(&mut self) {
+        self.times.clear();
+    }
+
+    /// Returns the number of steps executed up to `time`, out of `steps`.
+    fn steps_until(&self, time: f64, steps: usize) -> usize {
+        self.times
+            .iter()
+            .find(|(t, _)| *t > time)
+            .map_or(steps, |(_, step)| step - 1)
+    }
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Allow the simulation to be rolled back with `rollback_to`, rebuilding its model
+    /// with `factory`. The inputs of the simulation are recorded from now on.
+    /// See the [`rollback`](crate::rollback) module.
+    ///
+    /// # Panics
+    /// Panics if the simulation already executed a step.
+    pub fn enable_rollback(&mut self, factory: ModelFactory<T>) {
+        assert_eq!(
+            self.steps, 0,
+            "ERROR. Rollback must be enabled before the first step."
+        );
+        if self.journal.is_none() {
+            self.record_inputs();
+        }
+        let saved = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(r, resource)| Some((r, resource.snapshot()?)))
+            .collect();
+        self.rollback = Some(Rollback {
+            factory,
+            times: Vec::new(),
+            saved,
+        });
+    }
+
+    /// Bring the simulation back to the state it had right after executing the last event
+    /// at or before `time`, before receiving the inputs that followed it.
+    ///
+    /// Returns the error of a step executed again, if any, leaving the simulation as it was.
+    ///
+    /// # Panics
+    /// Panics if rollback was not enabled with `enable_rollback`.
+    pub fn rollback_to(&mut self, time: f64) -> Result<(), SimError> {
+        let mut rollback = self
+            .rollback
+            .take()
+            .expect("ERROR. Rollback is not enabled.");
+        let steps = rollback.steps_until(time, self.steps);
+        let mut journal = self
+            .journal
+            .clone()
+            .expect("ERROR. The inputs of a simulation with rollback must be recorded.");
+        // the calls received after the last step are left out, the injected events are
+        // received by the step itself
+        journal.retain(|step, input| {
+            step < steps || (step == steps && matches!(input, Input::Injected { .. }))
+        });
+        let mut sim = (rollback.factory)();
+        for (r, state) in rollback.saved.iter() {
+            sim.resources[*r].restore(state);
+            sim.resource_snapshots.lock().unwrap()[*r] = ResourceSnapshot::of(&sim.resources[*r]);
+        }
+        sim.record_inputs();
+        sim.replay(journal);
+        let times = std::mem::take(&mut rollback.times);
+        sim.rollback = Some(rollback);
+        while sim.steps < steps {
+            if let Err(e) = sim.step() {
+                // keep the simulation as it was, able to roll back
+                let mut rollback = sim.rollback.take().unwrap();
+                rollback.times = times;
+                self.rollback = Some(rollback);
+                return Err(e);
+            }
+        }
+        sim.replaying = None;
+        // the stop handles given out keep working
+        sim.stop = std::mem::take(&mut self.stop);
+        *self = sim;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,145 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, Simulation};
+
+#[test]
+fn rollback_inputs() {
+    let build = || {
+        let mut s = Simulation::new();
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::Wait;
+            },
+        ));
+        (s, p)
+    };
+    let (mut s, p) = build();
+    s.enable_rollback(Box::new(move || build().0));
+    s.injector().inject(1.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    s.resume_process(p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    s.injector().inject(4.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    assert_eq!(s.time(), 4.0);
+    // the event resumed at 1.0 is kept, the injection received after it is not
+    s.rollback_to(1.0).unwrap();
+    assert_eq!((s.time(), s.steps()), (1.0, 2));
+    assert_eq!(s.peek_next_time(), None);
+    assert_eq!(s.processed_events().len(), 2);
+    // the simulation can receive new inputs and be rolled back again
+    s.injector().inject(3.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    s.injector().inject(5.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    assert_eq!(s.time(), 5.0);
+    s.rollback_to(3.5).unwrap();
+    assert_eq!((s.time(), s.steps()), (3.0, 3));
+    s.rollback_to(0.0).unwrap();
+    assert_eq!((s.time(), s.steps()), (0.0, 0));
+}
+
+#[test]
+fn rollback_failure() {
+    use crate::error::SimError;
+    use crate::EndCondition;
+
+    // the second model built fails at the first step
+    let mut builds = 0;
+    let mut build = move || {
+        builds += 1;
+        let delay = if builds == 2 { f64::NAN } else { 1.0 };
+        let mut s = Simulation::new();
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| loop {
+                yield Effect::TimeOut(delay);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        (s, p)
+    };
+    let (mut s, p) = build();
+    s.enable_rollback(Box::new(move || build().0));
+    s = s.run(EndCondition::Time(3.0));
+    assert_eq!(s.rollback_to(2.0), Err(SimError::InvalidTime(p)));
+    assert_eq!(s.time(), 3.0);
+    // the simulation can still be rolled back
+    s.rollback_to(2.0).unwrap();
+    assert_eq!(s.time(), 2.0);
+}
+
+#[test]
+fn rollback_resources() {
+    use crate::resources::{Resource, SimpleResource};
+    use crate::rollback::SavedState;
+    use crate::{EndCondition, Event};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // a resource that counts its grants in an external system, not rebuilt by the factory
+    struct Counted {
+        inner: SimpleResource<Effect>,
+        grants: Arc<AtomicUsize>,
+    }
+    impl Resource<Effect> for Counted {
+        fn allocate_or_enqueue(&mut self, event: Event<Effect>) -> Vec<Event<Effect>> {
+            let granted = self.inner.allocate_or_enqueue(event);
+            self.grants.fetch_add(granted.len(), Ordering::Relaxed);
+            granted
+        }
+        fn release_and_schedule_next(&mut self, event: Event<Effect>) -> Vec<Event<Effect>> {
+            let granted = self.inner.release_and_schedule_next(event);
+            self.grants.fetch_add(granted.len(), Ordering::Relaxed);
+            granted
+        }
+        fn snapshot(&self) -> Option<SavedState> {
+            Some(Box::new(self.grants.load(Ordering::Relaxed)))
+        }
+        fn restore(&mut self, state: &SavedState) {
+            let grants = state.downcast_ref::<usize>().unwrap();
+            self.grants.store(*grants, Ordering::Relaxed);
+        }
+    }
+
+    let grants = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&grants);
+    let build = move || {
+        let mut s = Simulation::new();
+        let r = s.create_resource(Box::new(Counted {
+            inner: SimpleResource::new(1),
+            grants: Arc::clone(&counter),
+        }));
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| loop {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(1.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        s
+    };
+    let mut s = build();
+    s.enable_rollback(Box::new(build));
+    s = s.run(EndCondition::Time(5.0));
+    assert_eq!(grants.load(Ordering::Relaxed), 5);
+    // the grants counted again are the ones up to the time of the rollback
+    s.rollback_to(2.0).unwrap();
+    assert_eq!(grants.load(Ordering::Relaxed), 3);
+}
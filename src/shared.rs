@@ -0,0 +1,143 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! State shared by the processes of a simulation.
+//!
+//! The simulation owns a blackboard holding at most one value of each type, inserted with
+//! `Simulation::insert_shared`. The processes read it with `SimContext::with_shared` and
+//! modify it with `SimContext::with_shared_mut`, e.g. to count the parts produced by a line
+//! without global statics. The predicates of `Effect::WaitUntil` read it with
+//! `SimView::with_shared`, and are evaluated again after a step that modified it.
+//!
+//! The value is borrowed only for the duration of the closure: calling the methods of the
+//! blackboard from inside the closure deadlocks.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, SimContext, Simulation};
+//!
+//! struct Produced(usize);
+//!
+//! let mut sim = Simulation::new();
+//! sim.insert_shared(Produced(0));
+//! let enough = sim.create_predicate(Box::new(|view| {
+//!     view.with_shared(|p: &Produced| p.0 >= 5).unwrap_or(false)
+//! }));
+//! let line = sim.create_process(Box::new(#[coroutine] |mut ctx: SimContext<Effect>| loop {
+//!     ctx.with_shared_mut(|p: &mut Produced| p.0 += 1);
+//!     ctx = yield Effect::TimeOut(1.0);
+//! }));
+//! let supervisor = sim.create_process(Box::new(#[coroutine] move |_| {
+//!     yield Effect::WaitUntil(enough);
+//!     yield Effect::Trace;
+//! }));
+//! sim.schedule_event(0.0, line, Effect::TimeOut(0.0)).unwrap();
+//! sim.schedule_event(0.0, supervisor, Effect::TimeOut(0.0)).unwrap();
+//! let sim = sim.run(EndCondition::Time(10.0));
+//! assert_eq!(sim.with_shared(|p: &Produced| p.0), Some(11));
+//! let (done, _) = sim
+//!     .processed_events()
+//!     .iter()
+//!     .find(|(_, state)| matches!(state, Effect::Trace))
+//!     .unwrap();
+//! assert_eq!(done.time(), 4.0);
+//! ```
+use crate::factory::SpawnRequest;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A value that can be stored in the blackboard
+#[cfg(not(feature = "send"))]
+pub trait SharedValue: Any {}
+#[cfg(not(feature = "send"))]
+impl<S: Any> SharedValue for S {}
+/// A value that can be stored in the blackboard
+#[cfg(feature = "send")]
+pub trait SharedValue: Any + Send {}
+#[cfg(feature = "send")]
+impl<S: Any + Send> SharedValue for S {}
+
+/// The values of the blackboard, by type.
+#[derive(Default)]
+pub(crate) struct Blackboard {
+    values: HashMap<TypeId, Box<dyn SharedValue>>,
+    /// Set when a value is modified, to evaluate the predicates again
+    changed: bool,
+    /// The processes requested by the process being resumed
+    spawns: Vec<SpawnRequest>,
+}
+
+impl fmt::Debug for Blackboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blackboard")
+            .field("values", &self.values.len())
+            .field("changed", &self.changed)
+            .field("spawns", &self.spawns)
+            .finish()
+    }
+}
+
+/// A handle to the blackboard of a simulation.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Shared(Arc<Mutex<Blackboard>>);
+
+impl Shared {
+    /// Insert a value, returning the previous one of the same type
+    pub(crate) fn insert<S: SharedValue>(&self, value: S) -> Option<S> {
+        let mut blackboard = self.0.lock().unwrap();
+        blackboard.changed = true;
+        let previous = blackboard
+            .values
+            .insert(TypeId::of::<S>(), Box::new(value))?;
+        let previous: Box<dyn Any> = previous;
+        previous.downcast().ok().map(|value| *value)
+    }
+
+    /// Call `f` with the value of type `S`, if there is one
+    pub(crate) fn with<S: SharedValue, R>(&self, f: impl FnOnce(&S) -> R) -> Option<R> {
+        let blackboard = self.0.lock().unwrap();
+        let value: &dyn Any = blackboard.values.get(&TypeId::of::<S>())?.as_ref();
+        value.downcast_ref().map(f)
+    }
+
+    /// Call `f` with the value of type `S` mutably borrowed, if there is one
+    pub(crate) fn with_mut<S: SharedValue, R>(&self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        let mut blackboard = self.0.lock().unwrap();
+        let value: &mut dyn Any = blackboard.values.get_mut(&TypeId::of::<S>())?.as_mut();
+        let result = value.downcast_mut().map(f);
+        blackboard.changed = true;
+        result
+    }
+
+    /// Request a process from a factory, created after the step
+    pub(crate) fn request_spawn(&self, request: SpawnRequest) {
+        self.0.lock().unwrap().spawns.push(request);
+    }
+
+    /// Take the processes requested since the last call
+    pub(crate) fn take_spawns(&self) -> Vec<SpawnRequest> {
+        std::mem::take(&mut self.0.lock().unwrap().spawns)
+    }
+
+    /// Returns `true` if a value was modified since the last call
+    pub(crate) fn take_changed(&self) -> bool {
+        std::mem::take(&mut self.0.lock().unwrap().changed)
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
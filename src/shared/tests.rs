@@ -0,0 +1,52 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition, SimContext, Simulation};
+
+#[derive(Debug, PartialEq)]
+struct Open(bool);
+
+#[test]
+fn blackboard() {
+    let mut s = Simulation::new();
+    assert_eq!(s.insert_shared(Open(false)), None);
+    assert_eq!(s.with_shared(|_: &u32| ()), None);
+    let open = s.create_predicate(Box::new(|view| {
+        view.with_shared(|o: &Open| o.0).unwrap_or(false)
+    }));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            let mut ctx: SimContext<Effect> = yield Effect::WaitUntil(open);
+            assert_eq!(ctx.time(), 1.0);
+            ctx.with_shared_mut(|o: &mut Open| o.0 = false);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    assert_eq!(s.blocked_processes().len(), 1);
+    // a change made by the owner is noticed at the end of the next step
+    s.with_shared_mut(|o: &mut Open| o.0 = true);
+    let q = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::Trace;
+        },
+    ));
+    s.schedule_event(1.0, q, Effect::TimeOut(0.)).unwrap();
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.active_processes(), 0);
+    assert_eq!(s.insert_shared(Open(true)), Some(Open(false)));
+}
@@ -0,0 +1,105 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Signals processes can wait on.
+//!
+//! A signal, created with `Simulation::create_signal`, works like a condition variable:
+//! processes yielding `Effect::WaitSignal` are parked until another process yields
+//! `Effect::Fire`, that wakes all of them, or `Effect::FireOne`, that wakes the one that
+//! waited the longest. Firing a signal with no waiters has no effect.
+//!
+//! A gate, created with `Simulation::create_gate`, is a signal that stays fired: it is
+//! either open or closed. Processes yielding `Effect::WaitGate` pass an open gate
+//! immediately, and wait at a closed one until another process yields `Effect::OpenGate`,
+//! that releases all of them. `Effect::CloseGate` closes it again, e.g. to model a traffic
+//! light or the start of a shift.
+use crate::{Event, ProcessId};
+use std::collections::VecDeque;
+
+/// The processes waiting for a signal.
+pub(crate) struct Signal<T> {
+    waiting: VecDeque<Event<T>>,
+}
+
+impl<T> Signal<T> {
+    pub(crate) fn new() -> Signal<T> {
+        Signal {
+            waiting: VecDeque::new(),
+        }
+    }
+
+    /// Park the event of a waiting process until the signal is fired.
+    pub(crate) fn wait(&mut self, event: Event<T>) {
+        self.waiting.push_back(event);
+    }
+
+    /// Remove a waiting process, e.g. because it was interrupted.
+    pub(crate) fn cancel(&mut self, process: ProcessId) {
+        self.waiting.retain(|e| e.process() != process);
+    }
+
+    /// Returns the events that resume the processes woken at `time`: all of them if `all`
+    /// is set, otherwise the first one.
+    pub(crate) fn fire(&mut self, time: f64, all: bool) -> Vec<Event<T>> {
+        let n = if all { self.waiting.len() } else { 1 };
+        let mut woken: Vec<_> = self.waiting.drain(..n.min(self.waiting.len())).collect();
+        for event in woken.iter_mut() {
+            event.set_time(time);
+        }
+        woken
+    }
+}
+
+/// A signal that stays fired while open.
+pub(crate) struct Gate<T> {
+    open: bool,
+    signal: Signal<T>,
+}
+
+impl<T> Gate<T> {
+    pub(crate) fn new(open: bool) -> Gate<T> {
+        Gate {
+            open,
+            signal: Signal::new(),
+        }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Let the process of `event` pass, returning the event if the gate is open.
+    pub(crate) fn wait(&mut self, event: Event<T>) -> Option<Event<T>> {
+        if self.open {
+            return Some(event);
+        }
+        self.signal.wait(event);
+        None
+    }
+
+    /// Open the gate, returning the events that resume the waiting processes.
+    pub(crate) fn open(&mut self, time: f64) -> Vec<Event<T>> {
+        self.open = true;
+        self.signal.fire(time, true)
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Remove a waiting process, e.g. because it was interrupted.
+    pub(crate) fn cancel(&mut self, process: ProcessId) {
+        self.signal.cancel(process);
+    }
+}
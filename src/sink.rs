@@ -0,0 +1,450 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Destinations of the log of processed events.
+//!
+//! Every event executed by the simulation is logged together with the state yielded by the
+//! process, if the state should be logged. By default the simulation keeps the log in
+//! memory, where it can be read with `Simulation::processed_events`. Long simulations can
+//! stream the log to a file with a `CsvSink` or a `JsonLinesSink`, or drop it with a
+//! `DiscardSink`, implementing `LogSink`; in that case `processed_events` returns an empty
+//! slice. A `MemorySink` keeps the log in memory like the default, a `TailSink` keeps only
+//! its tail: the last events, or the ones of the last period of simulated time, so that
+//! soak runs can be debugged without growing the memory.
+//!
+//! Which events are logged can be tuned without changing the state type: a filter set with
+//! `Simulation::set_log_filter` is asked about each event whose state should be logged, and
+//! `Simulation::set_process_logging` mutes or unmutes the events of a single process.
+//!
+//! The sink is set with `Simulation::set_log_sink`. Writer sinks buffer nothing themselves
+//! and keep the first error they encounter, that is returned by `Simulation::flush_log`.
+//! Before the first event, they write the metadata of the run: as comment lines starting
+//! with `#` in CSV, and as a first line with a `metadata` object in JSON lines. Sinks
+//! created with `new` write the attributes of the schema registered with
+//! `Simulation::set_attribute_schema`, or the `Debug` representation of the states if there
+//! is none. The exporters that read the log, like `export::write_csv`, return an error if
+//! the sink does not keep the events in memory.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::sink::CsvSink;
+//! use desim::{Effect, EndCondition, Simulation};
+//! use std::sync::{Arc, Mutex};
+//!
+//! # #[derive(Clone, Default)]
+//! # struct Shared(Arc<Mutex<Vec<u8>>>);
+//! # impl std::io::Write for Shared {
+//! #     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+//! #         self.0.lock().unwrap().write(buf)
+//! #     }
+//! #     fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+//! # }
+//! let out = Shared::default();
+//! let mut sim = Simulation::new();
+//! sim.set_log_sink(Box::new(CsvSink::new(out.clone())));
+//! let p = sim.create_process(Box::new(#[coroutine] |_| {
+//!     yield Effect::TimeOut(1.0);
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! let mut sim = sim.run(EndCondition::NoEvents);
+//! sim.flush_log().unwrap();
+//! assert!(sim.processed_events().is_empty());
+//! let csv = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+//! assert!(csv.starts_with("# desim_version="));
+//! assert!(csv.ends_with("\ntime,process,state\n0,0,TimeOut(1.0)\n"));
+//! ```
+use crate::export::{
+    json_metadata, json_number, json_string, write_csv_header, write_csv_record, write_metadata,
+    AttributeSchema, Value,
+};
+use crate::metadata::RunMetadata;
+use crate::Event;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Receives the log of processed events.
+pub trait LogSink<T> {
+    /// Log an event, together with the state yielded by the process when it was resumed.
+    fn log(&mut self, event: &Event<T>, state: &T);
+
+    /// Log an event with the attribute schema registered in the simulation, if any.
+    /// The simulation logs the events with this method; the default implementation
+    /// ignores the schema and calls `log`.
+    fn log_with_schema(
+        &mut self,
+        event: &Event<T>,
+        state: &T,
+        schema: Option<&AttributeSchema<T>>,
+    ) {
+        let _ = schema;
+        self.log(event, state);
+    }
+
+    /// Called by the simulation before logging the first event, with the metadata of the
+    /// run. The default implementation does nothing.
+    fn begin(&mut self, metadata: &RunMetadata) {
+        let _ = metadata;
+    }
+
+    /// Returns the events kept in memory. The default implementation returns an empty slice.
+    fn events(&self) -> &[(Event<T>, T)] {
+        &[]
+    }
+
+    /// Returns `true` if the sink keeps the events in memory, so that they can be read
+    /// with `events`, also partially. The default implementation returns `false`.
+    fn keeps_events(&self) -> bool {
+        false
+    }
+
+    /// Remove the events kept in memory and return them. The default implementation
+    /// returns an empty vector.
+    fn take_events(&mut self) -> Vec<(Event<T>, T)> {
+        Vec::new()
+    }
+
+    /// Flush the output, returning the first error encountered, if any.
+    /// The default implementation does nothing.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A log sink added to a simulation
+#[cfg(not(feature = "send"))]
+pub type BoxedLogSink<T> = Box<dyn LogSink<T>>;
+/// A log sink added to a simulation
+#[cfg(feature = "send")]
+pub type BoxedLogSink<T> = Box<dyn LogSink<T> + Send>;
+
+/// Decides if an event, with the state yielded by the process, is logged
+#[cfg(not(feature = "send"))]
+pub type LogFilter<T> = Box<dyn Fn(&Event<T>, &T) -> bool>;
+/// Decides if an event, with the state yielded by the process, is logged
+#[cfg(feature = "send")]
+pub type LogFilter<T> = Box<dyn Fn(&Event<T>, &T) -> bool + Send>;
+
+/// Keeps the log in memory, like a simulation without a sink.
+#[derive(Debug, Clone)]
+pub struct MemorySink<T> {
+    events: Vec<(Event<T>, T)>,
+}
+
+/// Keeps in memory only the most recent events: at most a number of them, and only the
+/// ones executed in a window of simulated time before the last one.
+#[derive(Debug, Clone)]
+pub struct TailSink<T> {
+    events: Vec<(Event<T>, T)>,
+    start: usize,
+    capacity: Option<usize>,
+    window: Option<f64>,
+}
+
+/// Drops the log.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DiscardSink;
+
+/// Writes the log in CSV format, with a column for each attribute of a schema.
+pub struct CsvSink<T, W> {
+    writer: W,
+    schema: AttributeSchema<T>,
+    /// The schema was given explicitly and takes precedence over the one of the simulation
+    explicit: bool,
+    header: bool,
+    error: Option<io::Error>,
+}
+
+/// Writes the log in JSON lines format: a JSON object for each event, with its time,
+/// process and the attributes of a schema.
+pub struct JsonLinesSink<T, W> {
+    writer: W,
+    schema: AttributeSchema<T>,
+    /// The schema was given explicitly and takes precedence over the one of the simulation
+    explicit: bool,
+    error: Option<io::Error>,
+}
+
+impl<T> MemorySink<T> {
+    /// Create an empty sink
+    pub fn new() -> MemorySink<T> {
+        MemorySink { events: Vec::new() }
+    }
+}
+
+impl<T> Default for MemorySink<T> {
+    fn default() -> Self {
+        MemorySink::new()
+    }
+}
+
+impl<T: Clone> LogSink<T> for MemorySink<T> {
+    fn log(&mut self, event: &Event<T>, state: &T) {
+        self.events.push((event.clone(), state.clone()));
+    }
+
+    fn events(&self) -> &[(Event<T>, T)] {
+        self.events.as_slice()
+    }
+
+    fn keeps_events(&self) -> bool {
+        true
+    }
+
+    fn take_events(&mut self) -> Vec<(Event<T>, T)> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T> TailSink<T> {
+    /// Create a sink keeping the last `capacity` events
+    pub fn with_capacity(capacity: usize) -> TailSink<T> {
+        TailSink {
+            events: Vec::new(),
+            start: 0,
+            capacity: Some(capacity),
+            window: None,
+        }
+    }
+
+    /// Create a sink keeping the events executed in the last `window` time units
+    pub fn with_window(window: f64) -> TailSink<T> {
+        TailSink {
+            events: Vec::new(),
+            start: 0,
+            capacity: None,
+            window: Some(window),
+        }
+    }
+
+    /// Keep also at most `capacity` events
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Keep also only the events executed in the last `window` time units
+    pub fn window(mut self, window: f64) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Drop the events that are out of the tail.
+    fn evict(&mut self, now: f64) {
+        if let Some(capacity) = self.capacity {
+            self.start = self.start.max(self.events.len().saturating_sub(capacity));
+        }
+        if let Some(window) = self.window {
+            let kept = &self.events[self.start..];
+            self.start += kept.partition_point(|(e, _)| e.time() < now - window);
+        }
+        // move the tail to the front once the dropped events are the majority
+        if self.start > self.events.len() / 2 {
+            self.events.drain(..self.start);
+            self.start = 0;
+        }
+    }
+}
+
+impl<T: Clone> LogSink<T> for TailSink<T> {
+    fn log(&mut self, event: &Event<T>, state: &T) {
+        self.events.push((event.clone(), state.clone()));
+        self.evict(event.time());
+    }
+
+    fn events(&self) -> &[(Event<T>, T)] {
+        &self.events[self.start..]
+    }
+
+    fn keeps_events(&self) -> bool {
+        true
+    }
+
+    fn take_events(&mut self) -> Vec<(Event<T>, T)> {
+        let start = std::mem::take(&mut self.start);
+        let mut events = std::mem::take(&mut self.events);
+        events.drain(..start);
+        events
+    }
+}
+
+impl<T> LogSink<T> for DiscardSink {
+    fn log(&mut self, _event: &Event<T>, _state: &T) {}
+}
+
+impl<T: fmt::Debug + 'static, W: Write> CsvSink<T, W> {
+    /// Create a sink writing a column for each attribute of the schema of the simulation,
+    /// or the `Debug` representation of the states in a `state` column if it has none
+    pub fn new(writer: W) -> CsvSink<T, W> {
+        CsvSink {
+            explicit: false,
+            ..CsvSink::with_schema(writer, AttributeSchema::debug())
+        }
+    }
+}
+
+impl<T, W: Write> CsvSink<T, W> {
+    /// Create a sink writing a column for each attribute of `schema`
+    pub fn with_schema(writer: W, schema: AttributeSchema<T>) -> CsvSink<T, W> {
+        CsvSink {
+            writer,
+            schema,
+            explicit: true,
+            header: false,
+            error: None,
+        }
+    }
+
+    /// Returns the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<T, W: Write> LogSink<T> for CsvSink<T, W> {
+    fn log(&mut self, event: &Event<T>, state: &T) {
+        self.log_with_schema(event, state, None);
+    }
+
+    fn log_with_schema(
+        &mut self,
+        event: &Event<T>,
+        state: &T,
+        schema: Option<&AttributeSchema<T>>,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        let schema = match schema {
+            Some(schema) if !self.explicit => schema,
+            _ => &self.schema,
+        };
+        let mut result = Ok(());
+        if !self.header {
+            self.header = true;
+            result = write_csv_header(&mut self.writer, schema);
+        }
+        result = result.and_then(|_| write_csv_record(&mut self.writer, event, state, schema));
+        self.error = result.err();
+    }
+
+    fn begin(&mut self, metadata: &RunMetadata) {
+        if self.error.is_none() && !self.header {
+            self.error = write_metadata(&mut self.writer, metadata).err();
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+impl<T: fmt::Debug + 'static, W: Write> JsonLinesSink<T, W> {
+    /// Create a sink writing a field for each attribute of the schema of the simulation,
+    /// or the `Debug` representation of the states in a `state` field if it has none
+    pub fn new(writer: W) -> JsonLinesSink<T, W> {
+        JsonLinesSink {
+            explicit: false,
+            ..JsonLinesSink::with_schema(writer, AttributeSchema::debug())
+        }
+    }
+}
+
+impl<T, W: Write> JsonLinesSink<T, W> {
+    /// Create a sink writing a field for each attribute of `schema`
+    pub fn with_schema(writer: W, schema: AttributeSchema<T>) -> JsonLinesSink<T, W> {
+        JsonLinesSink {
+            writer,
+            schema,
+            explicit: true,
+            error: None,
+        }
+    }
+
+    /// Returns the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_line(
+        &mut self,
+        event: &Event<T>,
+        state: &T,
+        schema: Option<&AttributeSchema<T>>,
+    ) -> io::Result<()> {
+        let schema = match schema {
+            Some(schema) if !self.explicit => schema,
+            _ => &self.schema,
+        };
+        let values = schema
+            .values(state)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut line = format!(
+            "{{\"time\":{},\"process\":{}",
+            json_number(event.time()),
+            event.process()
+        );
+        for ((name, _), value) in schema.columns().zip(values) {
+            line.push(',');
+            json_string(&mut line, name);
+            line.push(':');
+            match value {
+                Value::Integer(i) => line.push_str(&i.to_string()),
+                Value::Float(x) => line.push_str(&json_number(x)),
+                Value::Boolean(b) => line.push_str(&b.to_string()),
+                Value::Text(s) => json_string(&mut line, &s),
+                Value::Missing => line.push_str("null"),
+            }
+        }
+        line.push('}');
+        writeln!(self.writer, "{}", line)
+    }
+}
+
+impl<T, W: Write> LogSink<T> for JsonLinesSink<T, W> {
+    fn log(&mut self, event: &Event<T>, state: &T) {
+        self.log_with_schema(event, state, None);
+    }
+
+    fn log_with_schema(
+        &mut self,
+        event: &Event<T>,
+        state: &T,
+        schema: Option<&AttributeSchema<T>>,
+    ) {
+        if self.error.is_none() {
+            self.error = self.write_line(event, state, schema).err();
+        }
+    }
+
+    fn begin(&mut self, metadata: &RunMetadata) {
+        if self.error.is_none() {
+            let line = format!("{{\"metadata\":{}}}", json_metadata(metadata));
+            self.error = writeln!(self.writer, "{}", line).err();
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
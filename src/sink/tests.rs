@@ -0,0 +1,156 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{JsonLinesSink, LogSink, TailSink};
+use crate::export::{write_csv, AttributeSchema, AttributeType, Value};
+use crate::{Effect, EndCondition, Event, ProcessId, Simulation};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn json_lines() {
+    let mut schema = AttributeSchema::new();
+    schema.add_attribute(
+        "timeout",
+        AttributeType::Float,
+        Box::new(|e: &Effect| match e {
+            Effect::TimeOut(t) => Value::Float(*t),
+            _ => Value::Missing,
+        }),
+    );
+    schema.add_attribute(
+        "name \"quoted\"",
+        AttributeType::Text,
+        Box::new(|_: &Effect| Value::Text("a\nb".to_string())),
+    );
+    let mut sink = JsonLinesSink::with_schema(Vec::new(), schema);
+    sink.log(
+        &Event::new(0.5, ProcessId(1), Effect::Wait),
+        &Effect::TimeOut(2.0),
+    );
+    sink.log(&Event::new(1.0, ProcessId(2), Effect::Wait), &Effect::Wait);
+    sink.flush().unwrap();
+    assert_eq!(
+        String::from_utf8(sink.into_inner()).unwrap(),
+        "{\"time\":0.5,\"process\":1,\"timeout\":2,\"name \\\"quoted\\\"\":\"a\\nb\"}\n\
+         {\"time\":1,\"process\":2,\"timeout\":null,\"name \\\"quoted\\\"\":\"a\\nb\"}\n"
+    );
+}
+
+#[test]
+fn log_filter() {
+    let mut s = Simulation::new();
+    let mut processes = Vec::new();
+    for _ in 0..3 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            |_| {
+                yield Effect::TimeOut(1.0);
+                yield Effect::Trace;
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+        processes.push(p);
+    }
+    s.set_log_filter(Box::new(|_, state| !matches!(state, Effect::Trace)));
+    s.set_process_logging(processes[0], false);
+    s.set_process_logging(processes[1], false);
+    s.set_process_logging(processes[1], true);
+    let s = s.run(EndCondition::NoEvents);
+    let logged: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| e.process())
+        .collect();
+    assert_eq!(logged, [processes[1], processes[2]]);
+}
+
+#[test]
+fn tail_sink() {
+    let log = |sink: &mut TailSink<Effect>, n: usize| {
+        for i in 0..n {
+            let event = Event::new(i as f64, ProcessId(0), Effect::Trace);
+            sink.log(&event, &Effect::TimeOut(i as f64));
+        }
+    };
+    let times = |sink: &TailSink<Effect>| -> Vec<f64> {
+        sink.events().iter().map(|(e, _)| e.time()).collect()
+    };
+    let mut last = TailSink::with_capacity(3);
+    log(&mut last, 10);
+    assert_eq!(times(&last), [7.0, 8.0, 9.0]);
+    let mut recent = TailSink::with_window(2.5);
+    log(&mut recent, 10);
+    assert_eq!(times(&recent), [7.0, 8.0, 9.0]);
+    let mut both = TailSink::with_window(2.5).capacity(2);
+    log(&mut both, 10);
+    assert_eq!(both.take_events().len(), 2);
+    assert!(both.events().is_empty());
+}
+
+#[test]
+fn simulation_schema_and_metadata() {
+    let mut schema = AttributeSchema::new();
+    schema.add_attribute(
+        "timeout",
+        AttributeType::Float,
+        Box::new(|e: &Effect| match e {
+            Effect::TimeOut(t) => Value::Float(*t),
+            _ => Value::Missing,
+        }),
+    );
+    let out = Shared::default();
+    let mut s = Simulation::new();
+    s.set_attribute_schema(schema);
+    s.metadata_mut().set_seed(42);
+    s.set_log_sink(Box::new(JsonLinesSink::new(out.clone())));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.5);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+    let mut s = s.run(EndCondition::NoEvents);
+    s.flush_log().unwrap();
+    let json = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<_> = json.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("{\"metadata\":{\"desim_version\":"));
+    assert!(lines[0].contains("\"seed\":\"42\""));
+    assert_eq!(lines[1], "{\"time\":0,\"process\":0,\"timeout\":1.5}");
+
+    assert!(!s.keeps_log());
+    assert_eq!(
+        write_csv(&s, Vec::new()).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+    s.set_log_sink(Box::new(TailSink::with_capacity(1)));
+    assert!(s.keeps_log());
+    assert!(write_csv(&s, Vec::new()).is_ok());
+}
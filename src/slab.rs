@@ -0,0 +1,172 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Storage of the processes of a simulation.
+//!
+//! Completed processes free their slot, that is reused by the processes created later.
+//! To detect ids of completed processes, each slot has a generation, incremented when the
+//! slot is freed, that is stored in the high bits of the ids. The first process created in
+//! a slot has generation 0, so its id is the index of the slot.
+//!
+//! The index and the generation share the bits of a `usize`, half each. The slots are
+//! reused in the order they were freed, and only when at least `MIN_FREE` of them are
+//! free, so that the ids of the processes that completed recently are not given out again
+//! soon. A slot whose generation is exhausted is never reused, so that an id can't alias
+//! the one of a later process, even on 32-bit targets.
+use crate::ProcessId;
+use std::collections::VecDeque;
+use std::ops::{Index, IndexMut};
+
+const SHIFT: u32 = usize::BITS / 2;
+const MASK: usize = (1 << SHIFT) - 1;
+/// Number of free slots kept before reusing them
+const MIN_FREE: usize = 64;
+
+struct Slot<E> {
+    generation: usize,
+    value: Option<E>,
+}
+
+/// A vector of values with stable ids, whose slots are reused after removal.
+pub(crate) struct Slab<E> {
+    slots: Vec<Slot<E>>,
+    free: VecDeque<usize>,
+    /// Number of slots that are never reused
+    retired: usize,
+}
+
+fn split(id: ProcessId) -> (usize, usize) {
+    (id.0 & MASK, id.0 >> SHIFT)
+}
+
+impl<E> Slab<E> {
+    pub fn new() -> Slab<E> {
+        Slab {
+            slots: Vec::new(),
+            free: VecDeque::new(),
+            retired: 0,
+        }
+    }
+
+    /// Reserve room for at least `additional` more values
+    pub fn reserve(&mut self, additional: usize) {
+        let reusable = self.free.len().saturating_sub(MIN_FREE);
+        self.slots.reserve(additional.saturating_sub(reusable));
+    }
+
+    /// Insert a value, returning its id
+    pub fn insert(&mut self, value: E) -> ProcessId {
+        let reused = (self.free.len() > MIN_FREE)
+            .then(|| self.free.pop_front())
+            .flatten();
+        match reused {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                ProcessId(index | (slot.generation << SHIFT))
+            }
+            None => {
+                assert!(
+                    self.slots.len() <= MASK,
+                    "ERROR. Too many processes for the ids of this target."
+                );
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                ProcessId(self.slots.len() - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, id: ProcessId) -> Option<&E> {
+        let (index, generation) = split(id);
+        self.slots
+            .get(index)
+            .filter(|slot| slot.generation == generation)?
+            .value
+            .as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: ProcessId) -> Option<&mut E> {
+        let (index, generation) = split(id);
+        self.slots
+            .get_mut(index)
+            .filter(|slot| slot.generation == generation)?
+            .value
+            .as_mut()
+    }
+
+    /// Remove a value, freeing its slot
+    pub fn remove(&mut self, id: ProcessId) -> Option<E> {
+        let (index, generation) = split(id);
+        let slot = self.slots.get_mut(index)?;
+        if slot.generation != generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        if slot.generation < MASK {
+            slot.generation += 1;
+            self.free.push_back(index);
+        } else {
+            self.retired += 1;
+        }
+        Some(value)
+    }
+
+    /// Returns `true` if `id` was the id of a value that was removed
+    pub fn was_removed(&self, id: ProcessId) -> bool {
+        let (index, generation) = split(id);
+        self.slots.get(index).is_some_and(|slot| {
+            generation < slot.generation || (generation == slot.generation && slot.value.is_none())
+        })
+    }
+
+    /// Returns the ids and the values, in order of slot
+    pub fn iter(&self) -> impl Iterator<Item = (ProcessId, &E)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let value = slot.value.as_ref()?;
+            Some((ProcessId(index | (slot.generation << SHIFT)), value))
+        })
+    }
+
+    /// Returns the number of values
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len() - self.retired
+    }
+}
+
+impl<E> Default for Slab<E> {
+    fn default() -> Self {
+        Slab::new()
+    }
+}
+
+impl<E> Index<ProcessId> for Slab<E> {
+    type Output = E;
+
+    fn index(&self, id: ProcessId) -> &E {
+        self.get(id).expect("ERROR. Unknown or completed process.")
+    }
+}
+
+impl<E> IndexMut<ProcessId> for Slab<E> {
+    fn index_mut(&mut self, id: ProcessId) -> &mut E {
+        self.get_mut(id)
+            .expect("ERROR. Unknown or completed process.")
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,65 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{Slab, MASK, MIN_FREE, SHIFT};
+use crate::ProcessId;
+
+#[test]
+fn slab() {
+    let mut slab = Slab::new();
+    let a = slab.insert('a');
+    let b = slab.insert('b');
+    assert_eq!((a, b), (ProcessId(0), ProcessId(1)));
+    assert_eq!(slab.remove(a), Some('a'));
+    assert!(slab.was_removed(a));
+    assert_eq!(slab.get(a), None);
+    let c = slab.insert('c');
+    assert_eq!(c, ProcessId(2));
+    assert_eq!(slab[c], 'c');
+    assert!(!slab.was_removed(c));
+    assert!(!slab.was_removed(ProcessId(7)));
+    assert_eq!(slab.remove(a), None);
+    assert_eq!(slab.iter().collect::<Vec<_>>(), [(b, &'b'), (c, &'c')]);
+
+    // the slots are reused once enough of them are free, the oldest first
+    let ids: Vec<_> = (0..MIN_FREE).map(|_| slab.insert('d')).collect();
+    for id in ids {
+        slab.remove(id);
+    }
+    let e = slab.insert('e');
+    assert_ne!(e, a);
+    assert_eq!(e.0 & MASK, a.0);
+    assert!(slab.was_removed(a));
+    assert_eq!(slab.get(a), None);
+    assert_eq!((slab.len(), slab.slots.len()), (3, MIN_FREE + 3));
+}
+
+#[test]
+fn exhausted_slot() {
+    let mut slab = Slab::new();
+    let a = slab.insert('a');
+    slab.slots[0].generation = MASK;
+    let a = ProcessId(a.0 | (MASK << SHIFT));
+    assert_eq!(slab.remove(a), Some('a'));
+    for _ in 0..=MIN_FREE {
+        let id = slab.insert('b');
+        slab.remove(id);
+    }
+    // the exhausted slot is never reused
+    let c = slab.insert('c');
+    assert_ne!(c.0 & MASK, 0);
+    assert!(slab.was_removed(a));
+    assert_eq!(slab.len(), 1);
+}
@@ -0,0 +1,169 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Soak testing with invariant checks.
+//!
+//! Long randomized runs are a good way to find bugs in a model, provided that the model
+//! is checked along the way. Instead of adding assertions to the processes, invariants
+//! can be registered with `Simulation::add_invariant`: each one is a closure that inspects
+//! the simulation, e.g. the queues of the resources, the level of the stores or some
+//! global state captured by the closure, and returns an error message if the invariant
+//! does not hold.
+//!
+//! `Simulation::soak` runs the simulation like `run`, checking every invariant at its
+//! interval, and stops at the first violation with a report of the state of the simulation,
+//! or at the first step that fails.
+use crate::error::SimError;
+use crate::{ResourceId, StoreId};
+use std::error::Error;
+use std::fmt;
+
+/// A check over the simulation. Returns a description of the problem if the invariant
+/// does not hold.
+#[cfg(not(feature = "send"))]
+pub type InvariantCheck<S> = Box<dyn Fn(&S) -> Result<(), String>>;
+/// A check over the simulation. Returns a description of the problem if the invariant
+/// does not hold.
+#[cfg(feature = "send")]
+pub type InvariantCheck<S> = Box<dyn Fn(&S) -> Result<(), String> + Send>;
+
+/// How often an invariant is checked, or the progress of a run is reported.
+///
+/// The interval must be positive.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CheckInterval {
+    /// Check after every `n` steps
+    Steps(usize),
+    /// Check every time that the simulation time advanced by the given amount
+    Time(f64),
+}
+
+/// Reports the first invariant that did not hold during a soak run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvariantViolation {
+    /// Name of the invariant
+    pub invariant: String,
+    /// Message returned by the check
+    pub message: String,
+    /// Simulation time of the check
+    pub time: f64,
+    /// Number of steps executed before the check
+    pub step: usize,
+    /// Length of the queue of each resource at the time of the check
+    pub queues: Vec<(ResourceId, usize)>,
+    /// Level of each store at the time of the check
+    pub levels: Vec<(StoreId, usize)>,
+}
+
+/// Why a soak run stopped before meeting its ending condition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SoakError {
+    /// An invariant did not hold
+    Invariant(InvariantViolation),
+    /// A step failed, as reported by `Simulation::error`
+    Step(SimError),
+}
+
+/// An invariant registered on a simulation.
+pub(crate) struct Invariant<S> {
+    pub(crate) name: String,
+    pub(crate) interval: CheckInterval,
+    pub(crate) check: InvariantCheck<S>,
+    /// Step or time of the next check
+    pub(crate) next: f64,
+}
+
+impl<S> Invariant<S> {
+    pub(crate) fn new(name: String, interval: CheckInterval, check: InvariantCheck<S>) -> Self {
+        Invariant {
+            name,
+            interval,
+            check,
+            next: interval.first(),
+        }
+    }
+
+    /// Returns `true` if the invariant must be checked at the given step and time, and
+    /// moves on to the next check.
+    pub(crate) fn is_due(&mut self, step: usize, time: f64) -> bool {
+        self.interval.is_due(&mut self.next, step, time)
+    }
+}
+
+impl CheckInterval {
+    /// Returns the step or time of the first check
+    pub(crate) fn first(&self) -> f64 {
+        match *self {
+            CheckInterval::Steps(n) => n as f64,
+            CheckInterval::Time(dt) => dt,
+        }
+    }
+
+    /// Returns `true` if the step or time of the `next` check was reached at the given
+    /// step and time, and moves `next` on.
+    pub(crate) fn is_due(&self, next: &mut f64, step: usize, time: f64) -> bool {
+        let (now, interval) = match *self {
+            CheckInterval::Steps(n) => (step as f64, n as f64),
+            CheckInterval::Time(dt) => (time, dt),
+        };
+        if now < *next {
+            return false;
+        }
+        // skip the checks missed by a large jump at once
+        let missed = ((now - *next) / interval).floor() + 1.0;
+        *next += missed * interval;
+        if *next <= now {
+            // the interval is lost in the rounding of `now`
+            *next = now + interval;
+        }
+        true
+    }
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "invariant `{}` violated at t={} (step {}): {}",
+            self.invariant, self.time, self.step, self.message
+        )?;
+        for (resource, len) in &self.queues {
+            writeln!(f, "  resource {}: {} waiting", resource, len)?;
+        }
+        for (store, level) in &self.levels {
+            writeln!(f, "  store {}: {} items", store, level)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvariantViolation {}
+
+impl fmt::Display for SoakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoakError::Invariant(violation) => violation.fmt(f),
+            SoakError::Step(error) => write!(f, "step failed: {}", error),
+        }
+    }
+}
+
+impl Error for SoakError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SoakError::Invariant(violation) => Some(violation),
+            SoakError::Step(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Generators of arrivals.
+//!
+//! Most models have entities entering the system at random times, e.g. the customers of a
+//! queue. A `Source` is an iterator of `Arrival`s, to be added to a simulation with
+//! `Simulation::add_arrivals`: it draws the time between two arrivals with a random number
+//! generator and creates the process of each entity with a factory, that receives the index
+//! of the arrival. The source stops after a time or a number of arrivals, if set.
+//!
+//! `poisson_source` creates a source with exponentially distributed interarrival times.
+//! The processes of a simulation draw from the streams indexed by their ids, so a source
+//! should take another stream, e.g. `SOURCE_STREAM`.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::sources::{poisson_source, SOURCE_STREAM};
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::with_seed(42);
+//! let rng = sim.streams().stream(SOURCE_STREAM);
+//! let customers = poisson_source(2.0, rng, |_| {
+//!     let process: Box<desim::Process<Effect>> = Box::new(#[coroutine] |_| {
+//!         yield Effect::TimeOut(0.5);
+//!     });
+//!     (process, Effect::TimeOut(0.0))
+//! });
+//! sim.add_arrivals(Box::new(customers.limit(10)));
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(sim.processed_events().len(), 10);
+//! ```
+use crate::rng::StreamRng;
+use crate::trace::Arrival;
+use crate::Process;
+
+/// A stream of random numbers for a source, far from the ids of the processes.
+/// Models with more sources can count down from it.
+pub const SOURCE_STREAM: u32 = u32::MAX;
+
+/// Generates arrivals with random interarrival times.
+pub struct Source<R, I, F> {
+    rng: R,
+    interarrival: I,
+    factory: F,
+    time: f64,
+    until: f64,
+    limit: Option<usize>,
+    count: usize,
+}
+
+impl<R, I, F> Source<R, I, F>
+where
+    R: StreamRng,
+    I: FnMut(&mut R) -> f64,
+{
+    /// Create a source drawing the time between two arrivals with `interarrival`, that
+    /// receives `rng`, and creating the process of each arrival with `factory`.
+    ///
+    /// The first arrival is an interarrival time after 0.
+    pub fn new(rng: R, interarrival: I, factory: F) -> Source<R, I, F> {
+        Source {
+            rng,
+            interarrival,
+            factory,
+            time: 0.0,
+            until: f64::INFINITY,
+            limit: None,
+            count: 0,
+        }
+    }
+
+    /// Start generating arrivals at `time` instead of 0
+    pub fn starting_at(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Stop generating arrivals at `time`
+    pub fn until(mut self, time: f64) -> Self {
+        self.until = time;
+        self
+    }
+
+    /// Stop after `n` arrivals
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+}
+
+impl<T, R, I, F> Iterator for Source<R, I, F>
+where
+    R: StreamRng,
+    I: FnMut(&mut R) -> f64,
+    F: FnMut(usize) -> (Box<Process<T>>, T),
+{
+    type Item = Arrival<T>;
+
+    fn next(&mut self) -> Option<Arrival<T>> {
+        if self.limit.is_some_and(|n| self.count >= n) {
+            return None;
+        }
+        self.time += (self.interarrival)(&mut self.rng);
+        if self.time >= self.until {
+            self.limit = Some(self.count);
+            return None;
+        }
+        let (process, state) = (self.factory)(self.count);
+        self.count += 1;
+        Some(Arrival::new(self.time, process, state))
+    }
+}
+
+/// Create a source of arrivals with exponentially distributed interarrival times, with
+/// `rate` arrivals per unit of time on average.
+///
+/// # Panics
+/// Panics if `rate` is not positive.
+pub fn poisson_source<T, R, F>(
+    rate: f64,
+    rng: R,
+    factory: F,
+) -> Source<R, impl FnMut(&mut R) -> f64, F>
+where
+    R: StreamRng,
+    F: FnMut(usize) -> (Box<Process<T>>, T),
+{
+    assert!(
+        rate > 0.0,
+        "ERROR. The rate of arrivals of a source must be positive."
+    );
+    Source::new(
+        rng,
+        move |rng: &mut R| -(1.0 - rng.next_f64()).ln() / rate,
+        factory,
+    )
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
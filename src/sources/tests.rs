@@ -0,0 +1,51 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{poisson_source, Source};
+use crate::rng::{Philox4x32, StreamRng};
+use crate::{Effect, Process};
+
+#[test]
+fn source() {
+    let rng = Philox4x32::from_stream(1, 0, 0);
+    let factory = |i: usize| {
+        let process: Box<Process<Effect>> = Box::new(
+            #[coroutine]
+            |_| {
+                yield Effect::Wait;
+            },
+        );
+        (process, Effect::TimeOut(i as f64))
+    };
+    let times: Vec<_> = Source::new(rng.clone(), |_: &mut Philox4x32| 1.5, factory)
+        .starting_at(1.0)
+        .until(7.0)
+        .map(|a| a.time())
+        .collect();
+    assert_eq!(times, [2.5, 4.0, 5.5]);
+    let n = Source::new(rng, |r: &mut Philox4x32| r.next_f64(), factory)
+        .limit(4)
+        .count();
+    assert_eq!(n, 4);
+}
+
+#[test]
+#[should_panic(expected = "must be positive")]
+fn poisson_rate() {
+    let rng = Philox4x32::from_stream(1, 0, 0);
+    poisson_source(f64::NAN, rng, |_| -> (Box<Process<Effect>>, Effect) {
+        unreachable!()
+    });
+}
@@ -0,0 +1,892 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Statistics collected by the simulation.
+//!
+//! The simulation monitors every resource, counting the requests, the time the processes
+//! waited to be granted the resource, the length of its queue and the number of units in
+//! use over time.
+//! The statistics of a resource are returned by `Simulation::resource_stats`. After
+//! `Simulation::track_occupancy`, they also record which process held each unit and when,
+//! returned by `ResourceStats::occupancy`.
+//!
+//! Resources can be grouped with `Simulation::create_resource_group`, e.g. all the
+//! checkout counters of a shop. The statistics of a group, returned by
+//! `Simulation::group_stats`, pool the ones of its members.
+//!
+//! The time averages are computed since the start of the simulation, or since the end of
+//! the warm-up period set with `Simulation::set_warm_up`, when all the statistics are reset
+//! to leave out the transient behavior of the model.
+//!
+//! Models can collect their own statistics too, with accumulators created by
+//! `Simulation::create_counter`, `Simulation::create_tally` and
+//! `Simulation::create_time_weighted`. Processes update them yielding `Effect::Record`,
+//! and `Simulation::stats` returns all of them, e.g. at the end of the run:
+//! * a `Counter` sums the recorded values, e.g. the number of served customers;
+//! * a `Tally` summarizes a series of observations, e.g. the time each customer spent in
+//!   the system;
+//! * a `TimeWeighted` summarizes a level that changes over time, e.g. the number of
+//!   customers in the system, weighting each value by how long it lasted;
+//! * a `Histogram`, created by `Simulation::create_histogram`, counts the observations in
+//!   bins, to report percentiles like the 95th of the waiting times.
+use crate::{EntityId, ProcessId, StatId};
+use std::collections::BTreeMap;
+
+/// Statistics of a resource, or of a group of resources.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResourceStats {
+    requests: usize,
+    grants: usize,
+    total_wait: f64,
+    max_wait: f64,
+    queue_len: usize,
+    queue_area: f64,
+    queue_timeline: Vec<(f64, usize)>,
+    waits: Vec<f64>,
+    capacity: usize,
+    in_use: usize,
+    busy_area: f64,
+    /// The units in use, with the time they were acquired, the process holding them and
+    /// the entity it carried
+    holding: Vec<(f64, ProcessId, Option<EntityId>)>,
+    /// The units granted from a later time on, e.g. at the opening of a shift, sorted by
+    /// time
+    deferred: Vec<(f64, ProcessId, Option<EntityId>)>,
+    /// The units released, with the times they were acquired and released, if tracked
+    occupancy: Vec<(f64, f64, ProcessId, Option<EntityId>)>,
+    track_occupancy: bool,
+    start: f64,
+    last_update: f64,
+}
+
+impl ResourceStats {
+    /// Returns the number of requests of the resource
+    pub fn requests(&self) -> usize {
+        self.requests
+    }
+
+    /// Returns the number of requests that were granted
+    pub fn grants(&self) -> usize {
+        self.grants
+    }
+
+    /// Returns the mean time waited by the granted requests
+    pub fn mean_wait(&self) -> f64 {
+        if self.grants == 0 {
+            0.0
+        } else {
+            self.total_wait / self.grants as f64
+        }
+    }
+
+    /// Returns the longest time waited by a granted request
+    pub fn max_wait(&self) -> f64 {
+        self.max_wait
+    }
+
+    /// Returns the current number of requests in the queue
+    pub fn queue_len(&self) -> usize {
+        self.queue_len
+    }
+
+    /// Returns the time-weighted average length of the queue since the start of the
+    /// simulation
+    pub fn mean_queue_len(&self) -> f64 {
+        if self.last_update > self.start {
+            self.queue_area / (self.last_update - self.start)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the length of the queue over time, as a list of changes: each entry holds
+    /// the time of a change and the length of the queue from that time on.
+    pub fn queue_timeline(&self) -> &[(f64, usize)] {
+        self.queue_timeline.as_slice()
+    }
+
+    /// Returns the times waited by the granted requests, in the order they were granted
+    pub fn waits(&self) -> &[f64] {
+        self.waits.as_slice()
+    }
+
+    /// Returns a histogram of the waiting times, counted in the bins of `histogram`
+    pub fn wait_histogram(&self, mut histogram: Histogram) -> Histogram {
+        for wait in self.waits.iter() {
+            histogram.record(*wait);
+        }
+        histogram
+    }
+
+    /// Returns the `q`-quantile of the waiting times, e.g. 0.5 for the median, or `None`
+    /// if no request was granted.
+    ///
+    /// # Panics
+    /// Panics if `q` is not in `[0, 1]`.
+    pub fn wait_quantile(&self, q: f64) -> Option<f64> {
+        assert!(
+            (0.0..=1.0).contains(&q),
+            "ERROR. Quantiles must be in [0, 1]."
+        );
+        let mut waits = self.waits.clone();
+        waits.sort_by(f64::total_cmp);
+        let index = (q * (waits.len() as f64 - 1.0)).round() as usize;
+        waits.get(index).copied()
+    }
+
+    /// Returns the number of units of the resource, if the resource reports it
+    pub fn capacity(&self) -> Option<usize> {
+        Some(self.capacity).filter(|c| *c > 0)
+    }
+
+    /// Returns the number of units currently in use
+    pub fn in_use(&self) -> usize {
+        self.in_use
+    }
+
+    /// Returns the time-weighted average number of units in use since the start of the
+    /// simulation
+    pub fn mean_in_use(&self) -> f64 {
+        if self.last_update > self.start {
+            self.busy_area / (self.last_update - self.start)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the occupancy of the resource over time, e.g. to draw a Gantt chart: each
+    /// entry holds the time a unit was acquired, the time it was released and the process
+    /// that held it, sorted by the time of acquisition. The units still in use are reported
+    /// as released at the time the statistics were updated to.
+    ///
+    /// The units already released are reported only if the occupancy is tracked with
+    /// `Simulation::track_occupancy`.
+    pub fn occupancy(&self) -> Vec<(f64, f64, ProcessId)> {
+        self.segments()
+            .into_iter()
+            .map(|(start, end, process, _)| (start, end, process))
+            .collect()
+    }
+
+    /// Like `occupancy`, with the entity carried by the process when it acquired the unit.
+    pub(crate) fn segments(&self) -> Vec<(f64, f64, ProcessId, Option<EntityId>)> {
+        let mut segments = self.occupancy.clone();
+        segments.extend(
+            self.holding
+                .iter()
+                .map(|(start, process, entity)| (*start, self.last_update, *process, *entity)),
+        );
+        segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+        segments
+    }
+
+    /// Returns the time-weighted average fraction of the units in use since the start of
+    /// the simulation, or `None` if the resource does not report its capacity
+    pub fn utilization(&self) -> Option<f64> {
+        self.capacity().map(|c| self.mean_in_use() / c as f64)
+    }
+
+    /// Pool the statistics of another resource into these ones.
+    ///
+    /// Counts and waiting times are pooled over all the requests, queue lengths, units in
+    /// use and capacities are summed. Both must be updated to the same time.
+    pub fn merge(&mut self, other: &ResourceStats) {
+        self.requests += other.requests;
+        self.grants += other.grants;
+        self.total_wait += other.total_wait;
+        self.max_wait = self.max_wait.max(other.max_wait);
+        self.queue_len += other.queue_len;
+        self.queue_area += other.queue_area;
+        self.queue_timeline = merge_timelines(&self.queue_timeline, &other.queue_timeline);
+        self.waits.extend_from_slice(&other.waits);
+        self.capacity += other.capacity;
+        self.in_use += other.in_use;
+        self.busy_area += other.busy_area;
+        self.holding.extend_from_slice(&other.holding);
+        for unit in &other.deferred {
+            self.defer_use(*unit);
+        }
+        self.occupancy.extend_from_slice(&other.occupancy);
+        self.track_occupancy |= other.track_occupancy;
+        self.start = self.start.min(other.start);
+        self.last_update = self.last_update.max(other.last_update);
+    }
+
+    pub(crate) fn with_capacity(capacity: Option<usize>) -> ResourceStats {
+        ResourceStats {
+            capacity: capacity.unwrap_or(0),
+            ..ResourceStats::default()
+        }
+    }
+
+    /// Discard the statistics collected before `time`, keeping the current length of the
+    /// queue and units in use.
+    pub(crate) fn reset(&mut self, time: f64) {
+        *self = ResourceStats {
+            queue_len: self.queue_len,
+            queue_timeline: vec![(time, self.queue_len)],
+            capacity: self.capacity,
+            in_use: self.in_use,
+            holding: self
+                .holding
+                .iter()
+                .map(|(_, p, e)| (time, *p, *e))
+                .collect(),
+            deferred: std::mem::take(&mut self.deferred),
+            track_occupancy: self.track_occupancy,
+            start: time,
+            last_update: time,
+            ..ResourceStats::default()
+        };
+    }
+
+    /// Keep the units released in the occupancy, from now on.
+    pub(crate) fn track_occupancy(&mut self) {
+        self.track_occupancy = true;
+    }
+
+    pub(crate) fn record_request(&mut self) {
+        self.requests += 1;
+    }
+
+    pub(crate) fn record_grant(&mut self, wait: f64) {
+        self.grants += 1;
+        self.total_wait += wait;
+        self.max_wait = self.max_wait.max(wait);
+        self.waits.push(wait);
+    }
+
+    /// Record that the queue has length `len` from `time` on.
+    pub(crate) fn record_queue(&mut self, time: f64, len: usize) {
+        self.advance(time);
+        match self.queue_timeline.last_mut() {
+            // keep only the last change at the same time
+            Some(last) if last.0 == time => last.1 = len,
+            Some(_) if len == self.queue_len => {}
+            _ => self.queue_timeline.push((time, len)),
+        }
+        self.queue_len = len;
+    }
+
+    /// Record that a unit was acquired (`true`) or released (`false`) by `process` at `time`.
+    /// `entity` is the entity carried by the process when it acquires the unit.
+    ///
+    /// A unit acquired at a later time than the statistics were updated to, e.g. granted
+    /// by a `ScheduledResource` at the next opening, is in use from that time on.
+    ///
+    /// Returns the time from which the number of units in use changes, if it does.
+    pub(crate) fn record_use(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        entity: Option<EntityId>,
+        acquired: bool,
+    ) -> Option<f64> {
+        if acquired {
+            self.defer_use((time, process, entity));
+            self.advance(self.last_update);
+            return Some(time);
+        }
+        self.advance(time);
+        // the unit held for the longest time is released first
+        if let Some(i) = self.holding.iter().position(|(_, p, _)| *p == process) {
+            self.in_use = self.in_use.saturating_sub(1);
+            let (start, _, entity) = self.holding.remove(i);
+            if self.track_occupancy {
+                self.occupancy.push((start, time, process, entity));
+            }
+            Some(time)
+        } else if let Some(i) = self.deferred.iter().position(|(_, p, _)| *p == process) {
+            // given back before it was in use
+            Some(self.deferred.remove(i).0)
+        } else if self.in_use > 0 {
+            self.in_use -= 1;
+            Some(time)
+        } else {
+            None
+        }
+    }
+
+    fn defer_use(&mut self, unit: (f64, ProcessId, Option<EntityId>)) {
+        let i = self.deferred.partition_point(|(t, _, _)| *t <= unit.0);
+        self.deferred.insert(i, unit);
+    }
+
+    /// Extend the time-weighted statistics up to `time`, counting the deferred units from
+    /// the time they are in use.
+    pub(crate) fn advance(&mut self, time: f64) {
+        while let Some(&(start, process, entity)) = self.deferred.first() {
+            if start > time {
+                break;
+            }
+            self.deferred.remove(0);
+            self.extend(start);
+            self.in_use += 1;
+            self.holding
+                .push((start.max(self.last_update), process, entity));
+        }
+        self.extend(time);
+    }
+
+    fn extend(&mut self, time: f64) {
+        if time > self.last_update {
+            self.queue_area += self.queue_len as f64 * (time - self.last_update);
+            self.busy_area += self.in_use as f64 * (time - self.last_update);
+            self.last_update = time;
+        }
+    }
+}
+
+/// Sums the recorded values.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Counter {
+    value: f64,
+    count: usize,
+}
+
+impl Counter {
+    /// Add `value` to the counter
+    pub fn add(&mut self, value: f64) {
+        self.value += value;
+        self.count += 1;
+    }
+
+    /// Returns the sum of the recorded values
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns the number of recorded values
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Summarizes a series of observations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tally {
+    count: usize,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for Tally {
+    fn default() -> Self {
+        Tally {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl Tally {
+    /// Record an observation
+    pub fn record(&mut self, value: f64) {
+        // Welford's online algorithm
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Returns the number of observations
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the mean of the observations, 0 if there are none
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the sample variance of the observations, 0 if there are less than two
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Returns the sample standard deviation of the observations
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the smallest observation, if any
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Returns the largest observation, if any
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+}
+
+/// Summarizes a level that changes over time, weighting each value by how long it lasted.
+///
+/// The level is 0 from the time the accumulator is created until the first update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWeighted {
+    start: f64,
+    last_update: f64,
+    value: f64,
+    area: f64,
+    squares_area: f64,
+    min: f64,
+    max: f64,
+}
+
+impl TimeWeighted {
+    /// Create an accumulator starting at `time`
+    pub fn new(time: f64) -> TimeWeighted {
+        TimeWeighted {
+            start: time,
+            last_update: time,
+            value: 0.0,
+            area: 0.0,
+            squares_area: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Set the level to `value` from `time` on
+    pub fn update(&mut self, time: f64, value: f64) {
+        self.advance(time);
+        self.value = value;
+    }
+
+    /// Discard the history before `time`, keeping the current level
+    pub fn reset(&mut self, time: f64) {
+        let value = self.value;
+        *self = TimeWeighted {
+            value,
+            ..TimeWeighted::new(time)
+        };
+    }
+
+    /// Extend the current level up to `time`
+    pub fn advance(&mut self, time: f64) {
+        let elapsed = time - self.last_update;
+        if elapsed > 0.0 {
+            // a level replaced at the time it was set never held
+            self.min = self.min.min(self.value);
+            self.max = self.max.max(self.value);
+        }
+        self.area += self.value * elapsed;
+        self.squares_area += self.value * self.value * elapsed;
+        self.last_update = time;
+    }
+
+    /// Returns the current level
+    pub fn current(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns the time-weighted mean of the level, or the current level if no time
+    /// elapsed
+    pub fn mean(&self) -> f64 {
+        let duration = self.last_update - self.start;
+        if duration > 0.0 {
+            self.area / duration
+        } else {
+            self.value
+        }
+    }
+
+    /// Returns the time-weighted variance of the level
+    pub fn variance(&self) -> f64 {
+        let duration = self.last_update - self.start;
+        if duration > 0.0 {
+            (self.squares_area / duration - self.mean().powi(2)).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the time-weighted standard deviation of the level
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the lowest level held for some time, or the current one
+    pub fn min(&self) -> f64 {
+        self.min.min(self.value)
+    }
+
+    /// Returns the highest level held for some time, or the current one
+    pub fn max(&self) -> f64 {
+        self.max.max(self.value)
+    }
+}
+
+/// Counts observations in bins, to estimate the distribution of a quantity and its
+/// quantiles.
+///
+/// A linear histogram has bins of the same width in an interval, plus one bin for the
+/// observations below it and one for those above. A logarithmic histogram, like HDR
+/// histograms, divides each power of two in the same number of bins, so that it covers any
+/// range of positive values with a bounded relative error.
+///
+/// NaN and infinite observations fit in no bin: they are counted apart, see
+/// `Histogram::non_finite`, and ignored by the other methods.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bins: Bins,
+    counts: BTreeMap<i64, u64>,
+    count: u64,
+    non_finite: u64,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bins {
+    Linear { low: f64, width: f64, bins: i64 },
+    Log { sub_buckets: i64 },
+}
+
+impl Histogram {
+    /// Create a histogram with `bins` bins of the same width between `low` and `high`.
+    ///
+    /// # Panics
+    /// Panics if there are no bins or `high` is not greater than `low`.
+    pub fn linear(low: f64, high: f64, bins: usize) -> Histogram {
+        assert!(
+            bins > 0 && high > low,
+            "ERROR. A histogram needs at least a bin in a non-empty interval."
+        );
+        Histogram::with_bins(Bins::Linear {
+            low,
+            width: (high - low) / bins as f64,
+            bins: bins as i64,
+        })
+    }
+
+    /// Create a logarithmic histogram, dividing each power of two in `sub_buckets` bins.
+    /// The quantiles of positive values have a relative error of at most `1 / sub_buckets`;
+    /// values not greater than 0 are counted together.
+    ///
+    /// # Panics
+    /// Panics if `sub_buckets` is 0.
+    pub fn log(sub_buckets: u32) -> Histogram {
+        assert!(sub_buckets > 0, "ERROR. A histogram needs at least a bin.");
+        Histogram::with_bins(Bins::Log {
+            sub_buckets: sub_buckets as i64,
+        })
+    }
+
+    fn with_bins(bins: Bins) -> Histogram {
+        Histogram {
+            bins,
+            counts: BTreeMap::new(),
+            count: 0,
+            non_finite: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Record an observation
+    pub fn record(&mut self, value: f64) {
+        if !value.is_finite() {
+            self.non_finite += 1;
+            return;
+        }
+        *self.counts.entry(self.bin(value)).or_insert(0) += 1;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Returns the number of finite observations
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the number of NaN or infinite observations, that are not in any bin
+    pub fn non_finite(&self) -> u64 {
+        self.non_finite
+    }
+
+    /// Returns the smallest observation, if any
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Returns the largest observation, if any
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Returns the non-empty bins in increasing order, each with its bounds and the number
+    /// of observations in it. The bounds of the bins below and above the interval of a
+    /// linear histogram are extended to the smallest and largest observations.
+    pub fn bins(&self) -> impl Iterator<Item = (f64, f64, u64)> + '_ {
+        self.counts.iter().map(|(bin, count)| {
+            let (low, high) = self.bounds(*bin);
+            (low, high, *count)
+        })
+    }
+
+    /// Returns an estimate of the `q`-quantile of the observations, e.g. 0.95 for the 95th
+    /// percentile, or `None` if there are none: the middle of the bin that holds it, within
+    /// the smallest and the largest observation.
+    ///
+    /// # Panics
+    /// Panics if `q` is not in `[0, 1]`.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        assert!(
+            (0.0..=1.0).contains(&q),
+            "ERROR. Quantiles must be in [0, 1]."
+        );
+        let rank = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.bins().find_map(|(low, high, count)| {
+            seen += count;
+            (seen >= rank).then(|| ((low + high) / 2.0).clamp(self.min, self.max))
+        })
+    }
+
+    /// Discard all the observations
+    pub fn reset(&mut self) {
+        *self = Histogram::with_bins(self.bins);
+    }
+
+    /// Add the observations of another histogram with the same bins to this one.
+    ///
+    /// # Panics
+    /// Panics if the bins of the histograms differ.
+    pub fn merge(&mut self, other: &Histogram) {
+        assert!(
+            self.bins == other.bins,
+            "ERROR. Only histograms with the same bins can be merged."
+        );
+        for (bin, count) in other.counts.iter() {
+            *self.counts.entry(*bin).or_insert(0) += count;
+        }
+        self.count += other.count;
+        self.non_finite += other.non_finite;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    fn bin(&self, value: f64) -> i64 {
+        match self.bins {
+            Bins::Linear { low, width, bins } => {
+                if value < low {
+                    -1
+                } else {
+                    (((value - low) / width).floor() as i64).min(bins)
+                }
+            }
+            Bins::Log { sub_buckets } => {
+                if value <= 0.0 {
+                    return i64::MIN;
+                }
+                let exponent = value.log2().floor();
+                let mantissa = value / exponent.exp2();
+                let sub = (((mantissa - 1.0) * sub_buckets as f64) as i64).min(sub_buckets - 1);
+                exponent as i64 * sub_buckets + sub
+            }
+        }
+    }
+
+    fn bounds(&self, bin: i64) -> (f64, f64) {
+        match self.bins {
+            Bins::Linear { low, width, bins } => match bin {
+                -1 => (self.min, low),
+                b if b == bins => (low + width * bins as f64, self.max),
+                b => (low + width * b as f64, low + width * (b + 1) as f64),
+            },
+            Bins::Log { sub_buckets } => {
+                if bin == i64::MIN {
+                    return (self.min, 0.0);
+                }
+                let base = (bin.div_euclid(sub_buckets) as f64).exp2();
+                let sub = bin.rem_euclid(sub_buckets) as f64;
+                let width = base / sub_buckets as f64;
+                (base + width * sub, base + width * (sub + 1.0))
+            }
+        }
+    }
+}
+
+/// An accumulator of statistics of the model.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Statistic {
+    /// See `Simulation::create_counter`
+    Counter(Counter),
+    /// See `Simulation::create_tally`
+    Tally(Tally),
+    /// See `Simulation::create_time_weighted`
+    TimeWeighted(TimeWeighted),
+    /// See `Simulation::create_histogram`
+    Histogram(Histogram),
+}
+
+impl Statistic {
+    /// Record `value` at `time`: add it to a counter, observe it in a tally or set it as the
+    /// level of a time-weighted accumulator.
+    pub fn record(&mut self, time: f64, value: f64) {
+        match self {
+            Statistic::Counter(c) => c.add(value),
+            Statistic::Tally(t) => t.record(value),
+            Statistic::TimeWeighted(w) => w.update(time, value),
+            Statistic::Histogram(h) => h.record(value),
+        }
+    }
+
+    /// Discard what was recorded before `time`. The level of a time-weighted accumulator is
+    /// kept.
+    pub fn reset(&mut self, time: f64) {
+        match self {
+            Statistic::Counter(c) => *c = Counter::default(),
+            Statistic::Tally(t) => *t = Tally::default(),
+            Statistic::TimeWeighted(w) => w.reset(time),
+            Statistic::Histogram(h) => h.reset(),
+        }
+    }
+}
+
+/// The accumulators of statistics of a simulation, see `Simulation::stats`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    statistics: Vec<(String, Statistic)>,
+}
+
+impl Stats {
+    /// Returns the accumulator `stat`
+    pub fn get(&self, stat: StatId) -> &Statistic {
+        &self.statistics[stat].1
+    }
+
+    /// Returns the id of the accumulator called `name`, if any
+    pub fn find(&self, name: &str) -> Option<StatId> {
+        self.statistics.iter().position(|(n, _)| n == name)
+    }
+
+    /// Returns the name of the accumulator `stat`
+    pub fn name(&self, stat: StatId) -> &str {
+        &self.statistics[stat].0
+    }
+
+    /// Returns the accumulator `stat` if it is a counter
+    pub fn counter(&self, stat: StatId) -> Option<&Counter> {
+        match self.get(stat) {
+            Statistic::Counter(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the accumulator `stat` if it is a tally
+    pub fn tally(&self, stat: StatId) -> Option<&Tally> {
+        match self.get(stat) {
+            Statistic::Tally(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns the accumulator `stat` if it is time-weighted
+    pub fn time_weighted(&self, stat: StatId) -> Option<&TimeWeighted> {
+        match self.get(stat) {
+            Statistic::TimeWeighted(w) => Some(w),
+            _ => None,
+        }
+    }
+
+    /// Returns the accumulator `stat` if it is a histogram
+    pub fn histogram(&self, stat: StatId) -> Option<&Histogram> {
+        match self.get(stat) {
+            Statistic::Histogram(h) => Some(h),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of accumulators
+    pub fn len(&self) -> usize {
+        self.statistics.len()
+    }
+
+    /// Returns `true` if there are no accumulators
+    pub fn is_empty(&self) -> bool {
+        self.statistics.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, name: String, statistic: Statistic) -> StatId {
+        self.statistics.push((name, statistic));
+        self.statistics.len() - 1
+    }
+
+    pub(crate) fn record(&mut self, stat: StatId, time: f64, value: f64) {
+        self.statistics[stat].1.record(time, value);
+    }
+
+    pub(crate) fn reset(&mut self, time: f64) {
+        for (_, statistic) in self.statistics.iter_mut() {
+            statistic.reset(time);
+        }
+    }
+
+    /// Extend the time-weighted accumulators up to `time`.
+    pub(crate) fn advance(&mut self, time: f64) {
+        for (_, statistic) in self.statistics.iter_mut() {
+            if let Statistic::TimeWeighted(w) = statistic {
+                w.advance(time);
+            }
+        }
+    }
+}
+
+/// Sum two queue timelines.
+fn merge_timelines(a: &[(f64, usize)], b: &[(f64, usize)]) -> Vec<(f64, usize)> {
+    let (mut i, mut j) = (0, 0);
+    let (mut len_a, mut len_b) = (0, 0);
+    let mut merged: Vec<(f64, usize)> = Vec::with_capacity(a.len() + b.len());
+    while i < a.len() || j < b.len() {
+        let time = match (a.get(i), b.get(j)) {
+            (Some(x), Some(y)) => x.0.min(y.0),
+            (Some(x), None) => x.0,
+            (None, Some(y)) => y.0,
+            (None, None) => unreachable!(),
+        };
+        while i < a.len() && a[i].0 == time {
+            len_a = a[i].1;
+            i += 1;
+        }
+        while j < b.len() && b[j].0 == time {
+            len_b = b[j].1;
+            j += 1;
+        }
+        match merged.last_mut() {
+            Some(last) if last.0 == time => last.1 = len_a + len_b,
+            Some(last) if last.1 == len_a + len_b => {}
+            _ => merged.push((time, len_a + len_b)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests;
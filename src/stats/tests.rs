@@ -0,0 +1,145 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{Counter, Histogram, ResourceStats, Tally, TimeWeighted};
+use crate::ProcessId;
+
+#[test]
+fn resource_stats() {
+    let mut a = ResourceStats::default();
+    a.record_request();
+    a.record_queue(0.0, 1);
+    a.record_request();
+    a.record_grant(0.0);
+    a.record_queue(2.0, 0);
+    a.record_grant(2.0);
+    a.advance(4.0);
+    assert_eq!(a.mean_wait(), 1.0);
+    assert_eq!(a.mean_queue_len(), 0.5);
+
+    let mut b = ResourceStats::default();
+    b.record_request();
+    b.record_queue(0.0, 1);
+    b.advance(4.0);
+    b.merge(&a);
+    assert_eq!(b.requests(), 3);
+    assert_eq!(b.grants(), 2);
+    assert_eq!(b.max_wait(), 2.0);
+    assert_eq!(b.mean_queue_len(), 1.5);
+    assert_eq!(b.queue_timeline(), [(0.0, 2), (2.0, 1)]);
+    assert_eq!(b.wait_quantile(1.0), Some(2.0));
+    assert_eq!(b.wait_quantile(0.0), Some(0.0));
+
+    let mut c = ResourceStats::with_capacity(Some(2));
+    c.track_occupancy();
+    c.record_use(0.0, ProcessId(0), None, true);
+    c.record_use(1.0, ProcessId(1), None, true);
+    c.record_use(3.0, ProcessId(0), None, false);
+    c.advance(4.0);
+    assert_eq!(c.in_use(), 1);
+    assert_eq!(c.mean_in_use(), 1.5);
+    assert_eq!(c.utilization(), Some(0.75));
+    assert_eq!(
+        c.occupancy(),
+        [(0.0, 3.0, ProcessId(0)), (1.0, 4.0, ProcessId(1))]
+    );
+    c.reset(4.0);
+    assert_eq!(c.occupancy(), [(4.0, 4.0, ProcessId(1))]);
+    // without tracking, only the units in use are reported
+    let mut d = ResourceStats::with_capacity(Some(2));
+    d.record_use(0.0, ProcessId(0), None, true);
+    d.record_use(1.0, ProcessId(1), None, true);
+    d.record_use(3.0, ProcessId(0), None, false);
+    d.advance(4.0);
+    assert_eq!(d.occupancy(), [(1.0, 4.0, ProcessId(1))]);
+    assert_eq!(ResourceStats::default().utilization(), None);
+}
+
+#[test]
+fn accumulators() {
+    let mut c = Counter::default();
+    c.add(1.0);
+    c.add(2.0);
+    assert_eq!((c.value(), c.count()), (3.0, 2));
+
+    let mut t = Tally::default();
+    assert_eq!(t.min(), None);
+    for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+        t.record(x);
+    }
+    assert_eq!(t.mean(), 5.0);
+    assert_eq!(t.variance(), 32.0 / 7.0);
+    assert_eq!((t.min(), t.max()), (Some(2.0), Some(9.0)));
+
+    let mut w = TimeWeighted::new(0.0);
+    w.update(1.0, 2.0);
+    w.update(3.0, 1.0);
+    w.advance(4.0);
+    assert_eq!(w.mean(), 1.25);
+    assert_eq!(w.variance(), 9.0 / 4.0 - 1.25 * 1.25);
+    assert_eq!((w.min(), w.max(), w.current()), (0.0, 2.0, 1.0));
+
+    let mut w = TimeWeighted::new(0.0);
+    w.update(0.0, 5.0);
+    w.update(2.0, 3.0);
+    assert_eq!((w.min(), w.max()), (3.0, 5.0));
+    w.reset(2.0);
+    assert_eq!((w.min(), w.max()), (3.0, 3.0));
+}
+
+#[test]
+fn histograms() {
+    let mut linear = Histogram::linear(0.0, 10.0, 5);
+    for x in [-1.0, 0.5, 1.0, 2.5, 3.0, 9.0, 12.0] {
+        linear.record(x);
+    }
+    let bins: Vec<_> = linear.bins().collect();
+    assert_eq!(
+        bins,
+        [
+            (-1.0, 0.0, 1),
+            (0.0, 2.0, 2),
+            (2.0, 4.0, 2),
+            (8.0, 10.0, 1),
+            (10.0, 12.0, 1)
+        ]
+    );
+    assert_eq!(linear.quantile(0.5), Some(3.0));
+    assert_eq!(linear.quantile(0.0), Some(-0.5));
+    assert_eq!(linear.quantile(1.0), Some(11.0));
+
+    let mut log = Histogram::log(100);
+    assert_eq!(log.quantile(0.5), None);
+    for i in 1..=1000 {
+        log.record(i as f64 / 10.0);
+    }
+    for (q, exact) in [(0.5, 50.0), (0.95, 95.0), (0.99, 99.0)] {
+        let estimate = log.quantile(q).unwrap();
+        assert!((estimate - exact).abs() / exact <= 0.01);
+    }
+    let mut merged = Histogram::log(100);
+    merged.record(0.0);
+    merged.merge(&log);
+    assert_eq!((merged.count(), merged.min()), (1001, Some(0.0)));
+
+    for x in [f64::NAN, f64::INFINITY] {
+        linear.record(x);
+        log.record(x);
+    }
+    assert_eq!((linear.count(), linear.non_finite()), (7, 2));
+    assert_eq!(linear.bins().count(), 5);
+    assert_eq!((log.count(), log.non_finite()), (1000, 2));
+    assert_eq!(log.max(), Some(100.0));
+}
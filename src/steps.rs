@@ -0,0 +1,79 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Iterating over the steps of a simulation.
+//!
+//! `Simulation::iter_steps` runs the simulation lazily: each call to `next` executes steps
+//! until a process is resumed, and returns the event together with the state yielded by
+//! the process. Online statistics can be computed while the simulation advances; to avoid
+//! retaining the log in memory, combine it with a `DiscardSink`
+//! (see the [`sink`](crate::sink) module).
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let p = sim.create_process(Box::new(#[coroutine] |_| loop {
+//!     yield Effect::TimeOut(1.0);
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! let times: Vec<f64> = sim
+//!     .iter_steps(EndCondition::Time(3.0))
+//!     .map(|(event, _)| event.time())
+//!     .collect();
+//! assert_eq!(times, [0.0, 1.0, 2.0, 3.0]);
+//! ```
+use crate::{EndCondition, Event, SimState, Simulation, StepResult};
+
+/// An iterator over the events executed by a simulation, returned by
+/// `Simulation::iter_steps`.
+pub struct Steps<'a, T: SimState + Clone> {
+    sim: &'a mut Simulation<T>,
+    until: EndCondition,
+}
+
+impl<T: 'static + SimState + Clone> Simulation<T> {
+    /// Returns an iterator that runs the simulation until the ending condition is met,
+    /// yielding each event that resumed a process together with the state the process
+    /// yielded. See the [`steps`](crate::steps) module.
+    ///
+    /// Like `run`, the iteration stops if a step fails, and the error is returned by `error`.
+    pub fn iter_steps(&mut self, until: EndCondition) -> Steps<'_, T> {
+        self.start_run();
+        Steps { sim: self, until }
+    }
+}
+
+impl<T: 'static + SimState + Clone> Iterator for Steps<'_, T> {
+    type Item = (Event<T>, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.sim.should_stop(&self.until) {
+            match self.sim.step() {
+                Ok(StepResult::Executed { event, state }) => return Some((event, state)),
+                Ok(_) => {}
+                Err(e) => {
+                    self.sim.fail(e);
+                    break;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "coroutines"))]
+mod tests;
@@ -0,0 +1,42 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::sink::DiscardSink;
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn iter_steps() {
+    let mut s = Simulation::new();
+    s.set_log_sink(Box::new(DiscardSink));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            for i in 1..=3 {
+                yield Effect::TimeOut(i as f64);
+            }
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let mut total = 0.0;
+    for (_, state) in s.iter_steps(EndCondition::NoEvents) {
+        if let Effect::TimeOut(t) = state {
+            total += t;
+        }
+    }
+    assert_eq!(total, 6.0);
+    assert_eq!(s.time(), 6.0);
+    assert!(s.processed_events().is_empty());
+    assert!(s.iter_steps(EndCondition::NoEvents).next().is_none());
+}
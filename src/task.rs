@@ -0,0 +1,247 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Processes written as async functions.
+//!
+//! An `AsyncProcess` wraps a future built from a `Sim` handle. Each time the future awaits
+//! one of the operations of the handle, e.g. `sim.timeout(5.0).await`, the process yields
+//! the corresponding state to the simulation, and the await completes with the context of
+//! the step that resumed the process. The simulation drives the future: it is polled only
+//! when the process is resumed, so it must not await anything other than the operations
+//! of its `Sim` handle.
+//!
+//! Like `process::StateMachine`, this lets a model be written without enabling the
+//! unstable `coroutines` feature of Rust, and it works with the default `coroutines`
+//! feature of desim disabled, on stable Rust.
+//!
+//! ```
+//! use desim::resources::SimpleResource;
+//! use desim::task::AsyncProcess;
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let mut sim = Simulation::new();
+//! let r = sim.create_resource(Box::new(SimpleResource::new(1)));
+//! for _ in 0..2 {
+//!     let p = sim.create_process(Box::new(AsyncProcess::new(move |sim| async move {
+//!         sim.request(r).await;
+//!         sim.timeout(3.0).await;
+//!         sim.release(r).await;
+//!     })));
+//!     sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+//! }
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(sim.time(), 6.0);
+//! ```
+use crate::process::ProcessStep;
+use crate::{Effect, ResourceId, SimContext, StoreId};
+use std::future::Future;
+#[cfg(feature = "coroutines")]
+use std::ops::{Coroutine, CoroutineState};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[cfg(not(feature = "send"))]
+type BoxedFuture = Pin<Box<dyn Future<Output = ()>>>;
+#[cfg(feature = "send")]
+type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// What is passed between the process and the future at each step.
+struct Exchange<T> {
+    /// State yielded by the future
+    yielded: Option<T>,
+    /// Context the process was resumed with
+    ctx: Option<SimContext<T>>,
+    /// Time of the last step
+    time: f64,
+}
+
+/// Handle used by the future of an `AsyncProcess` to interact with the simulation.
+pub struct Sim<T> {
+    exchange: Arc<Mutex<Exchange<T>>>,
+}
+
+impl<T> Clone for Sim<T> {
+    fn clone(&self) -> Self {
+        Sim {
+            exchange: Arc::clone(&self.exchange),
+        }
+    }
+}
+
+impl<T> Sim<T> {
+    /// Yield `state` to the simulation. The returned future completes with the context
+    /// the process is resumed with.
+    pub fn yield_state(&self, state: T) -> Resume<T> {
+        Resume {
+            exchange: Arc::clone(&self.exchange),
+            state: Some(state),
+        }
+    }
+
+    /// Returns the simulation time of the last step of the process
+    pub fn time(&self) -> f64 {
+        self.exchange.lock().unwrap().time
+    }
+}
+
+impl Sim<Effect> {
+    /// Wait for `time`
+    pub fn timeout(&self, time: f64) -> Resume<Effect> {
+        self.yield_state(Effect::TimeOut(time))
+    }
+
+    /// Request a unit of `resource`
+    pub fn request(&self, resource: ResourceId) -> Resume<Effect> {
+        self.yield_state(Effect::Request(resource))
+    }
+
+    /// Release a unit of `resource`
+    pub fn release(&self, resource: ResourceId) -> Resume<Effect> {
+        self.yield_state(Effect::Release(resource))
+    }
+
+    /// Push an item into `store`
+    pub fn push(&self, store: StoreId) -> Resume<Effect> {
+        self.yield_state(Effect::Push(store))
+    }
+
+    /// Pull an item from `store`
+    pub fn pull(&self, store: StoreId) -> Resume<Effect> {
+        self.yield_state(Effect::Pull(store))
+    }
+}
+
+/// Future returned by the operations of `Sim`.
+pub struct Resume<T> {
+    exchange: Arc<Mutex<Exchange<T>>>,
+    state: Option<T>,
+}
+
+impl<T> Unpin for Resume<T> {}
+
+impl<T> Future for Resume<T> {
+    type Output = SimContext<T>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<SimContext<T>> {
+        let this = self.get_mut();
+        let mut exchange = this.exchange.lock().unwrap();
+        if let Some(state) = this.state.take() {
+            exchange.yielded = Some(state);
+            // the context of the current step was already used
+            exchange.ctx = None;
+            return Poll::Pending;
+        }
+        match exchange.ctx.take() {
+            Some(ctx) => Poll::Ready(ctx),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Adapts a future to be used as a `Process`.
+pub struct AsyncProcess<T> {
+    exchange: Arc<Mutex<Exchange<T>>>,
+    future: BoxedFuture,
+}
+
+impl<T> AsyncProcess<T> {
+    /// Build the future of the process from its `Sim` handle
+    #[cfg(not(feature = "send"))]
+    pub fn new<F, Fut>(process: F) -> AsyncProcess<T>
+    where
+        F: FnOnce(Sim<T>) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let (exchange, sim) = Self::handle();
+        AsyncProcess {
+            exchange,
+            future: Box::pin(process(sim)),
+        }
+    }
+
+    /// Build the future of the process from its `Sim` handle
+    #[cfg(feature = "send")]
+    pub fn new<F, Fut>(process: F) -> AsyncProcess<T>
+    where
+        F: FnOnce(Sim<T>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (exchange, sim) = Self::handle();
+        AsyncProcess {
+            exchange,
+            future: Box::pin(process(sim)),
+        }
+    }
+
+    fn handle() -> (Arc<Mutex<Exchange<T>>>, Sim<T>) {
+        let exchange = Arc::new(Mutex::new(Exchange {
+            yielded: None,
+            ctx: None,
+            time: 0.0,
+        }));
+        let sim = Sim {
+            exchange: Arc::clone(&exchange),
+        };
+        (exchange, sim)
+    }
+}
+
+impl<T> AsyncProcess<T> {
+    /// Poll the future with the context the process is resumed with.
+    ///
+    /// # Panics
+    /// Panics if the future awaits something other than an operation of its `Sim` handle.
+    fn poll_step(&mut self, ctx: SimContext<T>) -> ProcessStep<T> {
+        {
+            let mut exchange = self.exchange.lock().unwrap();
+            exchange.time = ctx.time();
+            exchange.ctx = Some(ctx);
+        }
+        let mut cx = Context::from_waker(Waker::noop());
+        if self.future.as_mut().poll(&mut cx).is_ready() {
+            return ProcessStep::Complete;
+        }
+        let yielded = self.exchange.lock().unwrap().yielded.take();
+        match yielded {
+            Some(state) => ProcessStep::Yield(state),
+            None => panic!(
+                "ERROR. An async process awaited a future that is not an operation of its Sim handle."
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "coroutines")]
+impl<T> Coroutine<SimContext<T>> for AsyncProcess<T> {
+    type Yield = T;
+    type Return = ();
+
+    fn resume(self: Pin<&mut Self>, ctx: SimContext<T>) -> CoroutineState<T, ()> {
+        match self.get_mut().poll_step(ctx) {
+            ProcessStep::Yield(state) => CoroutineState::Yielded(state),
+            ProcessStep::Complete => CoroutineState::Complete(()),
+        }
+    }
+}
+
+#[cfg(not(feature = "coroutines"))]
+impl<T> crate::process::ProcessFn<T> for AsyncProcess<T> {
+    fn resume(&mut self, ctx: SimContext<T>) -> ProcessStep<T> {
+        self.poll_step(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests;
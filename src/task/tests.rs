@@ -0,0 +1,33 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::AsyncProcess;
+use crate::{Effect, EndCondition, Simulation};
+
+#[test]
+fn async_process() {
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(AsyncProcess::new(|sim| async move {
+        for i in 1..=3 {
+            let ctx = sim.timeout(1.0).await;
+            assert_eq!(ctx.time(), i as f64);
+            assert_eq!(sim.time(), i as f64);
+        }
+    })));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+    let s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.time(), 3.0);
+    assert_eq!(s.active_processes(), 0);
+}
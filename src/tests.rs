@@ -0,0 +1,3012 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+#[test]
+fn it_works() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            let mut a = 0.0;
+            loop {
+                a += 1.0;
+
+                yield Effect::TimeOut(a);
+            }
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    s.step().unwrap();
+    assert_eq!(s.time(), 1.0);
+    s.step().unwrap();
+    assert_eq!(s.time(), 3.0);
+    s.step().unwrap();
+    assert_eq!(s.time(), 6.0);
+}
+
+#[test]
+fn run() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            let tik = 0.7;
+            loop {
+                println!("tik");
+                yield Effect::TimeOut(tik);
+            }
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let s = s.run(EndCondition::Time(10.0));
+    println!("{}", s.time());
+    assert!(s.time() >= 10.0);
+}
+
+#[test]
+fn resource() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+
+    // simple process that lock the resource for 7 time units
+    let p1 = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(7.0);
+            yield Effect::Release(r);
+        },
+    ));
+    // simple process that holds the resource for 3 time units
+    let p2 = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(3.0);
+            yield Effect::Release(r);
+        },
+    ));
+
+    // let p1 start immediately...
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.)).unwrap();
+    // let p2 start after 2 t.u., when r is not available
+    s.schedule_event(2.0, p2, Effect::TimeOut(2.)).unwrap();
+    // p2 will wait r to be free (time 7.0) and its timeout
+    // of 3.0 t.u. The simulation will end at time 10.0
+
+    let s = s.run(NoEvents);
+    println!("{:?}", s.processed_events());
+    assert_eq!(s.time(), 10.0);
+}
+
+#[test]
+fn store() {
+    use crate::resources::SimpleStore;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut sim = Simulation::new();
+    let store = sim.create_store(Box::new(SimpleStore::new(1)));
+
+    // simple process that pulls out of the store immediately and after 7 time units
+    let p1 = sim.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Pull(store);
+            yield Effect::TimeOut(7.0);
+            yield Effect::Pull(store);
+        },
+    ));
+    // simple process that pushes into the store immediately and after 3 time units
+    let p2 = sim.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Push(store);
+            yield Effect::TimeOut(3.0);
+            yield Effect::Push(store);
+        },
+    ));
+
+    // let p1 start immediately...
+    sim.schedule_event(0.0, p1, Effect::TimeOut(0.)).unwrap();
+    // let p2 start after 2 t.u., when r is not available
+    sim.schedule_event(2.0, p2, Effect::TimeOut(2.)).unwrap();
+    // p2 will wait r to be free (time 7.0) and its timeout
+    // of 3.0 t.u. The simulation will end at time 10.0
+
+    let s = sim.run(NoEvents);
+    println!("{:?}", s.processed_events());
+    assert_eq!(s.time(), 9.0);
+}
+
+#[test]
+fn queue_disciplines() {
+    use crate::resources::{QueueDiscipline, SimpleResource};
+    use crate::rng::{Philox4x32, StreamRng};
+    use crate::{Effect, EndCondition::NoEvents, SimState, Simulation};
+
+    #[derive(Debug, Clone)]
+    struct State {
+        effect: Effect,
+        job: usize,
+    }
+    impl SimState for State {
+        fn get_effect(&self) -> Effect {
+            self.effect
+        }
+        fn set_effect(&mut self, effect: Effect) {
+            self.effect = effect;
+        }
+        fn should_log(&self) -> bool {
+            true
+        }
+    }
+    const SERVICE: [f64; 3] = [3.0, 1.0, 2.0];
+    const PRIORITY: [i32; 3] = [2, 1, 3];
+
+    // the jobs arrive in order while the resource is held, returns the order of service
+    let served = |discipline| {
+        let mut sim = Simulation::new();
+        let r = sim.create_resource(Box::new(SimpleResource::with_discipline(1, discipline)));
+        let state = move |effect, job| State { effect, job };
+        let holder = sim.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield state(Effect::Request(r), 0);
+                yield state(Effect::TimeOut(10.0), 0);
+                yield state(Effect::Release(r), 0);
+            },
+        ));
+        sim.schedule_event(0.0, holder, state(Effect::TimeOut(0.), 0))
+            .unwrap();
+        for job in 0..3 {
+            let p = sim.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield state(Effect::Request(r), job);
+                    yield state(Effect::Trace, job);
+                    yield state(Effect::TimeOut(1.0), job);
+                    yield state(Effect::Release(r), job);
+                },
+            ));
+            sim.schedule_event(1.0 + job as f64, p, state(Effect::TimeOut(0.), job))
+                .unwrap();
+        }
+        sim.run(NoEvents)
+            .processed_events()
+            .iter()
+            .filter(|(_, state)| matches!(state.effect, Effect::Trace))
+            .map(|(_, state)| state.job)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(served(QueueDiscipline::Fifo), vec![0, 1, 2]);
+    assert_eq!(served(QueueDiscipline::Lifo), vec![2, 1, 0]);
+    assert_eq!(
+        served(QueueDiscipline::ShortestService(|s| SERVICE[s.job])),
+        vec![1, 2, 0]
+    );
+    assert_eq!(
+        served(QueueDiscipline::Priority(|s| PRIORITY[s.job])),
+        vec![2, 0, 1]
+    );
+    let random = || QueueDiscipline::Random(Philox4x32::from_stream(7, 0, 0));
+    let mut order = served(random());
+    assert_eq!(order, served(random()));
+    order.sort();
+    assert_eq!(order, vec![0, 1, 2]);
+}
+
+#[test]
+fn overflow_policies() {
+    use crate::resources::{OverflowPolicy, SimpleStore};
+    use crate::{Effect, EndCondition::NoEvents, EndReason, Outcome, SimContext, Simulation};
+
+    for policy in [
+        OverflowPolicy::Block,
+        OverflowPolicy::DropNewest,
+        OverflowPolicy::DropOldest,
+        OverflowPolicy::Reject,
+    ] {
+        let mut s = Simulation::new();
+        let store = s.create_store(Box::new(SimpleStore::with_overflow_policy(2, policy)));
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_: SimContext<Effect>| {
+                for i in 0..4 {
+                    let ctx = yield Effect::Push(store);
+                    let expected = match policy {
+                        OverflowPolicy::Reject if i >= 2 => Outcome::Full(store),
+                        _ => Outcome::Pushed(store),
+                    };
+                    assert_eq!(ctx.outcome(), expected);
+                    yield Effect::TimeOut(1.0);
+                }
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        s = s.run(NoEvents);
+        assert_eq!(s.store(store).level(), 2);
+        match policy {
+            OverflowPolicy::Block => {
+                assert!(matches!(s.end_reason(), Some(EndReason::Deadlock(_))));
+                assert_eq!(s.time(), 2.0);
+            }
+            OverflowPolicy::DropNewest | OverflowPolicy::DropOldest => {
+                assert_eq!(s.store(store).dropped(), 2);
+                assert_eq!(s.time(), 4.0);
+            }
+            OverflowPolicy::Reject => {
+                assert_eq!(s.store(store).dropped(), 0);
+                assert_eq!(s.time(), 4.0);
+            }
+        }
+    }
+}
+
+#[test]
+fn soft_limits() {
+    use crate::limits::SoftLimit;
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    s.add_soft_limit(SoftLimit::QueueLength {
+        resource: r,
+        max: 1,
+    });
+    s.add_soft_limit(SoftLimit::WaitTime {
+        resource: r,
+        max: 5.0,
+    });
+    let hook_calls = Arc::new(AtomicUsize::new(0));
+    let calls = hook_calls.clone();
+    s.on_warning(Box::new(move |_| {
+        calls.fetch_add(1, Ordering::Relaxed);
+    }));
+
+    // three processes hold the resource for 4 time units each
+    for _ in 0..3 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(4.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+
+    let s = s.run(NoEvents);
+    // the queue reaches length 2 once, the last process waits 8 time units
+    let warnings = s.warnings();
+    assert_eq!(warnings.len(), 2);
+    assert!(matches!(warnings[0].limit, SoftLimit::QueueLength { .. }));
+    assert_eq!(warnings[0].value, 2.0);
+    assert!(matches!(warnings[1].limit, SoftLimit::WaitTime { .. }));
+    assert_eq!(warnings[1].value, 8.0);
+    assert_eq!(hook_calls.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn event_budget() {
+    use crate::limits::BudgetPolicy;
+    use crate::{Effect, EndCondition::NSteps, Simulation};
+
+    let mut s = Simulation::new();
+    // a process stuck in a zero-delay loop
+    let looping = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(0.0);
+        },
+    ));
+    let well_behaved = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.set_event_budget(10, BudgetPolicy::Suspend);
+    s.schedule_event(0.0, looping, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(0.0, well_behaved, Effect::TimeOut(0.))
+        .unwrap();
+
+    let s = s.run(NSteps(20));
+    assert_eq!(s.budget_violations().len(), 1);
+    assert_eq!(s.budget_violations()[0].process, looping);
+    assert_eq!(s.budget_violations()[0].events, 11);
+    // once the looping process was suspended the other one could go on
+    assert_eq!(s.time(), 1.0);
+}
+
+#[test]
+fn item_store() {
+    use crate::resources::{ItemStore, Payload};
+    use crate::{Effect, EndCondition::NoEvents, SimContext, SimState, Simulation};
+
+    #[derive(Debug, Clone)]
+    struct State {
+        effect: Effect,
+        item: Option<u32>,
+    }
+    impl SimState for State {
+        fn get_effect(&self) -> Effect {
+            self.effect
+        }
+        fn set_effect(&mut self, effect: Effect) {
+            self.effect = effect;
+        }
+        fn should_log(&self) -> bool {
+            true
+        }
+    }
+    impl Payload<u32> for State {
+        fn take_item(&mut self) -> Option<u32> {
+            self.item.take()
+        }
+        fn put_item(&mut self, item: u32) {
+            self.item = Some(item);
+        }
+    }
+    let state = |effect, item| State { effect, item };
+
+    let mut sim = Simulation::new();
+    let store = sim.create_store(Box::new(ItemStore::<State, u32>::new(1)));
+    let producer = sim.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            for i in 1..=3 {
+                yield state(Effect::Push(store), Some(i));
+            }
+        },
+    ));
+    let consumer = sim.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<State>| {
+            let mut received = Vec::new();
+            for _ in 0..3 {
+                let ctx = yield state(Effect::Pull(store), None);
+                received.push(ctx.state().item.unwrap());
+                yield state(Effect::TimeOut(1.0), None);
+            }
+            assert_eq!(received, vec![1, 2, 3]);
+        },
+    ));
+    sim.schedule_event(0.0, producer, state(Effect::TimeOut(0.), None))
+        .unwrap();
+    sim.schedule_event(0.0, consumer, state(Effect::TimeOut(0.), None))
+        .unwrap();
+    let sim = sim.run(NoEvents);
+    assert_eq!(sim.time(), 3.0);
+}
+
+#[test]
+fn filter_store() {
+    use crate::resources::{FilterStore, ItemFilter, Payload};
+    use crate::{Effect, EndCondition::NoEvents, SimContext, SimState, Simulation};
+
+    // jobs are identified by their class
+    #[derive(Debug, Clone)]
+    struct State {
+        effect: Effect,
+        job: Option<char>,
+        class: char,
+    }
+    impl SimState for State {
+        fn get_effect(&self) -> Effect {
+            self.effect
+        }
+        fn set_effect(&mut self, effect: Effect) {
+            self.effect = effect;
+        }
+        fn should_log(&self) -> bool {
+            true
+        }
+    }
+    impl Payload<char> for State {
+        fn take_item(&mut self) -> Option<char> {
+            self.job.take()
+        }
+        fn put_item(&mut self, item: char) {
+            self.job = Some(item);
+        }
+    }
+    impl ItemFilter<char> for State {
+        fn accepts(&self, item: &char) -> bool {
+            *item == self.class
+        }
+    }
+    let state = |effect, job, class| State { effect, job, class };
+
+    let mut sim = Simulation::new();
+    let store = sim.create_store(Box::new(FilterStore::<State, char>::new(10)));
+    let producer = sim.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            for job in ['a', 'b', 'a'] {
+                yield state(Effect::Push(store), Some(job), ' ');
+                yield state(Effect::TimeOut(1.0), None, ' ');
+            }
+        },
+    ));
+    let mut workers = Vec::new();
+    for (class, jobs) in [('b', 1), ('a', 2)] {
+        workers.push(sim.create_process(Box::new(
+            #[coroutine]
+            move |_: SimContext<State>| {
+                for _ in 0..jobs {
+                    let ctx = yield state(Effect::Pull(store), None, class);
+                    assert_eq!(ctx.state().job, Some(class));
+                }
+            },
+        )));
+    }
+    sim.schedule_event(0.0, producer, state(Effect::TimeOut(0.), None, ' '))
+        .unwrap();
+    for w in workers {
+        sim.schedule_event(0.0, w, state(Effect::TimeOut(0.), None, ' '))
+            .unwrap();
+    }
+    let sim = sim.run(NoEvents);
+    assert_eq!(sim.time(), 3.0);
+    // every job was pulled by a worker of the right class
+    let pulled = sim
+        .processed_events()
+        .iter()
+        .filter(|(_, s)| matches!(s.effect, Effect::Pull(_)))
+        .count();
+    assert_eq!(pulled, 3);
+
+    // without capacity, the matching item is taken from the waiting producer
+    let mut sim = Simulation::new();
+    let store = sim.create_store(Box::new(FilterStore::<State, char>::new(0)));
+    let producer = sim.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield state(Effect::Push(store), Some('a'), ' ');
+        },
+    ));
+    let worker = sim.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<State>| {
+            let ctx = yield state(Effect::Pull(store), None, 'a');
+            assert_eq!(ctx.time(), 1.0);
+            assert_eq!(ctx.state().job, Some('a'));
+        },
+    ));
+    sim.schedule_event(0.0, producer, state(Effect::TimeOut(0.), None, ' '))
+        .unwrap();
+    sim.schedule_event(1.0, worker, state(Effect::TimeOut(0.), None, ' '))
+        .unwrap();
+    let sim = sim.run(NoEvents);
+    assert_eq!(sim.active_processes(), 0);
+}
+
+#[test]
+fn resume_process() {
+    use crate::error::SimError;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_: SimContext<Effect>| {
+            let ctx = yield Effect::Wait;
+            // the decision taken outside is received as state
+            if let Effect::TimeOut(t) = ctx.state() {
+                yield Effect::TimeOut(*t);
+            }
+        },
+    ));
+    s.schedule_event(1.0, p, Effect::TimeOut(1.)).unwrap();
+    assert_eq!(
+        s.resume_process(p, Effect::Trace),
+        Err(SimError::ProcessNotWaiting(p))
+    );
+    s = s.run(NoEvents);
+    assert!(s.is_waiting(p));
+    assert_eq!(s.time(), 1.0);
+
+    s.resume_process(p, Effect::TimeOut(4.0)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 5.0);
+    assert_eq!(
+        s.resume_process(p, Effect::Trace),
+        Err(SimError::ProcessCompleted(p))
+    );
+}
+
+#[test]
+fn metadata() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::<Effect>::new();
+    s.metadata_mut().set_seed(42);
+    s.metadata_mut().set_parameter("servers", 3);
+    assert!(s.metadata().started_at().is_none());
+    s.step().unwrap();
+    assert!(s.metadata().started_at().is_some());
+    let entries = s.metadata().entries();
+    assert!(entries.contains(&("seed".to_string(), "42".to_string())));
+    assert!(entries.contains(&("param.servers".to_string(), "3".to_string())));
+    assert_eq!(s.metadata().parameter("servers"), Some("3"));
+}
+
+#[test]
+fn interrupt() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, ProcessId, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    // works for 10 time units, unless it is interrupted
+    let worker = s.create_process(Box::new(
+        #[coroutine]
+        |_: SimContext<Effect>| {
+            let ctx = yield Effect::TimeOut(10.0);
+            assert!(ctx.interrupted());
+            assert_eq!(ctx.time(), 3.0);
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    let breakdown = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(3.0);
+            yield Effect::Interrupt(worker);
+        },
+    ));
+    // holds the resource forever
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::Wait;
+        },
+    ));
+    // waits for the resource until interrupted
+    let waiting = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::Request(r);
+            assert!(ctx.interrupted());
+        },
+    ));
+    for p in [worker, breakdown, holder, waiting] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    // the timeout of the worker at time 10 was discarded
+    assert_eq!(s.time(), 4.0);
+
+    assert_eq!(s.resource(r).queue_len(), 1);
+    s.interrupt(waiting, Effect::Trace).unwrap();
+    // the interrupted process left the queue of the resource
+    assert_eq!(s.resource(r).queue_len(), 0);
+    s = s.run(NoEvents);
+    assert!(s.interrupt(waiting, Effect::Trace).is_err());
+
+    // a process that interrupts itself is resumed once
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_: SimContext<Effect>| {
+            let ctx = yield Effect::Interrupt(ProcessId::from_raw(0));
+            assert!(ctx.interrupted());
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    assert_eq!(p, ProcessId::from_raw(0));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!((s.time(), s.steps()), (1.0, 3));
+}
+
+#[test]
+fn conditions() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r1 = s.create_resource(Box::new(SimpleResource::new(1)));
+    let r2 = s.create_resource(Box::new(SimpleResource::new(1)));
+    let any = s.create_condition(vec![Effect::TimeOut(2.0), Effect::Request(r1)]);
+    let all = s.create_condition(vec![Effect::TimeOut(1.0), Effect::Request(r2)]);
+    // holds r1 forever
+    let holder1 = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r1);
+            yield Effect::Wait;
+        },
+    ));
+    // holds r2 until time 6
+    let holder2 = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r2);
+            yield Effect::TimeOut(6.0);
+            yield Effect::Release(r2);
+        },
+    ));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::TimeOut(0.0);
+            assert_eq!(ctx.time(), 0.0);
+            // the timeout wins
+            let ctx = yield Effect::AnyOf(any);
+            assert_eq!(ctx.fired(), Some(0));
+            assert_eq!(ctx.time(), 2.0);
+            // resumed when r2 is granted
+            let ctx = yield Effect::AllOf(all);
+            assert_eq!(ctx.fired(), None);
+            assert_eq!(ctx.time(), 6.0);
+            yield Effect::Release(r2);
+        },
+    ));
+    for p in [holder1, holder2, p] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 6.0);
+    // the request of the cancelled effect left the queue
+    assert_eq!(s.resource(r1).queue_len(), 0);
+}
+
+#[test]
+fn cancel_event() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let alarm = s.create_process(Box::new(
+        #[coroutine]
+        |ctx: SimContext<Effect>| {
+            // the first event was cancelled
+            assert_eq!(ctx.time(), 10.0);
+        },
+    ));
+    let first = s.schedule_event(5.0, alarm, Effect::Trace).unwrap();
+    let second = s.schedule_event(10.0, alarm, Effect::Trace).unwrap();
+    assert_ne!(first, second);
+    let canceller = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Cancel(first);
+        },
+    ));
+    s.schedule_event(1.0, canceller, Effect::Trace).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 10.0);
+
+    // a process cancels the event it scheduled for another one
+    let mut s = Simulation::new();
+    let alarm = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            panic!("the alarm was cancelled");
+        },
+    ));
+    let timer = s.create_process(Box::new(
+        #[coroutine]
+        move |ctx: SimContext<Effect>| {
+            assert_eq!(ctx.scheduled(), None);
+            let ctx = yield Effect::Event {
+                time: 5.0,
+                process: alarm,
+            };
+            assert_eq!(ctx.time(), 1.0);
+            let alarm_event = ctx.scheduled().unwrap();
+            assert_ne!(alarm_event, ctx.event_id());
+            yield Effect::Cancel(alarm_event);
+        },
+    ));
+    let start = s.schedule_event(0.0, timer, Effect::Trace).unwrap();
+    s.schedule_event(1.0, timer, Effect::Trace).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 1.0);
+    assert_eq!(s.processed_events()[0].0.id(), start);
+
+    // the ids of the events that already fired do not pile up
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    let first = s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    for fired in first..first + 100 {
+        s.step().unwrap();
+        s.cancel_event(fired);
+    }
+    assert!(s.cancelled_events.len() < 20);
+}
+
+#[test]
+fn request_group() {
+    use crate::error::SimError;
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Outcome, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let counters: Vec<_> = (0..2)
+        .map(|_| s.create_resource(Box::new(SimpleResource::new(1))))
+        .collect();
+    let group = s.create_resource_group("counters", counters.clone());
+    let mut customers = Vec::new();
+    for _ in 0..3 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                let ctx: SimContext<Effect> = yield Effect::RequestGroup(group);
+                let Outcome::Granted(r) = ctx.outcome() else {
+                    panic!("the request was not granted");
+                };
+                yield Effect::TimeOut(2.0);
+                let ctx = yield Effect::ReleaseGroup(group);
+                assert_eq!(ctx.outcome(), Outcome::Released(r));
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        customers.push(p);
+    }
+    while s.peek_next_time() == Some(0.0) {
+        s.step().unwrap();
+    }
+    assert_eq!(s.resource_holders(counters[0]), [(customers[0], 1)]);
+    assert_eq!(s.resource_holders(counters[1]), [(customers[1], 1)]);
+    // both queues are empty, the third customer waits for the first counter
+    assert_eq!(s.resource(counters[0]).queue_len(), 1);
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 4.0);
+    assert_eq!(s.group_stats(group).grants(), 3);
+
+    let mut s = Simulation::new();
+    let empty = s.create_resource_group("empty", Vec::new());
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::RequestGroup(empty);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    assert!(matches!(s.step(), Err(SimError::UnknownGroup(g)) if g == empty));
+
+    // releasing a group whose members are not held follows the release policy
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let group = s.create_resource_group("counter", vec![r]);
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::ReleaseGroup(group);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    assert_eq!(
+        s.step().unwrap_err(),
+        SimError::NotHolding {
+            process: p,
+            resource: r
+        }
+    );
+    assert_eq!(s.resource(r).available(), Some(1));
+}
+
+#[test]
+fn resource_group() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let counters: Vec<_> = (0..2)
+        .map(|_| s.create_resource(Box::new(SimpleResource::new(1))))
+        .collect();
+    let group = s.create_resource_group("counters", counters.clone());
+    for r in [counters[0], counters[0], counters[1]] {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(2.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 4.0);
+    assert_eq!(s.resource_group(group).name(), "counters");
+    let first = s.resource_stats(counters[0]);
+    assert_eq!(first.mean_wait(), 1.0);
+    assert_eq!(first.mean_queue_len(), 0.5);
+    let stats = s.group_stats(group);
+    assert_eq!(stats.requests(), 3);
+    assert_eq!(stats.grants(), 3);
+    assert_eq!(stats.max_wait(), 2.0);
+    assert_eq!(stats.mean_queue_len(), 0.5);
+}
+
+#[test]
+fn batch_release() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(3)));
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::Request(r);
+            yield Effect::Request(r);
+            yield Effect::TimeOut(1.0);
+            yield Effect::ReleaseN(r, 1);
+            yield Effect::TimeOut(1.0);
+            yield Effect::ReleaseAll(r);
+        },
+    ));
+    s.schedule_event(0.0, holder, Effect::TimeOut(0.)).unwrap();
+    let mut waiters = Vec::new();
+    for _ in 0..3 {
+        let w = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                // keep the unit
+                yield Effect::Wait;
+            },
+        ));
+        s.schedule_event(0.5, w, Effect::TimeOut(0.)).unwrap();
+        waiters.push(w);
+    }
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 2.0);
+    assert_eq!(s.held(holder, r), 0);
+    // the first waiter got the unit released at 1, the others both at 2
+    let grants: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| waiters.contains(&e.process()) && e.time() > 0.5)
+        .map(|(e, _)| (e.process(), e.time()))
+        .collect();
+    assert_eq!(
+        grants,
+        [(waiters[0], 1.0), (waiters[1], 2.0), (waiters[2], 2.0)]
+    );
+    for w in waiters {
+        assert_eq!(s.held(w, r), 1);
+    }
+    let stats = s.resource_stats(r);
+    assert_eq!(stats.in_use(), 3);
+    assert_eq!(stats.utilization(), Some(1.0));
+    assert_eq!(
+        stats.queue_timeline(),
+        [(0.0, 0), (0.5, 3), (1.0, 2), (2.0, 0)]
+    );
+}
+
+#[test]
+fn realtime() {
+    use crate::{Effect, EndCondition::NoEvents, EndReason, Simulation};
+    use std::time::{Duration, Instant};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(2.0);
+            yield Effect::TimeOut(3.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let start = Instant::now();
+    // 10 ms per time unit
+    s = s.run_realtime(NoEvents, 0.01);
+    assert_eq!(s.time(), 5.0);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(s.end_reason(), Some(&EndReason::NoEvents));
+    // the run is accounted in the counters of the engine
+    let stats = s.engine_stats();
+    assert_eq!(stats.steps(), 3);
+    assert!(stats.wall_time() >= Duration::from_millis(50));
+}
+
+#[test]
+fn add_resources() {
+    use crate::error::SimError;
+    use crate::resources::{SimpleResource, SimpleStore};
+    use crate::{Effect, EndCondition::NoEvents, ResourceId, SimContext, Simulation, StoreId};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let pool = s.register_resource_factory(Box::new(|| Box::new(SimpleResource::new(2))));
+    let stores = s.register_store_factory(Box::new(|| Box::new(SimpleStore::new(1))));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            yield Effect::TimeOut(10.0);
+            let ctx = yield Effect::AddResource(pool);
+            let new = ResourceId::from_raw(ctx.created().unwrap());
+            assert_ne!(new, r);
+            yield Effect::Request(new);
+            yield Effect::Request(new);
+            let ctx = yield Effect::AddStore(stores);
+            let store = StoreId::from_raw(ctx.created().unwrap());
+            yield Effect::Push(store);
+            yield Effect::Pull(store);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 10.0);
+    // the existing resource kept its id
+    assert_eq!(s.resource_stats(r).requests(), 0);
+    assert_eq!(s.resource_stats(ResourceId(r.0 + 1)).grants(), 2);
+    let created = s.create_resource(Box::new(SimpleResource::new(1)));
+    assert_eq!(created, ResourceId(r.0 + 2));
+
+    // the ids of unknown factories are rejected
+    for effect in [
+        Effect::AddResource(stores + 1),
+        Effect::AddStore(stores + 1),
+    ] {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield effect;
+            },
+        ));
+        s.schedule_event(s.time(), p, Effect::TimeOut(0.)).unwrap();
+        assert_eq!(
+            s.step().unwrap_err(),
+            SimError::UnknownFactoryId(stores + 1)
+        );
+    }
+    let created = s.create_resource(Box::new(SimpleResource::new(1)));
+    assert_eq!(created, ResourceId(r.0 + 3));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn export_log() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.5);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.metadata_mut().set_seed(7);
+    s = s.run(NoEvents);
+    let mut out = Vec::new();
+    s.export_log(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("{\"metadata\":{\"desim_version\":"));
+    assert!(out.contains("\"seed\":\"7\""));
+    assert!(out.ends_with(
+        ",\"events\":[[{\"time\":0.0,\"process\":0,\"state\":{\"TimeOut\":0.0},\
+         \"interrupted\":false,\"id\":0,\"priority\":0},{\"TimeOut\":1.5}]]}"
+    ));
+}
+
+#[test]
+fn end_conditions() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition, Simulation};
+
+    let build = || {
+        let mut s = Simulation::new();
+        let r = s.create_resource(Box::new(SimpleResource::new(1)));
+        for i in 0..10 {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::Request(r);
+                    yield Effect::TimeOut(1.0);
+                    yield Effect::Release(r);
+                },
+            ));
+            s.schedule_event(i as f64 * 0.1, p, Effect::TimeOut(0.))
+                .unwrap();
+        }
+        (s, r)
+    };
+    let (s, r) = build();
+    let s = s.run_while(EndCondition::NoEvents, |s| {
+        s.resource_stats(r).queue_len() < 5
+    });
+    assert_eq!(s.resource_stats(r).queue_len(), 5);
+    assert!(s.time() < 1.0);
+
+    let (s, _) = build();
+    let s = s.run(EndCondition::Time(2.0).or(EndCondition::NSteps(3)));
+    assert_eq!(s.steps(), 3);
+    let (s, _) = build();
+    let s = s.run(EndCondition::Time(2.0).and(EndCondition::NoEvents));
+    assert_eq!(s.time(), 10.0);
+}
+
+#[test]
+fn end_reason() {
+    use crate::{Effect, EndCondition, EndReason, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    assert_eq!(s.end_reason(), None);
+    for _ in 0..5 {
+        s.step().unwrap();
+    }
+    // the steps are counted from the start of the run
+    s = s.run(EndCondition::NSteps(3));
+    assert_eq!(s.steps(), 8);
+    assert_eq!(s.end_reason(), Some(&EndReason::StepLimit));
+    s = s.run(EndCondition::MaxWallClock(std::time::Duration::ZERO));
+    assert_eq!(s.steps(), 8);
+    assert_eq!(s.end_reason(), Some(&EndReason::WallClock));
+    s = s.run(EndCondition::Time(10.0).or(EndCondition::NSteps(100)));
+    assert_eq!(s.end_reason(), Some(&EndReason::TimeReached));
+    s = s.run_while(EndCondition::NoEvents, |s| s.time() < 12.0);
+    assert_eq!(s.end_reason(), Some(&EndReason::Condition));
+    s.set_zero_delay_policy(crate::limits::ZeroDelayPolicy::Fail(0));
+    s.schedule_event(12.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(EndCondition::NoEvents);
+    assert!(matches!(s.end_reason(), Some(EndReason::Error(_))));
+}
+
+#[test]
+fn ownership() {
+    use crate::error::SimError;
+    use crate::limits::ReleasePolicy;
+    use crate::resources::{ReleaseOutcome, SimpleResource};
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(2)));
+    s.track_occupancy(r);
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::Request(r);
+            yield Effect::TimeOut(1.0);
+            yield Effect::ReleaseN(r, 2);
+        },
+    ));
+    let thief = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::Release(r);
+            yield Effect::Release(r);
+        },
+    ));
+    s.schedule_event(0.0, holder, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(0.5, thief, Effect::TimeOut(0.)).unwrap();
+    s = s.run_while(NoEvents, |s| s.time() < 0.5);
+    assert_eq!(s.resource_holders(r), [(holder, 2)]);
+    s = s.run(NoEvents);
+    assert_eq!(
+        s.error(),
+        Some(&SimError::NotHolding {
+            process: thief,
+            resource: r
+        })
+    );
+    assert_eq!(s.resource_holders(r), []);
+    // the second release of the thief is not in the occupancy
+    assert_eq!(
+        s.resource_stats(r).occupancy(),
+        [(0.0, 1.0, holder), (0.0, 1.0, holder), (1.0, 1.0, thief)]
+    );
+
+    // the spurious releases are skipped
+    let mut s = Simulation::new();
+    s.set_release_policy(ReleasePolicy::Ignore);
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let thief = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            let ctx: SimContext<Effect> = yield Effect::Release(r);
+            assert_eq!(ctx.release_outcome(), Some(ReleaseOutcome::NotHeld));
+            yield Effect::Request(r);
+            let ctx: SimContext<Effect> = yield Effect::ReleaseN(r, 2);
+            assert_eq!(ctx.release_outcome(), Some(ReleaseOutcome::NotHeld));
+            yield Effect::Trace;
+        },
+    ));
+    s.schedule_event(0.0, thief, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.error(), None);
+    assert_eq!(s.active_processes(), 0);
+    assert_eq!(s.resource(r).available(), Some(1));
+}
+
+#[test]
+fn step_result() {
+    use crate::{Effect, Simulation, StepResult};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.5, p, Effect::TimeOut(0.)).unwrap();
+    match s.step().unwrap() {
+        StepResult::Executed { event, state } => {
+            assert_eq!((event.time(), event.process()), (0.5, p));
+            assert!(matches!(state, Effect::TimeOut(t) if t == 1.0));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    let completed = s.step().unwrap();
+    assert!(matches!(completed, StepResult::Completed { .. }));
+    assert_eq!(
+        (completed.time(), completed.process()),
+        (Some(1.5), Some(p))
+    );
+    assert!(matches!(s.step().unwrap(), StepResult::Idle));
+}
+
+#[test]
+fn peek() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    assert_eq!(s.peek_next_time(), None);
+    let first = s.schedule_event(0.5, p, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(2.0, p, Effect::TimeOut(0.)).unwrap();
+    assert_eq!(s.peek_next_event().map(|e| e.id()), Some(first));
+    s.cancel_event(first);
+    assert_eq!(s.peek_next_time(), Some(2.0));
+    assert_eq!(s.time(), 0.0);
+    s.step().unwrap();
+    assert_eq!(s.time(), 2.0);
+    assert_eq!(s.peek_next_time(), Some(3.0));
+}
+
+#[test]
+fn spawn() {
+    use crate::error::SimError;
+    use crate::{Effect, EndCondition::NoEvents, ProcessId, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    // customers stay for the time of the timeout yielded by the source
+    let customer = s.register_spawner(Box::new(|state: &Effect| {
+        let stay = match state {
+            Effect::Spawn(_) => 1.0,
+            _ => unreachable!(),
+        };
+        Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::TimeOut(stay);
+            },
+        )
+    }));
+    let source = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            for _ in 0..3 {
+                let ctx = yield Effect::Spawn(customer);
+                assert!(ctx.created().is_some());
+                yield Effect::TimeOut(2.0);
+            }
+        },
+    ));
+    s.schedule_event(0.0, source, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 6.0);
+    let children: Vec<_> = (0..5)
+        .map(ProcessId)
+        .filter(|p| s.parent(*p) == Some(source))
+        .collect();
+    assert_eq!(children, [ProcessId(1), ProcessId(2), ProcessId(3)]);
+    assert_eq!(s.parent(source), None);
+
+    let orphan = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Spawn(customer + 1);
+        },
+    ));
+    s.schedule_event(s.time(), orphan, Effect::TimeOut(0.))
+        .unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.error(), Some(&SimError::UnknownFactoryId(customer + 1)));
+}
+
+#[test]
+fn slot_reuse() {
+    use crate::sink::DiscardSink;
+    use crate::{Effect, EndCondition::NoEvents, SimError, Simulation};
+
+    let mut s = Simulation::new();
+    let done = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, done, Effect::TimeOut(0.)).unwrap();
+    s.set_process_name(done, "done");
+    s = s.run(NoEvents);
+    assert_eq!(s.active_processes(), 0);
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::Wait;
+        },
+    ));
+    assert_ne!(p, done);
+    assert_eq!(s.active_processes(), 1);
+    assert_eq!(s.process_name(done), Some("done"));
+    assert!(matches!(
+        s.resume_process(done, Effect::Wait),
+        Err(SimError::ProcessCompleted(id)) if id == done
+    ));
+    // events for completed processes are discarded
+    s.schedule_event(2.0, done, Effect::TimeOut(0.)).unwrap();
+    assert_eq!(s.peek_next_time(), None);
+
+    // the names of completed processes are dropped with the log
+    s.set_log_sink(Box::new(DiscardSink));
+    let q = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.set_process_name(q, "dropped");
+    s.schedule_event(s.time(), q, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.active_processes(), 1);
+    assert_eq!(s.process_name(q), None);
+}
+
+#[test]
+fn kill() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, SimError, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let customer = || {
+        Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(10.0);
+                yield Effect::Release(r);
+            },
+        )
+    };
+    let served = s.create_process(customer());
+    let waiting = s.create_process(customer());
+    let last = s.create_process(customer());
+    for p in [served, waiting, last] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    // the waiting customer abandons the queue
+    let killer = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(1.0);
+            yield Effect::Kill(waiting);
+        },
+    ));
+    s.schedule_event(0.0, killer, Effect::TimeOut(0.)).unwrap();
+    while s.time() < 1.0 {
+        s.step().unwrap();
+    }
+    assert_eq!(s.resource(r).queue_len(), 1);
+    // the served customer is aborted and the resource goes to the last one
+    s.terminate_process(served, Effect::Wait).unwrap();
+    assert_eq!(s.resource_holders(r), [(last, 1)]);
+    assert!(matches!(
+        s.terminate_process(served, Effect::Wait),
+        Err(SimError::ProcessCompleted(_))
+    ));
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 11.0);
+    assert_eq!(s.active_processes(), 0);
+}
+
+#[test]
+fn signals() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let green = s.create_signal();
+    let mut cars = Vec::new();
+    for _ in 0..3 {
+        let car = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                let ctx: SimContext<Effect> = yield Effect::WaitSignal(green);
+                assert!(matches!(ctx.state(), Effect::WaitSignal(_)));
+                yield Effect::Trace;
+            },
+        ));
+        s.schedule_event(0.0, car, Effect::TimeOut(0.)).unwrap();
+        cars.push(car);
+    }
+    let light = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(1.0);
+            yield Effect::FireOne(green);
+            yield Effect::TimeOut(1.0);
+            yield Effect::Fire(green);
+            yield Effect::Fire(green);
+        },
+    ));
+    s.schedule_event(0.0, light, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    let passed: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::Trace))
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    assert_eq!(passed, [(1.0, cars[0]), (2.0, cars[1]), (2.0, cars[2])]);
+}
+
+#[test]
+fn wait_until() {
+    use crate::deadlock::{BlockedProcess, WaitingFor};
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, EndReason, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(2)));
+    let idle = s.create_predicate(Box::new(move |view| view.resource(r).in_use() == Some(0)));
+    let never = s.create_predicate(Box::new(|_| false));
+    for hold in [2.0, 3.0] {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(hold);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    let waiters: Vec<_> = [1.0, 4.0]
+        .into_iter()
+        .map(|arrival| {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::WaitUntil(idle);
+                    yield Effect::Trace;
+                },
+            ));
+            s.schedule_event(arrival, p, Effect::TimeOut(0.)).unwrap();
+            p
+        })
+        .collect();
+    let stuck = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::WaitUntil(never);
+        },
+    ));
+    s.schedule_event(0.0, stuck, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    let woken: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::Trace))
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    // the first waits for the resource to be idle, the second finds it idle
+    assert_eq!(woken, [(3.0, waiters[0]), (4.0, waiters[1])]);
+    assert_eq!(
+        s.end_reason(),
+        Some(&EndReason::Deadlock(vec![BlockedProcess {
+            process: stuck,
+            waiting_for: WaitingFor::Predicate(never),
+        }]))
+    );
+}
+
+#[test]
+fn wait_until_interrupted() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let idle = s.create_predicate(Box::new(move |view| view.resource(r).in_use() == Some(0)));
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(1.0);
+            yield Effect::Release(r);
+        },
+    ));
+    let waiter = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::WaitUntil(idle);
+            assert!(ctx.interrupted());
+        },
+    ));
+    s.schedule_event(0.0, holder, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(0.5, waiter, Effect::TimeOut(0.)).unwrap();
+    for _ in 0..4 {
+        s.step().unwrap();
+    }
+    // woken up by the release, but not resumed yet
+    assert_eq!(s.time(), 1.0);
+    assert!(s.blocked_processes().is_empty());
+    s.interrupt(waiter, Effect::Trace).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.active_processes(), 0);
+    let resumed = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.process() == waiter)
+        .count();
+    assert_eq!(resumed, 1);
+}
+
+#[test]
+fn gate() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let gate = s.create_gate(false);
+    let workers: Vec<_> = [0.0, 1.0, 5.0]
+        .into_iter()
+        .map(|arrival| {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::WaitGate(gate);
+                    yield Effect::Trace;
+                },
+            ));
+            s.schedule_event(arrival, p, Effect::TimeOut(0.)).unwrap();
+            p
+        })
+        .collect();
+    let shift = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(2.0);
+            yield Effect::OpenGate(gate);
+            yield Effect::TimeOut(2.0);
+            yield Effect::CloseGate(gate);
+        },
+    ));
+    s.schedule_event(0.0, shift, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    let passed: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::Trace))
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    assert_eq!(passed, [(2.0, workers[0]), (2.0, workers[1])]);
+    assert!(!s.is_gate_open(gate));
+}
+
+#[test]
+fn mailbox() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let m = s.create_mailbox(Some(1));
+    // messages are the timeouts yielded by the sender before sending
+    let sender = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            for i in 1..=3 {
+                yield Effect::TimeOut(i as f64);
+                yield Effect::Send(m);
+            }
+        },
+    ));
+    let receiver = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(10.0);
+            loop {
+                let ctx: SimContext<Effect> = yield Effect::Receive(m);
+                assert!(matches!(ctx.state(), Effect::Send(_)));
+                yield Effect::Trace;
+            }
+        },
+    ));
+    for p in [sender, receiver] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    while s.time() < 10.0 {
+        s.step().unwrap();
+    }
+    // the second message waits for space
+    assert_eq!(s.mailbox_len(m), 1);
+    s = s.run(NoEvents);
+    assert_eq!(s.mailbox_len(m), 0);
+    let received: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, state)| e.process() == receiver && matches!(state, Effect::Trace))
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(received, [10.0, 10.0, 13.0]);
+}
+
+#[test]
+fn rendezvous() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let m = s.create_mailbox(Some(0));
+    // the first sender waits for the receiver, the second one finds it waiting
+    let sender = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            let ctx: SimContext<Effect> = yield Effect::Send(m);
+            assert_eq!(ctx.time(), 2.0);
+            yield Effect::TimeOut(3.0);
+            let ctx: SimContext<Effect> = yield Effect::Send(m);
+            assert_eq!(ctx.time(), 5.0);
+        },
+    ));
+    let receiver = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(2.0);
+            for _ in 0..2 {
+                let ctx: SimContext<Effect> = yield Effect::Receive(m);
+                assert!(matches!(ctx.state(), Effect::Send(_)));
+                yield Effect::Trace;
+            }
+        },
+    ));
+    for p in [sender, receiver] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    assert_eq!(s.end_reason(), Some(&crate::EndReason::NoEvents));
+    let received: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, state)| e.process() == receiver && matches!(state, Effect::Trace))
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(received, [2.0, 5.0]);
+}
+
+#[test]
+fn multiple_grants() {
+    use crate::error::SimError;
+    use crate::resources::Resource;
+    use crate::{Effect, EndCondition::NoEvents, Event, Simulation};
+
+    // grants the requests in batches
+    struct Batch {
+        size: usize,
+        queue: Vec<Event<Effect>>,
+    }
+    impl Resource<Effect> for Batch {
+        fn allocate_or_enqueue(&mut self, event: Event<Effect>) -> Vec<Event<Effect>> {
+            let time = event.time();
+            self.queue.push(event);
+            if self.queue.len() < self.size {
+                return Vec::new();
+            }
+            let mut batch: Vec<_> = self.queue.drain(..).collect();
+            batch.iter_mut().for_each(|e| e.set_time(time));
+            batch
+        }
+        fn release_and_schedule_next(&mut self, _: Event<Effect>) -> Vec<Event<Effect>> {
+            Vec::new()
+        }
+    }
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(Batch {
+        size: 3,
+        queue: Vec::new(),
+    }));
+    let passengers: Vec<_> = (0..3)
+        .map(|i| {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::Request(r);
+                    yield Effect::Wait;
+                },
+            ));
+            s.schedule_event(i as f64, p, Effect::TimeOut(0.)).unwrap();
+            p
+        })
+        .collect();
+    s = s.run(NoEvents);
+    let mut holders = s.resource_holders(r);
+    holders.sort();
+    assert_eq!(
+        holders,
+        passengers.iter().map(|p| (*p, 1)).collect::<Vec<_>>()
+    );
+    let boarded = s
+        .processed_events()
+        .iter()
+        .filter(|(e, state)| e.time() == 2.0 && matches!(state, Effect::Wait))
+        .count();
+    assert_eq!(boarded, 3);
+
+    // the batches are made of single units
+    let group = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::RequestN(r, 2);
+        },
+    ));
+    s.schedule_event(s.time(), group, Effect::TimeOut(0.))
+        .unwrap();
+    assert_eq!(
+        s.step().unwrap_err(),
+        SimError::InvalidUnits {
+            process: group,
+            resource: r,
+            units: 2
+        }
+    );
+}
+
+#[test]
+fn scheduled_resource() {
+    use crate::observer::{ResourceChange, SimulationObserver};
+    use crate::resources::{ScheduledResource, SimpleResource};
+    use crate::{Effect, EndCondition::NoEvents, ResourceId, Simulation};
+    use std::sync::{Arc, Mutex};
+
+    struct Shifts(Arc<Mutex<Vec<(f64, ResourceChange)>>>);
+    impl SimulationObserver<Effect> for Shifts {
+        fn on_resource_state_change(&mut self, _: ResourceId, time: f64, change: &ResourceChange) {
+            if let ResourceChange::CapacityChanged { .. } = change {
+                self.0.lock().unwrap().push((time, *change));
+            }
+        }
+    }
+
+    let mut s = Simulation::new();
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    s.add_observer(Box::new(Shifts(changes.clone())));
+    let shifts = vec![(8.0, 12.0), (14.0, 18.0)];
+    let r = s.create_resource(Box::new(ScheduledResource::new(
+        SimpleResource::new(1),
+        shifts,
+    )));
+    let jobs: Vec<_> = [0.0, 9.0, 20.0]
+        .into_iter()
+        .map(|arrival| {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::Request(r);
+                    yield Effect::TimeOut(3.0);
+                    yield Effect::Release(r);
+                },
+            ));
+            s.schedule_event(arrival, p, Effect::TimeOut(0.)).unwrap();
+            p
+        })
+        .collect();
+    s = s.run(NoEvents);
+    let started: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::TimeOut(t) if *t == 3.0))
+        .map(|(e, _)| (e.process(), e.time()))
+        .collect();
+    // the last job arrives after the end of the calendar
+    assert_eq!(started, [(jobs[0], 8.0), (jobs[1], 11.0)]);
+    assert_eq!(s.resource(r).queue_len(), 1);
+    assert_eq!(s.resource_stats(r).waits(), [8.0, 2.0]);
+    // the units are in use from the opening, and the stranded request gave its unit back
+    assert_eq!(s.resource_stats(r).mean_in_use(), 6.0 / 20.0);
+    assert_eq!(s.resource(r).available(), Some(1));
+    let capacity = |capacity| ResourceChange::CapacityChanged { capacity };
+    assert_eq!(
+        *changes.lock().unwrap(),
+        [
+            (8.0, capacity(1)),
+            (12.0, capacity(0)),
+            (14.0, capacity(1)),
+            (18.0, capacity(0)),
+        ]
+    );
+}
+
+#[test]
+fn resource_inspection() {
+    use crate::resources::{Resource, SimpleResource};
+    use crate::{Effect, EndCondition::NoEvents, Event, SimContext, Simulation};
+
+    // reports more available units than its capacity, e.g. after a shrink
+    struct Shrunk;
+    impl Resource<Effect> for Shrunk {
+        fn allocate_or_enqueue(&mut self, event: Event<Effect>) -> Vec<Event<Effect>> {
+            vec![event]
+        }
+        fn release_and_schedule_next(&mut self, _: Event<Effect>) -> Vec<Event<Effect>> {
+            Vec::new()
+        }
+        fn capacity(&self) -> Option<usize> {
+            Some(1)
+        }
+        fn available(&self) -> Option<usize> {
+            Some(2)
+        }
+    }
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(2)));
+    for _ in 0..3 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_: SimContext<Effect>| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(1.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    let observer = s.create_process(Box::new(
+        #[coroutine]
+        move |ctx: SimContext<Effect>| {
+            // the context sees the occupancy when the process is resumed
+            let resource = ctx.resource(r);
+            assert_eq!(resource.capacity(), Some(2));
+            assert_eq!(resource.in_use(), Some(2));
+            assert_eq!(resource.queue_len(), 1);
+            let ctx = yield Effect::TimeOut(1.5);
+            assert_eq!(ctx.resource(r).available(), Some(1));
+            assert_eq!(ctx.resource(r).queue_len(), 0);
+        },
+    ));
+    s.schedule_event(0.5, observer, Effect::TimeOut(0.))
+        .unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 2.0);
+    assert_eq!(s.active_processes(), 0);
+    assert_eq!(s.resource(r).available(), Some(2));
+    assert_eq!(s.resource(r).in_use(), Some(0));
+    let shrunk = s.create_resource(Box::new(Shrunk));
+    assert_eq!(s.resource(shrunk).in_use(), Some(0));
+}
+
+#[test]
+fn breakdown() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            let ctx: SimContext<Effect> = yield Effect::TimeOut(10.0);
+            assert!(ctx.interrupted());
+            assert!(matches!(ctx.state(), Effect::Fail(_)));
+        },
+    ));
+    let waiter = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::Release(r);
+        },
+    ));
+    let mechanic = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(2.0);
+            yield Effect::Fail(r);
+            yield Effect::TimeOut(3.0);
+            yield Effect::Repair(r);
+        },
+    ));
+    for p in [holder, waiter, mechanic] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    while s.time() < 2.0 {
+        s.step().unwrap();
+    }
+    assert!(s.is_offline(r));
+    assert_eq!(s.held(holder, r), 0);
+    assert!(s.resource_holders(r).is_empty());
+    s = s.run(NoEvents);
+    assert!(!s.is_offline(r));
+    let released = s
+        .processed_events()
+        .iter()
+        .find(|(_, state)| matches!(state, Effect::Release(_)))
+        .map(|(e, _)| (e.process(), e.time()));
+    assert_eq!(released, Some((waiter, 5.0)));
+}
+
+#[test]
+fn request_units() {
+    use crate::error::SimError;
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let servers = s.create_resource(Box::new(SimpleResource::new(5)));
+    let jobs: Vec<_> = [(0.0, 3), (1.0, 3), (2.0, 1)]
+        .into_iter()
+        .map(|(arrival, n)| {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::RequestN(servers, n);
+                    yield Effect::TimeOut(4.0);
+                    yield Effect::ReleaseN(servers, n);
+                },
+            ));
+            s.schedule_event(arrival, p, Effect::TimeOut(0.)).unwrap();
+            p
+        })
+        .collect();
+    while s.time() < 2.0 {
+        s.step().unwrap();
+    }
+    // the last job waits behind the second one, though a server is free
+    assert_eq!(s.resource_holders(servers), [(jobs[0], 3)]);
+    assert_eq!(s.resource(servers).queue_len(), 2);
+    s = s.run(NoEvents);
+    assert_eq!(s.resource_stats(servers).waits(), [0.0, 3.0, 2.0]);
+    assert!(s.resource_holders(servers).is_empty());
+
+    let greedy = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::RequestN(servers, 6);
+        },
+    ));
+    s.schedule_event(s.time(), greedy, Effect::TimeOut(0.))
+        .unwrap();
+    assert_eq!(
+        s.step().unwrap_err(),
+        SimError::InvalidUnits {
+            process: greedy,
+            resource: servers,
+            units: 6
+        }
+    );
+}
+
+#[test]
+fn select() {
+    use crate::resources::SimpleStore;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let a = s.create_store(Box::new(SimpleStore::new(5)));
+    let b = s.create_store(Box::new(SimpleStore::new(5)));
+    let inputs = s.create_condition(vec![Effect::Pull(a), Effect::Pull(b)]);
+    let consumer = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(1.0);
+            loop {
+                let ctx: SimContext<Effect> = yield Effect::Select(inputs);
+                assert!(!ctx.timed_out());
+                yield Effect::Trace;
+            }
+        },
+    ));
+    let producer = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Push(a);
+            yield Effect::Push(b);
+            yield Effect::TimeOut(5.0);
+            yield Effect::Push(b);
+        },
+    ));
+    for p in [consumer, producer] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    while s.time() < 1.0 {
+        s.step().unwrap();
+    }
+    s.step().unwrap();
+    // only one item is pulled, out of the first store
+    assert_eq!((s.store(a).level(), s.store(b).level()), (0, 1));
+    s = s.run(NoEvents);
+    let fired: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, state)| e.process() == consumer && matches!(state, Effect::Trace))
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(fired, [1.0, 1.0, 5.0]);
+    assert_eq!(s.store(b).level(), 0);
+}
+
+#[test]
+fn wait_timeout() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let customer = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            // the first reply arrives in time, the second one does not
+            for _ in 0..2 {
+                let ctx: SimContext<Effect> = yield Effect::WaitTimeout(5.0);
+                if ctx.timed_out() {
+                    yield Effect::Trace;
+                }
+            }
+        },
+    ));
+    let server = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Event {
+                time: 2.0,
+                process: customer,
+            };
+        },
+    ));
+    for p in [customer, server] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    let gave_up: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::Trace))
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(gave_up, [7.0]);
+    assert_eq!(s.time(), 7.0);
+}
+
+#[test]
+fn process_rng() {
+    use crate::rng::StreamRng;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let draws = |seed| {
+        let mut s = Simulation::with_seed(seed);
+        for _ in 0..2 {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                |mut ctx: SimContext<Effect>| {
+                    for _ in 0..3 {
+                        let t = ctx.rng().next_f64();
+                        ctx = yield Effect::TimeOut(t);
+                    }
+                },
+            ));
+            s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        }
+        s.run(NoEvents)
+            .processed_events()
+            .iter()
+            .map(|(e, state)| match state {
+                Effect::TimeOut(t) => (e.process(), *t),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+    let a = draws(1);
+    assert_eq!(a, draws(1));
+    assert_ne!(a, draws(2));
+    // each process and step draws different numbers
+    let mut numbers: Vec<_> = a.iter().map(|(_, t)| t.to_bits()).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    assert_eq!(numbers.len(), 6);
+}
+
+#[test]
+fn model_stats() {
+    use crate::stats::Histogram;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, SimError, Simulation};
+
+    let mut s = Simulation::new();
+    let served = s.create_counter("served");
+    let sojourn = s.create_tally("sojourn");
+    let in_system = s.create_time_weighted("in system");
+    let sojourns = s.create_histogram("sojourns", Histogram::linear(0.0, 10.0, 10));
+    for (i, t) in [2.0, 3.0].into_iter().enumerate() {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |ctx: SimContext<Effect>| {
+                let arrival = ctx.time();
+                yield Effect::Record(in_system, i as f64 + 1.0);
+                let ctx = yield Effect::TimeOut(t);
+                yield Effect::Record(sojourn, ctx.time() - arrival);
+                yield Effect::Record(sojourns, ctx.time() - arrival);
+                yield Effect::Record(in_system, 1.0 - i as f64);
+                yield Effect::Record(served, 1.0);
+            },
+        ));
+        s.schedule_event(i as f64, p, Effect::TimeOut(0.)).unwrap();
+    }
+    let mut s = s.run(NoEvents);
+    s.record(served, 1.0);
+    let stats = s.stats();
+    assert_eq!(stats.find("sojourn"), Some(sojourn));
+    assert_eq!(stats.counter(served).unwrap().value(), 3.0);
+    let sojourn = stats.tally(sojourn).unwrap();
+    assert_eq!((sojourn.mean(), sojourn.variance()), (2.5, 0.5));
+    // one entity in [0, 1) and [2, 4), two in [1, 2)
+    let in_system = stats.time_weighted(in_system).unwrap();
+    assert_eq!((in_system.mean(), in_system.max()), (1.25, 2.0));
+    assert!(stats.tally(served).is_none());
+    assert_eq!(stats.histogram(sojourns).unwrap().quantile(0.5), Some(2.5));
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Record(3, 1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    assert!(matches!(s.step(), Err(SimError::UnknownStat(3))));
+}
+
+#[test]
+fn warm_up() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::Time, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let busy = s.create_time_weighted("busy");
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| loop {
+            yield Effect::Request(r);
+            yield Effect::Record(busy, 1.0);
+            yield Effect::TimeOut(3.0);
+            yield Effect::Release(r);
+            yield Effect::Record(busy, 0.0);
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.set_warm_up(5.0);
+    let s = s.run(Time(10.0));
+    assert!(s.processed_events().iter().all(|(e, _)| e.time() >= 5.0));
+    // the run stops at 11, busy in [5, 7) and [8, 11), granted at 8
+    let stats = s.resource_stats(r);
+    assert_eq!((stats.requests(), stats.grants()), (1, 1));
+    assert_eq!(stats.mean_in_use(), 5.0 / 6.0);
+    let busy = s.stats().time_weighted(busy).unwrap().clone();
+    assert_eq!((busy.mean(), busy.min()), (5.0 / 6.0, 0.0));
+}
+
+#[test]
+fn request_timeout() {
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(5.0);
+            yield Effect::Release(r);
+        },
+    ));
+    // reneges after 2 time units
+    let impatient = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::RequestTimeout(r, 2.0);
+            assert!(ctx.timed_out());
+            assert_eq!(ctx.time(), 2.0);
+        },
+    ));
+    let patient = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::RequestTimeout(r, 10.0);
+            assert!(!ctx.timed_out());
+            assert_eq!(ctx.time(), 5.0);
+            yield Effect::Release(r);
+        },
+    ));
+    for p in [holder, impatient, patient] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    // the timeout of the patient process was discarded
+    assert_eq!(s.time(), 5.0);
+    let stats = s.resource_stats(r);
+    assert_eq!(stats.requests(), 3);
+    assert_eq!(stats.grants(), 2);
+    assert_eq!(stats.queue_len(), 0);
+}
+
+#[test]
+fn simultaneous_events() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let processes: Vec<_> = (0..20)
+        .map(|_| {
+            s.create_process(Box::new(
+                #[coroutine]
+                |_| {
+                    yield Effect::Trace;
+                    yield Effect::TimeOut(1.0);
+                },
+            ))
+        })
+        .collect();
+    for p in processes.iter().rev() {
+        s.schedule_event(0.0, *p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    let order: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| e.process())
+        .collect();
+    // each round runs in the order the events were scheduled
+    let round: Vec<_> = processes.iter().rev().copied().collect();
+    assert_eq!(order, [round.clone(), round].concat());
+}
+
+#[test]
+fn release_outcome() {
+    use crate::resources::{ReleaseOutcome, SimpleResource};
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let second = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            yield Effect::Request(r);
+            let ctx = yield Effect::Release(r);
+            assert_eq!(ctx.release_outcome(), Some(ReleaseOutcome::Freed));
+        },
+    ));
+    let first = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::Request(r);
+            assert_eq!(ctx.release_outcome(), None);
+            yield Effect::TimeOut(1.0);
+            let ctx = yield Effect::Release(r);
+            assert_eq!(ctx.release_outcome(), Some(ReleaseOutcome::Woke(second)));
+            yield Effect::TimeOut(1.0);
+        },
+    ));
+    s.schedule_event(0.0, first, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(0.0, second, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 2.0);
+}
+
+#[test]
+fn outcome() {
+    use crate::resources::{Resource, SimpleResource};
+    use crate::{Effect, EndCondition::NoEvents, Event, Outcome, SimContext, Simulation};
+
+    // rejects every request
+    struct Full;
+    impl Resource<Effect> for Full {
+        fn allocate_or_enqueue(&mut self, mut event: Event<Effect>) -> Vec<Event<Effect>> {
+            event.reject();
+            vec![event]
+        }
+        fn release_and_schedule_next(&mut self, _: Event<Effect>) -> Vec<Event<Effect>> {
+            Vec::new()
+        }
+    }
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let full = s.create_resource(Box::new(Full));
+    let m = s.create_mailbox(None);
+    let holder = s.create_process(Box::new(
+        #[coroutine]
+        move |ctx: SimContext<Effect>| {
+            assert_eq!(ctx.outcome(), Outcome::Started);
+            let ctx = yield Effect::Request(r);
+            assert_eq!(ctx.outcome(), Outcome::Granted(r));
+            let ctx = yield Effect::TimeOut(2.0);
+            assert_eq!(ctx.outcome(), Outcome::Elapsed);
+            let ctx = yield Effect::Send(m);
+            assert_eq!(ctx.outcome(), Outcome::Sent(m));
+            let ctx = yield Effect::Release(r);
+            assert_eq!(ctx.outcome(), Outcome::Released(r));
+            let ctx = yield Effect::Request(full);
+            assert_eq!(ctx.outcome(), Outcome::Rejected(full));
+        },
+    ));
+    let waiter = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::RequestTimeout(r, 1.0);
+            assert_eq!(ctx.outcome(), Outcome::TimedOut);
+            let ctx = yield Effect::Receive(m);
+            assert_eq!(ctx.outcome(), Outcome::Received(m));
+            assert_eq!(ctx.time(), 2.0);
+            let ctx = yield Effect::Wait;
+            assert_eq!(ctx.outcome(), Outcome::Interrupted);
+        },
+    ));
+    let interrupter = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            yield Effect::TimeOut(3.0);
+            yield Effect::Interrupt(waiter);
+        },
+    ));
+    for p in [holder, waiter, interrupter] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    assert_eq!(s.active_processes(), 0);
+    // the rejected request is not held
+    assert_eq!(s.held(holder, full), 0);
+
+    // a process woken up by the owner did not get what it was waiting for
+    let signal = s.create_signal();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::WaitSignal(signal);
+            assert_eq!(ctx.outcome(), Outcome::Resumed);
+            let ctx = yield Effect::Wait;
+            assert_eq!(ctx.outcome(), Outcome::Resumed);
+        },
+    ));
+    s.schedule_event(s.time(), p, Effect::TimeOut(0.)).unwrap();
+    s.step().unwrap();
+    s.schedule_event(s.time() + 1.0, p, Effect::TimeOut(0.))
+        .unwrap();
+    s.step().unwrap();
+    s.resume_process(p, Effect::Wait).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.active_processes(), 0);
+}
+
+#[test]
+fn priority() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let processes: Vec<_> = (0..3)
+        .map(|_| {
+            s.create_process(Box::new(
+                #[coroutine]
+                |_| {
+                    yield Effect::Trace;
+                },
+            ))
+        })
+        .collect();
+    s.schedule_event(1.0, processes[0], Effect::TimeOut(0.))
+        .unwrap();
+    s.schedule_event_with_priority(1.0, processes[1], Effect::TimeOut(0.), -1)
+        .unwrap();
+    let last = processes[2];
+    let urgent = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::EventWithPriority {
+                time: 1.0,
+                process: last,
+                priority: 10,
+            };
+        },
+    ));
+    s.schedule_event(0.0, urgent, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    let order: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.time() == 1.0)
+        .map(|(e, _)| e.process())
+        .collect();
+    assert_eq!(order, [processes[2], processes[0], processes[1]]);
+}
+
+#[test]
+fn event_bus() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+    use std::sync::{Arc, Mutex};
+
+    let mut s = Simulation::new();
+    let shipped = s.create_topic("order_shipped");
+    assert_eq!(s.topic("order_shipped"), Some(shipped));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let l = log.clone();
+    s.subscribe(
+        shipped,
+        Box::new(move |time, _| l.lock().unwrap().push(time)),
+    );
+    let customer = s.create_process(Box::new(
+        #[coroutine]
+        move |_: SimContext<Effect>| {
+            let ctx = yield Effect::Subscribe(shipped);
+            assert_eq!(ctx.time(), 3.0);
+            assert!(matches!(ctx.state(), Effect::Publish(_)));
+        },
+    ));
+    let warehouse = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(3.0);
+            yield Effect::Publish(shipped);
+            yield Effect::TimeOut(1.0);
+            yield Effect::Publish(shipped);
+        },
+    ));
+    for p in [customer, warehouse] {
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 4.0);
+    assert_eq!(*log.lock().unwrap(), [3.0, 4.0]);
+}
+
+#[test]
+fn soak() {
+    use crate::error::SimError;
+    use crate::resources::SimpleResource;
+    use crate::soak::{CheckInterval, SoakError};
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    for _ in 0..5 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(1.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s.add_invariant(
+        "short queue",
+        CheckInterval::Time(0.5),
+        Box::new(move |sim: &Simulation<Effect>| {
+            let len = sim.resource(r).queue_len();
+            if len > 2 {
+                Err(format!("{} processes waiting", len))
+            } else {
+                Ok(())
+            }
+        }),
+    );
+    let Err(SoakError::Invariant(violation)) = s.soak(NoEvents) else {
+        panic!("the invariant should not hold");
+    };
+    assert_eq!(violation.invariant, "short queue");
+    assert_eq!(violation.queues, [(r, 3)]);
+    assert_eq!(violation.time, 1.0);
+    assert!(violation.to_string().contains("3 processes waiting"));
+
+    // a large jump with a tiny interval counts as a single check
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1e6);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    let checks = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counted = std::sync::Arc::clone(&checks);
+    s.add_invariant(
+        "counted",
+        CheckInterval::Time(1e-9),
+        Box::new(move |_: &Simulation<Effect>| {
+            counted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(())
+        }),
+    );
+    s.soak(NoEvents).unwrap();
+    assert_eq!(checks.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+    // a failed step stops the run with its error
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(f64::NAN);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    assert_eq!(
+        s.soak(NoEvents),
+        Err(SoakError::Step(SimError::InvalidTime(p)))
+    );
+    assert_eq!(s.error(), Some(&SimError::InvalidTime(p)));
+}
+
+#[test]
+#[should_panic(expected = "must be positive")]
+fn invariant_interval() {
+    use crate::soak::CheckInterval;
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::<Effect>::new();
+    s.add_invariant("never", CheckInterval::Steps(0), Box::new(|_| Ok(())));
+}
+
+#[test]
+fn stat_updates() {
+    use crate::observer::{ResourceChange, SimulationObserver, StatUpdate};
+    use crate::resources::SimpleResource;
+    use crate::stats::Histogram;
+    use crate::{Effect, EndCondition::NoEvents, Event, ProcessId, ResourceId, Simulation};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Dashboard {
+        events: usize,
+        scheduled: usize,
+        completed: Vec<ProcessId>,
+        changes: Vec<ResourceChange>,
+        updates: Vec<StatUpdate>,
+    }
+    struct Shared(Arc<Mutex<Dashboard>>);
+    impl SimulationObserver<Effect> for Shared {
+        fn on_event_scheduled(&mut self, _: &Event<Effect>) {
+            self.0.lock().unwrap().scheduled += 1;
+        }
+        fn on_process_complete(&mut self, process: ProcessId, _: f64) {
+            self.0.lock().unwrap().completed.push(process);
+        }
+        fn on_resource_state_change(&mut self, _: ResourceId, _: f64, change: &ResourceChange) {
+            self.0.lock().unwrap().changes.push(*change);
+        }
+        fn on_event_fired(&mut self, _: &Event<Effect>, _: &Effect) {
+            self.0.lock().unwrap().events += 1;
+        }
+        fn on_stat_update(&mut self, update: &StatUpdate) {
+            self.0.lock().unwrap().updates.push(*update);
+        }
+    }
+
+    let mut s = Simulation::new();
+    let dashboard = Arc::new(Mutex::new(Dashboard::default()));
+    s.add_observer(Box::new(Shared(dashboard.clone())));
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    let mut processes = Vec::new();
+    for _ in 0..2 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| {
+                yield Effect::Request(r);
+                yield Effect::TimeOut(1.0);
+                yield Effect::Release(r);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        processes.push(p);
+    }
+    s = s.run(NoEvents);
+    let dashboard = dashboard.lock().unwrap();
+    assert_eq!(dashboard.events, s.processed_events().len());
+    // each executed event was scheduled, plus the one that completes each process
+    assert_eq!(dashboard.scheduled, dashboard.events + 2);
+    assert_eq!(dashboard.completed, processes);
+    let (p0, p1) = (processes[0], processes[1]);
+    assert_eq!(
+        dashboard.changes,
+        [
+            ResourceChange::Acquired {
+                process: p0,
+                units: 1
+            },
+            ResourceChange::Released { process: p0 },
+            ResourceChange::Acquired {
+                process: p1,
+                units: 1
+            },
+            ResourceChange::Released { process: p1 },
+        ]
+    );
+    let waits: Vec<_> = dashboard
+        .updates
+        .iter()
+        .filter_map(|u| match u {
+            StatUpdate::WaitTime { wait, .. } => Some(*wait),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(waits, [0.0, 1.0]);
+    let queues = dashboard
+        .updates
+        .iter()
+        .filter(|u| matches!(u, StatUpdate::QueueLength { .. }))
+        .count();
+    assert_eq!(queues, 2);
+    let in_use: Vec<_> = dashboard
+        .updates
+        .iter()
+        .filter_map(|u| match u {
+            StatUpdate::InUse { time, delta, .. } => Some((*time, *delta)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(in_use, [(0.0, 1), (1.0, -1), (1.0, 1), (2.0, -1)]);
+    drop(dashboard);
+
+    // the values recorded by the processes are streamed as samples
+    let mut s = Simulation::new();
+    let dashboard = Arc::new(Mutex::new(Dashboard::default()));
+    s.add_observer(Box::new(Shared(dashboard.clone())));
+    let sizes = s.create_histogram("sizes", Histogram::linear(0.0, 10.0, 5));
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Record(sizes, 3.0);
+            yield Effect::TimeOut(1.0);
+            yield Effect::Record(sizes, 7.0);
+        },
+    ));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.run(NoEvents);
+    assert_eq!(
+        dashboard.lock().unwrap().updates,
+        [
+            StatUpdate::Sample {
+                stat: sizes,
+                time: 0.0,
+                value: 3.0
+            },
+            StatUpdate::Sample {
+                stat: sizes,
+                time: 1.0,
+                value: 7.0
+            },
+        ]
+    );
+}
+
+#[test]
+fn invalid_time() {
+    use crate::error::SimError;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(1.0);
+            yield Effect::TimeOut(f64::NAN);
+        },
+    ));
+    assert_eq!(
+        s.schedule_event(f64::NAN, p, Effect::TimeOut(0.)),
+        Err(SimError::InvalidTime(p))
+    );
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.error(), Some(&SimError::InvalidTime(p)));
+    assert_eq!(s.time(), 1.0);
+}
+
+#[test]
+fn periodic_events() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    let mut s = Simulation::new();
+    let monitor = s.create_process(Box::new(
+        #[coroutine]
+        |_| loop {
+            yield Effect::Wait;
+        },
+    ));
+    let series = s
+        .schedule_periodic(1.0, 2.0, monitor, Effect::Trace)
+        .unwrap();
+    s = s.run(EndCondition::Time(5.0));
+    let times: Vec<_> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, [1.0, 3.0, 5.0]);
+    s.cancel_series(series);
+    s = s.run(EndCondition::NoEvents);
+    assert_eq!(s.processed_events().len(), 3);
+    assert!(s
+        .schedule_periodic(0.0, 0.0, monitor, Effect::Trace)
+        .is_err());
+}
+
+#[test]
+fn event_at() {
+    use crate::error::SimError;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let customer = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::Trace;
+        },
+    ));
+    let scheduler = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(3.0);
+            yield Effect::EventAt {
+                time: 5.0,
+                process: customer,
+            };
+        },
+    ));
+    s.schedule_event(0.0, scheduler, Effect::TimeOut(0.))
+        .unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 5.0);
+    assert!(s.error().is_none());
+
+    let mut s = Simulation::new();
+    let late = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::TimeOut(3.0);
+            yield Effect::EventAt {
+                time: 2.0,
+                process: customer,
+            };
+        },
+    ));
+    s.schedule_event(0.0, late, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.error(), Some(&SimError::InvalidTime(late)));
+}
+
+#[test]
+fn start_time() {
+    use crate::error::SimError;
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    s.set_start_time(1000.0);
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(5.0);
+        },
+    ));
+    assert_eq!(
+        s.schedule_at(999.0, p, Effect::TimeOut(0.)),
+        Err(SimError::InvalidTime(p))
+    );
+    assert_eq!(
+        s.schedule_in(-1.0, p, Effect::TimeOut(0.)),
+        Err(SimError::InvalidTime(p))
+    );
+    s.schedule_in(2.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.time(), 1007.0);
+    assert_eq!(s.start_time(), 1000.0);
+    assert_eq!(s.elapsed(), 7.0);
+
+    // the statistics start from the start time
+    let mut s = Simulation::new();
+    let r = s.create_resource(Box::new(SimpleResource::new(1)));
+    s.set_start_time(1000.0);
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(5.0);
+            yield Effect::Release(r);
+            yield Effect::TimeOut(5.0);
+        },
+    ));
+    s.schedule_at(1000.0, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    assert_eq!(s.resource_stats(r).mean_in_use(), 0.5);
+}
+
+#[test]
+#[should_panic(expected = "scheduled before the start time")]
+fn start_time_after_events() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::TimeOut(5.0);
+        },
+    ));
+    s.schedule_event(1.0, p, Effect::TimeOut(0.)).unwrap();
+    s.set_start_time(1000.0);
+}
+
+#[test]
+fn zero_delay_policy() {
+    use crate::error::SimError;
+    use crate::limits::ZeroDelayPolicy;
+    use crate::{Effect, EndCondition, Simulation};
+
+    let looping = || -> Box<crate::Process<Effect>> {
+        Box::new(
+            #[coroutine]
+            |_| loop {
+                yield Effect::TimeOut(0.0);
+            },
+        )
+    };
+
+    let mut s = Simulation::new();
+    let p = s.create_process(looping());
+    let q = s.create_process(looping());
+    s.schedule_event(1.0, p, Effect::TimeOut(0.)).unwrap();
+    s.schedule_event(1.0, q, Effect::TimeOut(0.)).unwrap();
+    s.set_zero_delay_policy(ZeroDelayPolicy::Fail(10));
+    s = s.run(EndCondition::NSteps(100));
+    assert_eq!(
+        s.error(),
+        Some(&SimError::ZeroDelayLoop {
+            process: q,
+            time: 1.0,
+            processes: vec![p, q],
+        })
+    );
+
+    let mut s = Simulation::new();
+    let p = s.create_process(looping());
+    s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    s.set_zero_delay_policy(ZeroDelayPolicy::Advance {
+        max_steps: 9,
+        epsilon: 0.5,
+    });
+    s = s.run(EndCondition::NSteps(100));
+    assert_eq!(s.error(), None);
+    // one advance every 10 steps
+    assert_eq!(s.time(), 5.0);
+}
+
+#[test]
+fn future_event_list() {
+    use crate::event_list::CalendarQueue;
+    use crate::resources::SimpleResource;
+    use crate::{Effect, EndCondition, Simulation};
+
+    let run = |calendar: bool| {
+        let mut s = Simulation::builder().seed(7).build();
+        let r = s.create_resource(Box::new(SimpleResource::new(2)));
+        for i in 0..20 {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| loop {
+                    yield Effect::Request(r);
+                    yield Effect::TimeOut(0.5 + (i % 3) as f64);
+                    yield Effect::Release(r);
+                    yield Effect::TimeOut((i % 5) as f64);
+                },
+            ));
+            s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        }
+        if calendar {
+            // the events already scheduled are moved
+            s.set_future_event_list(Box::new(CalendarQueue::new()));
+        }
+        let s = s.run(EndCondition::NSteps(2000));
+        s.processed_events()
+            .iter()
+            .map(|(e, _)| (e.time(), e.process()))
+            .collect::<Vec<_>>()
+    };
+    let calendar = run(true);
+    assert_eq!(calendar.len(), 2000);
+    assert_eq!(calendar, run(false));
+}
+
+#[test]
+fn with_capacity() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    let mut s = Simulation::with_capacity(16, 4, 100);
+    assert!(s.processed_events.capacity() >= 100);
+    let c = s.create_condition(vec![Effect::TimeOut(1.0), Effect::TimeOut(2.0)]);
+    for _ in 0..4 {
+        let p = s.create_process(Box::new(
+            #[coroutine]
+            move |_| loop {
+                yield Effect::AnyOf(c);
+                yield Effect::AllOf(c);
+            },
+        ));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+    }
+    s = s.run(EndCondition::NSteps(100));
+    assert_eq!(s.processed_events().len(), 100);
+    // the branches of the conditions are kept for the next ones, a vector per process
+    let waiting = s.processes.iter().filter(|(_, e)| e.condition.is_some());
+    assert_eq!(s.branch_buffers.len() + waiting.count(), 4);
+}
+
+#[test]
+fn standard_resources() {
+    use crate::resources::{ScheduledResource, SimpleResource};
+    use crate::{Effect, EndCondition, ResourceId, Simulation};
+
+    let run = |standard: bool| {
+        let mut s = Simulation::new();
+        let (r, shift): (ResourceId, ResourceId) = if standard {
+            (
+                s.create_standard_resource(SimpleResource::new(2)),
+                s.create_standard_resource(ScheduledResource::new(
+                    SimpleResource::new(1),
+                    vec![(5.0, 8.0)],
+                )),
+            )
+        } else {
+            (
+                s.create_resource(Box::new(SimpleResource::new(2))),
+                s.create_resource(Box::new(ScheduledResource::new(
+                    SimpleResource::new(1),
+                    vec![(5.0, 8.0)],
+                ))),
+            )
+        };
+        for i in 0..5 {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::Request(r);
+                    yield Effect::TimeOut(1.0 + i as f64);
+                    yield Effect::Release(r);
+                    yield Effect::Request(shift);
+                    yield Effect::TimeOut(1.0);
+                    yield Effect::Release(shift);
+                },
+            ));
+            s.schedule_event(0.0, p, Effect::TimeOut(0.)).unwrap();
+        }
+        let s = s.run(EndCondition::NoEvents);
+        assert_eq!(s.resource(r).capacity(), Some(2));
+        // the shift closes at 8.0, before granting the last two requests
+        assert_eq!(s.resource(shift).queue_len(), 2);
+        s.processed_events()
+            .iter()
+            .map(|(e, _)| (e.time(), e.process()))
+            .collect::<Vec<_>>()
+    };
+    let standard = run(true);
+    assert_eq!(standard, run(false));
+}
+
+#[test]
+fn split_clock() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    // ~100 years in seconds, with millisecond timeouts
+    let start = 36_500.0 * 86_400.0;
+    let mut s = Simulation::new();
+    s.set_start_time(start);
+    let p = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            for _ in 0..1000 {
+                yield Effect::TimeOut(0.001);
+            }
+        },
+    ));
+    s.schedule_event(start, p, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    let clock = s.split_time().unwrap();
+    assert_eq!(clock.period(), 36_500);
+    assert!((clock.offset() - 1.0).abs() < 1e-12);
+    assert_eq!(s.time(), clock.as_f64());
+
+    // the times that do not fit fall back to f64
+    let mut s = Simulation::<Effect>::new();
+    s.set_start_time(-1.0);
+    assert_eq!(s.split_time(), None);
+    assert_eq!(s.time(), -1.0);
+}
+
+#[test]
+fn split_clock_order() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    // the offsets are below the resolution of a f64 at this time
+    let start = 1e9;
+    let mut s = Simulation::new();
+    s.set_start_time(start);
+    let processes: Vec<_> = [2e-8, 1e-8]
+        .into_iter()
+        .map(|delay| {
+            let p = s.create_process(Box::new(
+                #[coroutine]
+                move |_| {
+                    yield Effect::TimeOut(delay);
+                    yield Effect::Trace;
+                },
+            ));
+            s.schedule_event(start, p, Effect::TimeOut(0.)).unwrap();
+            p
+        })
+        .collect();
+    s.step().unwrap();
+    s.step().unwrap();
+    let late = s.create_process(Box::new(
+        #[coroutine]
+        |_| {
+            yield Effect::Trace;
+        },
+    ));
+    s.schedule_in(3e-8, late, Effect::TimeOut(0.)).unwrap();
+    s = s.run(NoEvents);
+    let order: Vec<_> = s
+        .processed_events()
+        .iter()
+        .skip(2)
+        .map(|(e, _)| e.process())
+        .collect();
+    assert_eq!(order, [processes[1], processes[0], late]);
+    assert!(s.processed_events().iter().all(|(e, _)| e.time() == start));
+    let clock = s.split_time().unwrap();
+    assert_eq!(clock.period(), 11_574);
+    assert!((clock.offset() - 6_400.0 - 3e-8).abs() < 1e-12);
+}
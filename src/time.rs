@@ -0,0 +1,228 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Two-level time representation.
+//!
+//! A `f64` has 53 bits of mantissa: the larger the simulation time, the coarser the
+//! durations that can be added to it. A model that runs for many years with a resolution
+//! of fractions of a second loses precision at every step.
+//!
+//! `SplitTime` keeps an integer count of periods (e.g. days) and a fractional offset within
+//! the current period, so that arithmetic within a period is always done on small numbers.
+//! The length of the period is given by a type implementing `Period`.
+//!
+//! The clock of the simulation is a `SplitTime<Day>`: the timeouts and the delays of
+//! `Simulation::schedule_in` are added to it, so that the events they schedule keep the
+//! precision of the offset within the day and are executed in the right order, and
+//! `Simulation::split_time` returns it. `Simulation::time` and `Event::time` round it to a
+//! `f64`. The times that do not fit in a `SplitTime`, e.g. before 0, fall back to `f64`
+//! arithmetic.
+//!
+//! ```
+//! use desim::time::{Day, SplitTime};
+//!
+//! let t = SplitTime::<Day>::new(36_500, 0.0);
+//! let later = t + 86_400.5;
+//! assert_eq!(later.period(), 36_501);
+//! assert_eq!(later.offset(), 0.5);
+//! assert_eq!(later - t, 86_400.5);
+//! ```
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Sub};
+
+/// The length of the periods of a `SplitTime`, in time units of the simulation.
+pub trait Period {
+    /// Length of a period
+    const LENGTH: f64;
+}
+
+/// A day, when the time unit of the simulation is the second.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Day;
+
+impl Period for Day {
+    const LENGTH: f64 = 86_400.0;
+}
+
+/// A period of length 1: the offset is the fractional part of the time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Unit;
+
+impl Period for Unit {
+    const LENGTH: f64 = 1.0;
+}
+
+/// A point in time represented as a number of periods plus an offset within the period.
+///
+/// The offset is always in `[0, P::LENGTH)`. Times are totally ordered, first by period
+/// and then by offset.
+pub struct SplitTime<P = Day> {
+    period: u32,
+    offset: f64,
+    _period: PhantomData<P>,
+}
+
+impl<P: Period> SplitTime<P> {
+    /// Create the time at `offset` time units after the start of `period`.
+    ///
+    /// The offset is normalized, so it can be larger than the length of a period.
+    ///
+    /// # Panics
+    /// Panics if `offset` is not finite or if the resulting period does not fit in a `u32`.
+    pub fn new(period: u32, offset: f64) -> SplitTime<P> {
+        SplitTime {
+            period,
+            offset: 0.0,
+            _period: PhantomData,
+        } + offset
+    }
+
+    /// Split a time expressed as a `f64`.
+    pub fn from_f64(time: f64) -> SplitTime<P> {
+        SplitTime::new(0, time)
+    }
+
+    /// Split a time expressed as a `f64`, or returns `None` if it is not finite or out of
+    /// the range of `SplitTime`, e.g. negative.
+    pub fn checked_from_f64(time: f64) -> Option<SplitTime<P>> {
+        SplitTime::default().checked_add(time)
+    }
+
+    /// Returns the time `duration` time units later, or `None` if `duration` is not finite
+    /// or the result is out of the range of `SplitTime`.
+    pub fn checked_add(self, duration: f64) -> Option<SplitTime<P>> {
+        if !duration.is_finite() {
+            return None;
+        }
+        let offset = self.offset + duration;
+        let periods = (offset / P::LENGTH).floor();
+        let period = self.period as f64 + periods;
+        (0.0..=u32::MAX as f64)
+            .contains(&period)
+            .then(|| SplitTime {
+                period: period as u32,
+                // the remainder of a small number keeps the precision within the period
+                offset: (offset - periods * P::LENGTH).clamp(0.0, P::LENGTH.next_down()),
+                _period: PhantomData,
+            })
+    }
+
+    /// Returns the number of whole periods
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    /// Returns the offset within the current period
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Returns the time as a single `f64`, possibly losing precision.
+    pub fn as_f64(&self) -> f64 {
+        self.period as f64 * P::LENGTH + self.offset
+    }
+}
+
+impl<P: Period> Add<f64> for SplitTime<P> {
+    type Output = SplitTime<P>;
+
+    fn add(self, duration: f64) -> SplitTime<P> {
+        assert!(duration.is_finite(), "ERROR. Time must be finite.");
+        self.checked_add(duration)
+            .expect("ERROR. Time out of the range of SplitTime.")
+    }
+}
+
+impl<P: Period> AddAssign<f64> for SplitTime<P> {
+    fn add_assign(&mut self, duration: f64) {
+        *self = *self + duration;
+    }
+}
+
+impl<P: Period> Sub for SplitTime<P> {
+    type Output = f64;
+
+    /// Returns the duration between two times
+    fn sub(self, other: SplitTime<P>) -> f64 {
+        (self.period as f64 - other.period as f64) * P::LENGTH + (self.offset - other.offset)
+    }
+}
+
+impl<P> Clone for SplitTime<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for SplitTime<P> {}
+
+impl<P> PartialEq for SplitTime<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<P> Eq for SplitTime<P> {}
+
+impl<P> PartialOrd for SplitTime<P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P> Ord for SplitTime<P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.period
+            .cmp(&other.period)
+            .then(self.offset.total_cmp(&other.offset))
+    }
+}
+
+impl<P> Hash for SplitTime<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.period.hash(state);
+        self.offset.to_bits().hash(state);
+    }
+}
+
+impl<P> Default for SplitTime<P> {
+    fn default() -> Self {
+        SplitTime {
+            period: 0,
+            offset: 0.0,
+            _period: PhantomData,
+        }
+    }
+}
+
+impl<P> fmt::Debug for SplitTime<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SplitTime")
+            .field("period", &self.period)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<P> fmt::Display for SplitTime<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}", self.period, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,43 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{Day, SplitTime, Unit};
+
+#[test]
+fn split_time() {
+    // ~100 years in seconds
+    let start = SplitTime::<Day>::new(36_500, 0.0);
+    let mut t = start;
+    for _ in 0..1000 {
+        t += 0.001;
+    }
+    // the offset is accumulated within the day, without the error that the same
+    // sum would have on a f64 holding the whole time
+    assert!((t.offset() - 1.0).abs() < 1e-12);
+    let mut f = start.as_f64();
+    for _ in 0..1000 {
+        f += 0.001;
+    }
+    assert!((f - start.as_f64() - 1.0).abs() > 1e-9);
+
+    let a = SplitTime::<Unit>::new(1, 2.25);
+    assert_eq!(a.period(), 3);
+    assert_eq!(a.offset(), 0.25);
+    let b = a + (-0.5);
+    assert_eq!((b.period(), b.offset()), (2, 0.75));
+    assert!(b < a);
+    assert_eq!(a - b, 0.5);
+    assert_eq!(SplitTime::<Unit>::from_f64(3.25), a);
+}
@@ -0,0 +1,285 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+//! Trace-driven workloads.
+//!
+//! Instead of generating the arrivals of the entities of a model from random
+//! distributions, a simulation can be driven by an external trace, e.g. a log
+//! collected from a production system.
+//!
+//! Arrivals are added to a simulation as an iterator of `Arrival`s through the
+//! `Simulation::add_arrivals` method. The iterator is consumed lazily: only the
+//! next arrival of each source is created and scheduled, so the trace is streamed
+//! rather than loaded in memory up front.
+//!
+//! `CsvTrace` reads a CSV file with a header line, where one of the columns holds the
+//! arrival times. Any other format can be supported converting its records into
+//! `Arrival`s.
+//!
+#![cfg_attr(feature = "coroutines", doc = "```")]
+#![cfg_attr(not(feature = "coroutines"), doc = "```ignore")]
+//! #![feature(coroutines)]
+//! use desim::trace::{Arrival, CsvTrace};
+//! use desim::{Effect, EndCondition, Simulation};
+//!
+//! let data = "arrival,service\n0.5,2.0\n1.5,1.0\n";
+//! let trace = CsvTrace::new(data.as_bytes(), "arrival").unwrap();
+//!
+//! let mut sim = Simulation::new();
+//! sim.add_arrivals(Box::new(trace.map(|record| {
+//!     let record = record.unwrap();
+//!     let service: f64 = record.parse("service").unwrap();
+//!     Arrival::new(
+//!         record.time(),
+//!         Box::new(#[coroutine] move |_| {
+//!             yield Effect::TimeOut(service);
+//!         }),
+//!         Effect::TimeOut(0.0),
+//!     )
+//! })));
+//! let sim = sim.run(EndCondition::NoEvents);
+//! assert_eq!(sim.time(), 2.5);
+//! ```
+use crate::Process;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A process that enters the simulation at a given time.
+pub struct Arrival<T> {
+    time: f64,
+    process: Box<Process<T>>,
+    state: T,
+}
+
+/// A record of a trace, with the arrival time and the other fields of the line.
+#[derive(Debug, Clone)]
+pub struct TraceRecord {
+    time: f64,
+    line: usize,
+    header: Arc<[String]>,
+    fields: Vec<String>,
+}
+
+/// Reads a trace from a CSV file with a header line.
+///
+/// The CSV dialect is simple: fields are separated by commas and may be enclosed
+/// in double quotes, in which case they can contain commas and escaped (doubled) quotes.
+/// Empty lines are skipped.
+pub struct CsvTrace<R> {
+    reader: R,
+    header: Arc<[String]>,
+    time_column: usize,
+    line: usize,
+}
+
+/// An error found reading a trace.
+#[derive(Debug)]
+pub enum TraceError {
+    /// The underlying reader failed
+    Io(io::Error),
+    /// The header does not contain the requested column
+    MissingColumn(String),
+    /// A line could not be parsed
+    Parse { line: usize, message: String },
+}
+
+impl<T> Arrival<T> {
+    /// Create an arrival of `process` at the absolute simulation `time`.
+    ///
+    /// When the process is resumed for the first time, `state` is passed to it.
+    pub fn new(time: f64, process: Box<Process<T>>, state: T) -> Arrival<T> {
+        Arrival {
+            time,
+            process,
+            state,
+        }
+    }
+
+    /// Returns the arrival time
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub(crate) fn into_parts(self) -> (f64, Box<Process<T>>, T) {
+        (self.time, self.process, self.state)
+    }
+}
+
+impl TraceRecord {
+    /// Returns the arrival time
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the number of the line of the record in the trace, starting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the raw content of the field in `column`
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.header
+            .iter()
+            .position(|c| c == column)
+            .and_then(|i| self.fields.get(i))
+            .map(String::as_str)
+    }
+
+    /// Parse the field in `column`
+    pub fn parse<F: FromStr>(&self, column: &str) -> Result<F, TraceError> {
+        let field = self
+            .get(column)
+            .ok_or_else(|| TraceError::MissingColumn(column.to_string()))?;
+        field.trim().parse().map_err(|_| TraceError::Parse {
+            line: self.line,
+            message: format!("invalid value `{}` in column `{}`", field, column),
+        })
+    }
+
+    /// Returns all the fields of the record, in the order of the header
+    pub fn fields(&self) -> &[String] {
+        self.fields.as_slice()
+    }
+}
+
+impl<R: BufRead> CsvTrace<R> {
+    /// Create a trace reading the header from `reader`.
+    ///
+    /// `time_column` is the name of the column that holds the arrival times.
+    pub fn new(mut reader: R, time_column: &str) -> Result<CsvTrace<R>, TraceError> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let header: Arc<[String]> = split_line(&line, 1)?.into();
+        let time_column = header
+            .iter()
+            .position(|c| c == time_column)
+            .ok_or_else(|| TraceError::MissingColumn(time_column.to_string()))?;
+        Ok(CsvTrace {
+            reader,
+            header,
+            time_column,
+            line: 1,
+        })
+    }
+
+    /// Returns the names of the columns
+    pub fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    fn parse_record(&self, line: &str) -> Result<TraceRecord, TraceError> {
+        let fields = split_line(line, self.line)?;
+        if fields.len() != self.header.len() {
+            return Err(TraceError::Parse {
+                line: self.line,
+                message: format!(
+                    "expected {} fields, found {}",
+                    self.header.len(),
+                    fields.len()
+                ),
+            });
+        }
+        let time = fields[self.time_column]
+            .trim()
+            .parse()
+            .map_err(|_| TraceError::Parse {
+                line: self.line,
+                message: format!("invalid time `{}`", fields[self.time_column]),
+            })?;
+        Ok(TraceRecord {
+            time,
+            line: self.line,
+            header: self.header.clone(),
+            fields,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for CsvTrace<R> {
+    type Item = Result<TraceRecord, TraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            self.line += 1;
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => return Some(self.parse_record(&line)),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+/// Split a CSV line in its fields.
+fn split_line(line: &str, line_number: usize) -> Result<Vec<String>, TraceError> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(TraceError::Parse {
+            line: line_number,
+            message: "unterminated quoted field".to_string(),
+        });
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+impl From<io::Error> for TraceError {
+    fn from(e: io::Error) -> Self {
+        TraceError::Io(e)
+    }
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(e) => write!(f, "error reading the trace: {}", e),
+            TraceError::MissingColumn(c) => write!(f, "missing column `{}`", c),
+            TraceError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for TraceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TraceError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
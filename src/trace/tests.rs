@@ -0,0 +1,40 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::{CsvTrace, TraceError};
+
+#[test]
+fn csv_trace() {
+    let data = "id,time,name\n1,0.5,\"a, b\"\n\n2,1.25,\"say \"\"hi\"\"\"\n";
+    let records: Vec<_> = CsvTrace::new(data.as_bytes(), "time")
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].time(), 0.5);
+    assert_eq!(records[0].get("name"), Some("a, b"));
+    assert_eq!(records[1].parse::<u32>("id").unwrap(), 2);
+    assert_eq!(records[1].get("name"), Some("say \"hi\""));
+    assert_eq!(records[1].line(), 4);
+    assert!(matches!(
+        records[1].parse::<u32>("name"),
+        Err(TraceError::Parse { line: 4, .. })
+    ));
+
+    let bad = "time\nfoo\n";
+    let mut trace = CsvTrace::new(bad.as_bytes(), "time").unwrap();
+    assert!(trace.next().unwrap().is_err());
+    assert!(CsvTrace::new(bad.as_bytes(), "arrival").is_err());
+}
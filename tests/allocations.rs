@@ -0,0 +1,62 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! The allocations made by the steps of a simulation, counted by `CountingAllocator`.
+//! The global allocator is shared by the whole test binary, so this is the only test in it.
+#![cfg(feature = "count-allocations")]
+
+use desim::engine::CountingAllocator;
+use desim::process::{ProcessStep, StateMachine};
+use desim::resources::SimpleResource;
+use desim::{Effect, EndCondition, ResourceId, Simulation};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the allocations per step of 10 processes cycling through `effects`, after the
+/// simulation warmed up
+fn allocations_per_step(effects: fn(ResourceId) -> Vec<Effect>) -> f64 {
+    let mut sim = Simulation::new();
+    let r = sim.create_resource(Box::new(SimpleResource::new(2)));
+    let effects = effects(r);
+    for _ in 0..10 {
+        let effects = effects.clone();
+        let mut i = 0;
+        let p = sim.create_process(Box::new(StateMachine::new(move |_| {
+            let effect = effects[i % effects.len()];
+            i += 1;
+            ProcessStep::Yield(effect)
+        })));
+        sim.schedule_event(0.0, p, Effect::TimeOut(0.0)).unwrap();
+    }
+    let sim = sim.run(EndCondition::NSteps(1000));
+    let before = sim.engine_stats();
+    let sim = sim.run(EndCondition::NSteps(11000));
+    let after = sim.engine_stats();
+    (after.allocations().unwrap() - before.allocations().unwrap()) as f64
+        / (after.steps() - before.steps()) as f64
+}
+
+#[test]
+fn no_allocations_per_request() {
+    let timeouts = allocations_per_step(|_| vec![Effect::TimeOut(1.0)]);
+    let requests = allocations_per_step(|r| {
+        vec![Effect::Request(r), Effect::TimeOut(1.0), Effect::Release(r)]
+    });
+    // the grants of the resource reuse the same vector, so the requests and the releases
+    // allocate about as much as the timeouts
+    assert!(timeouts < 0.05, "{timeouts} allocations per step");
+    assert!(requests < 0.05, "{requests} allocations per step");
+}